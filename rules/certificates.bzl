@@ -3,6 +3,7 @@
 # SPDX-License-Identifier: Apache-2.0
 
 load("@bazel_skylib//lib:paths.bzl", "paths")
+load("@rules_rust//rust:defs.bzl", "rust_test")
 load("//rules/opentitan:toolchain.bzl", "LOCALTOOLS_TOOLCHAIN")
 
 def _certificate_codegen_impl(ctx):
@@ -80,6 +81,55 @@ certificate_codegen = rule(
     toolchains = [LOCALTOOLS_TOOLCHAIN],
 )
 
+def _certificate_rust_codegen_impl(ctx):
+    tc = ctx.toolchains[LOCALTOOLS_TOOLCHAIN]
+
+    basename = paths.replace_extension(ctx.file.template.basename, "")
+
+    # The unittest declares the generated module with `mod <basename>;` so
+    # both files must be in the same directory.
+    out_rs = ctx.actions.declare_file("{}.rs".format(basename))
+    out_ut = ctx.actions.declare_file("{}_unittest.rs".format(basename))
+    ctx.actions.run(
+        outputs = [out_rs, out_ut],
+        inputs = [
+            ctx.file.template,
+        ],
+        arguments = [
+            "--rcfile=",
+            "--quiet",
+            "certificate",
+            "codegen",
+            "--lang=rust",
+            "--template={}".format(ctx.file.template.path),
+            "--output-rs={}".format(out_rs.path),
+            "--output-unittest={}".format(out_ut.path),
+        ],
+        executable = tc.tools.opentitantool,
+        mnemonic = "GenCertTemplateRust",
+    )
+
+    return [
+        DefaultInfo(files = depset([out_rs, out_ut])),
+        OutputGroupInfo(
+            sources = depset([out_rs]),
+            unittest = depset([out_ut]),
+        ),
+    ]
+
+# This rule uses `opentitantool certificate codegen --lang=rust` to generate
+# a self-contained `no_std` Rust module implementing the certificate generator.
+# The generated files are put in the following output groups:
+# - "sources": contains the module.
+# - "unittest": contains the crate root of the unittest, which includes the module.
+certificate_rust_codegen = rule(
+    implementation = _certificate_rust_codegen_impl,
+    attrs = {
+        "template": attr.label(allow_single_file = True, doc = "path to the hjson template file"),
+    },
+    toolchains = [LOCALTOOLS_TOOLCHAIN],
+)
+
 # This macro leverages the `certificate_codegen` rule to
 # generate the C code of a certificate generator. It creates
 # the following targets:
@@ -88,6 +138,9 @@ certificate_codegen = rule(
 # - <name>_hdrs: filegroup corresponding to the `headers` output group of <name>
 # - <name>_library: cc_library that compiles the sources, exports the headers and links to the asn1
 #   library.
+# - <name>_rust: output of `certificate_rust_codegen`
+# - <name>_rust_srcs: filegroup containing the generated Rust module.
+# - <name>_rust_unittest: rust_test of the generated Rust module.
 def certificate_template(name, template):
     certificate_codegen(
         name = name,
@@ -128,3 +181,29 @@ def certificate_template(name, template):
             "@googletest//:gtest_main",
         ],
     )
+
+    certificate_rust_codegen(
+        name = "{}_rust".format(name),
+        template = template,
+    )
+
+    native.filegroup(
+        name = "{}_rust_srcs".format(name),
+        srcs = [":{}_rust".format(name)],
+        output_group = "sources",
+    )
+
+    native.filegroup(
+        name = "{}_rust_unittest_srcs".format(name),
+        srcs = [":{}_rust".format(name)],
+        output_group = "unittest",
+    )
+
+    rust_test(
+        name = "{}_rust_unittest".format(name),
+        srcs = [
+            ":{}_rust_srcs".format(name),
+            ":{}_rust_unittest_srcs".format(name),
+        ],
+        crate_root = ":{}_rust_unittest_srcs".format(name),
+    )
@@ -124,6 +124,7 @@ typedef enum asn1_tag_number {
   kAsn1TagNumberOid = 0x06,
   kAsn1TagNumberUtf8String = 0x0c,
   kAsn1TagNumberPrintableString = 0x13,
+  kAsn1TagNumberIa5String = 0x16,
   kAsn1TagNumberGeneralizedTime = 0x18,
   kAsn1TagNumberSequence = 0x30,
   kAsn1TagNumberSet = 0x31,
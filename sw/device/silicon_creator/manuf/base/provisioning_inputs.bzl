@@ -42,8 +42,15 @@ LOCAL_CERT_ENDORSEMENT_PARAMS = """
   --ca-certificate="$(rootpath //sw/device/silicon_creator/manuf/keys/fake:fake_ca.pem)"
 """
 
+# The Cloud KMS key is reached through the Cloud KMS PKCS#11 library, which
+# must be listed in the test data (see CLOUD_KMS_CERT_ENDORSEMENT_DATA). The
+# token label and the library configuration are site specific and are passed in
+# with `--test_env=HSMTOOL_TOKEN=...` and `--test_env=KMS_PKCS11_CONFIG=...`.
 CLOUD_KMS_CERT_ENDORSEMENT_PARAMS = """
-  --ca-key-ckms-id="gcs-kms-earlgrey-ze-ca-p256-sha256-key"
+  --ca-key-pkcs11-label="gcs-kms-earlgrey-ze-ca-p256-sha256-key"
+  --hsm-module="$(rootpath @cloud_kms_hsm//:libkmsp11)"
   --ca-key-id="0x40aac5fb_2b1205f9_003f40ab_7f3df784_1d5b59f5"
   --ca-certificate="$(rootpath //sw/device/silicon_creator/manuf/keys/fake:ckms_ca.pem)"
 """
+
+CLOUD_KMS_CERT_ENDORSEMENT_DATA = ["@cloud_kms_hsm//:libkmsp11"]
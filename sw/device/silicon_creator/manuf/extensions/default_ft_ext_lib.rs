@@ -2,9 +2,7 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::Result;
-use arrayvec::ArrayVec;
+use ft_ext_api::FtExtRegistry;
 
-pub fn ft_ext(endorsed_cert_concat: ArrayVec<u8, 4096>) -> Result<ArrayVec<u8, 4096>> {
-    Ok(endorsed_cert_concat)
-}
+/// The default FT flow has no extensions.
+pub fn register_extensions(_registry: &mut FtExtRegistry) {}
@@ -3,9 +3,41 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use anyhow::Result;
-use arrayvec::ArrayVec;
 
-pub fn ft_ext(endorsed_cert_concat: ArrayVec<u8, 4096>) -> Result<ArrayVec<u8, 4096>> {
-    log::info!("Running example host FT extension ...");
-    Ok(endorsed_cert_concat)
+use ft_ext_api::{FtExtContext, FtExtRegistry, FtExtension};
+use perso_tlv_lib::PersoObject;
+
+/// An extension that only logs the hooks it is called from.
+struct ExampleExtension;
+
+impl FtExtension for ExampleExtension {
+    fn name(&self) -> &str {
+        "example"
+    }
+
+    fn pre_lc_transition(&self, _ctx: &FtExtContext) -> Result<()> {
+        log::info!("Running example host FT extension before the LC transition ...");
+        Ok(())
+    }
+
+    fn post_cert_endorse(
+        &self,
+        _ctx: &FtExtContext,
+        endorsed_certs: &mut Vec<PersoObject>,
+    ) -> Result<()> {
+        log::info!(
+            "Running example host FT extension on {} endorsed certificates ...",
+            endorsed_certs.len()
+        );
+        Ok(())
+    }
+
+    fn post_personalize(&self, _ctx: &FtExtContext) -> Result<()> {
+        log::info!("Running example host FT extension after personalization ...");
+        Ok(())
+    }
+}
+
+pub fn register_extensions(registry: &mut FtExtRegistry) {
+    registry.register(ExampleExtension);
 }
@@ -0,0 +1,150 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! AES block cipher test vectors.
+
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+
+use crate::rsp;
+
+/// The block cipher modes supported by the cryptotest firmware.
+const SUPPORTED_MODES: &[&str] = &["cbc", "cfb128", "ecb", "ofb"];
+
+fn vendor_cryptotest() -> String {
+    String::from("cryptotest")
+}
+
+/// An AES block cipher test case.  `key_len` is in bits.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AesTestCase {
+    #[serde(default = "vendor_cryptotest")]
+    pub vendor: String,
+    #[serde(default)]
+    pub test_case_id: usize,
+    pub algorithm: String,
+    pub operation: String,
+    pub key_len: usize,
+    pub mode: String,
+    pub padding: String,
+    pub key: Vec<u8>,
+    pub iv: Option<Vec<u8>>,
+    pub ciphertext: Vec<u8>,
+    pub plaintext: Vec<u8>,
+}
+
+impl AesTestCase {
+    /// Returns the name of the test group: `${mode}/${key_len}`.
+    pub fn group(&self) -> String {
+        format!("{}/{}", self.mode, self.key_len)
+    }
+}
+
+/// Reads test cases in the cryptotest JSON format.  The format has no test
+/// case ids, so the test cases are numbered in file order.
+pub fn load_json(text: &str) -> Result<Vec<AesTestCase>> {
+    let mut cases: Vec<AesTestCase> = serde_json::from_str(text)?;
+    for (i, case) in cases.iter_mut().enumerate() {
+        case.test_case_id = i + 1;
+    }
+    Ok(cases)
+}
+
+/// Reads test cases from a NIST CAVP AESAVS known-answer file (e.g.
+/// `CBCGFSbox128.rsp`).  The mode is taken from the file's header comment
+/// (`# AESVS GFSbox test data for CBC`) and the key length from the keys.
+pub fn load_nist(text: &str) -> Result<Vec<AesTestCase>> {
+    let mode = text
+        .lines()
+        .find_map(|line| {
+            let (_, mode) = line.strip_prefix('#')?.split_once(" test data for ")?;
+            Some(mode.trim().to_lowercase())
+        })
+        .ok_or_else(|| anyhow!("missing AESVS test data comment"))?;
+    if !SUPPORTED_MODES.contains(&mode.as_str()) {
+        bail!("Unsupported AES mode: {mode}");
+    }
+    let mut cases = Vec::new();
+    for record in rsp::parse(text, &[])? {
+        let operation = match record.section.as_slice() {
+            [s] if s == "ENCRYPT" => "encrypt",
+            [s] if s == "DECRYPT" => "decrypt",
+            _ => bail!(
+                "line {}: unexpected section {:?}",
+                record.line,
+                record.section
+            ),
+        };
+        let key = record.get_hex("KEY")?;
+        cases.push(AesTestCase {
+            vendor: "nist".into(),
+            test_case_id: cases.len() + 1,
+            algorithm: "aes".into(),
+            operation: operation.into(),
+            key_len: key.len() * 8,
+            mode: mode.clone(),
+            padding: "null".into(),
+            key,
+            // ECB does not have an IV.
+            iv: if mode == "ecb" {
+                None
+            } else {
+                Some(record.get_hex("IV")?)
+            },
+            ciphertext: record.get_hex("CIPHERTEXT")?,
+            plaintext: record.get_hex("PLAINTEXT")?,
+        });
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NIST: &str = r#"
+# CAVS 11.1
+# Config info for aes_values
+# AESVS VarKey test data for CFB128
+# State : Encrypt and Decrypt
+# Key Length : 192
+# Generated on Fri Apr 22 15:11:55 2011
+
+[ENCRYPT]
+
+COUNT = 0
+KEY = 800000000000000000000000000000000000000000000000
+IV = 00000000000000000000000000000000
+PLAINTEXT = 00000000000000000000000000000000
+CIPHERTEXT = de885dc87f5a92594082d02cc1e1b42c
+
+[DECRYPT]
+
+COUNT = 0
+KEY = 800000000000000000000000000000000000000000000000
+IV = 00000000000000000000000000000000
+CIPHERTEXT = de885dc87f5a92594082d02cc1e1b42c
+PLAINTEXT = 00000000000000000000000000000000
+"#;
+
+    #[test]
+    fn test_nist() -> Result<()> {
+        let cases = load_nist(NIST)?;
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].mode, "cfb128");
+        assert_eq!(cases[0].operation, "encrypt");
+        assert_eq!(cases[0].key_len, 192);
+        assert_eq!(cases[0].iv, Some(vec![0; 16]));
+        assert_eq!(cases[0].ciphertext[0], 0xde);
+        assert_eq!(cases[0].group(), "cfb128/192");
+        assert_eq!(cases[1].operation, "decrypt");
+        assert_eq!(cases[1].test_case_id, 2);
+
+        let ecb = NIST.replace("CFB128", "ECB");
+        assert!(load_nist(&ecb)?.iter().all(|c| c.iv.is_none()));
+        let cfb8 = NIST.replace("CFB128", "CFB8");
+        assert!(load_nist(&cfb8).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! CTR_DRBG test vectors.
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+use crate::rsp;
+
+/// A CTR_DRBG test case.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DrbgTestCase {
+    pub vendor: String,
+    pub test_case_id: usize,
+    pub entropy: Vec<u8>,
+    pub personalization_string: Vec<u8>,
+    pub reseed: bool,
+    #[serde(default)]
+    pub reseed_entropy: Vec<u8>,
+    #[serde(default)]
+    pub reseed_additional_input: Vec<u8>,
+    pub additional_input_1: Vec<u8>,
+    pub additional_input_2: Vec<u8>,
+    pub output: Vec<u8>,
+    pub result: bool,
+}
+
+impl DrbgTestCase {
+    /// Returns the name of the test group: `reseed` or `no-reseed`.
+    pub fn group(&self) -> String {
+        if self.reseed { "reseed" } else { "no-reseed" }.into()
+    }
+}
+
+/// Reads test cases in the cryptotest JSON format.
+pub fn load_json(text: &str) -> Result<Vec<DrbgTestCase>> {
+    Ok(serde_json::from_str(text)?)
+}
+
+/// Reads test cases from a NIST CAVP SP 800-90A `CTR_DRBG.rsp` file.  Only the
+/// configuration supported by the cryptolib (AES-256 without a derivation
+/// function) is read.  Test cases which provide `EntropyInputReseed` reseed
+/// the DRBG before generating the second output.
+pub fn load_nist(text: &str) -> Result<Vec<DrbgTestCase>> {
+    let mut cases = Vec::new();
+    for record in rsp::parse(text, &[])? {
+        if record.section != ["AES-256 no df"] {
+            continue;
+        }
+        let [input_1, input_2] = record.get_all("AdditionalInput") else {
+            bail!("line {}: expected two additional inputs", record.line);
+        };
+        let reseed = record.fields.contains_key("EntropyInputReseed");
+        let (reseed_entropy, reseed_additional_input) = if reseed {
+            (
+                record.get_hex("EntropyInputReseed")?,
+                record.get_hex("AdditionalInputReseed")?,
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        cases.push(DrbgTestCase {
+            vendor: "nist".into(),
+            test_case_id: record.get_usize("COUNT")?,
+            entropy: record.get_hex("EntropyInput")?,
+            personalization_string: record.get_hex("PersonalizationString")?,
+            reseed,
+            reseed_entropy,
+            reseed_additional_input,
+            additional_input_1: rsp::decode_hex(input_1)?,
+            additional_input_2: rsp::decode_hex(input_2)?,
+            output: record.get_hex("ReturnedBits")?,
+            result: true,
+        });
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESEED: &str = r#"
+# CAVS 14.3
+# DRBG information for "CTR_DRBG"
+
+[AES-128 no df]
+[PredictionResistance = False]
+[EntropyInputLen = 128]
+
+COUNT = 0
+EntropyInput = 00
+Nonce =
+PersonalizationString =
+EntropyInputReseed = 01
+AdditionalInputReseed =
+AdditionalInput =
+AdditionalInput =
+ReturnedBits = 02
+
+[AES-256 no df]
+[PredictionResistance = False]
+[EntropyInputLen = 384]
+
+COUNT = 0
+EntropyInput = e4bc23c5
+Nonce =
+PersonalizationString =
+EntropyInputReseed = fd85a836
+AdditionalInputReseed =
+AdditionalInput =
+AdditionalInput =
+ReturnedBits = 5862eb38
+
+COUNT = 1
+EntropyInput = 1d2be6f2
+Nonce =
+PersonalizationString = 3e
+EntropyInputReseed = 4c8a
+AdditionalInputReseed = 5a
+AdditionalInput = 6b
+AdditionalInput = 7c
+ReturnedBits = 8d
+"#;
+
+    #[test]
+    fn test_nist_reseed() -> Result<()> {
+        let cases = load_nist(RESEED)?;
+        // The AES-128 section is not supported and is skipped.
+        assert_eq!(cases.len(), 2);
+        assert!(cases[0].reseed);
+        assert_eq!(cases[0].test_case_id, 0);
+        assert_eq!(cases[0].group(), "reseed");
+        assert_eq!(cases[0].reseed_entropy, vec![0xfd, 0x85, 0xa8, 0x36]);
+        assert!(cases[0].additional_input_1.is_empty());
+        assert_eq!(cases[1].personalization_string, vec![0x3e]);
+        assert_eq!(cases[1].reseed_additional_input, vec![0x5a]);
+        assert_eq!(cases[1].additional_input_1, vec![0x6b]);
+        assert_eq!(cases[1].additional_input_2, vec![0x7c]);
+        Ok(())
+    }
+
+    const NO_RESEED: &str = r#"
+[AES-256 no df]
+[PredictionResistance = False]
+
+COUNT = 3
+EntropyInput = 0a
+Nonce =
+PersonalizationString =
+AdditionalInput =
+AdditionalInput =
+ReturnedBits = 0b
+"#;
+
+    #[test]
+    fn test_nist_no_reseed() -> Result<()> {
+        let cases = load_nist(NO_RESEED)?;
+        assert_eq!(cases.len(), 1);
+        assert!(!cases[0].reseed);
+        assert!(cases[0].reseed_entropy.is_empty());
+        assert_eq!(cases[0].test_case_id, 3);
+        assert_eq!(cases[0].group(), "no-reseed");
+        assert_eq!(cases[0].output, vec![0x0b]);
+        Ok(())
+    }
+}
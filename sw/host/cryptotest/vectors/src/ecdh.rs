@@ -0,0 +1,220 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! ECDH test vectors.
+
+use anyhow::{bail, Result};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::pkcs8::DecodePublicKey;
+use serde::Deserialize;
+
+use crate::rsp;
+use crate::wycheproof::{EcdhGroup, Expected, TestFile};
+
+/// An ECDH test case.  All integer values are big-endian.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EcdhTestCase {
+    pub vendor: String,
+    pub test_case_id: usize,
+    pub algorithm: String,
+    pub curve: String,
+    pub d: Vec<u8>,
+    pub qx: Vec<u8>,
+    pub qy: Vec<u8>,
+    pub z: Vec<u8>,
+    pub result: bool,
+}
+
+impl EcdhTestCase {
+    /// Returns the name of the test group: `${curve}`.
+    pub fn group(&self) -> String {
+        self.curve.clone()
+    }
+}
+
+/// Reads test cases in the cryptotest JSON format.
+pub fn load_json(text: &str) -> Result<Vec<EcdhTestCase>> {
+    Ok(serde_json::from_str(text)?)
+}
+
+/// Reads test cases from the NIST CAVP SP 800-56A
+/// `KAS_ECC_CDH_PrimitiveTest.txt` file.  Only the P-256 section is read.
+pub fn load_nist(text: &str) -> Result<Vec<EcdhTestCase>> {
+    let mut cases = Vec::new();
+    for record in rsp::parse(text, &[])? {
+        if record.section != ["P-256"] {
+            continue;
+        }
+        cases.push(EcdhTestCase {
+            vendor: "nist".into(),
+            test_case_id: record.get_usize("COUNT")?,
+            algorithm: "ecdh".into(),
+            curve: "p256".into(),
+            d: record.get_hex("dIUT")?,
+            qx: record.get_hex("QCAVSx")?,
+            qy: record.get_hex("QCAVSy")?,
+            z: record.get_hex("ZIUT")?,
+            result: true,
+        });
+    }
+    Ok(cases)
+}
+
+/// Decodes a DER `SubjectPublicKeyInfo` on `curve` into its affine
+/// coordinates.
+fn decode_public_key(curve: &str, der: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let point = match curve {
+        "p256" => p256::PublicKey::from_public_key_der(der)?
+            .to_encoded_point(false)
+            .as_bytes()
+            .to_vec(),
+        "p384" => p384::PublicKey::from_public_key_der(der)?
+            .to_encoded_point(false)
+            .as_bytes()
+            .to_vec(),
+        _ => bail!("Unsupported curve: {curve}"),
+    };
+    // An uncompressed point is encoded as `04 || x || y`.
+    let (x, y) = point[1..].split_at(point.len() / 2);
+    Ok((x.to_vec(), y.to_vec()))
+}
+
+/// Reads test cases from a Wycheproof `EcdhTest` file with ASN.1-encoded
+/// public keys.
+///
+/// Public keys that cannot be decoded, or which are not on the group's
+/// curve, are skipped.  Wycheproof marks valid tests using compressed public
+/// keys as "acceptable"; those are expected to succeed, while other
+/// "acceptable" tests are expected to be rejected.
+pub fn load_wycheproof(text: &str) -> Result<Vec<EcdhTestCase>> {
+    let file = TestFile::<EcdhGroup>::parse(text)?;
+    let mut cases = Vec::new();
+    for group in file.test_groups {
+        let curve = match group.curve.as_str() {
+            "secp256r1" => "p256",
+            "secp384r1" => "p384",
+            _ => {
+                log::info!("Skipped group: unsupported curve {}", group.curve);
+                continue;
+            }
+        };
+        if group.encoding != "asn" {
+            log::info!("Skipped group: unsupported encoding {}", group.encoding);
+            continue;
+        }
+        for test in group.tests {
+            if test.flags.iter().any(|f| f == "InvalidAsn") {
+                log::info!("Skipped tcId {}: invalid ASN.1", test.tc_id);
+                continue;
+            }
+            let (qx, qy) = match decode_public_key(curve, &hex::decode(&test.public)?) {
+                Ok(q) => q,
+                Err(e) => {
+                    log::info!("Skipped tcId {}: {e}", test.tc_id);
+                    continue;
+                }
+            };
+            let result = match test.result {
+                Expected::Acceptable => test.flags.iter().any(|f| f == "CompressedPoint"),
+                expected => expected.is_valid(),
+            };
+            cases.push(EcdhTestCase {
+                vendor: "wycheproof".into(),
+                test_case_id: test.tc_id,
+                algorithm: "ecdh".into(),
+                curve: curve.into(),
+                d: hex::decode(&test.private)?,
+                qx,
+                qy,
+                z: hex::decode(&test.shared)?,
+                result,
+            });
+        }
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NIST: &str = r#"
+#  CAVS 14.1
+#  ECC CDH Primitive (SP800-56A Section 5.7.1.2) Test Information for "testecccdh"
+
+[P-224]
+
+COUNT = 0
+QCAVSx = af33cd06
+QCAVSy = 882fc3e4
+dIUT = 8346a60f
+QIUTx = 8de2e269
+QIUTy = 4d95d8e1
+ZIUT = 7d96f9a3
+
+[P-256]
+
+COUNT = 0
+QCAVSx = 700c48f7
+QCAVSy = db71e509
+dIUT = 7d7dc5f7
+QIUTx = ead21859
+QIUTy = 28af61b5
+ZIUT = 46fc62106420ff012e54a434fbdd2d25ccc5852060561e68040dd7778997bd7b
+"#;
+
+    #[test]
+    fn test_nist() -> Result<()> {
+        let cases = load_nist(NIST)?;
+        // The P-224 section is not supported and is skipped.
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].group(), "p256");
+        assert_eq!(cases[0].qx, vec![0x70, 0x0c, 0x48, 0xf7]);
+        assert_eq!(cases[0].d, vec![0x7d, 0x7d, 0xc5, 0xf7]);
+        assert_eq!(cases[0].z.len(), 32);
+        Ok(())
+    }
+
+    // The public key of tcId 2 is the compressed encoding of tcId 1's; that of
+    // tcId 3 is a P-384 key.
+    const WYCHEPROOF: &str = r#"{
+        "algorithm": "ECDH",
+        "numberOfTests": 4,
+        "testGroups": [{
+            "type": "EcdhTest",
+            "curve": "secp256r1",
+            "encoding": "asn",
+            "tests": [
+                {"tcId": 1, "comment": "normal case", "flags": ["Normal"],
+                 "public": "3059301306072a8648ce3d020106082a8648ce3d030107034200041e18532fd4754c02f3041d9c75ceb33b83ffd81ac7ce4fe882ccb1c98bc5896ea46c311c4e2ff40dd96a3653e6e45445d32dfe486eced75c7a90c6a18881c0a3",
+                 "private": "00", "shared": "01", "result": "valid"},
+                {"tcId": 2, "comment": "compressed public key", "flags": ["CompressedPoint"],
+                 "public": "3039301306072a8648ce3d020106082a8648ce3d030107032200031e18532fd4754c02f3041d9c75ceb33b83ffd81ac7ce4fe882ccb1c98bc5896e",
+                 "private": "00", "shared": "01", "result": "acceptable"},
+                {"tcId": 3, "comment": "public key on another curve", "flags": ["InvalidCurveAttack"],
+                 "public": "3076301006072a8648ce3d020106052b8104002203620004b01bfc4a2d7fe2095e6627cc5caf9abd153d5b551fe7f6da615da9d1bfd101e8d191e85902c0a34eaa3a16e666880b73059f658c4d6a6e57c0959de80109b0fa6b56bd63d1d5701a93d15322f2267a15526641c60cd07c912a6df58aa993d89b",
+                 "private": "00", "shared": "", "result": "invalid"},
+                {"tcId": 4, "comment": "invalid asn", "flags": ["InvalidAsn"],
+                 "public": "30", "private": "00", "shared": "", "result": "invalid"}
+            ]
+        }]
+    }"#;
+
+    #[test]
+    fn test_wycheproof() -> Result<()> {
+        let cases = load_wycheproof(WYCHEPROOF)?;
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].test_case_id, 1);
+        assert_eq!(cases[0].curve, "p256");
+        assert_eq!(cases[0].qx[..4], [0x1e, 0x18, 0x53, 0x2f]);
+        assert_eq!(cases[0].qy.len(), 32);
+        assert!(cases[0].result);
+        // The compressed point decodes to the same public key and is expected
+        // to be accepted.
+        assert_eq!(cases[1].qx, cases[0].qx);
+        assert_eq!(cases[1].qy, cases[0].qy);
+        assert!(cases[1].result);
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! ECDSA test vectors.
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+use crate::rsp;
+use crate::wycheproof::{parse_der_signature, EcdsaVerifyGroup, TestFile};
+
+/// The hash algorithms supported by the cryptotest firmware.
+const SUPPORTED_HASHES: &[&str] = &[
+    "sha-256", "sha-384", "sha-512", "sha3-256", "sha3-384", "sha3-512",
+];
+
+fn scalar_zero() -> String {
+    String::from("00")
+}
+
+/// An ECDSA test case.  All integer values are big-endian hex strings.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EcdsaTestCase {
+    pub vendor: String,
+    pub test_case_id: usize,
+    pub algorithm: String,
+    pub operation: String,
+    pub curve: String,
+    pub hash_alg: String,
+    pub message: Vec<u8>,
+    pub qx: String,
+    pub qy: String,
+    #[serde(default = "scalar_zero")]
+    pub r: String,
+    #[serde(default = "scalar_zero")]
+    pub s: String,
+    #[serde(default)]
+    pub d: String,
+    pub result: bool,
+}
+
+impl EcdsaTestCase {
+    /// Returns the name of the test group: `${curve}/${hash_alg}`.
+    pub fn group(&self) -> String {
+        format!("{}/{}", self.curve, self.hash_alg)
+    }
+}
+
+/// Maps curve names used by NIST and Wycheproof to those used by cryptotest,
+/// returning the curve name and the length of its scalars in bytes.
+fn curve(name: &str) -> Option<(&'static str, usize)> {
+    match name {
+        "P-256" | "secp256r1" => Some(("p256", 32)),
+        "P-384" | "secp384r1" => Some(("p384", 48)),
+        _ => None,
+    }
+}
+
+fn hash_alg(name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    SUPPORTED_HASHES.contains(&name.as_str()).then_some(name)
+}
+
+/// Reads test cases in the cryptotest JSON format.
+pub fn load_json(text: &str) -> Result<Vec<EcdsaTestCase>> {
+    Ok(serde_json::from_str(text)?)
+}
+
+/// Reads test cases from a NIST CAVP FIPS 186-4 file: either `SigVer.rsp`
+/// (`operation = "verify"`) or `SigGen.txt` (`operation = "sign"`, which
+/// includes the private keys omitted from `SigGen.rsp`).  Sections for curves
+/// or hashes not supported by cryptotest are skipped.
+pub fn load_nist(text: &str, operation: &str) -> Result<Vec<EcdsaTestCase>> {
+    if operation != "sign" && operation != "verify" {
+        bail!("Unsupported ECDSA operation: {operation}");
+    }
+    let mut cases = Vec::new();
+    for record in rsp::parse(text, &[])? {
+        let [curve_name, hash_name] = record.section.as_slice() else {
+            bail!(
+                "line {}: unexpected section {:?}",
+                record.line,
+                record.section
+            );
+        };
+        let (Some((curve, _)), Some(hash_alg)) = (curve(curve_name), hash_alg(hash_name)) else {
+            continue;
+        };
+        let result = if operation == "verify" {
+            // NIST expresses the expected result as a string with a short
+            // description of the failure mode, e.g. `P (0 )` or
+            // `F (3 - S changed)`.
+            match record.get("Result")?.chars().next() {
+                Some('P') => true,
+                Some('F') => false,
+                _ => bail!("line {}: unknown verification result", record.line),
+            }
+        } else {
+            true
+        };
+        cases.push(EcdsaTestCase {
+            vendor: "nist".into(),
+            test_case_id: cases.len() + 1,
+            algorithm: "ecdsa".into(),
+            operation: operation.into(),
+            curve: curve.into(),
+            hash_alg,
+            message: record.get_hex("Msg")?,
+            qx: record.get("Qx")?.into(),
+            qy: record.get("Qy")?.into(),
+            r: record.get("R")?.into(),
+            s: record.get("S")?.into(),
+            d: if operation == "sign" {
+                record.get("d")?.into()
+            } else {
+                String::new()
+            },
+            result,
+        });
+    }
+    Ok(cases)
+}
+
+/// Reads test cases from a Wycheproof `EcdsaVerify` file.
+///
+/// The cryptolib does not accept DER-encoded signatures, so tests whose
+/// signatures are not strict DER encodings of in-range values are skipped.
+pub fn load_wycheproof(text: &str) -> Result<Vec<EcdsaTestCase>> {
+    let file = TestFile::<EcdsaVerifyGroup>::parse(text)?;
+    let mut cases = Vec::new();
+    for group in file.test_groups {
+        let key = &group.public_key;
+        let (Some((curve, scalar_len)), Some(hash_alg)) = (curve(&key.curve), hash_alg(&group.sha))
+        else {
+            log::info!("Skipped group: unsupported {} {}", key.curve, group.sha);
+            continue;
+        };
+        for test in group.tests {
+            let (r, s) = match parse_der_signature(&hex::decode(&test.sig)?, scalar_len) {
+                Ok(rs) => rs,
+                Err(e) => {
+                    log::info!("Skipped tcId {}: {e}", test.tc_id);
+                    continue;
+                }
+            };
+            cases.push(EcdsaTestCase {
+                vendor: "wycheproof".into(),
+                test_case_id: test.tc_id,
+                algorithm: "ecdsa".into(),
+                operation: "verify".into(),
+                curve: curve.into(),
+                hash_alg: hash_alg.clone(),
+                message: hex::decode(&test.msg)?,
+                qx: key.wx.clone(),
+                qy: key.wy.clone(),
+                r: hex::encode(r),
+                s: hex::encode(s),
+                d: String::new(),
+                result: test.result.is_valid(),
+            });
+        }
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGVER: &str = r#"
+[P-224,SHA-256]
+
+Msg = 00
+Qx = 01
+Qy = 02
+R = 03
+S = 04
+Result = P (0 )
+
+[P-256,SHA-256]
+
+Msg = e4796db5
+Qx = 87f8f2b2
+Qy = 0d0b9f5a
+R = 0a
+S = 0b
+Result = F (3 - S changed)
+
+Msg = 069a6e6b
+Qx = 5cf02a00
+Qy = 0f0c7c0b
+R = 0c
+S = 0d
+Result = P (0 )
+"#;
+
+    #[test]
+    fn test_nist_sig_ver() -> Result<()> {
+        let cases = load_nist(SIGVER, "verify")?;
+        // The P-224 section is not supported and is skipped.
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].group(), "p256/sha-256");
+        assert_eq!(cases[0].test_case_id, 1);
+        assert!(!cases[0].result);
+        assert!(cases[1].result);
+        assert_eq!(cases[1].message, vec![0x06, 0x9a, 0x6e, 0x6b]);
+        assert_eq!(cases[1].r, "0c");
+        Ok(())
+    }
+
+    const SIGGEN: &str = r#"
+[P-256,SHA-384]
+
+Msg = 5905
+d = 519b
+Qx = 1ccb
+Qy = ce4a
+k = 94a1
+R = f3ac
+S = 8bf7
+"#;
+
+    #[test]
+    fn test_nist_sig_gen() -> Result<()> {
+        let cases = load_nist(SIGGEN, "sign")?;
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].group(), "p256/sha-384");
+        assert_eq!(cases[0].d, "519b");
+        assert!(cases[0].result);
+        Ok(())
+    }
+
+    const WYCHEPROOF: &str = r#"{
+        "algorithm": "ECDSA",
+        "numberOfTests": 3,
+        "testGroups": [{
+            "type": "EcdsaVerify",
+            "publicKey": {
+                "type": "EcPublicKey",
+                "curve": "secp256r1",
+                "keySize": 256,
+                "wx": "2927b10512bae3eddcfe467828128bad2903269919f7086069c8c4df6c732838",
+                "wy": "00c7787964eaac00e5921fb1498a60f4606766b3d9685001558d1a974e7341513e"
+            },
+            "sha": "SHA-256",
+            "tests": [
+                {"tcId": 1, "comment": "valid", "flags": [], "msg": "313233343030",
+                 "sig": "3006020101020101", "result": "valid"},
+                {"tcId": 2, "comment": "BER length", "flags": ["BerEncodedSignature"],
+                 "msg": "313233343030", "sig": "308106020101020101", "result": "invalid"},
+                {"tcId": 3, "comment": "acceptable", "flags": [], "msg": "",
+                 "sig": "3006020102020100", "result": "acceptable"}
+            ]
+        }]
+    }"#;
+
+    #[test]
+    fn test_wycheproof() -> Result<()> {
+        let cases = load_wycheproof(WYCHEPROOF)?;
+        // The BER-encoded signature is skipped.
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].test_case_id, 1);
+        assert_eq!(cases[0].group(), "p256/sha-256");
+        assert_eq!(cases[0].r, "01");
+        assert!(cases[0].result);
+        assert_eq!(cases[1].test_case_id, 3);
+        assert_eq!(cases[1].s, "00");
+        assert!(!cases[1].result);
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hash function (SHA-2, SHA-3, SHAKE and cSHAKE) test vectors.
+
+use anyhow::{anyhow, bail, Result};
+use serde::Deserialize;
+
+use crate::rsp;
+
+/// A hash function test case.
+#[derive(Clone, Debug, Deserialize)]
+pub struct HashTestCase {
+    pub vendor: String,
+    pub test_case_id: usize,
+    pub algorithm: String,
+    pub message: Vec<u8>,
+    // Customization string used for cSHAKE only
+    #[serde(default)]
+    pub customization_string: Vec<u8>,
+    pub digest: Vec<u8>,
+    pub result: bool,
+}
+
+impl HashTestCase {
+    /// Returns the name of the test group: `${algorithm}`.
+    pub fn group(&self) -> String {
+        self.algorithm.clone()
+    }
+}
+
+/// Reads test cases in the cryptotest JSON format.
+pub fn load_json(text: &str) -> Result<Vec<HashTestCase>> {
+    Ok(serde_json::from_str(text)?)
+}
+
+/// Maps the name of a NIST CAVP hash response file (e.g.
+/// `SHA3_256ShortMsg.rsp`) to the name of its hash algorithm.
+pub fn nist_algorithm(file_name: &str) -> Result<&'static str> {
+    let algorithm = [
+        ("SHA256", "sha-256"),
+        ("SHA384", "sha-384"),
+        ("SHA512", "sha-512"),
+        ("SHA3_256", "sha3-256"),
+        ("SHA3_384", "sha3-384"),
+        ("SHA3_512", "sha3-512"),
+        ("SHAKE128", "shake-128"),
+        ("SHAKE256", "shake-256"),
+    ]
+    .into_iter()
+    .find_map(|(prefix, algorithm)| {
+        let rest = file_name.strip_prefix(prefix)?;
+        ["ShortMsg", "LongMsg", "VariableOut"]
+            .iter()
+            .any(|msg_type| rest.starts_with(msg_type))
+            .then_some(algorithm)
+    });
+    algorithm.ok_or_else(|| anyhow!("Unsupported hash test vector file: {file_name}"))
+}
+
+/// Reads test cases for `algorithm` from a NIST CAVP FIPS 180-4 or FIPS 202
+/// response file.  Test cases are numbered by `COUNT` where the file provides
+/// it and sequentially otherwise.
+pub fn load_nist(text: &str, algorithm: &str) -> Result<Vec<HashTestCase>> {
+    // The SHA functions use "MD" as the key for the message digest and the
+    // SHAKE functions use "Output".
+    let digest_key = if algorithm.starts_with("shake") {
+        "Output"
+    } else {
+        "MD"
+    };
+    let mut cases = Vec::new();
+    for record in rsp::parse(text, &[])? {
+        // The test vectors include a single placeholder zero byte if the
+        // message length is 0.
+        let message = match record.get_usize("Len") {
+            Ok(0) => Vec::new(),
+            _ => record.get_hex("Msg")?,
+        };
+        let test_case_id = if record.fields.contains_key("COUNT") {
+            record.get_usize("COUNT")?
+        } else {
+            cases.len() + 1
+        };
+        cases.push(HashTestCase {
+            vendor: "nist".into(),
+            test_case_id,
+            algorithm: algorithm.into(),
+            message,
+            customization_string: Vec::new(),
+            digest: record.get_hex(digest_key)?,
+            result: true,
+        });
+    }
+    Ok(cases)
+}
+
+/// A hand-written hash test vector in the `*_hardcoded.hjson` files.
+#[derive(Debug, Deserialize)]
+struct HjsonVector {
+    operation: String,
+    security_str: usize,
+    input_msg: String,
+    #[serde(default)]
+    cust_str: String,
+    digest: String,
+}
+
+/// Reads test cases from one of the hand-written `*_hardcoded.hjson` test
+/// vector files in `sw/device/tests/crypto/testvectors`.
+pub fn load_hjson(text: &str) -> Result<Vec<HashTestCase>> {
+    let vectors: Vec<HjsonVector> = deser_hjson::from_str(text)?;
+    let mut cases = Vec::new();
+    for v in vectors {
+        let algorithm = match (v.operation.as_str(), v.security_str) {
+            ("SHA3", 224 | 256 | 384 | 512) => format!("sha3-{}", v.security_str),
+            ("SHAKE" | "CSHAKE", 128 | 256) => {
+                format!("{}-{}", v.operation.to_lowercase(), v.security_str)
+            }
+            (op, bits) => bail!("Unsupported hash operation: {op} {bits}"),
+        };
+        cases.push(HashTestCase {
+            vendor: "manual".into(),
+            test_case_id: cases.len() + 1,
+            algorithm,
+            message: rsp::decode_hex(&v.input_msg)?,
+            customization_string: rsp::decode_hex(&v.cust_str)?,
+            digest: rsp::decode_hex(&v.digest)?,
+            result: true,
+        });
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nist_algorithm() -> Result<()> {
+        assert_eq!(nist_algorithm("SHA256ShortMsg.rsp")?, "sha-256");
+        assert_eq!(nist_algorithm("SHA3_384LongMsg.rsp")?, "sha3-384");
+        assert_eq!(nist_algorithm("SHAKE128VariableOut.rsp")?, "shake-128");
+        assert!(nist_algorithm("SHA224ShortMsg.rsp").is_err());
+        assert!(nist_algorithm("SHA256Monte.rsp").is_err());
+        Ok(())
+    }
+
+    const SHORT_MSG: &str = r#"
+#  CAVS 19.0
+#  "SHA3-256 ShortMsg" information for "SHA3AllBytes1-28-16"
+[L = 256]
+
+Len = 0
+Msg = 00
+MD = a7ffc6f8
+
+Len = 8
+Msg = e9
+MD = f0d04dd1
+"#;
+
+    #[test]
+    fn test_nist_short_msg() -> Result<()> {
+        let cases = load_nist(SHORT_MSG, "sha3-256")?;
+        assert_eq!(cases.len(), 2);
+        assert!(cases[0].message.is_empty());
+        assert_eq!(cases[0].test_case_id, 1);
+        assert_eq!(cases[0].digest, vec![0xa7, 0xff, 0xc6, 0xf8]);
+        assert_eq!(cases[0].group(), "sha3-256");
+        assert_eq!(cases[1].message, vec![0xe9]);
+        assert_eq!(cases[1].test_case_id, 2);
+        Ok(())
+    }
+
+    const VARIABLE_OUT: &str = r#"
+[Tested for Output of byte-oriented messages]
+[Input Length = 128]
+[Minimum Output Length (bits) = 128]
+
+COUNT = 1244
+Outputlen = 128
+Msg = 84e950051876050dc851fbd99e6247b8
+Output = 8599bd89f63a848c49ca593ec37a12c6
+"#;
+
+    #[test]
+    fn test_nist_variable_out() -> Result<()> {
+        let cases = load_nist(VARIABLE_OUT, "shake-128")?;
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].test_case_id, 1244);
+        assert_eq!(cases[0].message.len(), 16);
+        assert_eq!(cases[0].digest.len(), 16);
+        Ok(())
+    }
+
+    const HJSON: &str = r#"
+// Comments are allowed.
+[
+  {
+    vector_identifier: "NIST CAVP, byte-oriented, SHAKE128ShortMsg_msg.rsp, Len = 56"
+    operation: SHAKE
+    security_str: 128
+    input_msg: 0x7216a825029da1
+    digest: 0x9de6ffacf3e59693a3de81b02f7db77a
+  }
+  {
+    vector_identifier: "NIST example values"
+    operation: CSHAKE
+    security_str: 256
+    input_msg: 0x00010203
+    cust_str: 0x456d61696c205369676e6174757265
+    digest: 0xd008828e2b80ac9d
+  }
+]
+"#;
+
+    #[test]
+    fn test_hjson() -> Result<()> {
+        let cases = load_hjson(HJSON)?;
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].algorithm, "shake-128");
+        assert_eq!(cases[0].message.len(), 7);
+        assert!(cases[0].customization_string.is_empty());
+        assert_eq!(cases[1].algorithm, "cshake-256");
+        assert_eq!(cases[1].test_case_id, 2);
+        assert_eq!(cases[1].customization_string, b"Email Signature");
+        Ok(())
+    }
+}
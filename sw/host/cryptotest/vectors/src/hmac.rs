@@ -0,0 +1,185 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! HMAC test vectors.
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::rsp;
+use crate::wycheproof::{MacGroup, TestFile};
+
+/// An HMAC test case.
+#[derive(Clone, Debug, Deserialize)]
+pub struct HmacTestCase {
+    pub vendor: String,
+    pub test_case_id: usize,
+    pub algorithm: String,
+    pub hash_alg: String,
+    pub key: Vec<u8>,
+    pub message: Vec<u8>,
+    pub tag: Vec<u8>,
+    pub result: bool,
+}
+
+impl HmacTestCase {
+    /// Returns the name of the test group: `${hash_alg}`.
+    pub fn group(&self) -> String {
+        self.hash_alg.clone()
+    }
+}
+
+/// Reads test cases in the cryptotest JSON format.
+pub fn load_json(text: &str) -> Result<Vec<HmacTestCase>> {
+    Ok(serde_json::from_str(text)?)
+}
+
+/// Reads test cases from the NIST CAVP FIPS 198-1 `HMAC.rsp` file.  The hash
+/// function of each section is identified by its output length `L`; sections
+/// for hash functions not supported by cryptotest (SHA-1 and SHA-224) are
+/// skipped.  `Count` restarts in every section, so test cases are numbered
+/// sequentially instead.
+pub fn load_nist(text: &str) -> Result<Vec<HmacTestCase>> {
+    let mut cases = Vec::new();
+    for record in rsp::parse(text, &[])? {
+        let hash_alg = match record.get_usize("L")? {
+            32 => "sha-256",
+            48 => "sha-384",
+            64 => "sha-512",
+            _ => continue,
+        };
+        cases.push(HmacTestCase {
+            vendor: "nist".into(),
+            test_case_id: cases.len() + 1,
+            algorithm: "hmac".into(),
+            hash_alg: hash_alg.into(),
+            key: record.get_hex("Key")?,
+            message: record.get_hex("Msg")?,
+            tag: record.get_hex("Mac")?,
+            result: true,
+        });
+    }
+    Ok(cases)
+}
+
+/// Maps a Wycheproof HMAC algorithm name (e.g. `HMACSHA3-256`) to the hash
+/// algorithm name used by cryptotest.
+fn hash_alg(algorithm: &str) -> Option<&'static str> {
+    match algorithm {
+        "HMACSHA256" => Some("sha-256"),
+        "HMACSHA384" => Some("sha-384"),
+        "HMACSHA512" => Some("sha-512"),
+        "HMACSHA3-256" => Some("sha3-256"),
+        "HMACSHA3-384" => Some("sha3-384"),
+        "HMACSHA3-512" => Some("sha3-512"),
+        _ => None,
+    }
+}
+
+/// Reads test cases from a Wycheproof `MacTest` file for HMAC.  Files for hash
+/// functions not supported by cryptotest yield no test cases.
+pub fn load_wycheproof(text: &str) -> Result<Vec<HmacTestCase>> {
+    let file = TestFile::<MacGroup>::parse(text)?;
+    let Some(hash_alg) = hash_alg(&file.algorithm) else {
+        log::info!("Skipped file: unsupported algorithm {}", file.algorithm);
+        return Ok(Vec::new());
+    };
+    let mut cases = Vec::new();
+    for group in file.test_groups {
+        for test in group.tests {
+            cases.push(HmacTestCase {
+                vendor: "wycheproof".into(),
+                test_case_id: test.tc_id,
+                algorithm: "hmac".into(),
+                hash_alg: hash_alg.into(),
+                key: hex::decode(&test.key)?,
+                message: hex::decode(&test.msg)?,
+                tag: hex::decode(&test.tag)?,
+                result: test.result.is_valid(),
+            });
+        }
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NIST: &str = r#"
+#  CAVS 11.0
+#  "HMAC" information
+[L=20]
+
+Count = 0
+Klen = 10
+Tlen = 10
+Key = 82f3b69a1bff4de15c33
+Msg = fcd6d98bef45ed6850806e96f255fa0c
+Mac = 1ba0e66cf72efc349207
+
+[L=32]
+
+Count = 30
+Klen = 40
+Tlen = 16
+Key = 6f35
+Msg = 7527
+Mac = 05d1
+
+[L=48]
+
+Count = 30
+Klen = 40
+Tlen = 24
+Key = 8f4d
+Msg = 4d11
+Mac = 77c2
+"#;
+
+    #[test]
+    fn test_nist() -> Result<()> {
+        let cases = load_nist(NIST)?;
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].group(), "sha-256");
+        assert_eq!(cases[0].test_case_id, 1);
+        assert_eq!(cases[0].key, vec![0x6f, 0x35]);
+        assert_eq!(cases[0].tag, vec![0x05, 0xd1]);
+        // `Count` repeats across sections but test case ids are unique.
+        assert_eq!(cases[1].group(), "sha-384");
+        assert_eq!(cases[1].test_case_id, 2);
+        Ok(())
+    }
+
+    const WYCHEPROOF: &str = r#"{
+        "algorithm": "HMACSHA3-256",
+        "numberOfTests": 2,
+        "testGroups": [{
+            "type": "MacTest",
+            "keySize": 256,
+            "tagSize": 128,
+            "tests": [
+                {"tcId": 1, "comment": "empty message", "flags": [],
+                 "key": "00", "msg": "", "tag": "ab", "result": "valid"},
+                {"tcId": 2, "comment": "modified tag", "flags": ["ModifiedTag"],
+                 "key": "00", "msg": "01", "tag": "cd", "result": "invalid"}
+            ]
+        }]
+    }"#;
+
+    #[test]
+    fn test_wycheproof() -> Result<()> {
+        let cases = load_wycheproof(WYCHEPROOF)?;
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].group(), "sha3-256");
+        assert!(cases[0].message.is_empty());
+        assert!(cases[0].result);
+        assert_eq!(cases[1].test_case_id, 2);
+        assert!(!cases[1].result);
+
+        let sha1 = WYCHEPROOF.replace("HMACSHA3-256", "HMACSHA1");
+        assert!(load_wycheproof(&sha1)?.is_empty());
+        Ok(())
+    }
+}
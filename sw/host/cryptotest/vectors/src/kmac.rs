@@ -0,0 +1,123 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! KMAC test vectors.
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::wycheproof::{MacGroup, TestFile};
+
+/// The longest key supported by the KMAC hardware.
+const MAX_KEY_BYTES: usize = 64;
+
+/// A KMAC test case.  `mode` is the security strength (128 or 256).
+#[derive(Clone, Debug, Deserialize)]
+pub struct KmacTestCase {
+    pub vendor: String,
+    pub test_case_id: usize,
+    pub algorithm: String,
+    pub mode: usize,
+    pub key: Vec<u8>,
+    pub message: Vec<u8>,
+    pub customization_string: Vec<u8>,
+    pub tag: Vec<u8>,
+    pub result: bool,
+}
+
+impl KmacTestCase {
+    /// Returns the name of the test group: `${algorithm}${mode}`.
+    pub fn group(&self) -> String {
+        format!("{}{}", self.algorithm, self.mode)
+    }
+}
+
+/// Reads test cases in the cryptotest JSON format.
+pub fn load_json(text: &str) -> Result<Vec<KmacTestCase>> {
+    Ok(serde_json::from_str(text)?)
+}
+
+/// Reads test cases from a Wycheproof `MacTest` file for KMAC without a
+/// customization string.  Files for other algorithms yield no test cases, and
+/// tests whose keys are longer than the hardware supports are skipped.
+pub fn load_wycheproof(text: &str) -> Result<Vec<KmacTestCase>> {
+    let file = TestFile::<MacGroup>::parse(text)?;
+    let mode = match file.algorithm.as_str() {
+        "KMAC128" => 128,
+        "KMAC256" => 256,
+        _ => {
+            log::info!("Skipped file: unsupported algorithm {}", file.algorithm);
+            return Ok(Vec::new());
+        }
+    };
+    let mut cases = Vec::new();
+    for group in file.test_groups {
+        for test in group.tests {
+            let key = hex::decode(&test.key)?;
+            if key.len() > MAX_KEY_BYTES {
+                log::info!("Skipped tcId {}: key too long", test.tc_id);
+                continue;
+            }
+            cases.push(KmacTestCase {
+                vendor: "wycheproof".into(),
+                test_case_id: test.tc_id,
+                algorithm: "kmac".into(),
+                mode,
+                key,
+                message: hex::decode(&test.msg)?,
+                customization_string: Vec::new(),
+                tag: hex::decode(&test.tag)?,
+                result: test.result.is_valid(),
+            });
+        }
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WYCHEPROOF: &str = r#"{
+        "algorithm": "KMAC256",
+        "numberOfTests": 3,
+        "testGroups": [{
+            "type": "MacTest",
+            "keySize": 256,
+            "tagSize": 256,
+            "tests": [
+                {"tcId": 1, "comment": "empty message", "flags": [],
+                 "key": "00", "msg": "", "tag": "ab", "result": "valid"},
+                {"tcId": 2, "comment": "modified tag", "flags": ["ModifiedTag"],
+                 "key": "00", "msg": "01", "tag": "cd", "result": "invalid"}
+            ]
+        }, {
+            "type": "MacTest",
+            "keySize": 1024,
+            "tagSize": 256,
+            "tests": [
+                {"tcId": 3, "comment": "long key", "flags": [],
+                 "key": "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f40",
+                 "msg": "", "tag": "ef", "result": "valid"}
+            ]
+        }]
+    }"#;
+
+    #[test]
+    fn test_wycheproof() -> Result<()> {
+        let cases = load_wycheproof(WYCHEPROOF)?;
+        // The test with a 65-byte key is skipped.
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].mode, 256);
+        assert_eq!(cases[0].group(), "kmac256");
+        assert!(cases[0].message.is_empty());
+        assert!(cases[0].result);
+        assert_eq!(cases[1].test_case_id, 2);
+        assert!(!cases[1].result);
+
+        let hmac = WYCHEPROOF.replace("KMAC256", "HMACSHA256");
+        assert!(load_wycheproof(&hmac)?.is_empty());
+        Ok(())
+    }
+}
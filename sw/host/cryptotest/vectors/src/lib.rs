@@ -0,0 +1,125 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Loaders for cryptotest test vectors.
+//!
+//! This crate reads upstream test vector files (NIST `.rsp` files, Wycheproof
+//! JSON files and the hand-written hjson files) directly into the test case
+//! types consumed by the cryptotest KAT harnesses, so the harnesses do not
+//! depend on pre-converted JSON.  The harnesses can still read the legacy cryptotest JSON format.
+
+use anyhow::{bail, Result};
+
+pub mod aes;
+pub mod drbg;
+pub mod ecdh;
+pub mod ecdsa;
+pub mod hash;
+pub mod hmac;
+pub mod kmac;
+pub mod rsp;
+pub mod sphincsplus;
+pub mod wycheproof;
+
+/// Selects test vectors by group and test case id.
+#[derive(Clone, Debug, Default, clap::Args)]
+pub struct Selection {
+    /// Only run test vectors in these groups.  A group is selected by its
+    /// full name (e.g. `p256/sha-256`) or by any `/`-separated prefix of its
+    /// name (e.g. `p256`).
+    #[arg(long = "group")]
+    pub groups: Vec<String>,
+
+    /// Only run the test vectors with these test case ids.
+    #[arg(long = "tc-id")]
+    pub tc_ids: Vec<usize>,
+}
+
+impl Selection {
+    /// Returns whether the test vector `tc_id` in `group` is selected.
+    pub fn matches(&self, group: &str, tc_id: usize) -> bool {
+        let group_ok = self.groups.is_empty()
+            || self.groups.iter().any(|g| {
+                group == g
+                    || group
+                        .strip_prefix(g.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            });
+        let tc_ok = self.tc_ids.is_empty() || self.tc_ids.contains(&tc_id);
+        group_ok && tc_ok
+    }
+}
+
+/// Collects and reports per-vector results.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub passed: usize,
+    pub failures: Vec<String>,
+}
+
+impl Report {
+    /// Records the result of a single test vector.
+    pub fn record(&mut self, vendor: &str, group: &str, tc_id: usize, passed: bool) {
+        let name = format!("{vendor} {group} tcId={tc_id}");
+        if passed {
+            log::info!("PASS {name}");
+            self.passed += 1;
+        } else {
+            log::error!("FAIL {name}");
+            self.failures.push(name);
+        }
+    }
+
+    /// Returns an error if any test vector failed.
+    pub fn check(&self) -> Result<()> {
+        let total = self.passed + self.failures.len();
+        log::info!("Passed {} of {} test vectors", self.passed, total);
+        if !self.failures.is_empty() {
+            bail!(
+                "Failed {} out of {} tests. Failures: {:?}",
+                self.failures.len(),
+                total,
+                self.failures
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection() {
+        let all = Selection::default();
+        assert!(all.matches("p384/sha-512", 7));
+
+        let sel = Selection {
+            groups: vec!["p256".into(), "p384/sha-384".into()],
+            tc_ids: vec![],
+        };
+        assert!(sel.matches("p256/sha-256", 1));
+        assert!(sel.matches("p384/sha-384", 1));
+        assert!(!sel.matches("p384/sha-512", 1));
+        assert!(!sel.matches("p2560/sha-256", 1));
+
+        let sel = Selection {
+            groups: vec![],
+            tc_ids: vec![3, 5],
+        };
+        assert!(sel.matches("sha-256", 3));
+        assert!(!sel.matches("sha-256", 4));
+    }
+
+    #[test]
+    fn test_report() {
+        let mut report = Report::default();
+        report.record("nist", "sha-256", 1, true);
+        assert!(report.check().is_ok());
+        report.record("wycheproof", "sha-256", 2, false);
+        assert_eq!(report.failures, vec!["wycheproof sha-256 tcId=2"]);
+        assert!(report.check().is_err());
+    }
+}
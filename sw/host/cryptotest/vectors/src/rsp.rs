@@ -0,0 +1,241 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parser for NIST CAVP response (`.rsp`) files.
+//!
+//! NIST response files provide the expected results of cryptographic
+//! operations.  No formal standard exists for these files, but they generally
+//! take the following format:
+//! - Lines beginning with a `[` are headers.  Headers contain either a bare
+//!   name, like `[Section Name]`, a single key-value pair, like
+//!   `[Hash = SHA-256]`, or a list of strings, like `[P-256,SHA-256]`.
+//! - A line that starts with a `#` is a comment and is ignored.
+//! - Other non-blank lines contain a single key-value pair.
+//!
+//! Key-value pairs in headers apply to all of the records which follow them,
+//! as do the pairs whose keys are listed as "persistent" by the caller.  A
+//! group of non-header key-value lines (delimited by blank lines or headers)
+//! constitutes a record unless it contains a persistent key.  A key repeated
+//! within a single record keeps all of its values.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::{BTreeMap, HashSet};
+
+/// A single record from an `.rsp` file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RspRecord {
+    /// The line number on which the record starts.
+    pub line: usize,
+    /// The name of the section containing the record (the comma-separated
+    /// elements of the most recent bare header).
+    pub section: Vec<String>,
+    /// The record's key-value pairs, including those inherited from headers
+    /// and persistent keys.
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+impl RspRecord {
+    /// Returns the (first) value of `key`.
+    pub fn get(&self, key: &str) -> Result<&str> {
+        self.fields
+            .get(key)
+            .and_then(|v| v.first())
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("line {}: missing field {key:?}", self.line))
+    }
+
+    /// Returns all of the values of `key`.
+    pub fn get_all(&self, key: &str) -> &[String] {
+        self.fields.get(key).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns the (first) value of `key` decoded from hex.
+    pub fn get_hex(&self, key: &str) -> Result<Vec<u8>> {
+        decode_hex(self.get(key)?).with_context(|| format!("line {}: field {key:?}", self.line))
+    }
+
+    /// Returns the (first) value of `key` parsed as an integer.
+    pub fn get_usize(&self, key: &str) -> Result<usize> {
+        self.get(key)?
+            .parse()
+            .with_context(|| format!("line {}: field {key:?}", self.line))
+    }
+}
+
+/// Decodes a hex string, tolerating an odd number of digits (as NIST does for
+/// integer values with a leading zero nibble).
+pub fn decode_hex(s: &str) -> Result<Vec<u8>> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if s.len() % 2 == 1 {
+        Ok(hex::decode(format!("0{s}"))?)
+    } else {
+        Ok(hex::decode(s)?)
+    }
+}
+
+/// Parses the text of an `.rsp` file into records.  Keys listed in `persists`
+/// apply to all subsequent records rather than forming records of their own.
+pub fn parse(text: &str, persists: &[&str]) -> Result<Vec<RspRecord>> {
+    let mut section = Vec::new();
+    let mut persistent = BTreeMap::<String, Vec<String>>::new();
+    let mut records = Vec::new();
+    let mut record: Option<RspRecord> = None;
+    let mut seen = HashSet::new();
+    let mut exclude = false;
+
+    let mut finish = |record: &mut Option<RspRecord>, exclude: &mut bool| {
+        if let Some(r) = record.take() {
+            if !*exclude {
+                records.push(r);
+            }
+        }
+        *exclude = false;
+    };
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        } else if line.starts_with('[') {
+            finish(&mut record, &mut exclude);
+            seen.clear();
+            let header = line.trim_start_matches('[').trim_end_matches(']');
+            match header.split_once('=') {
+                Some((k, v)) => {
+                    persistent.insert(k.trim().into(), vec![v.trim().into()]);
+                }
+                None => {
+                    section = header.split(',').map(|s| s.trim().to_string()).collect();
+                }
+            }
+        } else if line.is_empty() {
+            finish(&mut record, &mut exclude);
+            seen.clear();
+        } else if let Some((k, v)) = line.split_once('=') {
+            let (k, v) = (k.trim(), v.trim().to_string());
+            let r = record.get_or_insert_with(|| RspRecord {
+                line: n + 1,
+                section: section.clone(),
+                fields: persistent.clone(),
+            });
+            if persists.contains(&k) {
+                persistent.insert(k.into(), vec![v.clone()]);
+                exclude = true;
+            }
+            // A key repeated within the record accumulates values; otherwise
+            // the value replaces any inherited from a header or persistent key.
+            if seen.insert(k.to_string()) {
+                r.fields.insert(k.into(), vec![v]);
+            } else if let Some(values) = r.fields.get_mut(k) {
+                values.push(v);
+            }
+        } else {
+            return Err(anyhow!("line {}: cannot parse {line:?}", n + 1));
+        }
+    }
+    finish(&mut record, &mut exclude);
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGVER: &str = r#"
+#  CAVS 11.0
+#  "SigVer" information
+[P-256,SHA-256]
+
+Msg = e4796db5
+Qx = 87f8f2b2
+Qy = 0d0b9f5a
+R = 0a
+S = 0b
+Result = F (3 - S changed)
+
+Msg = 069a6e6b
+Qx = 5cf02a00
+Qy = 0f0c7c0b
+R = 0c
+S = 0d
+Result = P (0 )
+
+[P-384,SHA-512]
+
+Msg = 0102
+Qx = 03
+Qy = 04
+R = 05
+S = 06
+Result = P (0 )
+"#;
+
+    #[test]
+    fn test_sections() -> Result<()> {
+        let records = parse(SIGVER, &[])?;
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].section, vec!["P-256", "SHA-256"]);
+        assert_eq!(records[0].get("Result")?, "F (3 - S changed)");
+        assert_eq!(records[1].get_hex("Msg")?, vec![0x06, 0x9a, 0x6e, 0x6b]);
+        assert_eq!(records[2].section, vec!["P-384", "SHA-512"]);
+        assert_eq!(records[2].line, 22);
+        assert!(records[2].get("d").is_err());
+        Ok(())
+    }
+
+    const HMAC: &str = r#"
+[L=20]
+
+Count = 0
+Klen = 10
+Tlen = 10
+Key = 82f3b69a
+Msg = 63
+Mac = 4c
+
+[L=32]
+
+Count = 0
+Klen = 40
+Tlen = 16
+Key = 6f35
+Msg = 75
+Mac = 2f
+"#;
+
+    #[test]
+    fn test_header_values() -> Result<()> {
+        let records = parse(HMAC, &[])?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].get_usize("L")?, 20);
+        assert_eq!(records[1].get_usize("L")?, 32);
+        assert_eq!(records[1].get_usize("Count")?, 0);
+        Ok(())
+    }
+
+    const PERSIST: &str = r#"
+Len = 8
+AdditionalInput = 01
+AdditionalInput = 02
+
+COUNT = 0
+EntropyInput = aa
+AdditionalInput = 03
+AdditionalInput = 04
+
+Mod = 2
+COUNT = 1
+"#;
+
+    #[test]
+    fn test_persists_and_repeats() -> Result<()> {
+        let records = parse(PERSIST, &["Mod"])?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].get_all("AdditionalInput"), &["01", "02"]);
+        assert_eq!(records[1].get_all("AdditionalInput"), &["03", "04"]);
+        assert!(records[1].get("Mod").is_err());
+        assert_eq!(decode_hex("abc")?, vec![0x0a, 0xbc]);
+        Ok(())
+    }
+}
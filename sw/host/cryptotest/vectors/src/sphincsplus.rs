@@ -0,0 +1,114 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! SPHINCS+ test vectors.
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+use crate::rsp;
+
+/// A SPHINCS+ test case.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SphincsPlusTestCase {
+    pub vendor: String,
+    pub test_case_id: usize,
+    pub algorithm: String,
+    pub operation: String,
+    pub hash_alg: String,
+    pub public: Vec<u8>,
+    pub message: Vec<u8>,
+    pub signature: Vec<u8>,
+    pub result: bool,
+}
+
+impl SphincsPlusTestCase {
+    /// Returns the name of the test group: `${hash_alg}`.
+    pub fn group(&self) -> String {
+        self.hash_alg.clone()
+    }
+}
+
+/// Reads test cases in the cryptotest JSON format.
+pub fn load_json(text: &str) -> Result<Vec<SphincsPlusTestCase>> {
+    Ok(serde_json::from_str(text)?)
+}
+
+/// Maps the path of a SPHINCS+ NIST PQC KAT file (e.g.
+/// `sphincs-sha256-128s-simple/PQCsignKAT_64.rsp`) to the name of its hash
+/// algorithm.
+pub fn nist_hash_alg(path: &str) -> Result<&'static str> {
+    if path.contains("sphincs-sha256-") {
+        Ok("sha-256")
+    } else if path.contains("sphincs-shake256-") {
+        Ok("shake-256")
+    } else {
+        bail!("Unsupported SPHINCS+ test vector file: {path}")
+    }
+}
+
+/// Reads signature verification test cases for a parameter set using
+/// `hash_alg` from a NIST PQC KAT response file (`PQCsignKAT_*.rsp`).
+pub fn load_nist(text: &str, hash_alg: &str) -> Result<Vec<SphincsPlusTestCase>> {
+    let mut cases = Vec::new();
+    for record in rsp::parse(text, &[])? {
+        let message = record.get_hex("msg")?;
+        // The signed message `sm` is the signature followed by the message.
+        let signed = record.get_hex("sm")?;
+        let Some(signature) = signed.strip_suffix(message.as_slice()) else {
+            bail!("line {}: signed message does not end with msg", record.line);
+        };
+        cases.push(SphincsPlusTestCase {
+            vendor: "sphincs+".into(),
+            test_case_id: record.get_usize("count")?,
+            algorithm: "sphincs+".into(),
+            operation: "verify".into(),
+            hash_alg: hash_alg.into(),
+            public: record.get_hex("pk")?,
+            signature: signature.to_vec(),
+            message,
+            result: true,
+        });
+    }
+    Ok(cases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NIST: &str = r#"
+# sphincs-sha256-128s-simple
+
+count = 0
+seed = 061550234d158c5e
+mlen = 3
+msg = d81c4d
+pk = b505d7cfad1b4974
+sk = 7c9935a0b07694aa
+smlen = 7
+sm = 0102030465d81c4d
+"#;
+
+    #[test]
+    fn test_nist() -> Result<()> {
+        assert_eq!(
+            nist_hash_alg("sphincs-sha256-128s-simple/PQCsignKAT_64.rsp")?,
+            "sha-256"
+        );
+        assert!(nist_hash_alg("sphincs-haraka-128s-simple/PQCsignKAT_64.rsp").is_err());
+
+        let cases = load_nist(NIST, "sha-256")?;
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].test_case_id, 0);
+        assert_eq!(cases[0].group(), "sha-256");
+        assert_eq!(cases[0].message, vec![0xd8, 0x1c, 0x4d]);
+        assert_eq!(cases[0].signature, vec![0x01, 0x02, 0x03, 0x04, 0x65]);
+        assert_eq!(cases[0].public.len(), 8);
+
+        let bad = NIST.replace("sm = 0102030465d81c4d", "sm = 0102030465d81c4e");
+        assert!(load_nist(&bad, "sha-256").is_err());
+        Ok(())
+    }
+}
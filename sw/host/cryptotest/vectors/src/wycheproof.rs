@@ -0,0 +1,206 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Types for the Wycheproof test vector JSON files.
+//!
+//! Each Wycheproof file contains a list of test groups.  A group holds the
+//! parameters shared by its tests (e.g. the public key and hash function),
+//! and each test is identified by a `tcId` that is unique within the file.
+//! See <https://github.com/C2SP/wycheproof/tree/master/schemas>.
+
+use anyhow::{bail, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// A Wycheproof test vector file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestFile<G> {
+    pub algorithm: String,
+    #[serde(default)]
+    pub number_of_tests: usize,
+    pub test_groups: Vec<G>,
+}
+
+impl<G: DeserializeOwned> TestFile<G> {
+    /// Parses a Wycheproof test vector file.
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+}
+
+/// The expected result of a Wycheproof test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expected {
+    Valid,
+    Invalid,
+    Acceptable,
+}
+
+impl Expected {
+    /// Returns whether the operation is expected to succeed.  We err on the
+    /// side of caution and expect "acceptable" inputs to be rejected.
+    pub fn is_valid(&self) -> bool {
+        *self == Expected::Valid
+    }
+}
+
+/// An elliptic curve public key.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EcPublicKey {
+    pub curve: String,
+    pub wx: String,
+    pub wy: String,
+}
+
+/// A test group for `EcdsaVerify` files.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EcdsaVerifyGroup {
+    pub public_key: EcPublicKey,
+    pub sha: String,
+    pub tests: Vec<SignatureTest>,
+}
+
+/// A signature verification test.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureTest {
+    pub tc_id: usize,
+    #[serde(default)]
+    pub comment: String,
+    pub msg: String,
+    pub sig: String,
+    pub result: Expected,
+    #[serde(default)]
+    pub flags: Vec<String>,
+}
+
+/// A test group for `MacTest` files.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacGroup {
+    pub key_size: usize,
+    pub tag_size: usize,
+    pub tests: Vec<MacTest>,
+}
+
+/// A MAC test.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacTest {
+    pub tc_id: usize,
+    #[serde(default)]
+    pub comment: String,
+    pub key: String,
+    pub msg: String,
+    pub tag: String,
+    pub result: Expected,
+    #[serde(default)]
+    pub flags: Vec<String>,
+}
+
+/// A test group for `EcdhTest` files.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EcdhGroup {
+    pub curve: String,
+    pub encoding: String,
+    pub tests: Vec<EcdhTest>,
+}
+
+/// An ECDH shared secret test.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EcdhTest {
+    pub tc_id: usize,
+    #[serde(default)]
+    pub comment: String,
+    pub public: String,
+    pub private: String,
+    pub shared: String,
+    pub result: Expected,
+    #[serde(default)]
+    pub flags: Vec<String>,
+}
+
+/// Parses a DER-encoded ECDSA signature (a SEQUENCE of two INTEGERs) into the
+/// big-endian values of `r` and `s`, rejecting anything that is not strict
+/// DER or whose values are longer than `max_len` bytes.
+pub fn parse_der_signature(der: &[u8], max_len: usize) -> Result<(Vec<u8>, Vec<u8>)> {
+    fn read_tlv(data: &[u8], tag: u8) -> Result<(&[u8], &[u8])> {
+        match data {
+            [t, ..] if *t != tag => bail!("unexpected tag {t:#04x}"),
+            [_, len @ 0..=0x7f, rest @ ..] => {
+                let len = *len as usize;
+                if rest.len() < len {
+                    bail!("truncated value");
+                }
+                Ok(rest.split_at(len))
+            }
+            [_, 0x81, len @ 0x80..=0xff, rest @ ..] => {
+                let len = *len as usize;
+                if rest.len() < len {
+                    bail!("truncated value");
+                }
+                Ok(rest.split_at(len))
+            }
+            _ => bail!("bad or non-minimal length"),
+        }
+    }
+
+    fn read_uint(data: &[u8], max_len: usize) -> Result<(Vec<u8>, &[u8])> {
+        let (value, rest) = read_tlv(data, 0x02)?;
+        let value = match value {
+            [] => bail!("empty integer"),
+            [b, ..] if b & 0x80 != 0 => bail!("negative integer"),
+            [0, b, ..] if b & 0x80 == 0 => bail!("non-minimal integer"),
+            [0, v @ ..] if !v.is_empty() => v,
+            v => v,
+        };
+        if value.len() > max_len {
+            bail!("integer too large");
+        }
+        Ok((value.to_vec(), rest))
+    }
+
+    let (seq, rest) = read_tlv(der, 0x30)?;
+    if !rest.is_empty() {
+        bail!("trailing data after signature");
+    }
+    let (r, seq) = read_uint(seq, max_len)?;
+    let (s, seq) = read_uint(seq, max_len)?;
+    if !seq.is_empty() {
+        bail!("trailing data in signature sequence");
+    }
+    Ok((r, s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_der_signature() -> Result<()> {
+        let sig = hex::decode("3007020101020200ff")?;
+        assert_eq!(parse_der_signature(&sig, 32)?, (vec![0x01], vec![0xff]));
+        // A leading zero is only permitted when the next byte has its top bit set.
+        let sig = hex::decode("300702020001020101")?;
+        assert!(parse_der_signature(&sig, 32).is_err());
+        // Negative values are rejected.
+        let sig = hex::decode("30060201ff020101")?;
+        assert!(parse_der_signature(&sig, 32).is_err());
+        // Trailing data is rejected.
+        let sig = hex::decode("300602010102010100")?;
+        assert!(parse_der_signature(&sig, 32).is_err());
+        // Non-minimal lengths are rejected.
+        let sig = hex::decode("308106020101020101")?;
+        assert!(parse_der_signature(&sig, 32).is_err());
+        // Values longer than the curve's scalar size are rejected.
+        let sig = hex::decode("3007020201020201ff")?;
+        assert!(parse_der_signature(&sig, 1).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{Context, Result};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::Attribute;
+use cryptoki::session::Session;
+use serde::{Deserialize, Serialize};
+use serde_annotate::Annotate;
+use std::any::Any;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::commands::{BasicResult, Dispatch};
+use crate::error::HsmError;
+use crate::module::Module;
+use crate::util::approval::ApprovalToken;
+use crate::util::attribute::KeyType;
+use crate::util::helper;
+
+/// Create an approval token for a command, signed with the approver's
+/// ECDSA P-256 key.  The command's input files must be present, as the token
+/// also approves their contents.
+#[derive(clap::Args, Debug, Serialize, Deserialize)]
+pub struct Create {
+    /// The id of the approver's signing key.
+    #[arg(long)]
+    id: Option<String>,
+    /// The label of the approver's signing key.
+    #[arg(short, long)]
+    label: Option<String>,
+    /// The name of the approver as it appears in the approval policy.
+    #[arg(long)]
+    approver: String,
+    /// How long the approval remains valid.
+    #[arg(long, default_value = "1d", value_parser = humantime::parse_duration)]
+    #[serde(with = "humantime_serde")]
+    expires_in: Duration,
+    /// The command to approve, as printed by `hsmtool --show-json`.
+    command: PathBuf,
+    /// The output file for the approval token.
+    output: PathBuf,
+}
+
+#[typetag::serde(name = "approval-create")]
+impl Dispatch for Create {
+    fn run(
+        &self,
+        _context: &dyn Any,
+        _hsm: &Module,
+        session: Option<&Session>,
+    ) -> Result<Box<dyn Annotate>> {
+        let session = session.ok_or(HsmError::SessionRequired)?;
+        let mut attrs = helper::search_spec(self.id.as_deref(), self.label.as_deref())?;
+        attrs.push(Attribute::KeyType(KeyType::Ec.try_into()?));
+        attrs.push(Attribute::Sign(true));
+        let object = helper::find_one_object(session, &attrs)?;
+
+        let command = std::fs::read_to_string(&self.command)
+            .with_context(|| format!("Reading {:?}", self.command))?;
+        let command = serde_annotate::from_str::<serde_json::Value>(&command)?;
+        let token = ApprovalToken::new(&self.approver, command, self.expires_in)?;
+        let signature = session.sign(&Mechanism::Ecdsa, object, &token.digest()?)?;
+        let token = token.with_signature(&signature);
+        helper::write_file(
+            &self.output,
+            serde_json::to_string_pretty(&token)?.as_bytes(),
+        )?;
+        Ok(Box::<BasicResult>::default())
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use cryptoki::session::Session;
+use serde::{Deserialize, Serialize};
+use serde_annotate::Annotate;
+use std::any::Any;
+
+use crate::commands::Dispatch;
+use crate::module::Module;
+
+pub mod create;
+
+#[derive(clap::Subcommand, Debug, Serialize, Deserialize)]
+pub enum Approval {
+    Create(create::Create),
+}
+
+#[typetag::serde(name = "__approval__")]
+impl Dispatch for Approval {
+    fn run(
+        &self,
+        context: &dyn Any,
+        hsm: &Module,
+        session: Option<&Session>,
+    ) -> Result<Box<dyn Annotate>> {
+        match self {
+            Approval::Create(x) => x.run(context, hsm, session),
+        }
+    }
+    fn leaf(&self) -> &dyn Dispatch
+    where
+        Self: Sized,
+    {
+        match self {
+            Approval::Create(x) => x.leaf(),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{bail, ensure, Context, Result};
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, ObjectHandle};
+use cryptoki::session::Session;
+use der::asn1::AnyRef;
+use der::{Decode, Encode, Reader, Tag, TagNumber};
+use num_bigint_dig::BigUint;
+use serde::{Deserialize, Serialize};
+use serde_annotate::Annotate;
+use sha2::{Digest, Sha256};
+use std::any::Any;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use ot_certs::template::subst::{Subst, SubstData};
+use ot_certs::template::{EcdsaSignature, Signature, Template, Value};
+use ot_certs::x509;
+
+use crate::commands::{BasicResult, Dispatch};
+use crate::error::HsmError;
+use crate::module::Module;
+use crate::util::attribute::{AttrData, AttributeMap, AttributeType, KeyType};
+use crate::util::helper;
+
+/// Issue an X.509 certificate from an `ot_certs` template, signing it with
+/// an HSM key.
+#[derive(clap::Args, Debug, Serialize, Deserialize)]
+pub struct Issue {
+    /// The id of the signing key.
+    #[arg(long)]
+    id: Option<String>,
+    /// The label of the signing key.
+    #[arg(short, long)]
+    label: Option<String>,
+    /// Filename of the certificate template.
+    #[arg(long)]
+    template: PathBuf,
+    /// Filename of the substitution data for the template variables.
+    #[arg(long)]
+    subst: Option<PathBuf>,
+    /// Store the issued certificate on the token with this label.
+    #[arg(long)]
+    store: Option<String>,
+    /// The id of the stored certificate object.
+    #[arg(long, requires = "store")]
+    store_id: Option<String>,
+    /// Attributes to apply to the stored certificate object.
+    #[arg(long, requires = "store")]
+    store_attrs: Option<AttributeMap>,
+    /// The output file for the DER-encoded certificate.
+    output: PathBuf,
+}
+
+/// The tag of the explicitly tagged `version` field of a TBS certificate.
+const VERSION_TAG: Tag = Tag::ContextSpecific {
+    constructed: true,
+    number: TagNumber::N0,
+};
+
+/// The DER-encoded `ECParameters` of the curves supported for ECDSA signing
+/// keys, with the length of their field elements.
+const EC_CURVES: [(&[u8], usize); 2] = [
+    // prime256v1
+    (
+        &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07],
+        32,
+    ),
+    // secp384r1
+    (&[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22], 48),
+];
+
+/// The DER-encoded fields of a TBS certificate needed for a `CKO_CERTIFICATE`
+/// object.
+struct TbsFields {
+    serial_number: Vec<u8>,
+    issuer: Vec<u8>,
+    subject: Vec<u8>,
+}
+
+impl TbsFields {
+    fn from_der(tbs: &[u8]) -> Result<Self> {
+        let tbs = AnyRef::from_der(tbs).map_err(|e| HsmError::DerError(e.to_string()))?;
+        let fields = tbs
+            .sequence(|reader| {
+                // TBSCertificate  ::=  SEQUENCE  {
+                //   version         [0]  EXPLICIT Version DEFAULT v1,
+                //   serialNumber         CertificateSerialNumber,
+                //   signature            AlgorithmIdentifier,
+                //   issuer               Name,
+                //   validity             Validity,
+                //   subject              Name,
+                //   ... }
+                // The version is omitted in v1 certificates.
+                if reader.peek_tag()? == VERSION_TAG {
+                    let _version = reader.decode::<AnyRef>()?;
+                }
+                let serial_number = reader.decode::<AnyRef>()?.to_der()?;
+                let _signature = reader.decode::<AnyRef>()?;
+                let issuer = reader.decode::<AnyRef>()?.to_der()?;
+                let _validity = reader.decode::<AnyRef>()?;
+                let subject = reader.decode::<AnyRef>()?.to_der()?;
+                while !reader.is_finished() {
+                    let _ = reader.decode::<AnyRef>()?;
+                }
+                Ok(TbsFields {
+                    serial_number,
+                    issuer,
+                    subject,
+                })
+            })
+            .map_err(|e| HsmError::DerError(e.to_string()))?;
+        Ok(fields)
+    }
+}
+
+impl Issue {
+    const CERT_ATTRS: &str = r#"{
+        "CKA_TOKEN": true,
+        "CKA_CLASS": "CKO_CERTIFICATE",
+        "CKA_CERTIFICATE_TYPE": "CKC_X_509"
+    }"#;
+
+    fn load_template(&self) -> Result<Template> {
+        let content = std::fs::read_to_string(&self.template).with_context(|| {
+            format!(
+                "Could not load the template file {}",
+                self.template.display()
+            )
+        })?;
+        let template = Template::from_hjson_str(&content).with_context(|| {
+            format!("Failed to parse template file {}", self.template.display())
+        })?;
+        if let Some(subst) = &self.subst {
+            let content = std::fs::read_to_string(subst)
+                .with_context(|| format!("Could not load the data file {}", subst.display()))?;
+            let data = SubstData::from_json(&content)
+                .with_context(|| format!("Failed to parse data file {}", subst.display()))?;
+            template.subst(&data)
+        } else {
+            Ok(template)
+        }
+    }
+
+    /// Returns the type of the key signing with the signature algorithm of
+    /// the template.
+    fn key_type(signature: &Signature) -> KeyType {
+        match signature {
+            Signature::EcdsaWithSha256 { .. } => KeyType::Ec,
+        }
+    }
+
+    /// Signs `tbs` with `key`, using the signature algorithm of the template.
+    fn sign(
+        session: &Session,
+        key: ObjectHandle,
+        signature: &Signature,
+        tbs: &[u8],
+    ) -> Result<Signature> {
+        match signature {
+            Signature::EcdsaWithSha256 { .. } => {
+                let attrs =
+                    session.get_attributes(key, &[cryptoki::object::AttributeType::EcParams])?;
+                let [Attribute::EcParams(params)] = attrs.as_slice() else {
+                    bail!(HsmError::KeyError("missing EC param".into()));
+                };
+                let Some(&(_, size)) = EC_CURVES.iter().find(|(oid, _)| *oid == params.as_slice())
+                else {
+                    bail!(HsmError::KeyError(format!(
+                        "unsupported EC curve {}",
+                        hex::encode(params)
+                    )));
+                };
+                let digest = Sha256::digest(tbs);
+                let sig = session.sign(&Mechanism::Ecdsa, key, digest.as_slice())?;
+                ensure!(
+                    sig.len() == 2 * size,
+                    HsmError::KeyError(format!("bad ECDSA signature length {}", sig.len()))
+                );
+                let (r, s) = sig.split_at(size);
+                Ok(Signature::EcdsaWithSha256 {
+                    value: Some(EcdsaSignature {
+                        r: Value::Literal(BigUint::from_bytes_be(r)),
+                        s: Value::Literal(BigUint::from_bytes_be(s)),
+                    }),
+                })
+            }
+        }
+    }
+
+    fn store_cert(
+        &self,
+        session: &Session,
+        label: &str,
+        fields: &TbsFields,
+        der: &[u8],
+    ) -> Result<AttrData> {
+        helper::no_object_exists(session, self.store_id.as_deref(), Some(label))?;
+        let id = AttrData::Str(
+            self.store_id
+                .as_ref()
+                .cloned()
+                .unwrap_or_else(helper::random_id),
+        );
+        let mut attrs = AttributeMap::from_str(Self::CERT_ATTRS).expect("error in CERT_ATTRS");
+        attrs.insert(AttributeType::Id, id.clone());
+        attrs.insert(AttributeType::Label, AttrData::Str(label.into()));
+        attrs.insert(
+            AttributeType::Subject,
+            AttrData::from(fields.subject.as_slice()),
+        );
+        attrs.insert(
+            AttributeType::Issuer,
+            AttrData::from(fields.issuer.as_slice()),
+        );
+        attrs.insert(
+            AttributeType::SerialNumber,
+            AttrData::from(fields.serial_number.as_slice()),
+        );
+        attrs.insert(AttributeType::Value, AttrData::from(der));
+        if let Some(tpl) = &self.store_attrs {
+            attrs.merge(tpl.clone());
+        }
+        let _cert = session.create_object(&attrs.to_vec()?)?;
+        Ok(id)
+    }
+}
+
+#[typetag::serde(name = "cert-issue")]
+impl Dispatch for Issue {
+    fn run(
+        &self,
+        _context: &dyn Any,
+        _hsm: &Module,
+        session: Option<&Session>,
+    ) -> Result<Box<dyn Annotate>> {
+        let session = session.ok_or(HsmError::SessionRequired)?;
+        let template = self.load_template()?;
+        let mut attrs = helper::search_spec(self.id.as_deref(), self.label.as_deref())?;
+        attrs.push(Attribute::KeyType(
+            Self::key_type(&template.certificate.signature).try_into()?,
+        ));
+        attrs.push(Attribute::Sign(true));
+        let object = helper::find_one_object(session, &attrs)?;
+
+        let tbs = x509::generate_tbs_certificate(&template)
+            .context("could not generate the TBS certificate")?;
+        let fields = TbsFields::from_der(&tbs)?;
+        let signature = Self::sign(session, object, &template.certificate.signature, &tbs)?;
+        let der = x509::generate_certificate_from_tbs(tbs, &signature)
+            .context("could not generate the X509 certificate")?;
+        helper::write_file(&self.output, &der)?;
+
+        let mut result = Box::<BasicResult>::default();
+        if let Some(label) = &self.store {
+            result.id = self.store_cert(session, label, &fields, &der)?;
+            result.label = AttrData::Str(label.clone());
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cryptoki::object::{KeyType as CkKeyType, ObjectClass};
+    use cryptoki::session::UserType;
+    use der::asn1::BitStringRef;
+    use p256::ecdsa::signature::Verifier;
+    use p256::ecdsa::{Signature as EcdsaSig, VerifyingKey};
+    use p256::SecretKey;
+
+    const TEMPLATE: &str = "sw/host/ot_certs/tests/example.hjson";
+    const SUBST: &str = "sw/host/ot_certs/tests/example_data.json";
+
+    // TBSCertificate of a v1 certificate, without the version field: serial
+    // number 1, ecdsa-with-SHA256, empty issuer and subject names and an empty
+    // public key.
+    const TBS_V1: &str = concat!(
+        "3039020101300a06082a8648ce3d04030230003022180f3230323330313031303030",
+        "3030305a180f32303530303130313030303030305a30003000",
+    );
+
+    #[test]
+    fn tbs_fields() -> Result<()> {
+        let v1 = hex::decode(TBS_V1)?;
+        // The same TBSCertificate with the version field of a v3 certificate.
+        let v3 = [&[0x30, 0x3e, 0xa0, 0x03, 0x02, 0x01, 0x02], &v1[2..]].concat();
+        for tbs in [v1, v3] {
+            let fields = TbsFields::from_der(&tbs)?;
+            assert_eq!(fields.serial_number, [0x02, 0x01, 0x01]);
+            assert_eq!(fields.issuer, [0x30, 0x00]);
+            assert_eq!(fields.subject, [0x30, 0x00]);
+        }
+        Ok(())
+    }
+
+    /// Issues a certificate with a key on the SoftHSM token `fake_keys` and
+    /// checks its signature.  `HSMTOOL_MODULE` and `SOFTHSM2_CONF` are set by
+    /// the Bazel test rule.
+    #[test]
+    fn issue_and_verify() -> Result<()> {
+        let module = std::env::var("HSMTOOL_MODULE").context("HSMTOOL_MODULE is not set")?;
+        std::env::var("SOFTHSM2_CONF").context("SOFTHSM2_CONF is not set")?;
+        let mut hsm = Module::initialize(&module, None)?;
+        let session = hsm.connect("fake_keys", Some(UserType::User), Some("123456"))?;
+
+        let sk = SecretKey::from_slice(&[7u8; 32])?;
+        session.create_object(&[
+            Attribute::Token(false),
+            Attribute::Class(ObjectClass::PRIVATE_KEY),
+            Attribute::KeyType(CkKeyType::EC),
+            Attribute::EcParams(EC_CURVES[0].0.to_vec()),
+            Attribute::Value(sk.to_bytes().to_vec()),
+            Attribute::Label(b"cert-issue-test".to_vec()),
+            Attribute::Sign(true),
+        ])?;
+
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("cert.der");
+        let issue = Issue {
+            id: None,
+            label: Some("cert-issue-test".into()),
+            template: TEMPLATE.into(),
+            subst: Some(SUBST.into()),
+            store: None,
+            store_id: None,
+            store_attrs: None,
+            output: output.clone(),
+        };
+        issue.run(&(), &hsm, Some(&session))?;
+
+        // Certificate  ::=  SEQUENCE  {
+        //   tbsCertificate       TBSCertificate,
+        //   signatureAlgorithm   AlgorithmIdentifier,
+        //   signatureValue       BIT STRING  }
+        let der = std::fs::read(&output)?;
+        let (tbs, sig) = AnyRef::from_der(&der)?.sequence(|reader| {
+            let tbs = reader.decode::<AnyRef>()?.to_der()?;
+            let _algorithm = reader.decode::<AnyRef>()?;
+            let sig = reader.decode::<BitStringRef>()?.raw_bytes().to_vec();
+            Ok((tbs, sig))
+        })?;
+        let sig = EcdsaSig::from_der(&sig)?;
+        let verifying_key = VerifyingKey::from(sk.public_key());
+        assert!(verifying_key.verify(&tbs, &sig).is_ok());
+        Ok(())
+    }
+}
@@ -10,7 +10,7 @@ use std::any::Any;
 use std::path::PathBuf;
 use thiserror::Error;
 
-use crate::commands::{BasicResult, Dispatch};
+use crate::commands::{dispatch, BasicResult, Dispatch};
 use crate::module::Module;
 
 #[derive(clap::Args, Debug, Serialize, Deserialize)]
@@ -47,7 +47,7 @@ impl Dispatch for Exec {
         for command in commands {
             let name = command.typetag_name().to_string();
             log::info!("Executing command {name}");
-            match command.run(context, hsm, session) {
+            match dispatch(command.as_ref(), context, hsm, session) {
                 Ok(r) => status.push(ExecResult {
                     command: name,
                     result: r,
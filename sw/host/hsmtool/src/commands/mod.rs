@@ -9,9 +9,14 @@ use serde_annotate::{Annotate, ColorProfile};
 use std::any::Any;
 use std::io::IsTerminal;
 
+use crate::error::HsmError;
 use crate::module::Module;
+use crate::util::approval::{ApprovalPolicy, ApprovalToken};
 use crate::util::attribute::AttrData;
+use crate::util::audit::{input_digest, AuditEntry, AuditLog, AuditOp, AuditStatus};
 
+mod approval;
+mod cert;
 mod ecdsa;
 mod exec;
 mod object;
@@ -38,6 +43,10 @@ pub trait Dispatch {
 
 #[derive(clap::Subcommand, Debug, Serialize, Deserialize)]
 pub enum Commands {
+    #[command(subcommand)]
+    Approval(approval::Approval),
+    #[command(subcommand)]
+    Cert(cert::Cert),
     #[command(subcommand)]
     Ecdsa(ecdsa::Ecdsa),
     Exec(exec::Exec),
@@ -60,6 +69,8 @@ impl Dispatch for Commands {
         session: Option<&Session>,
     ) -> Result<Box<dyn Annotate>> {
         match self {
+            Commands::Approval(x) => x.run(context, hsm, session),
+            Commands::Cert(x) => x.run(context, hsm, session),
             Commands::Ecdsa(x) => x.run(context, hsm, session),
             Commands::Exec(x) => x.run(context, hsm, session),
             Commands::Object(x) => x.run(context, hsm, session),
@@ -74,6 +85,8 @@ impl Dispatch for Commands {
         Self: Sized,
     {
         match self {
+            Commands::Approval(x) => x.leaf(),
+            Commands::Cert(x) => x.leaf(),
             Commands::Ecdsa(x) => x.leaf(),
             Commands::Exec(x) => x.leaf(),
             Commands::Object(x) => x.leaf(),
@@ -84,6 +97,112 @@ impl Dispatch for Commands {
     }
 }
 
+/// Audit and approval state for `dispatch`.
+///
+/// When passed as the `context` of `dispatch`, sensitive operations are
+/// checked against the approval policy and recorded in the audit log.  An
+/// approval policy requires an audit log, which records the approval tokens
+/// consumed by each operation.
+#[derive(Default)]
+pub struct Audit {
+    pub log: Option<AuditLog>,
+    pub policy: Option<ApprovalPolicy>,
+    pub approvals: Vec<ApprovalToken>,
+}
+
+impl Audit {
+    fn entry(&self, op: AuditOp, name: &str, command: &serde_json::Value) -> Result<AuditEntry> {
+        let field = |f: &str| command.get(f).and_then(|v| v.as_str()).map(String::from);
+        Ok(AuditEntry {
+            seq: 0,
+            timestamp: 0,
+            command: name.into(),
+            op,
+            id: field("id"),
+            label: field("label"),
+            input_digest: input_digest(command)?,
+            approvers: Vec::new(),
+            nonces: Vec::new(),
+            status: AuditStatus::Pending,
+            error: None,
+            prev_hash: String::new(),
+            hash: String::new(),
+        })
+    }
+
+    fn run(
+        &self,
+        command: &dyn Dispatch,
+        context: &dyn Any,
+        hsm: &Module,
+        session: Option<&Session>,
+    ) -> Result<Box<dyn Annotate>> {
+        let name = command.typetag_name();
+        let Some(op) = AuditOp::classify(name) else {
+            return command.run(context, hsm, session);
+        };
+        let policy = self.policy.as_ref().filter(|p| p.requires_approval(op));
+        let Some(log) = &self.log else {
+            if policy.is_some() {
+                return Err(HsmError::ApprovalError(
+                    "an approval policy requires an audit log".into(),
+                )
+                .into());
+            }
+            return command.run(context, hsm, session);
+        };
+
+        // Hold the lock on the log until the command completes, so the
+        // approvals cannot be used concurrently by another instance.
+        let mut log = log.lock()?;
+        let json = serde_json::to_value(command)?;
+        let mut entry = self.entry(op, name, &json)?;
+        if let Some(policy) = policy {
+            let consumed = log.consumed_nonces();
+            match policy.check(
+                &json,
+                entry.input_digest.as_deref(),
+                &self.approvals,
+                &consumed,
+            ) {
+                Ok(tokens) => {
+                    entry.approvers = tokens.iter().map(|t| t.approver.clone()).collect();
+                    entry.nonces = tokens.iter().map(|t| t.nonce.clone()).collect();
+                }
+                Err(e) => {
+                    entry.status = AuditStatus::Failure;
+                    entry.error = Some(format!("{e:?}"));
+                    log.append(entry)?;
+                    return Err(e);
+                }
+            }
+        }
+        log.append(entry.clone())?;
+        let result = command.run(context, hsm, session);
+        entry.status = match &result {
+            Ok(_) => AuditStatus::Success,
+            Err(_) => AuditStatus::Failure,
+        };
+        entry.error = result.as_ref().err().map(|e| format!("{e:?}"));
+        log.append(entry)?;
+        result
+    }
+}
+
+/// Runs a leaf command.  If the `context` is an `Audit`, the command is
+/// subject to the approval policy and its outcome is recorded in the audit log.
+pub fn dispatch(
+    command: &dyn Dispatch,
+    context: &dyn Any,
+    hsm: &Module,
+    session: Option<&Session>,
+) -> Result<Box<dyn Annotate>> {
+    match context.downcast_ref::<Audit>() {
+        Some(audit) => audit.run(command, context, hsm, session),
+        None => command.run(context, hsm, session),
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct BasicResult {
     success: bool,
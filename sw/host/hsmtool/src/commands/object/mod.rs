@@ -14,14 +14,18 @@ use crate::module::Module;
 mod destroy;
 mod list;
 mod show;
+mod unwrap;
 mod update;
+mod wrap;
 
 #[derive(clap::Subcommand, Debug, Serialize, Deserialize)]
 pub enum Object {
     Destroy(destroy::Destroy),
     List(list::List),
     Show(show::Show),
+    Unwrap(unwrap::Unwrap),
     Update(update::Update),
+    Wrap(wrap::Wrap),
 }
 
 #[typetag::serde(name = "__object__")]
@@ -36,7 +40,9 @@ impl Dispatch for Object {
             Object::Destroy(x) => x.run(context, hsm, session),
             Object::List(x) => x.run(context, hsm, session),
             Object::Show(x) => x.run(context, hsm, session),
+            Object::Unwrap(x) => x.run(context, hsm, session),
             Object::Update(x) => x.run(context, hsm, session),
+            Object::Wrap(x) => x.run(context, hsm, session),
         }
     }
     fn leaf(&self) -> &dyn Dispatch
@@ -47,7 +53,9 @@ impl Dispatch for Object {
             Object::Destroy(x) => x.leaf(),
             Object::List(x) => x.leaf(),
             Object::Show(x) => x.leaf(),
+            Object::Unwrap(x) => x.leaf(),
             Object::Update(x) => x.leaf(),
+            Object::Wrap(x) => x.leaf(),
         }
     }
 }
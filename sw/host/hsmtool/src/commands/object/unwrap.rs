@@ -0,0 +1,91 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{Context, Result};
+use cryptoki::session::Session;
+use serde::{Deserialize, Serialize};
+use serde_annotate::Annotate;
+use std::any::Any;
+use std::path::PathBuf;
+
+use crate::commands::{BasicResult, Dispatch};
+use crate::error::HsmError;
+use crate::module::Module;
+use crate::util::attribute::{AttrData, AttributeMap, AttributeType};
+use crate::util::helper;
+use crate::util::wrap::WrappedKey;
+
+/// Unwrap a key produced by `object wrap` onto this token.
+#[derive(clap::Args, Debug, Serialize, Deserialize)]
+pub struct Unwrap {
+    /// Override the id stored in the wrapped key.
+    #[arg(long)]
+    id: Option<String>,
+    /// Override the label stored in the wrapped key.
+    #[arg(short, long)]
+    label: Option<String>,
+    /// The label of the unwrapping key.  Defaults to the label of the
+    /// wrapping key recorded in the wrapped key file.
+    #[arg(long)]
+    unwrapping_key: Option<String>,
+    /// Additional attributes to apply to the unwrapped key.
+    #[arg(long)]
+    template: Option<AttributeMap>,
+    /// The wrapped key file.
+    filename: PathBuf,
+}
+
+#[typetag::serde(name = "object-unwrap")]
+impl Dispatch for Unwrap {
+    fn run(
+        &self,
+        _context: &dyn Any,
+        _hsm: &Module,
+        session: Option<&Session>,
+    ) -> Result<Box<dyn Annotate>> {
+        let session = session.ok_or(HsmError::SessionRequired)?;
+        let wrapped = WrappedKey::load(&self.filename)?;
+        let mut template = wrapped.attributes.clone();
+        if let Some(id) = &self.id {
+            template.insert(AttributeType::Id, AttrData::Str(id.clone()));
+        }
+        if let Some(label) = &self.label {
+            template.insert(AttributeType::Label, AttrData::Str(label.clone()));
+        }
+        if let Some(tpl) = &self.template {
+            template.merge(tpl.clone());
+        }
+
+        let id = template
+            .get(&AttributeType::Id)
+            .cloned()
+            .unwrap_or(AttrData::None);
+        let label = template
+            .get(&AttributeType::Label)
+            .cloned()
+            .unwrap_or(AttrData::None);
+        helper::no_object_exists(session, id.try_str().ok(), label.try_str().ok())?;
+
+        let ukey_label = self
+            .unwrapping_key
+            .as_deref()
+            .unwrap_or(&wrapped.wrapping_key);
+        let uattrs = wrapped.mechanism.unwrapping_key_spec(ukey_label).to_vec()?;
+        let ukey = helper::find_one_object(session, &uattrs).context("Find unwrapping key")?;
+
+        log::info!("template = {}", serde_json::to_string_pretty(&template)?);
+        let _key = session.unwrap_key(
+            &wrapped.mechanism.mechanism(),
+            ukey,
+            &wrapped.wrapped()?,
+            &template.to_vec()?,
+        )?;
+        Ok(Box::new(BasicResult {
+            success: true,
+            id,
+            label,
+            error: None,
+        }))
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{Context, Result};
+use cryptoki::session::Session;
+use serde::{Deserialize, Serialize};
+use serde_annotate::Annotate;
+use std::any::Any;
+use std::path::PathBuf;
+
+use crate::commands::{BasicResult, Dispatch};
+use crate::error::HsmError;
+use crate::module::Module;
+use crate::util::attribute::{AttrData, AttributeMap, AttributeType, ObjectClass};
+use crate::util::helper;
+use crate::util::wrap::{WrapMechanism, WrappedKey};
+
+/// Wrap a private or secret key so it can be moved to another token.
+#[derive(clap::Args, Debug, Serialize, Deserialize)]
+pub struct Wrap {
+    #[arg(long)]
+    id: Option<String>,
+    #[arg(short, long)]
+    label: Option<String>,
+    /// The label of the wrapping key.
+    #[arg(long)]
+    wrapping_key: String,
+    /// The wrapping mechanism.
+    #[arg(short, long, value_enum, default_value = "aes-key-wrap-pad")]
+    mechanism: WrapMechanism,
+    /// The output file for the wrapped key.
+    filename: PathBuf,
+}
+
+#[typetag::serde(name = "object-wrap")]
+impl Dispatch for Wrap {
+    fn run(
+        &self,
+        _context: &dyn Any,
+        _hsm: &Module,
+        session: Option<&Session>,
+    ) -> Result<Box<dyn Annotate>> {
+        let session = session.ok_or(HsmError::SessionRequired)?;
+        let attrs = helper::search_spec(self.id.as_deref(), self.label.as_deref())?;
+        // Only private and secret keys can be wrapped; skip over any public
+        // keys or certificates sharing the same id or label.
+        let mut found = Vec::new();
+        for object in session.find_objects(&attrs)? {
+            let map = AttributeMap::from_object(session, object)?;
+            let class = map
+                .get(&AttributeType::Class)
+                .map(ObjectClass::try_from)
+                .transpose()?;
+            if matches!(
+                class,
+                Some(ObjectClass::PrivateKey) | Some(ObjectClass::SecretKey)
+            ) {
+                found.push((object, map));
+            }
+        }
+        let (object, map) = match found.len() {
+            0 => {
+                let spec = AttributeMap::from(attrs.as_slice());
+                return Err(HsmError::ObjectNotFound(serde_json::to_string(&spec)?).into());
+            }
+            1 => found.remove(0),
+            n => {
+                let spec = AttributeMap::from(attrs.as_slice());
+                return Err(HsmError::TooManyObjects(n, serde_json::to_string(&spec)?).into());
+            }
+        };
+
+        let wattrs = self
+            .mechanism
+            .wrapping_key_spec(&self.wrapping_key)
+            .to_vec()?;
+        let wkey = helper::find_one_object(session, &wattrs).context("Find wrapping key")?;
+        let wrapped = session.wrap_key(&self.mechanism.mechanism(), wkey, object)?;
+        let wrapped = WrappedKey::new(self.mechanism, &self.wrapping_key, map, &wrapped);
+        wrapped.save(&self.filename)?;
+
+        Ok(Box::new(BasicResult {
+            success: true,
+            id: wrapped
+                .attributes
+                .get(&AttributeType::Id)
+                .cloned()
+                .unwrap_or(AttrData::None),
+            label: wrapped
+                .attributes
+                .get(&AttributeType::Label)
+                .cloned()
+                .unwrap_or(AttrData::None),
+            error: None,
+        }))
+    }
+}
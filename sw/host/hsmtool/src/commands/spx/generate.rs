@@ -3,6 +3,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use anyhow::Result;
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, ObjectHandle};
 use cryptoki::session::Session;
 use serde::{Deserialize, Serialize};
 use serde_annotate::Annotate;
@@ -12,9 +14,10 @@ use std::path::PathBuf;
 use crate::commands::{BasicResult, Dispatch};
 use crate::error::HsmError;
 use crate::module::Module;
-use crate::util::attribute::AttrData;
+use crate::util::attribute::{AttrData, ObjectClass};
+use crate::util::helper;
 use acorn::GenerateFlags;
-use sphincsplus::{EncodeKey, SphincsPlus, SpxSecretKey};
+use sphincsplus::{EncodeKey, SphincsPlus, SpxPublicKey, SpxSecretKey};
 
 #[derive(clap::Args, Debug, Serialize, Deserialize)]
 pub struct Generate {
@@ -26,6 +29,57 @@ pub struct Generate {
     overwrite: bool,
     #[arg(short, long, help = "Export the private key material to a file")]
     export: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Derive the key deterministically from the HSM secret key with this label"
+    )]
+    seed_label: Option<String>,
+}
+
+impl Generate {
+    /// The domain separator for seed derivation.
+    const SEED_DOMAIN: &'static [u8] = b"OpenTitan SPX seed";
+
+    /// Derives a SPHINCS+ seed from the HSM-held secret `key`.
+    ///
+    /// The seed is derived with HMAC-SHA256 in counter mode (NIST SP 800-108):
+    ///   `HMAC(key, i || domain || 0 || algorithm || 0 || label)`
+    /// for `i = 1, 2, ...` until enough bytes have been produced.  The seed
+    /// therefore depends on the algorithm and the label of the generated key,
+    /// but the secret itself never leaves the HSM.
+    fn derive_seed(&self, session: &Session, key: ObjectHandle, len: usize) -> Result<Vec<u8>> {
+        let mut seed = Vec::with_capacity(len);
+        let mut counter = 1u32;
+        while seed.len() < len {
+            let algorithm = self.algorithm.to_string();
+            let data: [&[u8]; 6] = [
+                &counter.to_be_bytes(),
+                Self::SEED_DOMAIN,
+                &[0],
+                algorithm.as_bytes(),
+                &[0],
+                self.label.as_bytes(),
+            ];
+            seed.extend(session.sign(&Mechanism::Sha256Hmac, key, &data.concat())?);
+            counter += 1;
+        }
+        seed.truncate(len);
+        Ok(seed)
+    }
+
+    /// Derives a SPHINCS+ keypair from the secret key with label `seed_label`.
+    fn derive_keypair(
+        &self,
+        session: &Session,
+        seed_label: &str,
+    ) -> Result<(SpxSecretKey, SpxPublicKey)> {
+        let mut attrs = helper::search_spec(None, Some(seed_label))?;
+        attrs.push(Attribute::Class(ObjectClass::SecretKey.try_into()?));
+        attrs.push(Attribute::Sign(true));
+        let key = helper::find_one_object(session, &attrs)?;
+        let seed = self.derive_seed(session, key, self.algorithm.seed_len())?;
+        Ok(SpxSecretKey::keypair_from_seed(self.algorithm, &seed)?)
+    }
 }
 
 #[typetag::serde(name = "spx-generate")]
@@ -34,11 +88,33 @@ impl Dispatch for Generate {
         &self,
         _context: &dyn Any,
         hsm: &Module,
-        _session: Option<&Session>,
+        session: Option<&Session>,
     ) -> Result<Box<dyn Annotate>> {
         let acorn = hsm.acorn.as_ref().ok_or(HsmError::AcornUnavailable)?;
         let token = hsm.token.as_deref().ok_or(HsmError::SessionRequired)?;
 
+        if let Some(seed_label) = &self.seed_label {
+            let session = session.ok_or(HsmError::SessionRequired)?;
+            let (sk, pk) = self.derive_keypair(session, seed_label)?;
+            let key = acorn.import_keypair(
+                &self.label,
+                &self.algorithm.to_string(),
+                token,
+                self.overwrite,
+                pk.as_bytes(),
+                sk.as_bytes(),
+            )?;
+            if let Some(path) = &self.export {
+                sk.write_pem_file(path)?;
+            }
+            return Ok(Box::new(BasicResult {
+                success: true,
+                id: AttrData::Str(key.hash.expect("key hash")),
+                label: AttrData::Str(key.alias),
+                error: None,
+            }));
+        }
+
         #[rustfmt::skip]
         let flags =
             if self.overwrite { GenerateFlags::OVERWRITE } else { GenerateFlags::NONE }
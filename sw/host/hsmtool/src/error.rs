@@ -36,4 +36,8 @@ pub enum HsmError {
     DerError(String),
     #[error("This operation requires the acorn library")]
     AcornUnavailable,
+    #[error("Audit log is corrupt at line {0}")]
+    AuditLogCorrupt(usize),
+    #[error("Approval error: {0}")]
+    ApprovalError(String),
 }
@@ -9,10 +9,12 @@ use cryptoki::session::UserType;
 use log::LevelFilter;
 use std::path::PathBuf;
 
-use hsmtool::commands::{print_command, print_result, Commands, Dispatch, Format};
+use hsmtool::commands::{dispatch, print_command, print_result, Audit, Commands, Dispatch, Format};
 use hsmtool::module::{self, Module};
 use hsmtool::profile::Profile;
+use hsmtool::util::approval::{ApprovalPolicy, ApprovalToken};
 use hsmtool::util::attribute::AttributeMap;
+use hsmtool::util::audit::AuditLog;
 
 #[derive(Debug, Parser)]
 struct Args {
@@ -67,6 +69,18 @@ struct Args {
     #[arg(long, default_value = "false")]
     show_json: bool,
 
+    /// Append-only audit log of sensitive operations.
+    #[arg(long, env = "HSMTOOL_AUDIT_LOG")]
+    audit_log: Option<PathBuf>,
+
+    /// Approval policy for sensitive operations (requires an audit log).
+    #[arg(long, env = "HSMTOOL_APPROVAL_POLICY", requires = "audit_log")]
+    approval_policy: Option<PathBuf>,
+
+    /// Approval tokens authorizing the command.
+    #[arg(long)]
+    approval: Vec<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -111,6 +125,19 @@ fn main() -> Result<()> {
         None
     };
 
-    let result = args.command.run(&(), &hsm, session.as_ref());
+    let audit = Audit {
+        log: args.audit_log.as_ref().map(AuditLog::new),
+        policy: args
+            .approval_policy
+            .as_ref()
+            .map(ApprovalPolicy::load)
+            .transpose()?,
+        approvals: args
+            .approval
+            .iter()
+            .map(ApprovalToken::load)
+            .collect::<Result<Vec<_>>>()?,
+    };
+    let result = dispatch(args.command.leaf(), &audit, &hsm, session.as_ref());
     print_result(args.format, args.color, args.quiet, result)
 }
@@ -0,0 +1,372 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The `util::approval` module implements M-of-N quorum approval of
+//! `hsmtool` commands.
+//!
+//! An approval token binds an approver's name to the exact JSON form of a
+//! command (as printed by `hsmtool --show-json`) and to the digest of the
+//! command's input files.  The token carries an ECDSA P-256 signature over
+//! the SHA-256 digest of the canonical JSON encoding of the token.  Each
+//! token has an expiry time and a random nonce; the nonce is recorded in the
+//! audit log when the token is used, so a token authorizes a single execution
+//! of the command.  The approval policy names the approvers, their public
+//! keys, the number of approvals required and the classes of operations that
+//! require approval.
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use p256::ecdsa::signature::hazmat::{PrehashSigner, PrehashVerifier};
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::HsmError;
+use crate::util::audit::{input_digest, now, AuditOp};
+use crate::util::key::ecdsa::load_public_key;
+
+/// A signed approval of a single command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalToken {
+    /// The name of the approver.
+    pub approver: String,
+    /// The JSON form of the approved command.
+    pub command: serde_json::Value,
+    /// The digest of the approved command's input files (see
+    /// `audit::input_digest`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_digest: Option<String>,
+    /// A random value identifying this token.
+    pub nonce: String,
+    /// The expiry time of this token in seconds since the UNIX epoch.
+    pub expires: u64,
+    /// The hex-encoded signature (r || s) over `ApprovalToken::digest`.
+    #[serde(default)]
+    pub signature: String,
+}
+
+impl ApprovalToken {
+    /// Creates an unsigned approval token for `command`, valid for `lifetime`.
+    /// The command's input files are read to compute the input digest.
+    pub fn new(approver: &str, command: serde_json::Value, lifetime: Duration) -> Result<Self> {
+        Ok(ApprovalToken {
+            approver: approver.into(),
+            input_digest: input_digest(&command)?,
+            command,
+            nonce: hex::encode(rand::random::<[u8; 16]>()),
+            expires: now()? + lifetime.as_secs(),
+            signature: String::new(),
+        })
+    }
+
+    /// Computes the digest of the token as signed by the approver.
+    pub fn digest(&self) -> Result<Vec<u8>> {
+        // The digest is always computed over a `serde_json::Value` (rather
+        // than the text of a file), so whitespace and formatting differences
+        // in the approved command do not matter.
+        let mut token = self.clone();
+        token.signature = String::new();
+        Ok(Sha256::digest(serde_json::to_vec(&token)?).to_vec())
+    }
+
+    /// Attaches a pre-computed signature to the token.
+    pub fn with_signature(mut self, signature: &[u8]) -> Self {
+        self.signature = hex::encode(signature);
+        self
+    }
+
+    /// Signs the token with a local key.
+    pub fn sign(self, key: &SigningKey) -> Result<Self> {
+        let signature: Signature = key.sign_prehash(&self.digest()?)?;
+        Ok(self.with_signature(&signature.to_bytes()))
+    }
+
+    /// Verifies the token's signature with `key`.
+    pub fn verify(&self, key: &VerifyingKey) -> Result<()> {
+        let signature = Signature::from_slice(&hex::decode(&self.signature)?)?;
+        key.verify_prehash(&self.digest()?, &signature)
+            .map_err(|_| {
+                HsmError::ApprovalError(format!("bad signature from {}", self.approver))
+            })?;
+        Ok(())
+    }
+
+    /// Reads an approval token from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).context(format!("Reading {path:?}"))?;
+        Ok(serde_annotate::from_str(&data)?)
+    }
+}
+
+/// The approval policy.
+///
+/// The policy file is a JSON document of the form:
+/// ```ignore
+/// {
+///     "threshold": 2,
+///     "operations": ["sign", "decrypt", "import", "export", "destroy"],
+///     "approvers": {
+///         "alice": "alice.pub.pem",
+///         "bob": "bob.pub.pem",
+///         "carol": "carol.pub.pem"
+///     }
+/// }
+/// ```
+/// Relative public key paths are relative to the policy file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    /// The number of distinct approvals required (M).
+    pub threshold: usize,
+    /// The classes of operations requiring approval.
+    #[serde(default = "ApprovalPolicy::default_operations")]
+    pub operations: Vec<AuditOp>,
+    /// The approvers (N) and the paths to their public keys.
+    pub approvers: IndexMap<String, PathBuf>,
+    #[serde(skip)]
+    keys: IndexMap<String, VerifyingKey>,
+}
+
+impl ApprovalPolicy {
+    fn default_operations() -> Vec<AuditOp> {
+        vec![
+            AuditOp::Sign,
+            AuditOp::Decrypt,
+            AuditOp::Import,
+            AuditOp::Update,
+            AuditOp::Destroy,
+            AuditOp::Export,
+            AuditOp::Other,
+        ]
+    }
+
+    /// Reads the approval policy and the approvers' public keys.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read_to_string(path).context(format!("Reading {path:?}"))?;
+        let mut policy = serde_annotate::from_str::<ApprovalPolicy>(&data)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for (name, keyfile) in policy.approvers.iter() {
+            let key = load_public_key(base.join(keyfile))
+                .with_context(|| format!("Loading public key for approver {name}"))?;
+            policy.keys.insert(name.clone(), key);
+        }
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Creates a policy from in-memory keys.
+    pub fn new(
+        threshold: usize,
+        operations: Vec<AuditOp>,
+        keys: IndexMap<String, VerifyingKey>,
+    ) -> Result<Self> {
+        let policy = ApprovalPolicy {
+            threshold,
+            operations,
+            approvers: IndexMap::new(),
+            keys,
+        };
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.threshold == 0 || self.threshold > self.keys.len() {
+            return Err(HsmError::ApprovalError(format!(
+                "threshold {} is not satisfiable by {} approvers",
+                self.threshold,
+                self.keys.len()
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns true if the operation requires approval.
+    pub fn requires_approval(&self, op: AuditOp) -> bool {
+        self.operations.contains(&op)
+    }
+
+    /// Checks that `tokens` contain at least `threshold` valid approvals of
+    /// `command` with `input_digest` from distinct approvers.  Tokens which
+    /// have expired or whose nonce is in `consumed` are ignored.  Returns the
+    /// tokens which make up the quorum.
+    pub fn check<'a>(
+        &self,
+        command: &serde_json::Value,
+        input_digest: Option<&str>,
+        tokens: &'a [ApprovalToken],
+        consumed: &HashSet<String>,
+    ) -> Result<Vec<&'a ApprovalToken>> {
+        let now = now()?;
+        let mut approvers = HashSet::new();
+        let mut result = Vec::new();
+        for token in tokens {
+            if &token.command != command {
+                log::warn!(
+                    "Approval from {} is for a different command; ignoring",
+                    token.approver
+                );
+                continue;
+            }
+            if token.input_digest.as_deref() != input_digest {
+                log::warn!(
+                    "Approval from {} is for different input files; ignoring",
+                    token.approver
+                );
+                continue;
+            }
+            if token.expires <= now {
+                log::warn!("Approval from {} has expired; ignoring", token.approver);
+                continue;
+            }
+            if consumed.contains(&token.nonce) {
+                log::warn!(
+                    "Approval from {} has already been used; ignoring",
+                    token.approver
+                );
+                continue;
+            }
+            let Some(key) = self.keys.get(&token.approver) else {
+                log::warn!("Unknown approver {}; ignoring", token.approver);
+                continue;
+            };
+            if let Err(e) = token.verify(key) {
+                log::warn!("{e}; ignoring");
+                continue;
+            }
+            if approvers.insert(token.approver.clone()) {
+                result.push(token);
+            }
+        }
+        if result.len() < self.threshold {
+            return Err(HsmError::ApprovalError(format!(
+                "{} of {} required approvals present",
+                result.len(),
+                self.threshold
+            ))
+            .into());
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    const DAY: Duration = Duration::from_secs(86400);
+
+    fn command(label: &str) -> serde_json::Value {
+        serde_json::json!({
+            "command": "ecdsa-sign",
+            "label": label,
+            "format": "Sha256Hash",
+        })
+    }
+
+    #[test]
+    fn test_quorum() -> Result<()> {
+        let alice = SigningKey::random(&mut OsRng);
+        let bob = SigningKey::random(&mut OsRng);
+        let carol = SigningKey::random(&mut OsRng);
+        let mallory = SigningKey::random(&mut OsRng);
+        let keys = IndexMap::from([
+            ("alice".to_string(), *alice.verifying_key()),
+            ("bob".to_string(), *bob.verifying_key()),
+            ("carol".to_string(), *carol.verifying_key()),
+        ]);
+        let policy = ApprovalPolicy::new(2, vec![AuditOp::Sign], keys)?;
+        assert!(policy.requires_approval(AuditOp::Sign));
+        assert!(!policy.requires_approval(AuditOp::Generate));
+
+        let cmd = command("foo");
+        let none = HashSet::new();
+        let a = ApprovalToken::new("alice", cmd.clone(), DAY)?.sign(&alice)?;
+        let b = ApprovalToken::new("bob", cmd.clone(), DAY)?.sign(&bob)?;
+        // One approval is insufficient, even if presented twice.
+        assert!(policy
+            .check(&cmd, None, &[a.clone(), a.clone()], &none)
+            .is_err());
+        // Two distinct approvers meet the threshold.
+        let tokens = [a.clone(), b.clone()];
+        let approvers = policy
+            .check(&cmd, None, &tokens, &none)?
+            .iter()
+            .map(|t| t.approver.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(approvers, ["alice", "bob"]);
+        // Approvals do not transfer to a different command.
+        assert!(policy.check(&command("bar"), None, &tokens, &none).is_err());
+        // A token signed by an unknown key under a known name is rejected.
+        let forged = ApprovalToken::new("carol", cmd.clone(), DAY)?.sign(&mallory)?;
+        assert!(policy
+            .check(&cmd, None, &[a.clone(), forged], &none)
+            .is_err());
+        // A token whose expiry was altered no longer verifies.
+        let mut extended = b.clone();
+        extended.expires += 1;
+        assert!(policy.check(&cmd, None, &[a, extended], &none).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_single_use() -> Result<()> {
+        let alice = SigningKey::random(&mut OsRng);
+        let keys = IndexMap::from([("alice".to_string(), *alice.verifying_key())]);
+        let policy = ApprovalPolicy::new(1, vec![AuditOp::Sign], keys)?;
+        let cmd = command("foo");
+        let a = ApprovalToken::new("alice", cmd.clone(), DAY)?.sign(&alice)?;
+        let consumed = HashSet::from([a.nonce.clone()]);
+        assert!(policy
+            .check(&cmd, None, std::slice::from_ref(&a), &consumed)
+            .is_err());
+        // A new approval of the same command has a different nonce.
+        let b = ApprovalToken::new("alice", cmd.clone(), DAY)?.sign(&alice)?;
+        assert_ne!(a.nonce, b.nonce);
+        assert!(policy.check(&cmd, None, &[a, b], &consumed).is_ok());
+        // Expired tokens are rejected.
+        let expired = ApprovalToken::new("alice", cmd.clone(), Duration::ZERO)?.sign(&alice)?;
+        assert!(policy
+            .check(&cmd, None, &[expired], &HashSet::new())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_input_digest() -> Result<()> {
+        let alice = SigningKey::random(&mut OsRng);
+        let keys = IndexMap::from([("alice".to_string(), *alice.verifying_key())]);
+        let policy = ApprovalPolicy::new(1, vec![AuditOp::Sign], keys)?;
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("digest.bin");
+        std::fs::write(&input, [0u8; 32])?;
+        let mut cmd = command("foo");
+        cmd["input"] = serde_json::json!(input);
+        let a = ApprovalToken::new("alice", cmd.clone(), DAY)?.sign(&alice)?;
+        assert!(a.input_digest.is_some());
+        let none = HashSet::new();
+        let digest = input_digest(&cmd)?;
+        assert!(policy
+            .check(&cmd, digest.as_deref(), std::slice::from_ref(&a), &none)
+            .is_ok());
+        // Replacing the input file after approval invalidates the approval.
+        std::fs::write(&input, [1u8; 32])?;
+        let digest = input_digest(&cmd)?;
+        assert!(policy.check(&cmd, digest.as_deref(), &[a], &none).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsatisfiable_policy() {
+        let alice = SigningKey::random(&mut OsRng);
+        let keys = IndexMap::from([("alice".to_string(), *alice.verifying_key())]);
+        assert!(ApprovalPolicy::new(2, vec![AuditOp::Sign], keys).is_err());
+    }
+}
@@ -286,6 +286,11 @@ impl AttributeMap {
         }
     }
 
+    /// Retains only the attributes whose types are in `types`.
+    pub fn retain(&mut self, types: &HashSet<AttributeType>) {
+        self.0.retain(|k, _| types.contains(k));
+    }
+
     pub fn redact(&mut self, redactions: &HashSet<AttributeType>) {
         for (k, v) in self.0.iter_mut() {
             if redactions.contains(k) && !matches!(v, AttrData::Redacted(_)) {
@@ -0,0 +1,344 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The `util::audit` module provides an append-only, hash-chained log of the
+//! sensitive operations performed by `hsmtool`.
+//!
+//! The log is a file of JSON lines.  Each entry contains the SHA-256 hash of
+//! the previous entry, so removing or modifying any entry breaks the chain
+//! for all subsequent entries.
+//!
+//! An audited command is recorded twice: a `pending` entry is written before
+//! the command runs, and a `success` or `failure` entry once it completes.
+//! The log is locked for the whole operation, so concurrent instances of
+//! `hsmtool` cannot interleave their entries.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::HsmError;
+
+/// The class of operation recorded in the audit log.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditOp {
+    Sign,
+    Decrypt,
+    Generate,
+    Import,
+    Update,
+    Destroy,
+    Export,
+    /// A command which is not known to the classifier.
+    Other,
+}
+
+impl AuditOp {
+    /// Classifies a command by its `typetag` name.  Returns `None` for
+    /// commands which are known not to be sensitive.  Commands which are not
+    /// listed here are classified as `Other`, so that new commands are
+    /// audited until they are explicitly classified.
+    pub fn classify(command: &str) -> Option<Self> {
+        match command {
+            "ecdsa-sign" | "rsa-sign" | "spx-sign" | "cert-issue" => Some(AuditOp::Sign),
+            "rsa-decrypt" => Some(AuditOp::Decrypt),
+            "ecdsa-generate" | "rsa-generate" | "spx-generate" => Some(AuditOp::Generate),
+            "ecdsa-import" | "rsa-import" | "spx-import" | "object-unwrap" => Some(AuditOp::Import),
+            "object-update" => Some(AuditOp::Update),
+            "object-destroy" => Some(AuditOp::Destroy),
+            "ecdsa-export" | "rsa-export" | "spx-export" | "object-wrap" => Some(AuditOp::Export),
+            "approval-create" | "ecdsa-verify" | "exec" | "object-list" | "object-show"
+            | "rsa-encrypt" | "rsa-verify" | "spx-list" | "spx-verify" | "token-list" => None,
+            _ => Some(AuditOp::Other),
+        }
+    }
+}
+
+/// The fields of a command which name its input files.
+const INPUT_FIELDS: [&str; 4] = ["input", "filename", "template", "subst"];
+
+/// Computes the digest of the input files named by the JSON form of a
+/// command: the SHA-256 digest of the SHA-256 digests of each file, in the
+/// order of `INPUT_FIELDS`.  Returns `None` if the command has no input files.
+pub fn input_digest(command: &serde_json::Value) -> Result<Option<String>> {
+    let mut hasher = Sha256::new();
+    let mut found = false;
+    for field in INPUT_FIELDS {
+        let Some(path) = command.get(field).and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let data = std::fs::read(path).with_context(|| format!("Reading {path:?}"))?;
+        hasher.update(Sha256::digest(data));
+        found = true;
+    }
+    Ok(found.then(|| hex::encode(hasher.finalize())))
+}
+
+/// Returns the current time in seconds since the UNIX epoch.
+pub fn now() -> Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+/// The status of an audited command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditStatus {
+    /// The command was authorized and is about to run.
+    Pending,
+    /// The command succeeded.
+    Success,
+    /// The command was rejected or failed.
+    Failure,
+}
+
+/// A single record in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Sequence number of this entry in the log.
+    pub seq: u64,
+    /// Seconds since the UNIX epoch.
+    pub timestamp: u64,
+    /// The name of the command.
+    pub command: String,
+    /// The class of operation.
+    pub op: AuditOp,
+    /// The id of the key operated upon (if any).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The label of the key operated upon (if any).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The digest of the command's input files (see `input_digest`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_digest: Option<String>,
+    /// The approvers who authorized the operation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub approvers: Vec<String>,
+    /// The nonces of the approval tokens consumed by the operation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nonces: Vec<String>,
+    /// The status of the command.
+    pub status: AuditStatus,
+    /// The error message if the command failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The hash of the previous entry in the log.
+    pub prev_hash: String,
+    /// The hash of this entry.
+    #[serde(default)]
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// Computes the hash of this entry (excluding the `hash` field itself).
+    pub fn compute_hash(&self) -> Result<String> {
+        let mut entry = self.clone();
+        entry.hash = String::new();
+        let data = serde_json::to_vec(&entry)?;
+        Ok(hex::encode(Sha256::digest(data)))
+    }
+}
+
+/// An append-only, hash-chained audit log.
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// The `prev_hash` of the first entry in a log.
+    pub const GENESIS: &'static str =
+        "0000000000000000000000000000000000000000000000000000000000000000";
+
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        AuditLog {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    fn verify(file: &File) -> Result<Vec<AuditEntry>> {
+        let mut entries = Vec::<AuditEntry>::new();
+        for (n, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let entry = serde_json::from_str::<AuditEntry>(&line)
+                .with_context(|| format!("Parsing audit log line {}", n + 1))?;
+            let prev_hash = entries
+                .last()
+                .map(|e| e.hash.as_str())
+                .unwrap_or(Self::GENESIS);
+            if entry.seq != entries.len() as u64
+                || entry.prev_hash != prev_hash
+                || entry.hash != entry.compute_hash()?
+            {
+                return Err(HsmError::AuditLogCorrupt(n + 1).into());
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Reads and verifies all of the entries in the log.
+    pub fn read(&self) -> Result<Vec<AuditEntry>> {
+        let file = match File::open(&self.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context(format!("Opening audit log {:?}", self.path)),
+        };
+        rustix::fs::flock(&file, rustix::fs::FlockOperation::LockShared)?;
+        Self::verify(&file)
+    }
+
+    /// Locks the log for exclusive access and verifies it.  The lock is held
+    /// until the returned `LockedAuditLog` is dropped.
+    pub fn lock(&self) -> Result<LockedAuditLog> {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .context(format!("Opening audit log {:?}", self.path))?;
+        rustix::fs::flock(&file, rustix::fs::FlockOperation::LockExclusive)?;
+        let entries = Self::verify(&file)?;
+        Ok(LockedAuditLog { file, entries })
+    }
+
+    /// Verifies the log and appends `entry`, filling in the chaining fields.
+    pub fn append(&self, entry: AuditEntry) -> Result<AuditEntry> {
+        self.lock()?.append(entry)
+    }
+}
+
+/// An audit log which is locked for exclusive access.
+pub struct LockedAuditLog {
+    file: File,
+    entries: Vec<AuditEntry>,
+}
+
+impl LockedAuditLog {
+    /// The entries of the log.
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// The nonces of all approval tokens consumed by the entries of the log.
+    pub fn consumed_nonces(&self) -> HashSet<String> {
+        self.entries
+            .iter()
+            .flat_map(|e| e.nonces.iter().cloned())
+            .collect()
+    }
+
+    /// Appends `entry`, filling in the chaining fields.
+    pub fn append(&mut self, mut entry: AuditEntry) -> Result<AuditEntry> {
+        entry.seq = self.entries.len() as u64;
+        entry.timestamp = now()?;
+        entry.prev_hash = self
+            .entries
+            .last()
+            .map(|e| e.hash.clone())
+            .unwrap_or_else(|| AuditLog::GENESIS.into());
+        entry.hash = entry.compute_hash()?;
+        writeln!(self.file, "{}", serde_json::to_string(&entry)?)?;
+        self.file.sync_all()?;
+        self.entries.push(entry.clone());
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(command: &str) -> AuditEntry {
+        AuditEntry {
+            seq: 0,
+            timestamp: 0,
+            command: command.into(),
+            op: AuditOp::classify(command).unwrap(),
+            id: None,
+            label: Some("foo".into()),
+            input_digest: None,
+            approvers: Vec::new(),
+            nonces: Vec::new(),
+            status: AuditStatus::Success,
+            error: None,
+            prev_hash: String::new(),
+            hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(AuditOp::classify("ecdsa-sign"), Some(AuditOp::Sign));
+        assert_eq!(AuditOp::classify("cert-issue"), Some(AuditOp::Sign));
+        assert_eq!(AuditOp::classify("rsa-decrypt"), Some(AuditOp::Decrypt));
+        assert_eq!(AuditOp::classify("spx-generate"), Some(AuditOp::Generate));
+        assert_eq!(AuditOp::classify("rsa-import"), Some(AuditOp::Import));
+        assert_eq!(AuditOp::classify("object-unwrap"), Some(AuditOp::Import));
+        assert_eq!(AuditOp::classify("object-update"), Some(AuditOp::Update));
+        assert_eq!(AuditOp::classify("object-destroy"), Some(AuditOp::Destroy));
+        assert_eq!(AuditOp::classify("rsa-export"), Some(AuditOp::Export));
+        assert_eq!(AuditOp::classify("object-wrap"), Some(AuditOp::Export));
+        assert_eq!(AuditOp::classify("ecdsa-verify"), None);
+        assert_eq!(AuditOp::classify("object-list"), None);
+        assert_eq!(AuditOp::classify("frobnicate"), Some(AuditOp::Other));
+    }
+
+    #[test]
+    fn test_input_digest() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("input.bin");
+        std::fs::write(&input, b"foo")?;
+        let command = serde_json::json!({"command": "ecdsa-sign", "input": input});
+        let digest = input_digest(&command)?;
+        assert!(digest.is_some());
+        std::fs::write(&input, b"bar")?;
+        assert_ne!(input_digest(&command)?, digest);
+        assert_eq!(
+            input_digest(&serde_json::json!({"command": "object-destroy"}))?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_chain() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log = AuditLog::new(dir.path().join("audit.log"));
+        let a = log.append(entry("ecdsa-generate"))?;
+        let b = log.append(entry("ecdsa-sign"))?;
+        assert_eq!(a.prev_hash, AuditLog::GENESIS);
+        assert_eq!(b.prev_hash, a.hash);
+        assert_eq!(log.read()?, vec![a, b]);
+
+        // Tamper with the first entry and check that the log no longer verifies.
+        let data = std::fs::read_to_string(dir.path().join("audit.log"))?;
+        let data = data.replacen("ecdsa-generate", "rsa-generate", 1);
+        std::fs::write(dir.path().join("audit.log"), data)?;
+        assert!(log.read().is_err());
+        assert!(log.lock().is_err());
+        assert!(log.append(entry("ecdsa-sign")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_consumed_nonces() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let log = AuditLog::new(dir.path().join("audit.log"));
+        let mut locked = log.lock()?;
+        let mut e = entry("ecdsa-sign");
+        e.nonces = vec!["1234".into()];
+        locked.append(e)?;
+        locked.append(entry("ecdsa-sign"))?;
+        assert_eq!(locked.entries().len(), 2);
+        drop(locked);
+        assert!(log.lock()?.consumed_nonces().contains("1234"));
+        Ok(())
+    }
+}
@@ -2,11 +2,14 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod approval;
 pub mod attribute;
+pub mod audit;
 pub mod escape;
 pub mod helper;
 pub mod key;
 pub mod signing;
+pub mod wrap;
 
 /// The `testdata` macro can be used in tests to reference testdata directories.
 #[macro_export]
@@ -0,0 +1,226 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use cryptoki::mechanism::rsa::{PkcsMgfType, PkcsOaepParams, PkcsOaepSource};
+use cryptoki::mechanism::Mechanism;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::util::attribute::{AttrData, AttributeMap, AttributeType, KeyType, ObjectClass};
+use crate::util::helper;
+
+/// The key-wrapping mechanisms supported for moving keys between tokens.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WrapMechanism {
+    /// AES key wrap with padding (RFC 5649).  The wrapping key is an AES
+    /// secret key present on both tokens.
+    #[serde(rename = "CKM_AES_KEY_WRAP_PAD", alias = "aes-key-wrap-pad")]
+    AesKeyWrapPad,
+    /// RSA-OAEP with SHA-256 and MGF1-SHA256.  The wrapping key is the
+    /// destination token's RSA public key; unwrapping uses the corresponding
+    /// private key.
+    #[serde(rename = "CKM_RSA_PKCS_OAEP", alias = "rsa-pkcs-oaep")]
+    RsaPkcsOaep,
+}
+
+impl WrapMechanism {
+    /// Returns the cryptoki `Mechanism` for this wrapping mechanism.
+    pub fn mechanism(&self) -> Mechanism<'static> {
+        match self {
+            WrapMechanism::AesKeyWrapPad => Mechanism::AesKeyWrapPad,
+            WrapMechanism::RsaPkcsOaep => Mechanism::RsaPkcsOaep(PkcsOaepParams::new(
+                cryptoki::mechanism::MechanismType::SHA256,
+                PkcsMgfType::MGF1_SHA256,
+                PkcsOaepSource::empty(),
+            )),
+        }
+    }
+
+    /// Returns the search attributes for the key that wraps with this mechanism.
+    pub fn wrapping_key_spec(&self, label: &str) -> AttributeMap {
+        let mut attrs = AttributeMap::default();
+        attrs.insert(AttributeType::Label, AttrData::Str(label.into()));
+        attrs.insert(AttributeType::Wrap, AttrData::from(true));
+        match self {
+            WrapMechanism::AesKeyWrapPad => {
+                attrs.insert(AttributeType::Class, AttrData::from(ObjectClass::SecretKey));
+                attrs.insert(AttributeType::KeyType, AttrData::from(KeyType::Aes));
+            }
+            WrapMechanism::RsaPkcsOaep => {
+                attrs.insert(AttributeType::Class, AttrData::from(ObjectClass::PublicKey));
+                attrs.insert(AttributeType::KeyType, AttrData::from(KeyType::Rsa));
+            }
+        }
+        attrs
+    }
+
+    /// Returns the search attributes for the key that unwraps with this mechanism.
+    pub fn unwrapping_key_spec(&self, label: &str) -> AttributeMap {
+        let mut attrs = AttributeMap::default();
+        attrs.insert(AttributeType::Label, AttrData::Str(label.into()));
+        attrs.insert(AttributeType::Unwrap, AttrData::from(true));
+        match self {
+            WrapMechanism::AesKeyWrapPad => {
+                attrs.insert(AttributeType::Class, AttrData::from(ObjectClass::SecretKey));
+                attrs.insert(AttributeType::KeyType, AttrData::from(KeyType::Aes));
+            }
+            WrapMechanism::RsaPkcsOaep => {
+                attrs.insert(
+                    AttributeType::Class,
+                    AttrData::from(ObjectClass::PrivateKey),
+                );
+                attrs.insert(AttributeType::KeyType, AttrData::from(KeyType::Rsa));
+            }
+        }
+        attrs
+    }
+}
+
+/// A wrapped key together with the attribute template needed to recreate
+/// the key object on the destination token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrappedKey {
+    /// The mechanism used to wrap the key.
+    pub mechanism: WrapMechanism,
+    /// The label of the wrapping key at the time the key was wrapped.
+    pub wrapping_key: String,
+    /// The attributes of the wrapped key object.
+    pub attributes: AttributeMap,
+    /// The wrapped key material.
+    pub wrapped: AttrData,
+}
+
+impl WrappedKey {
+    /// Attributes of a key object which can be supplied in an unwrap template.
+    /// Attributes that are computed by the token (e.g. `CKA_LOCAL`,
+    /// `CKA_NEVER_EXTRACTABLE`) or which are part of the key material itself
+    /// are not carried in the wrapped blob.
+    pub fn portable_attributes() -> HashSet<AttributeType> {
+        HashSet::from([
+            AttributeType::Class,
+            AttributeType::KeyType,
+            AttributeType::Label,
+            AttributeType::Id,
+            AttributeType::Token,
+            AttributeType::Private,
+            AttributeType::Modifiable,
+            AttributeType::Copyable,
+            AttributeType::Destroyable,
+            AttributeType::Sensitive,
+            AttributeType::Extractable,
+            AttributeType::Encrypt,
+            AttributeType::Decrypt,
+            AttributeType::Sign,
+            AttributeType::SignRecover,
+            AttributeType::Verify,
+            AttributeType::VerifyRecover,
+            AttributeType::Wrap,
+            AttributeType::Unwrap,
+            AttributeType::WrapWithTrusted,
+            AttributeType::Derive,
+            AttributeType::AlwaysAuthenticate,
+            AttributeType::Subject,
+            AttributeType::StartDate,
+            AttributeType::EndDate,
+        ])
+    }
+
+    /// Creates a `WrappedKey` from the key object's attributes and its
+    /// wrapped key material.
+    pub fn new(
+        mechanism: WrapMechanism,
+        wrapping_key: &str,
+        mut attributes: AttributeMap,
+        wrapped: &[u8],
+    ) -> Self {
+        attributes.retain(&Self::portable_attributes());
+        WrappedKey {
+            mechanism,
+            wrapping_key: wrapping_key.into(),
+            attributes,
+            wrapped: AttrData::from(wrapped),
+        }
+    }
+
+    /// Returns the wrapped key material.
+    pub fn wrapped(&self) -> Result<Vec<u8>> {
+        Ok(Vec::<u8>::try_from(&self.wrapped)?)
+    }
+
+    /// Reads a `WrappedKey` from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Ok(serde_annotate::from_str(&data)?)
+    }
+
+    /// Writes the `WrappedKey` to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let data = serde_json::to_string_pretty(self)?;
+        helper::write_file(path, data.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const KEY_ATTRS: &str = r#"{
+        "CKA_CLASS": "CKO_PRIVATE_KEY",
+        "CKA_KEY_TYPE": "CKK_EC",
+        "CKA_LABEL": "foo",
+        "CKA_ID": "01:02:03:04",
+        "CKA_TOKEN": true,
+        "CKA_SIGN": true,
+        "CKA_EXTRACTABLE": true,
+        "CKA_LOCAL": true,
+        "CKA_NEVER_EXTRACTABLE": false,
+        "CKA_VALUE": "RedactedByHsm"
+    }"#;
+
+    #[test]
+    fn test_wrapped_key_template() -> Result<()> {
+        let attrs = AttributeMap::from_str(KEY_ATTRS)?;
+        let wk = WrappedKey::new(
+            WrapMechanism::AesKeyWrapPad,
+            "wrapper",
+            attrs,
+            &[0xaa, 0xbb, 0xcc],
+        );
+        assert_eq!(
+            wk.attributes.get(&AttributeType::Label),
+            Some(&AttrData::Str("foo".into()))
+        );
+        assert_eq!(
+            wk.attributes.get(&AttributeType::Extractable),
+            Some(&AttrData::Bool(true))
+        );
+        assert!(wk.attributes.get(&AttributeType::Local).is_none());
+        assert!(wk
+            .attributes
+            .get(&AttributeType::NeverExtractable)
+            .is_none());
+        assert!(wk.attributes.get(&AttributeType::Value).is_none());
+        assert_eq!(wk.wrapped()?, &[0xaa, 0xbb, 0xcc]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrapped_key_serde() -> Result<()> {
+        let attrs = AttributeMap::from_str(KEY_ATTRS)?;
+        let wk = WrappedKey::new(WrapMechanism::RsaPkcsOaep, "wrapper", attrs, &[1, 2, 3]);
+        let json = serde_json::to_string(&wk)?;
+        assert!(json.contains(r#""mechanism":"CKM_RSA_PKCS_OAEP""#));
+        let wk2 = serde_json::from_str::<WrappedKey>(&json)?;
+        assert_eq!(wk2.mechanism, WrapMechanism::RsaPkcsOaep);
+        assert_eq!(wk2.wrapped()?, &[1, 2, 3]);
+        assert_eq!(
+            wk2.attributes.to_vec()?.len(),
+            wk.attributes.to_vec()?.len()
+        );
+        Ok(())
+    }
+}
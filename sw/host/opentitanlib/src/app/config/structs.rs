@@ -54,14 +54,24 @@ pub struct IoExpander {
     pub i2c_bus: Option<String>,
     /// I2C address of this IO expander sits (if the driver uses I2C).
     pub i2c_address: Option<u8>,
+    /// SPI bus on which this IO expander sits (if the driver uses SPI).
+    pub spi_bus: Option<String>,
+    /// Hardware address of this IO expander on the SPI bus, defaults to zero (if the driver uses
+    /// SPI and supports hardware addressing).
+    pub spi_address: Option<u8>,
     /// Optional gpio strapping for MUXing the bus from the transport to this IO expander.
     pub mux_strapping: Option<String>,
+    /// Optional gpio pin of the transport connected to the interrupt output of this IO expander.
+    pub interrupt_pin: Option<String>,
 }
 
 /// Identifier of the driver/protocol uses by an IO expander.
 #[derive(Deserialize, Clone, Debug)]
 pub enum IoExpanderDriver {
     Sx1503,
+    Tca6416,
+    Pca9555,
+    Mcp23s17,
 }
 
 /// Configuration of a particular GPIO pin.
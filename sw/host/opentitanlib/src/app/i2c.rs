@@ -2,7 +2,7 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::io::i2c::{self, Bus, DeviceStatus, Mode};
+use crate::io::i2c::{self, Address, Bus, DeviceStatus, Mode};
 use crate::transport::Transport;
 
 use anyhow::Result;
@@ -47,6 +47,7 @@ pub struct LogicalI2cWrapper {
     uid: usize,
     default_addr: Cell<Option<u8>>,
     max_speed: Cell<Option<u32>>,
+    clock_stretch_timeout: Cell<Option<Duration>>,
 }
 
 impl LogicalI2cWrapper {
@@ -61,6 +62,7 @@ impl LogicalI2cWrapper {
             uid: COUNTER.fetch_add(1, Ordering::Relaxed),
             default_addr: Cell::new(conf.default_addr),
             max_speed: Cell::new(conf.bits_per_sec),
+            clock_stretch_timeout: Cell::new(None),
         })
     }
 
@@ -80,6 +82,11 @@ impl LogicalI2cWrapper {
                 .underlying_target
                 .set_max_speed(speed)?;
         }
+        if let Some(timeout) = self.clock_stretch_timeout.get() {
+            self.physical_wrapper
+                .underlying_target
+                .set_clock_stretch_timeout(timeout)?;
+        }
         self.physical_wrapper.last_used_by_uid.set(Some(self.uid));
         Ok(())
     }
@@ -110,6 +117,23 @@ impl Bus for LogicalI2cWrapper {
             .run_transaction(addr, transaction)
     }
 
+    fn run_transaction_at(&self, addr: Address, transaction: &mut [i2c::Transfer]) -> Result<()> {
+        self.apply_settings_to_underlying()?;
+        self.physical_wrapper
+            .underlying_target
+            .run_transaction_at(addr, transaction)
+    }
+
+    fn set_clock_stretch_timeout(&self, timeout: Duration) -> Result<()> {
+        // Unlike the other settings, apply immediately to report lack of support by the
+        // transport.
+        self.physical_wrapper
+            .underlying_target
+            .set_clock_stretch_timeout(timeout)?;
+        self.clock_stretch_timeout.set(Some(timeout));
+        Ok(())
+    }
+
     fn get_device_status(&self, timeout: Duration) -> Result<DeviceStatus> {
         self.physical_wrapper
             .underlying_target
@@ -222,6 +222,7 @@ pub struct TransportWrapper {
     spi_conf_map: HashMap<String, SpiConfiguration>,
     i2c_conf_map: HashMap<String, I2cConfiguration>,
     strapping_conf_map: HashMap<String, HashMap<String, PinConfiguration>>,
+    io_expander_map: HashMap<String, Rc<IoExpander>>,
     //
     // Below fields are lazily populated, as instances are requested.
     //
@@ -645,17 +646,18 @@ impl TransportWrapperBuilder {
             spi_conf_map,
             i2c_conf_map,
             strapping_conf_map,
+            io_expander_map: HashMap::new(),
             pin_instance_map: RefCell::new(HashMap::new()),
             spi_physical_map: RefCell::new(HashMap::new()),
             spi_logical_map: RefCell::new(HashMap::new()),
             i2c_physical_map: RefCell::new(HashMap::new()),
             i2c_logical_map: RefCell::new(HashMap::new()),
         };
-        let mut io_expanders: HashMap<String, IoExpander> = HashMap::new();
+        let mut io_expanders: HashMap<String, Rc<IoExpander>> = HashMap::new();
         for (name, conf) in self.io_expander_conf_map {
             io_expanders.insert(
                 name.to_string(),
-                ioexpander::create(&conf, &transport_wrapper)?,
+                Rc::new(ioexpander::create(&conf, &transport_wrapper)?),
             );
         }
         transport_wrapper
@@ -676,6 +678,7 @@ impl TransportWrapperBuilder {
                 ));
             }
         }
+        transport_wrapper.io_expander_map = io_expanders;
         Ok(transport_wrapper)
     }
 }
@@ -851,6 +854,13 @@ impl TransportWrapper {
         self.transport.gpio_bitbanging()
     }
 
+    /// Returns the IO expander declared with the given name in the configuration files.
+    pub fn io_expander(&self, name: &str) -> Result<Rc<IoExpander>> {
+        Ok(Rc::clone(self.io_expander_map.get(name).ok_or_else(
+            || TransportError::InvalidIoExpanderName(name.to_string()),
+        )?))
+    }
+
     pub fn pin_strapping(&self, name: &str) -> Result<PinStrapping> {
         let proxy = if self.capabilities()?.request(Capability::PROXY).ok().is_ok() {
             Some(self.proxy_ops()?)
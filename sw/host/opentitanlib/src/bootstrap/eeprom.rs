@@ -2,20 +2,26 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 
 use crate::app::TransportWrapper;
-use crate::bootstrap::{Bootstrap, UpdateProtocol};
-use crate::spiflash::SpiFlash;
+use crate::bootstrap::{Bootstrap, BootstrapOptions, UpdateProtocol};
+use crate::spiflash::{FlashLayout, SpiFlash};
 use crate::transport::{Capability, ProgressIndicator};
 
 /// Implements the SPI EEPROM bootstrap protocol.
-pub struct Eeprom;
+pub struct Eeprom {
+    pub layout: Option<FlashLayout>,
+    pub region: Option<String>,
+}
 
 impl Eeprom {
-    /// Creates a new `Eeprom` protocol updater.
-    pub fn new() -> Self {
-        Eeprom
+    /// Creates a new `Eeprom` protocol updater from `options`.
+    pub fn new(options: &BootstrapOptions) -> Self {
+        Eeprom {
+            layout: options.layout.clone(),
+            region: options.region.clone(),
+        }
     }
 }
 
@@ -45,9 +51,31 @@ impl UpdateProtocol for Eeprom {
         progress: &dyn ProgressIndicator,
     ) -> Result<()> {
         let spi = container.spi_params.create(transport, "BOOTSTRAP")?;
-        let flash = SpiFlash::from_spi(&*spi)?;
-        flash.chip_erase(&*spi)?;
-        flash.program_with_progress(&*spi, 0, payload, progress)?;
+        let mut flash = SpiFlash::from_spi(&*spi)?;
+        if let Some(name) = &self.region {
+            // Only replace the contents of the region, leaving the rest of the flash intact.
+            flash.set_address_mode_auto(&*spi)?;
+            let layout = match &self.layout {
+                Some(layout) => {
+                    layout.validate(Some(flash.size))?;
+                    layout.clone()
+                }
+                None => FlashLayout::read_fmap(&flash, &*spi)?,
+            };
+            let region = layout.region(name)?;
+            ensure!(
+                payload.len() <= region.size as usize,
+                "Payload of {} bytes does not fit in region {:?} of {} bytes",
+                payload.len(),
+                name,
+                region.size
+            );
+            flash.erase_with_progress(&*spi, region.offset, region.size, progress)?;
+            flash.program_with_progress(&*spi, region.offset, payload, progress)?;
+        } else {
+            flash.chip_erase(&*spi)?;
+            flash.program_with_progress(&*spi, 0, payload, progress)?;
+        }
         SpiFlash::chip_reset(&*spi)?;
         Ok(())
     }
@@ -15,6 +15,7 @@ use crate::impl_serializable_error;
 use crate::io::gpio::GpioPin;
 use crate::io::spi::SpiParams;
 use crate::io::uart::UartParams;
+use crate::spiflash::FlashLayout;
 use crate::transport::{Capability, ProgressIndicator};
 
 mod eeprom;
@@ -101,6 +102,13 @@ pub struct BootstrapOptions {
     /// Duration of the flash-erase delay.
     #[arg(long, value_parser = parse_duration)]
     pub flash_erase_delay: Option<Duration>,
+    /// Flash layout file, in JSON or HJSON format (EEPROM protocol only).
+    #[arg(long, value_parser = FlashLayout::load)]
+    pub layout: Option<FlashLayout>,
+    /// Program only this region of the flash layout, which is read from the FMAP of the flash
+    /// when `--layout` is not given (EEPROM protocol only).
+    #[arg(long)]
+    pub region: Option<String>,
 }
 
 /// Bootstrap wraps and drives the various bootstrap protocols.
@@ -151,7 +159,7 @@ impl<'a> Bootstrap<'a> {
             BootstrapProtocol::Primitive => Box::new(primitive::Primitive::new(options)),
             BootstrapProtocol::Legacy => Box::new(legacy::Legacy::new(options)),
             BootstrapProtocol::LegacyRescue => Box::new(legacy_rescue::LegacyRescue::new(options)),
-            BootstrapProtocol::Eeprom => Box::new(eeprom::Eeprom::new()),
+            BootstrapProtocol::Eeprom => Box::new(eeprom::Eeprom::new(options)),
             BootstrapProtocol::Emulator => {
                 // Not intended to be implemented by this struct.
                 unimplemented!();
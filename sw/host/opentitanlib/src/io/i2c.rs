@@ -2,7 +2,7 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use clap::Args;
 use serde::{Deserialize, Serialize};
 use std::rc::Rc;
@@ -63,11 +63,45 @@ pub enum I2cError {
     MissingAddress,
     #[error("I2C port not in device mode")]
     NotInDeviceMode,
+    #[error("Invalid I2C address 0x{0:x}")]
+    InvalidAddress(u16),
+    #[error("Packet error code mismatch: expected 0x{0:02x}, received 0x{1:02x}")]
+    PecMismatch(u8, u8),
     #[error("Generic error {0}")]
     Generic(String),
 }
 impl_serializable_error!(I2cError);
 
+/// Address of an I2C device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Address {
+    /// 7-bit address, the most common.
+    SevenBit(u8),
+    /// 10-bit address, transmitted as the reserved 7-bit address `11110xx` carrying the two
+    /// most significant bits, followed by a byte with the eight least significant bits.
+    TenBit(u16),
+}
+
+/// Reserved 7-bit address which starts the addressing of 10-bit devices.
+pub(crate) const TEN_BIT_ADDRESS_PREFIX: u8 = 0x78;
+
+/// Maximum length of a SMBus block read.
+const SMBUS_BLOCK_MAX_LEN: usize = 32;
+
+/// Computes the SMBus packet error code (PEC) of `data`, a CRC-8 with polynomial
+/// x^8 + x^2 + x + 1, over all bytes of a transaction including the address bytes.
+pub fn smbus_pec(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
 /// Represents a I2C transfer to be initiated by the debugger as I2C host.
 pub enum Transfer<'rd, 'wr> {
     Read(&'rd mut [u8]),
@@ -151,6 +185,100 @@ pub trait Bus {
     /// `None`, then the last value given to `set_default_adress()` is used instead.
     fn run_transaction(&self, addr: Option<u8>, transaction: &mut [Transfer]) -> Result<()>;
 
+    /// Runs a I2C transaction with a device which may use a 10-bit address.  By default, the
+    /// transaction is translated into a transaction with the reserved 7-bit address of the 10-bit
+    /// device, in which each write starts with the low byte of the address, and each read not
+    /// directly following a write is preceded by a write of that byte, so that the repeated start
+    /// of every read addresses the device.
+    fn run_transaction_at(&self, addr: Address, transaction: &mut [Transfer]) -> Result<()> {
+        let addr = match addr {
+            Address::SevenBit(addr) => {
+                ensure!(addr < 0x80, I2cError::InvalidAddress(addr as u16));
+                return self.run_transaction(Some(addr), transaction);
+            }
+            Address::TenBit(addr) => {
+                ensure!(addr < 0x400, I2cError::InvalidAddress(addr));
+                addr
+            }
+        };
+        let low_byte = [addr as u8];
+        let writes: Vec<Vec<u8>> = transaction
+            .iter()
+            .map(|transfer| match transfer {
+                Transfer::Write(wbuf) => [&low_byte, *wbuf].concat(),
+                Transfer::Read(_) => Vec::new(),
+            })
+            .collect();
+        let mut translated = Vec::new();
+        let mut after_write = false;
+        for (transfer, write) in transaction.iter_mut().zip(&writes) {
+            match transfer {
+                Transfer::Write(_) => {
+                    translated.push(Transfer::Write(write));
+                    after_write = true;
+                }
+                Transfer::Read(rbuf) => {
+                    if !after_write {
+                        translated.push(Transfer::Write(&low_byte));
+                    }
+                    translated.push(Transfer::Read(rbuf));
+                    after_write = false;
+                }
+            }
+        }
+        self.run_transaction(
+            Some(TEN_BIT_ADDRESS_PREFIX | (addr >> 8) as u8),
+            &mut translated,
+        )
+    }
+
+    /// Sets for how long the I2C host lets a device stretch the clock, before aborting the
+    /// transaction with [`I2cError::Timeout`].
+    fn set_clock_stretch_timeout(&self, _timeout: Duration) -> Result<()> {
+        Err(TransportError::UnsupportedOperation.into())
+    }
+
+    /// Performs a SMBus block write of `data` with command code `cmd`, followed by a packet error
+    /// code if `pec` is true.
+    fn smbus_block_write(&self, addr: u8, cmd: u8, data: &[u8], pec: bool) -> Result<()> {
+        ensure!(data.len() <= 255, I2cError::InvalidDataLength(data.len()));
+        let mut wbuf = vec![addr << 1, cmd, data.len() as u8];
+        wbuf.extend_from_slice(data);
+        if pec {
+            wbuf.push(smbus_pec(&wbuf));
+        }
+        self.run_transaction(Some(addr), &mut [Transfer::Write(&wbuf[1..])])
+    }
+
+    /// Performs a SMBus block read with command code `cmd`, verifying the packet error code
+    /// sent by the device if `pec` is true.  As the length of the block is only known from the
+    /// first byte sent by the device, the default implementation reads a block of the maximum
+    /// length in a single transaction, and discards the bytes following the block.
+    fn smbus_block_read(&self, addr: u8, cmd: u8, pec: bool) -> Result<Vec<u8>> {
+        let mut rbuf = vec![0u8; 1 + SMBUS_BLOCK_MAX_LEN + pec as usize];
+        self.run_transaction(
+            Some(addr),
+            &mut [Transfer::Write(&[cmd]), Transfer::Read(&mut rbuf)],
+        )?;
+        let count = rbuf[0] as usize;
+        ensure!(
+            count <= SMBUS_BLOCK_MAX_LEN,
+            I2cError::InvalidDataLength(count)
+        );
+        if pec {
+            let received = rbuf[1 + count];
+            let expected =
+                smbus_pec(&[&[addr << 1, cmd, (addr << 1) | 1], &rbuf[..1 + count]].concat());
+            ensure!(
+                received == expected,
+                I2cError::PecMismatch(expected, received)
+            );
+        }
+        rbuf.truncate(1 + count);
+        rbuf.remove(0);
+        Ok(rbuf)
+    }
+
     //
     // Methods for use in device mode.
     //
@@ -171,3 +299,94 @@ pub trait Bus {
         Err(TransportError::UnsupportedOperation.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::mock_i2c::RecordingBus;
+
+    #[test]
+    fn ten_bit_address() -> Result<()> {
+        let bus = RecordingBus::default();
+        bus.responses.borrow_mut().push(vec![0x55, 0xaa]);
+        let mut rbuf = [0u8; 2];
+        bus.run_transaction_at(
+            Address::TenBit(0x2a5),
+            &mut [Transfer::Read(&mut rbuf), Transfer::Write(&[0x01])],
+        )?;
+        assert_eq!(rbuf, [0x55, 0xaa]);
+        assert_eq!(
+            bus.transactions.take(),
+            [(
+                Some(0x7a),
+                vec![Some(vec![0xa5]), None, Some(vec![0xa5, 0x01])]
+            )]
+        );
+
+        // Every read not following a write is preceded by the low byte of the address.
+        bus.responses.borrow_mut().extend([vec![0x01], vec![0x02]]);
+        let (mut rbuf1, mut rbuf2) = ([0u8; 1], [0u8; 1]);
+        bus.run_transaction_at(
+            Address::TenBit(0x1ff),
+            &mut [
+                Transfer::Write(&[0x03]),
+                Transfer::Read(&mut rbuf1),
+                Transfer::Read(&mut rbuf2),
+            ],
+        )?;
+        assert_eq!((rbuf1, rbuf2), ([0x01], [0x02]));
+        assert_eq!(
+            bus.transactions.take(),
+            [(
+                Some(0x79),
+                vec![Some(vec![0xff, 0x03]), None, Some(vec![0xff]), None]
+            )]
+        );
+
+        bus.run_transaction_at(Address::SevenBit(0x50), &mut [Transfer::Write(&[0x02])])?;
+        assert_eq!(
+            bus.transactions.take(),
+            [(Some(0x50), vec![Some(vec![0x02])])]
+        );
+        assert!(bus
+            .run_transaction_at(Address::TenBit(0x400), &mut [])
+            .is_err());
+        assert!(bus
+            .run_transaction_at(Address::SevenBit(0x80), &mut [])
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn smbus_blocks() -> Result<()> {
+        assert_eq!(smbus_pec(b"123456789"), 0xf4);
+
+        let bus = RecordingBus::default();
+        bus.smbus_block_write(0x10, 0x20, &[0x01, 0x02], true)?;
+        let pec = smbus_pec(&[0x20, 0x20, 0x02, 0x01, 0x02]);
+        assert_eq!(
+            bus.transactions.take(),
+            [(Some(0x10), vec![Some(vec![0x20, 0x02, 0x01, 0x02, pec])])]
+        );
+
+        let pec = smbus_pec(&[0x20, 0x30, 0x21, 0x03, 0xaa, 0xbb, 0xcc]);
+        let block = vec![0x03, 0xaa, 0xbb, 0xcc, pec];
+        bus.responses.borrow_mut().push(block.clone());
+        assert_eq!(bus.smbus_block_read(0x10, 0x30, true)?, [0xaa, 0xbb, 0xcc]);
+        // The whole block is read in a single transaction.
+        let transactions = bus.transactions.take();
+        assert_eq!(transactions, [(Some(0x10), vec![Some(vec![0x30]), None])]);
+
+        bus.responses.borrow_mut().push(block[..4].to_vec());
+        assert_eq!(bus.smbus_block_read(0x10, 0x30, false)?, [0xaa, 0xbb, 0xcc]);
+
+        let mut corrupted = block.clone();
+        corrupted[4] ^= 0x01;
+        bus.responses.borrow_mut().push(corrupted);
+        assert!(bus.smbus_block_read(0x10, 0x30, true).is_err());
+
+        bus.responses.borrow_mut().push(vec![33]);
+        assert!(bus.smbus_block_read(0x10, 0x30, false).is_err());
+        Ok(())
+    }
+}
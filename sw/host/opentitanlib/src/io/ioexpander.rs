@@ -2,10 +2,28 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
+use anyhow::Result;
 use std::rc::Rc;
 
 use crate::io::gpio::GpioPin;
 
 pub struct IoExpander {
     pub pins: Vec<Rc<dyn GpioPin>>,
+    /// Present if the interrupt output of the chip is connected to a GPIO pin of the transport,
+    /// as declared by `interrupt_pin` in the configuration.
+    pub interrupt: Option<Rc<dyn IoExpanderInterrupt>>,
+}
+
+/// Interrupts of an IO expander, signalled on its interrupt output when input pins change.  In
+/// the masks, bit `n` corresponds to pin `n` of the IO expander.
+pub trait IoExpanderInterrupt {
+    /// Enables the interrupt on any change of the pins in `mask`, and disables it for the other
+    /// pins.
+    fn set_interrupt_mask(&self, mask: u16) -> Result<()>;
+
+    /// Returns whether the interrupt output of the IO expander is asserted.
+    fn is_pending(&self) -> Result<bool>;
+
+    /// Returns the pins whose change caused an interrupt, and clears the interrupt.
+    fn take_interrupts(&self) -> Result<u16>;
 }
@@ -58,6 +58,48 @@ impl<'a> TransportCommandHandler<'a> {
         }
     }
 
+    /// Runs the I2C transfers of a proxy request, and constructs the response with the data read.
+    fn run_i2c_transaction(
+        reqs: &[I2cTransferRequest],
+        run: impl FnOnce(&mut [i2c::Transfer]) -> Result<()>,
+    ) -> Result<Response> {
+        // Construct proper response to each transfer in request.
+        let mut resps: Vec<I2cTransferResponse> = reqs
+            .iter()
+            .map(|transfer| match transfer {
+                I2cTransferRequest::Read { len } => I2cTransferResponse::Read {
+                    data: vec![0; *len as usize],
+                },
+                I2cTransferRequest::Write { .. } => I2cTransferResponse::Write,
+            })
+            .collect();
+        // Now carefully craft a proper parameter to the
+        // `i2c::Bus::run_transactions()` method.  It will have reference
+        // into elements of both the request vector and mutable reference into
+        // the response vector.
+        let mut transaction: Vec<i2c::Transfer> = reqs
+            .iter()
+            .zip(resps.iter_mut())
+            .map(|pair| match pair {
+                (I2cTransferRequest::Read { .. }, I2cTransferResponse::Read { data }) => {
+                    i2c::Transfer::Read(data)
+                }
+                (I2cTransferRequest::Write { data }, I2cTransferResponse::Write) => {
+                    i2c::Transfer::Write(data)
+                }
+                _ => {
+                    // This can only happen if the logic in this method is
+                    // flawed.  (Never due to network input.)
+                    panic!("Mismatch");
+                }
+            })
+            .collect();
+        run(&mut transaction)?;
+        Ok(Response::I2c(I2cResponse::RunTransaction {
+            transaction: resps,
+        }))
+    }
+
     /// This method will perform whatever action on the underlying `Transport` that is requested
     /// by the given `Request`, and return a response to be sent to the client.  Any `Err`
     /// return from this method will be propagated to the remote client, without any server-side
@@ -447,44 +489,20 @@ impl<'a> TransportCommandHandler<'a> {
                     I2cRequest::RunTransaction {
                         address,
                         transaction: reqs,
-                    } => {
-                        // Construct proper response to each transfer in request.
-                        let mut resps: Vec<I2cTransferResponse> = reqs
-                            .iter()
-                            .map(|transfer| match transfer {
-                                I2cTransferRequest::Read { len } => I2cTransferResponse::Read {
-                                    data: vec![0; *len as usize],
-                                },
-                                I2cTransferRequest::Write { .. } => I2cTransferResponse::Write,
-                            })
-                            .collect();
-                        // Now carefully craft a proper parameter to the
-                        // `i2c::Bus::run_transactions()` method.  It will have reference
-                        // into elements of both the request vector and mutable reference into
-                        // the response vector.
-                        let mut transaction: Vec<i2c::Transfer> = reqs
-                            .iter()
-                            .zip(resps.iter_mut())
-                            .map(|pair| match pair {
-                                (
-                                    I2cTransferRequest::Read { .. },
-                                    I2cTransferResponse::Read { data },
-                                ) => i2c::Transfer::Read(data),
-                                (
-                                    I2cTransferRequest::Write { data },
-                                    I2cTransferResponse::Write,
-                                ) => i2c::Transfer::Write(data),
-                                _ => {
-                                    // This can only happen if the logic in this method is
-                                    // flawed.  (Never due to network input.)
-                                    panic!("Mismatch");
-                                }
-                            })
-                            .collect();
-                        instance.run_transaction(*address, &mut transaction)?;
-                        Ok(Response::I2c(I2cResponse::RunTransaction {
-                            transaction: resps,
-                        }))
+                    } => Self::run_i2c_transaction(reqs, |transaction| {
+                        instance.run_transaction(*address, transaction)
+                    }),
+                    I2cRequest::RunTransactionAt {
+                        address,
+                        transaction: reqs,
+                    } => Self::run_i2c_transaction(reqs, |transaction| {
+                        instance.run_transaction_at(*address, transaction)
+                    }),
+                    I2cRequest::SetClockStretchTimeout { timeout_micros } => {
+                        instance.set_clock_stretch_timeout(Duration::from_micros(
+                            *timeout_micros as u64,
+                        ))?;
+                        Ok(Response::I2c(I2cResponse::SetClockStretchTimeout))
                     }
                     I2cRequest::GetDeviceStatus { timeout_millis } => {
                         let status = instance
@@ -576,3 +594,118 @@ impl<'a> CommandHandler<Message, NonblockingUartRegistry> for TransportCommandHa
         self.nonblocking_help.nonblocking_help()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::TransportWrapperBuilder;
+    use crate::io::i2c::{Address, Bus, I2cError};
+    use crate::test_utils::mock_i2c::RecordingBus;
+    use crate::transport::{Capabilities, Capability, EmptyTransport, Transport};
+    use mio::Poll;
+
+    struct I2cTransport(Rc<RecordingBus>);
+
+    impl Transport for I2cTransport {
+        fn capabilities(&self) -> Result<Capabilities> {
+            Ok(EmptyTransport.capabilities()?.add(Capability::I2C))
+        }
+
+        fn i2c(&self, _instance: &str) -> Result<Rc<dyn Bus>> {
+            Ok(Rc::clone(&self.0) as Rc<dyn Bus>)
+        }
+    }
+
+    /// Passes an I2C request through the handler, serialized as on the connection with the
+    /// proxy client.
+    fn execute_i2c(
+        handler: &mut TransportCommandHandler,
+        command: I2cRequest,
+    ) -> Result<I2cResponse> {
+        let poll = Poll::new()?;
+        let req = Message::Req(Request::I2c {
+            id: "0".to_string(),
+            command,
+        });
+        let req: Message = serde_json::from_slice(&serde_json::to_vec(&req)?)?;
+        let res = handler.execute_cmd(
+            Token(0),
+            poll.registry(),
+            &mut NonblockingUartRegistry::new(),
+            &req,
+        )?;
+        match serde_json::from_slice(&serde_json::to_vec(&res)?)? {
+            Message::Res(Ok(Response::I2c(resp))) => Ok(resp),
+            Message::Res(Err(e)) => Err(e.into()),
+            _ => bail!("Unexpected response"),
+        }
+    }
+
+    #[test]
+    fn i2c_run_transaction_at() -> Result<()> {
+        let bus = Rc::new(RecordingBus::default());
+        let transport = TransportWrapperBuilder::new("test".to_string(), false)
+            .build(Box::new(I2cTransport(Rc::clone(&bus))))?;
+        let mut handler = TransportCommandHandler::new(&transport)?;
+
+        let resp = execute_i2c(
+            &mut handler,
+            I2cRequest::RunTransactionAt {
+                address: Address::TenBit(0x123),
+                transaction: vec![
+                    I2cTransferRequest::Write { data: vec![0x01] },
+                    I2cTransferRequest::Read { len: 2 },
+                ],
+            },
+        )?;
+        let I2cResponse::RunTransaction { transaction } = resp else {
+            panic!("Unexpected response");
+        };
+        assert!(matches!(
+            transaction.as_slice(),
+            [
+                I2cTransferResponse::Write,
+                I2cTransferResponse::Read { data },
+            ] if data == &[0x79, 0x79]
+        ));
+        assert_eq!(
+            bus.transactions.take(),
+            [(Some(0x79), vec![Some(vec![0x23, 0x01]), None])]
+        );
+
+        let err = execute_i2c(
+            &mut handler,
+            I2cRequest::RunTransactionAt {
+                address: Address::SevenBit(0x80),
+                transaction: vec![],
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<I2cError>(),
+            Some(I2cError::InvalidAddress(0x80))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn i2c_set_clock_stretch_timeout() -> Result<()> {
+        let bus = Rc::new(RecordingBus::default());
+        let transport = TransportWrapperBuilder::new("test".to_string(), false)
+            .build(Box::new(I2cTransport(Rc::clone(&bus))))?;
+        let mut handler = TransportCommandHandler::new(&transport)?;
+
+        let resp = execute_i2c(
+            &mut handler,
+            I2cRequest::SetClockStretchTimeout {
+                timeout_micros: 1500,
+            },
+        )?;
+        assert!(matches!(resp, I2cResponse::SetClockStretchTimeout));
+        assert_eq!(
+            bus.clock_stretch_timeout.get(),
+            Some(Duration::from_micros(1500))
+        );
+        Ok(())
+    }
+}
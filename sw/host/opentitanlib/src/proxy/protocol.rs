@@ -10,7 +10,7 @@ use crate::io::emu::{EmuState, EmuValue};
 use crate::io::gpio::{
     ClockNature, MonitoringReadResponse, MonitoringStartResponse, PinMode, PullMode,
 };
-use crate::io::i2c::DeviceStatus;
+use crate::io::i2c::{Address, DeviceStatus};
 use crate::io::spi::{MaxSizes, TransferMode};
 use crate::io::uart::Parity;
 use crate::proxy::errors::SerializedError;
@@ -308,6 +308,13 @@ pub enum I2cRequest {
         address: Option<u8>,
         transaction: Vec<I2cTransferRequest>,
     },
+    RunTransactionAt {
+        address: Address,
+        transaction: Vec<I2cTransferRequest>,
+    },
+    SetClockStretchTimeout {
+        timeout_micros: u32,
+    },
     GetDeviceStatus {
         timeout_millis: u32,
     },
@@ -328,6 +335,7 @@ pub enum I2cResponse {
     RunTransaction {
         transaction: Vec<I2cTransferResponse>,
     },
+    SetClockStretchTimeout,
     GetDeviceStatus {
         status: DeviceStatus,
     },
@@ -5,12 +5,15 @@
 use crate::app::NoProgressBar;
 use crate::io::eeprom::{AddressMode, Mode, Transaction, MODE_111, MODE_112, MODE_114};
 use crate::io::spi::Target;
+use crate::spiflash::protect::{ProtectedRange, ProtectionScheme, StatusRegisterMap};
 use crate::spiflash::sfdp::{
-    BlockEraseSize, FastReadParam, SectorErase, Sfdp, SupportedAddressModes,
+    BlockEraseSize, DetectAddressLength, FastReadParam, SectorErase, SectorMapConfig, Sfdp,
+    SupportedAddressModes,
 };
 use crate::transport::ProgressIndicator;
 use anyhow::{ensure, Result};
 use clap::ValueEnum;
+use std::cell::Cell;
 use std::convert::TryFrom;
 use thiserror::Error;
 
@@ -22,12 +25,30 @@ pub enum Error {
     BadEraseAddress(u32, u32),
     #[error("erase length {0} not a multiple of {1} bytes")]
     BadEraseLength(u32, u32),
+    #[error("erase not supported at address {0:#x}")]
+    EraseNotSupported(u32),
+    #[error("verification failed at address {0:#x}")]
+    VerifyFailed(u32),
     #[error("bad sequence length: {0}")]
     BadSequenceLength(usize),
     #[error("unsupported mode: {0:?}")]
     UnsupportedMode(ReadMode),
     #[error("unsupported opcode: {0:x?}")]
     UnsupportedOpcode(u8),
+    #[error("unknown block protection scheme for manufacturer {0:#04x}")]
+    UnknownProtectionScheme(u8),
+    #[error("unsupported protection range: start {0:#x}, length {1:#x}")]
+    UnsupportedProtectionRange(u32, u32),
+    #[error("bad security register: {0}")]
+    BadSecurityRegister(u8),
+    #[error("security register access out of bounds: {0} + {1} > {2}")]
+    SecurityRegisterOutOfBounds(u32, usize, u32),
+    #[error("status register 2 is not supported")]
+    NoStatusRegister2,
+    #[error("unknown security register layout for manufacturer {0:#04x}")]
+    UnknownSecurityRegisterLayout(u8),
+    #[error("sector map region at {0:#x} extends past 4 GiB")]
+    SectorMapOverflow(u32),
 }
 
 impl From<SupportedAddressModes> for AddressMode {
@@ -118,6 +139,63 @@ pub enum EraseMode {
     Block,
 }
 
+/// A region of the flash with its own erase types, as described by the SFDP sector map.
+#[derive(Clone, Debug)]
+pub struct EraseRegion {
+    pub start: u32,
+    pub size: u32,
+    /// The erase types usable in the region, sorted largest to smallest.
+    pub erase: Vec<SectorErase>,
+}
+
+/// The outcome of [`SpiFlash::sync_with_progress`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SyncSummary {
+    /// Number of sectors compared.
+    pub sectors: usize,
+    /// Number of sectors which differed, and were programmed.
+    pub changed: usize,
+    /// Number of changed sectors which had to be erased first.
+    pub erased: usize,
+}
+
+/// A sector whose contents differ from the desired ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SectorDiff {
+    address: u32,
+    length: u32,
+    /// Whether programming the desired contents requires an erase.
+    erase: bool,
+}
+
+/// Reports the progress of several operations as a single named stage.
+struct Stage<'a> {
+    progress: &'a dyn ProgressIndicator,
+    offset: Cell<usize>,
+}
+
+impl<'a> Stage<'a> {
+    fn new(progress: &'a dyn ProgressIndicator, name: &str, total: usize) -> Self {
+        progress.new_stage(name, total);
+        Stage {
+            progress,
+            offset: Cell::new(0),
+        }
+    }
+
+    fn advance(&self, length: usize) {
+        self.offset.set(self.offset.get() + length);
+    }
+}
+
+impl ProgressIndicator for Stage<'_> {
+    fn new_stage(&self, _name: &str, _total: usize) {}
+
+    fn progress(&self, absolute: usize) {
+        self.progress.progress(self.offset.get() + absolute);
+    }
+}
+
 pub struct SpiFlash {
     pub size: u32,
     pub program_size: u32,
@@ -127,6 +205,9 @@ pub struct SpiFlash {
     pub sfdp: Option<Sfdp>,
     pub read_type: ReadTypes,
     pub erase: Vec<SectorErase>,
+    /// The erase regions of devices with a non-uniform layout.  When empty, the erase types in
+    /// `erase` apply to the whole flash.
+    pub regions: Vec<EraseRegion>,
 }
 
 impl Default for SpiFlash {
@@ -144,6 +225,7 @@ impl Default for SpiFlash {
                 opcode: SpiFlash::SECTOR_ERASE,
                 time: None,
             }],
+            regions: Vec::new(),
         }
     }
 }
@@ -176,6 +258,13 @@ impl SpiFlash {
     // Winbond parts use 0x31 and 0x11 for extended status writes.
     pub const WRITE_STATUS2: u8 = 0x31;
     pub const WRITE_STATUS3: u8 = 0x11;
+    // Some parts use 0x3f and 0x3e to access status register 2.
+    pub const READ_STATUS2_ALT: u8 = 0x3f;
+    pub const WRITE_STATUS2_ALT: u8 = 0x3e;
+    pub const READ_UNIQUE_ID: u8 = 0x4b;
+    pub const READ_SECURITY_REGISTER: u8 = 0x48;
+    pub const PROGRAM_SECURITY_REGISTER: u8 = 0x42;
+    pub const ERASE_SECURITY_REGISTER: u8 = 0x44;
     pub const READ_ID: u8 = 0x9f;
     pub const ENTER_4B: u8 = 0xb7;
     pub const EXIT_4B: u8 = 0xe9;
@@ -192,6 +281,27 @@ impl SpiFlash {
     pub const STATUS_WIP: u8 = 0x01;
     /// The `WEL` bit is the write enable latch.
     pub const STATUS_WEL: u8 = 0x02;
+    /// The `LB1` to `LB3` bits of status register 2 lock the security registers.
+    pub const STATUS2_LB1: u8 = 0x08;
+
+    /// JEDEC manufacturer IDs.
+    pub const MFG_MACRONIX: u8 = 0xc2;
+    pub const MFG_GIGADEVICE: u8 = 0xc8;
+    pub const MFG_WINBOND: u8 = 0xef;
+
+    /// Size of each security register.
+    pub const SECURITY_REGISTER_SIZE: u32 = 256;
+    /// Number of security registers, numbered from 1.
+    pub const SECURITY_REGISTER_COUNT: u8 = 3;
+
+    /// Returns the manufacturer ID of a JEDEC ID, skipping its continuation codes.
+    pub fn manufacturer_id(jedec_id: &[u8]) -> u8 {
+        jedec_id
+            .iter()
+            .copied()
+            .find(|&b| b != 0x7f)
+            .unwrap_or_default()
+    }
 
     /// Read `length` bytes of the JEDEC ID from the `spi` target.
     pub fn read_jedec_id(spi: &dyn Target, length: usize) -> Result<Vec<u8>> {
@@ -306,6 +416,14 @@ impl SpiFlash {
         }
         // Sort largest to smallest.
         erase.sort_by(|a, b| b.size.cmp(&a.size));
+        // Without detection commands, the sector map has a single configuration.
+        let regions = match sfdp.sector_map.as_ref().and_then(|map| map.config(None)) {
+            Some(config) => SpiFlash::erase_regions(&sfdp, config).unwrap_or_else(|e| {
+                log::warn!("{}, assuming uniform erase", e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
 
         SpiFlash {
             size: sfdp.jedec.density,
@@ -316,13 +434,94 @@ impl SpiFlash {
             sfdp: Some(sfdp),
             read_type,
             erase,
+            regions,
         }
     }
 
     /// Create a new `SpiFlash` instance by reading an SFDP table from the `spi` Target.
     pub fn from_spi(spi: &dyn Target) -> Result<Self> {
         let sfdp = SpiFlash::read_sfdp(spi)?;
-        Ok(SpiFlash::from_sfdp(sfdp))
+        let mut flash = SpiFlash::from_sfdp(sfdp);
+        flash.detect_sector_map(spi)?;
+        Ok(flash)
+    }
+
+    /// Compute the erase regions of a sector map configuration.
+    fn erase_regions(sfdp: &Sfdp, config: &SectorMapConfig) -> Result<Vec<EraseRegion>> {
+        let mut start = 0u32;
+        config
+            .regions
+            .iter()
+            .map(|region| {
+                let mut erase = sfdp
+                    .jedec
+                    .erase
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, e)| region.erase_types & (1 << i) != 0 && e.size != 0)
+                    .map(|(_, e)| e.clone())
+                    .collect::<Vec<_>>();
+                erase.sort_by(|a, b| b.size.cmp(&a.size));
+                let region = EraseRegion {
+                    start,
+                    size: region.size,
+                    erase,
+                };
+                start = start
+                    .checked_add(region.size)
+                    .ok_or(Error::SectorMapOverflow(start))?;
+                Ok(region)
+            })
+            .collect()
+    }
+
+    /// Run the configuration detection commands of the SFDP sector map, if any, and set up the
+    /// erase regions of the current configuration.
+    pub fn detect_sector_map(&mut self, spi: &dyn Target) -> Result<()> {
+        let Some(sfdp) = &self.sfdp else {
+            return Ok(());
+        };
+        let Some(map) = sfdp
+            .sector_map
+            .as_ref()
+            .filter(|map| !map.detect.is_empty())
+        else {
+            return Ok(());
+        };
+        // Each command contributes one bit of the configuration ID, the first one being the
+        // most significant.
+        let mut id = 0u8;
+        for detect in map.detect.iter() {
+            let mode = MODE_111.dummy_cycles(
+                detect
+                    .dummy_cycles
+                    .unwrap_or(self.read_type.fast.wait_states),
+            );
+            let cmd = match detect.address_length {
+                DetectAddressLength::None => mode.cmd(detect.opcode),
+                DetectAddressLength::Mode3b => {
+                    mode.cmd_addr(detect.opcode, detect.address, AddressMode::Mode3b)
+                }
+                DetectAddressLength::Mode4b => {
+                    mode.cmd_addr(detect.opcode, detect.address, AddressMode::Mode4b)
+                }
+                _ => mode.cmd_addr(detect.opcode, detect.address, self.address_mode),
+            };
+            let mut value = 0u8;
+            spi.run_eeprom_transactions(&mut [Transaction::Read(
+                cmd,
+                std::slice::from_mut(&mut value),
+            )])?;
+            id = id << 1 | (value & detect.read_mask != 0) as u8;
+        }
+        match map.config(Some(id)) {
+            Some(config) => self.regions = SpiFlash::erase_regions(sfdp, config)?,
+            None => log::warn!(
+                "Sector map has no configuration {:#x}, assuming uniform erase",
+                id
+            ),
+        }
+        Ok(())
     }
 
     /// Set the SPI flash addressing mode to either 3b or 4b mode.
@@ -426,19 +625,56 @@ impl SpiFlash {
         self.erase_with_progress(spi, address, length, &NoProgressBar)
     }
 
-    fn select_erase(&self, address: u32, length: u32) -> Result<&SectorErase> {
+    /// Returns the erase types usable at `address`, sorted largest to smallest, and the end of
+    /// the region in which they apply.
+    fn erase_types(&self, address: u32) -> Result<(&[SectorErase], u32)> {
+        if self.regions.is_empty() {
+            return Ok((&self.erase, u32::MAX));
+        }
+        let region = self
+            .regions
+            .iter()
+            .find(|r| address >= r.start && address - r.start < r.size)
+            .ok_or(Error::AddressOutOfBounds(address, self.size))?;
+        Ok((&region.erase, region.start + region.size))
+    }
+
+    /// Selects the erase operation at `address`, with `remain` bytes of the `length` bytes of
+    /// the whole erase left.
+    fn select_erase(&self, address: u32, remain: u32, length: u32) -> Result<&SectorErase> {
+        let (erase, end) = self.erase_types(address)?;
+        // We assume the last element of the `erase` list is the standard
+        // SECTOR_ERASE.  So far, this has been true for all eeproms
+        // encountered by the author.
+        let standard = erase.last().ok_or(Error::EraseNotSupported(address))?;
+        if address % standard.size != 0 {
+            return Err(Error::BadEraseAddress(address, standard.size).into());
+        }
+        if remain < standard.size {
+            return Err(Error::BadEraseLength(length, standard.size).into());
+        }
         if self.erase_mode == EraseMode::Standard {
-            // We assume the last element of the `erase` list is the standard
-            // SECTOR_ERASE.  So far, this has been true for all eeproms
-            // encountered by the author.
-            return Ok(self.erase.last().unwrap());
-        }
-        for e in self.erase.iter() {
-            if address % e.size == 0 && length >= e.size {
-                return Ok(e);
-            }
+            return Ok(standard);
+        }
+        let remain = std::cmp::min(remain, end - address);
+        Ok(erase
+            .iter()
+            .find(|e| address % e.size == 0 && remain >= e.size)
+            .unwrap_or(standard))
+    }
+
+    /// Selects all the erase operations of a segment, so that a bad address or length is
+    /// reported before anything is erased.
+    fn erase_plan(&self, address: u32, length: u32) -> Result<Vec<(u32, &SectorErase)>> {
+        let end = address + length;
+        let mut plan = Vec::new();
+        let mut addr = address;
+        while addr < end {
+            let erase = self.select_erase(addr, end - addr, length)?;
+            plan.push((addr, erase));
+            addr += erase.size;
         }
-        Err(Error::BadEraseAddress(address, self.erase.last().unwrap().size).into())
+        Ok(plan)
     }
 
     /// Erase a segment of the SPI flash starting at `address` for `length` bytes.
@@ -451,25 +687,15 @@ impl SpiFlash {
         length: u32,
         progress: &dyn ProgressIndicator,
     ) -> Result<&Self> {
-        let min_erase_size = self.erase.last().unwrap().size;
-        if address % min_erase_size != 0 {
-            return Err(Error::BadEraseAddress(address, min_erase_size).into());
-        }
-        if length % min_erase_size != 0 {
-            return Err(Error::BadEraseLength(length, min_erase_size).into());
-        }
+        let plan = self.erase_plan(address, length)?;
         progress.new_stage("", length as usize);
-        let end = address + length;
-        let mut addr = address;
-        while addr < end {
-            let erase = self.select_erase(addr, end - addr)?;
+        for (addr, erase) in plan {
             spi.run_eeprom_transactions(&mut [
                 Transaction::Command(MODE_111.cmd(SpiFlash::WRITE_ENABLE)),
                 Transaction::Command(MODE_111.cmd_addr(erase.opcode, addr, self.address_mode)),
                 Transaction::WaitForBusyClear,
             ])?;
             progress.progress((addr - address) as usize);
-            addr += erase.size;
         }
         progress.progress(length as usize);
         Ok(self)
@@ -525,6 +751,149 @@ impl SpiFlash {
         Ok(self)
     }
 
+    /// Returns the start and size of the smallest erasable sector containing `address`.
+    fn sector_at(&self, address: u32) -> Result<(u32, u32)> {
+        let (erase, _) = self.erase_types(address)?;
+        let size = erase.last().ok_or(Error::EraseNotSupported(address))?.size;
+        Ok((address - address % size, size))
+    }
+
+    /// Compares the `current` and `desired` contents of the flash from the sector aligned
+    /// `address`.  Returns the number of sectors compared, and the differing sectors.
+    fn diff_sectors(
+        &self,
+        address: u32,
+        current: &[u8],
+        desired: &[u8],
+    ) -> Result<(usize, Vec<SectorDiff>)> {
+        let mut count = 0;
+        let mut diff = Vec::new();
+        let mut offset = 0;
+        while offset < desired.len() {
+            let (start, size) = self.sector_at(address + offset as u32)?;
+            let end = std::cmp::min((start + size - address) as usize, desired.len());
+            let (cur, des) = (&current[offset..end], &desired[offset..end]);
+            if cur != des {
+                // Programming can only clear bits, setting any bit requires an erase.
+                let erase = cur.iter().zip(des).any(|(c, d)| c & d != *d);
+                diff.push(SectorDiff {
+                    address: address + offset as u32,
+                    length: (end - offset) as u32,
+                    erase,
+                });
+            }
+            count += 1;
+            offset = end;
+        }
+        Ok((count, diff))
+    }
+
+    /// Merges consecutive `(start, length)` segments.
+    fn merge_segments(segments: impl Iterator<Item = (u32, u32)>) -> Vec<(u32, u32)> {
+        let mut merged: Vec<(u32, u32)> = Vec::new();
+        for (start, length) in segments {
+            match merged.last_mut() {
+                Some(last) if last.0 + last.1 == start => last.1 += length,
+                _ => merged.push((start, length)),
+            }
+        }
+        merged
+    }
+
+    /// Runs `op` on each of the `segments`, reporting the progress as a single stage.
+    fn run_stage(
+        progress: &dyn ProgressIndicator,
+        name: &str,
+        segments: &[(u32, u32)],
+        mut op: impl FnMut(u32, u32, &dyn ProgressIndicator) -> Result<()>,
+    ) -> Result<()> {
+        let total = segments.iter().map(|&(_, length)| length as usize).sum();
+        if total == 0 {
+            return Ok(());
+        }
+        let stage = Stage::new(progress, name, total);
+        for &(start, length) in segments {
+            op(start, length, &stage)?;
+            stage.advance(length as usize);
+        }
+        Ok(())
+    }
+
+    /// Synchronize a segment of the SPI flash starting at `address` with the contents of
+    /// `buffer`: the segment is read back, and only the sectors which differ are erased (if
+    /// needed) and programmed, then verified.  The contents of the sectors partially covered
+    /// by the segment are preserved.
+    /// The `progress` callback will be invoked with one stage per operation.
+    pub fn sync_with_progress(
+        &self,
+        spi: &dyn Target,
+        address: u32,
+        buffer: &[u8],
+        progress: &dyn ProgressIndicator,
+    ) -> Result<SyncSummary> {
+        if buffer.is_empty() {
+            return Ok(SyncSummary::default());
+        }
+        let (start, _) = self.sector_at(address)?;
+        let (last, last_size) = self.sector_at(address + (buffer.len() - 1) as u32)?;
+        let mut current = vec![0u8; (last + last_size - start) as usize];
+        SpiFlash::run_stage(
+            progress,
+            "Reading",
+            &[(start, current.len() as u32)],
+            |start, _, stage| {
+                self.read_with_progress(spi, start, &mut current, stage, false)?;
+                Ok(())
+            },
+        )?;
+
+        let mut desired = current.clone();
+        let offset = (address - start) as usize;
+        desired[offset..offset + buffer.len()].copy_from_slice(buffer);
+        let (sectors, diff) = self.diff_sectors(start, &current, &desired)?;
+        let desired_at =
+            |address: u32, length: u32| &desired[(address - start) as usize..][..length as usize];
+
+        let erase = SpiFlash::merge_segments(
+            diff.iter()
+                .filter(|d| d.erase)
+                .map(|d| (d.address, d.length)),
+        );
+        SpiFlash::run_stage(progress, "Erasing", &erase, |address, length, stage| {
+            self.erase_with_progress(spi, address, length, stage)?;
+            Ok(())
+        })?;
+
+        let changed = SpiFlash::merge_segments(diff.iter().map(|d| (d.address, d.length)));
+        SpiFlash::run_stage(
+            progress,
+            "Programming",
+            &changed,
+            |address, length, stage| {
+                self.program_with_progress(spi, address, desired_at(address, length), stage)?;
+                Ok(())
+            },
+        )?;
+        SpiFlash::run_stage(progress, "Verifying", &changed, |address, length, stage| {
+            let mut actual = vec![0u8; length as usize];
+            self.read_with_progress(spi, address, &mut actual, stage, false)?;
+            match actual
+                .iter()
+                .zip(desired_at(address, length))
+                .position(|(a, d)| a != d)
+            {
+                Some(i) => Err(Error::VerifyFailed(address + i as u32).into()),
+                None => Ok(()),
+            }
+        })?;
+
+        Ok(SyncSummary {
+            sectors,
+            changed: diff.len(),
+            erased: diff.iter().filter(|d| d.erase).count(),
+        })
+    }
+
     /// Send the software reset sequence to the `spi` target.
     pub fn chip_reset(spi: &dyn Target) -> Result<()> {
         spi.run_eeprom_transactions(&mut [
@@ -533,4 +902,544 @@ impl SpiFlash {
         ])?;
         Ok(())
     }
+
+    /// Read `length` bytes of the factory programmed unique ID from the `spi` target.
+    pub fn read_unique_id(&self, spi: &dyn Target, length: usize) -> Result<Vec<u8>> {
+        SpiFlash::check_security_register_layout(spi)?;
+        let mut buf = vec![0u8; length];
+        // The ID follows four dummy bytes, or five in 4-byte address mode.
+        let dummy_cycles = match self.address_mode {
+            AddressMode::Mode3b => 32,
+            AddressMode::Mode4b => 40,
+        };
+        spi.run_eeprom_transactions(&mut [Transaction::Read(
+            MODE_111
+                .dummy_cycles(dummy_cycles)
+                .cmd(SpiFlash::READ_UNIQUE_ID),
+            &mut buf,
+        )])?;
+        Ok(buf)
+    }
+
+    /// Returns how the status registers are accessed, according to the SFDP table.
+    pub fn status_register_map(&self) -> StatusRegisterMap {
+        StatusRegisterMap::from_sfdp(self.sfdp.as_ref())
+    }
+
+    /// Read the status registers, status register 2 in the upper byte if it exists.
+    pub fn read_status_registers(&self, spi: &dyn Target) -> Result<u16> {
+        let status2 = match self.status_register_map() {
+            StatusRegisterMap::Single => return Ok(SpiFlash::read_status(spi)? as u16),
+            StatusRegisterMap::Alternate => SpiFlash::READ_STATUS2_ALT,
+            _ => SpiFlash::READ_STATUS2,
+        };
+        let status = SpiFlash::read_status_ex(spi, Some(&[SpiFlash::READ_STATUS, status2]))?;
+        Ok(status as u16)
+    }
+
+    /// Write the status registers, status register 2 in the upper byte being ignored if it does
+    /// not exist.
+    pub fn write_status_registers(&self, spi: &dyn Target, status: u16) -> Result<()> {
+        let [status1, status2] = status.to_le_bytes();
+        let write_status2 = match self.status_register_map() {
+            StatusRegisterMap::Single => None,
+            StatusRegisterMap::Combined => {
+                return SpiFlash::write_status(spi, SpiFlash::WRITE_STATUS, &[status1, status2]);
+            }
+            StatusRegisterMap::Separate => Some(SpiFlash::WRITE_STATUS2),
+            StatusRegisterMap::Alternate => Some(SpiFlash::WRITE_STATUS2_ALT),
+        };
+        SpiFlash::write_status(spi, SpiFlash::WRITE_STATUS, &[status1])?;
+        if let Some(opcode) = write_status2 {
+            SpiFlash::write_status(spi, opcode, &[status2])?;
+        }
+        Ok(())
+    }
+
+    fn write_status(spi: &dyn Target, opcode: u8, data: &[u8]) -> Result<()> {
+        spi.run_eeprom_transactions(&mut [
+            Transaction::Command(MODE_111.cmd(SpiFlash::WRITE_ENABLE)),
+            Transaction::Write(MODE_111.cmd(opcode), data),
+            Transaction::WaitForBusyClear,
+        ])?;
+        Ok(())
+    }
+
+    /// Returns the protection scheme of the `spi` target, based on its JEDEC ID.
+    pub fn protection_scheme(&self, spi: &dyn Target) -> Result<ProtectionScheme> {
+        let jedec_id = SpiFlash::read_jedec_id(spi, 16)?;
+        ProtectionScheme::from_jedec_id(&jedec_id, self.size)
+    }
+
+    /// Read the range protected by the block protection bits of the status registers.
+    pub fn protection(
+        &self,
+        spi: &dyn Target,
+        scheme: &ProtectionScheme,
+    ) -> Result<ProtectedRange> {
+        let status = self.read_status_registers(spi)?;
+        Ok(scheme.decode(status, self.size))
+    }
+
+    /// Set the block protection bits of the status registers to protect `range`, leaving the
+    /// other bits untouched.  A `range` of zero length removes the protection.
+    pub fn set_protection(
+        &self,
+        spi: &dyn Target,
+        scheme: &ProtectionScheme,
+        range: ProtectedRange,
+    ) -> Result<()> {
+        let bits = scheme.encode(range, self.size)?;
+        let mask = if self.status_register_map().has_status2() {
+            scheme.mask()
+        } else {
+            ensure!(bits <= 0xff, Error::NoStatusRegister2);
+            scheme.mask() & 0xff
+        };
+        let status = self.read_status_registers(spi)?;
+        self.write_status_registers(spi, status & !mask | bits)?;
+        let actual = self.protection(spi, scheme)?;
+        ensure!(
+            actual == scheme.decode(bits, self.size),
+            "protection not applied: status registers may be locked (actual {:x?})",
+            actual
+        );
+        Ok(())
+    }
+
+    /// Checks that the `spi` target lays out its security registers and unique ID as Winbond
+    /// parts do, the only layout supported.
+    fn check_security_register_layout(spi: &dyn Target) -> Result<()> {
+        let jedec_id = SpiFlash::read_jedec_id(spi, 16)?;
+        let manufacturer = SpiFlash::manufacturer_id(&jedec_id);
+        ensure!(
+            manufacturer == SpiFlash::MFG_WINBOND,
+            Error::UnknownSecurityRegisterLayout(manufacturer)
+        );
+        Ok(())
+    }
+
+    /// Checks an access to `length` bytes at `offset` of security register `index`, and returns
+    /// the address of the access.
+    fn security_register_address(index: u8, offset: u32, length: usize) -> Result<u32> {
+        ensure!(
+            (1..=SpiFlash::SECURITY_REGISTER_COUNT).contains(&index),
+            Error::BadSecurityRegister(index)
+        );
+        ensure!(
+            offset as usize + length <= SpiFlash::SECURITY_REGISTER_SIZE as usize,
+            Error::SecurityRegisterOutOfBounds(offset, length, SpiFlash::SECURITY_REGISTER_SIZE)
+        );
+        Ok((index as u32) << 12 | offset)
+    }
+
+    /// Read security register `index` from `offset` into `buffer`.
+    pub fn read_security_register(
+        &self,
+        spi: &dyn Target,
+        index: u8,
+        offset: u32,
+        buffer: &mut [u8],
+    ) -> Result<()> {
+        let address = SpiFlash::security_register_address(index, offset, buffer.len())?;
+        SpiFlash::check_security_register_layout(spi)?;
+        spi.run_eeprom_transactions(&mut [Transaction::Read(
+            MODE_111.dummy_cycles(8).cmd_addr(
+                SpiFlash::READ_SECURITY_REGISTER,
+                address,
+                self.address_mode,
+            ),
+            buffer,
+        )])?;
+        Ok(())
+    }
+
+    /// Program security register `index` from `offset` with the contents of `buffer`.  The
+    /// register is not erased first.
+    pub fn program_security_register(
+        &self,
+        spi: &dyn Target,
+        index: u8,
+        offset: u32,
+        buffer: &[u8],
+    ) -> Result<()> {
+        let address = SpiFlash::security_register_address(index, offset, buffer.len())?;
+        SpiFlash::check_security_register_layout(spi)?;
+        spi.run_eeprom_transactions(&mut [
+            Transaction::Command(MODE_111.cmd(SpiFlash::WRITE_ENABLE)),
+            Transaction::Write(
+                MODE_111.cmd_addr(
+                    SpiFlash::PROGRAM_SECURITY_REGISTER,
+                    address,
+                    self.address_mode,
+                ),
+                buffer,
+            ),
+            Transaction::WaitForBusyClear,
+        ])?;
+        Ok(())
+    }
+
+    /// Erase security register `index`.
+    pub fn erase_security_register(&self, spi: &dyn Target, index: u8) -> Result<()> {
+        let address = SpiFlash::security_register_address(index, 0, 0)?;
+        SpiFlash::check_security_register_layout(spi)?;
+        spi.run_eeprom_transactions(&mut [
+            Transaction::Command(MODE_111.cmd(SpiFlash::WRITE_ENABLE)),
+            Transaction::Command(MODE_111.cmd_addr(
+                SpiFlash::ERASE_SECURITY_REGISTER,
+                address,
+                self.address_mode,
+            )),
+            Transaction::WaitForBusyClear,
+        ])?;
+        Ok(())
+    }
+
+    /// Permanently lock security register `index` by setting its one-time programmable lock bit
+    /// in status register 2.
+    pub fn lock_security_register(&self, spi: &dyn Target, index: u8) -> Result<()> {
+        SpiFlash::security_register_address(index, 0, 0)?;
+        SpiFlash::check_security_register_layout(spi)?;
+        ensure!(
+            self.status_register_map().has_status2(),
+            Error::NoStatusRegister2
+        );
+        let lock = (SpiFlash::STATUS2_LB1 as u16) << (index + 7);
+        let status = self.read_status_registers(spi)?;
+        self.write_status_registers(spi, status | lock)?;
+        ensure!(
+            self.read_status_registers(spi)? & lock != 0,
+            "security register {} not locked",
+            index
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::spi::{AssertChipSelect, MaxSizes, Transfer, TransferMode};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn sector(size: u32, opcode: u8) -> SectorErase {
+        SectorErase {
+            size,
+            opcode,
+            time: None,
+        }
+    }
+
+    fn plan(flash: &SpiFlash, address: u32, length: u32) -> Result<Vec<(u32, u8)>> {
+        Ok(flash
+            .erase_plan(address, length)?
+            .into_iter()
+            .map(|(addr, erase)| (addr, erase.opcode))
+            .collect())
+    }
+
+    #[test]
+    fn test_erase_uniform() -> Result<()> {
+        let flash = SpiFlash::default();
+        assert_eq!(
+            plan(&flash, 0x1000, 0x2000)?,
+            [(0x1000, 0x20), (0x2000, 0x20)]
+        );
+        assert!(plan(&flash, 0x800, 0x1000).is_err());
+        assert!(plan(&flash, 0x1000, 0x1800).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_sectors() -> Result<()> {
+        let flash = SpiFlash::default();
+        let current = [0xffu8; 0x4000];
+        let mut desired = current;
+        // Only clear bits in the second sector, set bits in the fourth one.
+        desired[0x1800] = 0x00;
+        let mut current = current;
+        current[0x3000] = 0x00;
+        desired[0x3000] = 0x01;
+        assert_eq!(
+            flash.diff_sectors(0x10000, &current, &desired)?,
+            (
+                4,
+                vec![
+                    SectorDiff {
+                        address: 0x11000,
+                        length: 0x1000,
+                        erase: false,
+                    },
+                    SectorDiff {
+                        address: 0x13000,
+                        length: 0x1000,
+                        erase: true,
+                    },
+                ]
+            )
+        );
+        assert_eq!(
+            SpiFlash::merge_segments(
+                [(0x1000, 0x1000), (0x2000, 0x1000), (0x4000, 0x1000)].into_iter()
+            ),
+            [(0x1000, 0x2000), (0x4000, 0x1000)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_erase_regions() -> Result<()> {
+        // 4KiB sectors in the lower 64KiB, 64KiB blocks above.
+        let flash = SpiFlash {
+            size: 0x100000,
+            erase_mode: EraseMode::Block,
+            regions: vec![
+                EraseRegion {
+                    start: 0,
+                    size: 0x10000,
+                    erase: vec![sector(4096, 0x20)],
+                },
+                EraseRegion {
+                    start: 0x10000,
+                    size: 0xf0000,
+                    erase: vec![sector(65536, 0xd8)],
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            plan(&flash, 0xe000, 0x12000)?,
+            [(0xe000, 0x20), (0xf000, 0x20), (0x10000, 0xd8)]
+        );
+        assert!(plan(&flash, 0x8000, 0x10000).is_err());
+        assert!(plan(&flash, 0x10000, 0x1000).is_err());
+        assert!(plan(&flash, 0xf0000, 0x20000).is_err());
+        Ok(())
+    }
+
+    /// A SPI flash with the given JEDEC ID, which records the bytes of the commands it receives.
+    /// Reads, page programs and 4KiB sector erases operate on `memory`.
+    struct FakeFlash {
+        jedec_id: Vec<u8>,
+        commands: RefCell<Vec<Vec<u8>>>,
+        memory: RefCell<Vec<u8>>,
+        /// The address of a byte which programming leaves unchanged.
+        stuck: Option<u32>,
+    }
+
+    impl FakeFlash {
+        fn new(jedec_id: &[u8]) -> Self {
+            Self::with_memory(jedec_id, Vec::new())
+        }
+
+        fn with_memory(jedec_id: &[u8], memory: Vec<u8>) -> Self {
+            Self {
+                jedec_id: jedec_id.to_vec(),
+                commands: RefCell::new(Vec::new()),
+                memory: RefCell::new(memory),
+                stuck: None,
+            }
+        }
+    }
+
+    impl Target for FakeFlash {
+        fn get_transfer_mode(&self) -> Result<TransferMode> {
+            unimplemented!()
+        }
+
+        fn set_transfer_mode(&self, _mode: TransferMode) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn get_bits_per_word(&self) -> Result<u32> {
+            unimplemented!()
+        }
+
+        fn set_bits_per_word(&self, _bits_per_word: u32) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn get_max_speed(&self) -> Result<u32> {
+            unimplemented!()
+        }
+
+        fn set_max_speed(&self, _max_speed: u32) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn supports_bidirectional_transfer(&self) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn get_max_transfer_count(&self) -> Result<usize> {
+            unimplemented!()
+        }
+
+        fn get_max_transfer_sizes(&self) -> Result<MaxSizes> {
+            Ok(MaxSizes {
+                read: 1024,
+                write: 1024,
+            })
+        }
+
+        fn run_transaction(&self, _transaction: &mut [Transfer]) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn run_eeprom_transactions(&self, transactions: &mut [Transaction]) -> Result<()> {
+            let mut commands = self.commands.borrow_mut();
+            let mut memory = self.memory.borrow_mut();
+            for transaction in transactions {
+                match transaction {
+                    Transaction::Command(cmd) => {
+                        commands.push(cmd.to_bytes()?.to_vec());
+                        if cmd.get_opcode() == [SpiFlash::SECTOR_ERASE] {
+                            let start = cmd.get_address() as usize;
+                            memory[start..start + 4096].fill(0xff);
+                        }
+                    }
+                    Transaction::Write(cmd, data) => {
+                        commands.push(cmd.to_bytes()?.to_vec());
+                        if cmd.get_opcode() == [SpiFlash::PAGE_PROGRAM] {
+                            let start = cmd.get_address();
+                            for (i, byte) in data.iter().enumerate() {
+                                let address = start + i as u32;
+                                if self.stuck != Some(address) {
+                                    memory[address as usize] &= byte;
+                                }
+                            }
+                        }
+                    }
+                    Transaction::Read(cmd, data) => {
+                        commands.push(cmd.to_bytes()?.to_vec());
+                        if cmd.get_opcode() == [SpiFlash::READ_ID] {
+                            let len = data.len().min(self.jedec_id.len());
+                            data[..len].copy_from_slice(&self.jedec_id[..len]);
+                        } else if cmd.get_opcode() == [SpiFlash::READ] {
+                            let start = cmd.get_address() as usize;
+                            data.copy_from_slice(&memory[start..start + data.len()]);
+                        }
+                    }
+                    Transaction::WaitForBusyClear => (),
+                }
+            }
+            Ok(())
+        }
+
+        fn assert_cs(self: Rc<Self>) -> Result<AssertChipSelect> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_security_registers() -> Result<()> {
+        let spi = FakeFlash::new(&[0xef, 0x40, 0x19]);
+        let mut flash = SpiFlash {
+            address_mode: AddressMode::Mode4b,
+            ..Default::default()
+        };
+        flash.read_security_register(&spi, 2, 0x10, &mut [0u8; 4])?;
+        flash.erase_security_register(&spi, 3)?;
+        flash.read_unique_id(&spi, 8)?;
+        flash.address_mode = AddressMode::Mode3b;
+        flash.program_security_register(&spi, 1, 0, &[0u8; 4])?;
+        flash.read_unique_id(&spi, 8)?;
+        assert!(flash
+            .read_security_register(&spi, 4, 0, &mut [0u8; 4])
+            .is_err());
+        assert!(flash
+            .read_security_register(&spi, 1, 0xff, &mut [0u8; 4])
+            .is_err());
+        assert_eq!(
+            spi.commands.take(),
+            [
+                vec![0x9f],
+                vec![0x48, 0x00, 0x00, 0x20, 0x10, 0x00],
+                vec![0x9f],
+                vec![0x06],
+                vec![0x44, 0x00, 0x00, 0x30, 0x00],
+                vec![0x9f],
+                vec![0x4b, 0x00, 0x00, 0x00, 0x00, 0x00],
+                vec![0x9f],
+                vec![0x06],
+                vec![0x42, 0x00, 0x10, 0x00],
+                vec![0x9f],
+                vec![0x4b, 0x00, 0x00, 0x00, 0x00],
+            ]
+        );
+
+        // Other manufacturers lay out their security registers differently.
+        let spi = FakeFlash::new(&[0xc2, 0x20, 0x1b]);
+        assert!(flash.read_unique_id(&spi, 8).is_err());
+        assert!(flash.erase_security_register(&spi, 1).is_err());
+        assert_eq!(spi.commands.take(), [vec![0x9f], vec![0x9f]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync() -> Result<()> {
+        // Four 4KiB sectors filled with 0x11, 0x22, 0x33 and 0x44.
+        let memory = [0x11u8, 0x22, 0x33, 0x44]
+            .iter()
+            .flat_map(|&b| [b; 4096])
+            .collect::<Vec<_>>();
+        let spi = FakeFlash::with_memory(&[0xef, 0x40, 0x19], memory.clone());
+        let flash = SpiFlash {
+            size: 0x4000,
+            ..Default::default()
+        };
+
+        // The image starts in the middle of the first sector, which it doesn't change, only
+        // clears bits in the second, and sets bits in the first half of the third.
+        let mut image = vec![0x11u8; 0x800];
+        image.extend([0x20; 0x1000]);
+        image.extend([0x55; 0x800]);
+        let summary = flash.sync_with_progress(&spi, 0x800, &image, &NoProgressBar)?;
+        assert_eq!(
+            summary,
+            SyncSummary {
+                sectors: 3,
+                changed: 2,
+                erased: 1,
+            }
+        );
+
+        let mut expected = memory;
+        expected[0x1000..0x2000].fill(0x20);
+        expected[0x2000..0x2800].fill(0x55);
+        assert_eq!(*spi.memory.borrow(), expected);
+
+        // Only the differing sectors are erased and programmed.
+        let commands = spi.commands.take();
+        let addresses = |opcode: u8| {
+            commands
+                .iter()
+                .filter(|c| c[0] == opcode)
+                .map(|c| u32::from_be_bytes([0, c[1], c[2], c[3]]))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(addresses(SpiFlash::SECTOR_ERASE), [0x2000]);
+        let programmed = addresses(SpiFlash::PAGE_PROGRAM);
+        assert_eq!(programmed.len(), 32);
+        assert!(programmed.iter().all(|a| (0x1000..0x3000).contains(a)));
+
+        // Syncing the same image again changes nothing.
+        let summary = flash.sync_with_progress(&spi, 0x800, &image, &NoProgressBar)?;
+        assert_eq!(summary.changed, 0);
+        assert!(spi.commands.take().iter().all(|c| c[0] == SpiFlash::READ));
+
+        // A byte which doesn't take the new value fails the verification.
+        let mut spi = FakeFlash::with_memory(&[0xef, 0x40, 0x19], vec![0xff; 0x4000]);
+        spi.stuck = Some(0x1234);
+        let err = flash
+            .sync_with_progress(&spi, 0x1000, &[0x00; 0x1000], &NoProgressBar)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::VerifyFailed(0x1234))
+        ));
+        Ok(())
+    }
 }
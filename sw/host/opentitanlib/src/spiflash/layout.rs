@@ -0,0 +1,321 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use byteorder::{LittleEndian, ReadBytesExt};
+use serde::{Deserialize, Serialize, Serializer};
+use std::io::Read;
+use thiserror::Error;
+
+use crate::io::spi::Target;
+use crate::spiflash::flash::SpiFlash;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("region {0:?} not found in the flash layout")]
+    RegionNotFound(String),
+    #[error("duplicate region {0:?} in the flash layout")]
+    DuplicateRegion(String),
+    #[error("regions {0:?} and {1:?} overlap")]
+    OverlappingRegions(String, String),
+    #[error("region {0:?} extends beyond the end of the flash ({1:#x} bytes)")]
+    RegionOutOfBounds(String, u64),
+    #[error("FMAP not found")]
+    FmapNotFound,
+    #[error("unsupported FMAP version {0}.{1}")]
+    UnsupportedFmapVersion(u8, u8),
+}
+
+/// A named region of the flash.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlashRegion {
+    pub name: String,
+    #[serde(
+        serialize_with = "serialize_hex",
+        deserialize_with = "crate::util::num_de::deserialize"
+    )]
+    pub offset: u32,
+    #[serde(
+        serialize_with = "serialize_hex",
+        deserialize_with = "crate::util::num_de::deserialize"
+    )]
+    pub size: u32,
+}
+
+fn serialize_hex<S: Serializer>(value: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:#x}", value))
+}
+
+/// The layout of the flash, as a list of regions, which is either described by a JSON (or HJSON)
+/// file, where offsets and sizes are strings holding decimal or hexadecimal integers, such as:
+///
+/// ```text
+/// {
+///     regions: [
+///         { name: "ro_a", offset: "0x0", size: "0x80000" },
+///         { name: "ro_b", offset: "0x80000", size: "0x80000" },
+///     ]
+/// }
+/// ```
+///
+/// or read from an FMAP in the flash itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlashLayout {
+    pub regions: Vec<FlashRegion>,
+}
+
+impl FlashLayout {
+    /// Signature of the FMAP header.
+    pub const FMAP_SIGNATURE: &'static [u8; 8] = b"__FMAP__";
+    /// Alignment of the FMAP header in the flash.
+    pub const FMAP_ALIGNMENT: u32 = 4096;
+    const FMAP_HEADER_SIZE: usize = 56;
+    const FMAP_AREA_SIZE: usize = 42;
+    const FMAP_NAME_SIZE: usize = 32;
+
+    /// Parses a layout in JSON (or HJSON) format.
+    pub fn parse(text: &str) -> Result<Self> {
+        let layout: Self = deser_hjson::from_str(text)?;
+        layout.validate(None)?;
+        Ok(layout)
+    }
+
+    /// Loads a layout file.
+    pub fn load(path: &str) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Checks that the region names are unique, that the regions do not overlap, and that they
+    /// fit in a flash of `size` bytes, if given.
+    pub fn validate(&self, size: Option<u32>) -> Result<()> {
+        self.validate_areas(size)?;
+        let mut regions = self.regions.iter().collect::<Vec<_>>();
+        regions.sort_by_key(|r| r.offset);
+        for pair in regions.windows(2) {
+            if pair[0].offset as u64 + pair[0].size as u64 > pair[1].offset as u64 {
+                return Err(
+                    Error::OverlappingRegions(pair[0].name.clone(), pair[1].name.clone()).into(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the region names are unique, and that the regions fit in a flash of `size`
+    /// bytes, if given, or in the 4GiB address space.  Unlike `validate`, this allows regions
+    /// to overlap, as the areas of an FMAP nest.
+    fn validate_areas(&self, size: Option<u32>) -> Result<()> {
+        let limit = size.map_or(1 << 32, u64::from);
+        for (i, region) in self.regions.iter().enumerate() {
+            if region.offset as u64 + region.size as u64 > limit {
+                return Err(Error::RegionOutOfBounds(region.name.clone(), limit).into());
+            }
+            if self.regions[..i].iter().any(|r| r.name == region.name) {
+                return Err(Error::DuplicateRegion(region.name.clone()).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the region named `name`.
+    pub fn region(&self, name: &str) -> Result<&FlashRegion> {
+        self.regions
+            .iter()
+            .find(|r| r.name == name)
+            .ok_or_else(|| Error::RegionNotFound(name.to_string()).into())
+    }
+
+    fn fmap_name(reader: &mut impl Read) -> Result<String> {
+        let mut name = [0u8; Self::FMAP_NAME_SIZE];
+        reader.read_exact(&mut name)?;
+        let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        Ok(String::from_utf8_lossy(&name[..len]).into_owned())
+    }
+
+    /// Returns the number of areas of the FMAP whose header is `buf`.
+    fn fmap_areas(buf: &[u8]) -> Result<usize> {
+        let mut reader = std::io::Cursor::new(&buf[Self::FMAP_SIGNATURE.len()..]);
+        let (major, minor) = (reader.read_u8()?, reader.read_u8()?);
+        if major != 1 {
+            return Err(Error::UnsupportedFmapVersion(major, minor).into());
+        }
+        reader.set_position(reader.position() + 8 + 4 + Self::FMAP_NAME_SIZE as u64);
+        Ok(reader.read_u16::<LittleEndian>()? as usize)
+    }
+
+    /// Parses an FMAP, as defined by flashrom.  The areas of the FMAP become the regions of the
+    /// layout.
+    pub fn from_fmap(buf: &[u8]) -> Result<Self> {
+        if buf.get(..Self::FMAP_SIGNATURE.len()) != Some(Self::FMAP_SIGNATURE) {
+            return Err(Error::FmapNotFound.into());
+        }
+        let areas = Self::fmap_areas(buf)?;
+        let mut reader = std::io::Cursor::new(buf.get(Self::FMAP_HEADER_SIZE..).unwrap_or(&[]));
+        let mut regions = Vec::with_capacity(areas);
+        for _ in 0..areas {
+            let offset = reader.read_u32::<LittleEndian>()?;
+            let size = reader.read_u32::<LittleEndian>()?;
+            let name = Self::fmap_name(&mut reader)?;
+            let _flags = reader.read_u16::<LittleEndian>()?;
+            regions.push(FlashRegion { name, offset, size });
+        }
+        Ok(FlashLayout { regions })
+    }
+
+    /// Returns the offsets at which to look for an FMAP in a flash of `size` bytes: the start of
+    /// the flash, then offsets in order of decreasing alignment, down to `FMAP_ALIGNMENT`.
+    fn fmap_search_offsets(size: u32) -> impl Iterator<Item = u32> {
+        let mut strides = Vec::new();
+        let mut stride = size.next_power_of_two() / 2;
+        while stride >= Self::FMAP_ALIGNMENT {
+            strides.push(stride);
+            stride /= 2;
+        }
+        std::iter::once(0).chain(
+            strides
+                .into_iter()
+                .flat_map(move |stride| (stride..size).step_by(stride as usize * 2)),
+        )
+    }
+
+    /// Searches the `flash` for an FMAP, and returns the layout it describes.
+    pub fn read_fmap(flash: &SpiFlash, spi: &dyn Target) -> Result<Self> {
+        let mut signature = [0u8; 8];
+        for offset in Self::fmap_search_offsets(flash.size) {
+            flash.read(spi, offset, &mut signature)?;
+            if &signature != Self::FMAP_SIGNATURE {
+                continue;
+            }
+            let mut header = vec![0u8; Self::FMAP_HEADER_SIZE];
+            flash.read(spi, offset, &mut header)?;
+            let areas = Self::fmap_areas(&header)?;
+            header.resize(Self::FMAP_HEADER_SIZE + areas * Self::FMAP_AREA_SIZE, 0);
+            flash.read(spi, offset, &mut header)?;
+            let layout = Self::from_fmap(&header)?;
+            layout.validate_areas(Some(flash.size))?;
+            log::info!("Found FMAP at offset {:#x}", offset);
+            return Ok(layout);
+        }
+        Err(Error::FmapNotFound.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(name: &str, offset: u32, size: u32) -> FlashRegion {
+        FlashRegion {
+            name: name.into(),
+            offset,
+            size,
+        }
+    }
+
+    #[test]
+    fn parse_layout() -> Result<()> {
+        let layout = FlashLayout::parse(
+            r#"{
+                regions: [
+                    { name: "ro_a", offset: "0x0", size: "0x80000" },
+                    { name: "ro_b", offset: "524288", size: "0x80000" },
+                ]
+            }"#,
+        )?;
+        assert_eq!(
+            layout.regions,
+            [region("ro_a", 0, 0x80000), region("ro_b", 0x80000, 0x80000)]
+        );
+        assert_eq!(layout.region("ro_b")?.offset, 0x80000);
+        assert!(layout.region("rw").is_err());
+        assert!(layout.validate(Some(0x100000)).is_ok());
+        assert!(layout.validate(Some(0xc0000)).is_err());
+        // Layouts are sent to proxies, and must survive a round trip.
+        assert_eq!(
+            serde_json::from_str::<FlashLayout>(&serde_json::to_string(&layout)?)?,
+            layout
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn bad_layout() {
+        let layout = |regions: &[FlashRegion]| FlashLayout {
+            regions: regions.to_vec(),
+        };
+        assert!(
+            layout(&[region("a", 0, 0x2000), region("b", 0x1000, 0x1000)])
+                .validate(None)
+                .is_err()
+        );
+        assert!(
+            layout(&[region("a", 0, 0x1000), region("a", 0x1000, 0x1000)])
+                .validate(None)
+                .is_err()
+        );
+        let err = layout(&[region("a", 0xffff_f000, 0x2000)])
+            .validate(None)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "region \"a\" extends beyond the end of the flash (0x100000000 bytes)"
+        );
+        assert!(FlashLayout::parse(r#"{ regions: [ { name: "a", offset: "0" } ] }"#).is_err());
+    }
+
+    #[test]
+    fn parse_fmap() -> Result<()> {
+        let mut fmap = Vec::new();
+        fmap.extend_from_slice(FlashLayout::FMAP_SIGNATURE);
+        fmap.extend_from_slice(&[1, 1]);
+        fmap.extend_from_slice(&0u64.to_le_bytes());
+        fmap.extend_from_slice(&0x100000u32.to_le_bytes());
+        fmap.extend_from_slice(&[0u8; 32]);
+        fmap.extend_from_slice(&3u16.to_le_bytes());
+        for (offset, size, name) in [
+            (0u32, 0x1000u32, "FMAP"),
+            (0x1000, 0xf000, "RO_A"),
+            (0x2000, 0x1000, "RO_A_FW"),
+        ] {
+            fmap.extend_from_slice(&offset.to_le_bytes());
+            fmap.extend_from_slice(&size.to_le_bytes());
+            let mut name = name.as_bytes().to_vec();
+            name.resize(32, 0);
+            fmap.extend_from_slice(&name);
+            fmap.extend_from_slice(&0u16.to_le_bytes());
+        }
+        let layout = FlashLayout::from_fmap(&fmap)?;
+        assert_eq!(
+            layout.regions,
+            [
+                region("FMAP", 0, 0x1000),
+                region("RO_A", 0x1000, 0xf000),
+                region("RO_A_FW", 0x2000, 0x1000)
+            ]
+        );
+        // Areas may nest, but must fit in the flash.
+        assert!(layout.validate_areas(Some(0x10000)).is_ok());
+        assert!(layout.validate_areas(Some(0x8000)).is_err());
+        assert!(layout.validate(None).is_err());
+        // Truncated.
+        assert!(FlashLayout::from_fmap(&fmap[..fmap.len() - 1]).is_err());
+        fmap[8] = 2;
+        assert!(FlashLayout::from_fmap(&fmap).is_err());
+        assert!(FlashLayout::from_fmap(&[0xff; 64]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn fmap_search_offsets() {
+        assert_eq!(
+            FlashLayout::fmap_search_offsets(0x4000).collect::<Vec<_>>(),
+            [0, 0x2000, 0x1000, 0x3000]
+        );
+        let offsets = FlashLayout::fmap_search_offsets(0x100000).collect::<Vec<_>>();
+        assert_eq!(offsets.len(), 0x100);
+        assert_eq!(offsets[..3], [0, 0x80000, 0x40000]);
+    }
+}
@@ -3,7 +3,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 pub mod flash;
+pub mod layout;
+pub mod protect;
 pub mod sfdp;
 
-pub use flash::{EraseMode, ReadMode, SpiFlash};
+pub use flash::{EraseMode, EraseRegion, ReadMode, SpiFlash, SyncSummary};
+pub use layout::{FlashLayout, FlashRegion};
+pub use protect::{ProtectedRange, ProtectionScheme, StatusRegisterMap};
 pub use sfdp::{BlockEraseSize, Sfdp, SupportedAddressModes, WriteGranularity};
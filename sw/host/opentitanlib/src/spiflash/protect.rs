@@ -0,0 +1,275 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_annotate::Annotate;
+
+use crate::spiflash::flash::{Error, SpiFlash};
+use crate::spiflash::sfdp::Sfdp;
+
+/// How the status registers of a SPI flash are accessed.  The JEDEC SFDP table does not describe
+/// the status registers directly, but its quad enable requirements (JESD216B pg. 26) tell whether
+/// a second status register exists, and which opcodes read and write it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum StatusRegisterMap {
+    /// A single status register, read with `READ_STATUS` and written with `WRITE_STATUS`.
+    #[default]
+    Single,
+    /// Status register 2 is read with `READ_STATUS2`, and written along with status register 1,
+    /// as the second byte of `WRITE_STATUS`.
+    Combined,
+    /// Status register 2 is read with `READ_STATUS2` and written with `WRITE_STATUS2`.
+    Separate,
+    /// Status register 2 is read with `READ_STATUS2_ALT` and written with `WRITE_STATUS2_ALT`.
+    Alternate,
+}
+
+impl StatusRegisterMap {
+    /// Decodes the quad enable requirements field of the JEDEC parameter table.
+    pub fn from_quad_enable_requirements(qer: u8) -> Self {
+        match qer {
+            // QE is bit 1 of status register 2, written with two bytes of `WRITE_STATUS`.
+            0b001 | 0b100 | 0b101 => Self::Combined,
+            // QE is bit 7 of status register 2, accessed with the 0x3f and 0x3e opcodes.
+            0b011 => Self::Alternate,
+            // QE is bit 1 of status register 2, written with its own opcode.
+            0b110 => Self::Separate,
+            // No QE bit, or QE is bit 6 of status register 1.
+            _ => Self::Single,
+        }
+    }
+
+    /// Determines the status registers from the SFDP table, assuming a single status register
+    /// when the table predates JESD216B.
+    pub fn from_sfdp(sfdp: Option<&Sfdp>) -> Self {
+        sfdp.and_then(|sfdp| sfdp.jedec.rev_b.as_ref())
+            .map(|rev_b| Self::from_quad_enable_requirements(rev_b.quad_enable_requirements))
+            .unwrap_or_default()
+    }
+
+    /// Whether a second status register exists.
+    pub fn has_status2(&self) -> bool {
+        *self != Self::Single
+    }
+}
+
+/// A range of the flash protected against program and erase operations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Annotate)]
+pub struct ProtectedRange {
+    #[annotate(format = hex)]
+    pub start: u32,
+    #[annotate(format = hex)]
+    pub length: u32,
+}
+
+/// Size of the sectors protected when the `SEC` bit is set.
+const SECTOR_SIZE: u32 = 4096;
+/// Largest range protected when the `SEC` bit is set.
+const MAX_SECTOR_PROTECTION: u32 = 32768;
+
+/// Layout of the block protection bits in the status registers, which differs between
+/// manufacturers.  Bit positions count from bit 0 of status register 1, bits 8 to 15 being those
+/// of status register 2.
+///
+/// A non-zero value `n` of the `BP` bits protects `block_size << (n - 1)` bytes, at most the
+/// whole flash, at the top of the flash, or at its bottom if the `TB` bit is set.  If the `SEC`
+/// bit is set, `4KiB << (n - 1)` bytes are protected instead, at most 32KiB, except that all ones
+/// in the `BP` bits still protect the whole flash.  If the `CMP` bit is set, the protection is
+/// complemented: the rest of the flash is protected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtectionScheme {
+    /// Position of the `BP0` bit, the other `BP` bits follow it.
+    pub bp_shift: u8,
+    /// Number of `BP` bits.
+    pub bp_bits: u8,
+    /// Position of the `TB` (top/bottom) bit, if writable.
+    pub tb_bit: Option<u8>,
+    /// Position of the `SEC` (sector/block) bit, if any.
+    pub sec_bit: Option<u8>,
+    /// Position of the `CMP` (complement) bit, if any.
+    pub cmp_bit: Option<u8>,
+    /// Size protected by a value of 1 of the `BP` bits.
+    pub block_size: u32,
+}
+
+impl ProtectionScheme {
+    /// Returns the protection scheme of the flash of `size` bytes with the given JEDEC ID.
+    pub fn from_jedec_id(jedec_id: &[u8], size: u32) -> Result<Self> {
+        let manufacturer = SpiFlash::manufacturer_id(jedec_id);
+        match manufacturer {
+            SpiFlash::MFG_WINBOND | SpiFlash::MFG_GIGADEVICE if size > 16 * 1024 * 1024 => {
+                Ok(Self {
+                    bp_shift: 2,
+                    bp_bits: 4,
+                    tb_bit: Some(6),
+                    sec_bit: None,
+                    cmp_bit: Some(14),
+                    block_size: 65536,
+                })
+            }
+            SpiFlash::MFG_WINBOND | SpiFlash::MFG_GIGADEVICE => Ok(Self {
+                bp_shift: 2,
+                bp_bits: 3,
+                tb_bit: Some(5),
+                sec_bit: Some(6),
+                cmp_bit: Some(14),
+                block_size: size >> 6,
+            }),
+            // The `TB` bit is a one-time programmable bit of the configuration register, which is
+            // not touched and assumed to be clear.
+            SpiFlash::MFG_MACRONIX => Ok(Self {
+                bp_shift: 2,
+                bp_bits: 4,
+                tb_bit: None,
+                sec_bit: None,
+                cmp_bit: None,
+                block_size: 65536,
+            }),
+            _ => Err(Error::UnknownProtectionScheme(manufacturer).into()),
+        }
+    }
+
+    /// Mask of all the bits of the scheme in the status registers.
+    pub fn mask(&self) -> u16 {
+        let bp = ((1u16 << self.bp_bits) - 1) << self.bp_shift;
+        [self.tb_bit, self.sec_bit, self.cmp_bit]
+            .iter()
+            .flatten()
+            .fold(bp, |mask, &bit| mask | 1 << bit)
+    }
+
+    fn bit(status: u16, bit: Option<u8>) -> bool {
+        bit.is_some_and(|bit| status & (1 << bit) != 0)
+    }
+
+    /// Decodes the range protected by the `status` registers, of a flash of `size` bytes.
+    pub fn decode(&self, status: u16, size: u32) -> ProtectedRange {
+        let bp = (status >> self.bp_shift) & ((1 << self.bp_bits) - 1);
+        let length = match bp {
+            0 => 0,
+            bp if bp == (1 << self.bp_bits) - 1 => size,
+            bp if Self::bit(status, self.sec_bit) => {
+                (SECTOR_SIZE << (bp - 1).min(3)).min(MAX_SECTOR_PROTECTION)
+            }
+            bp => (self.block_size as u64)
+                .checked_shl(bp as u32 - 1)
+                .map_or(size, |length| length.min(size as u64) as u32),
+        };
+        let bottom = Self::bit(status, self.tb_bit);
+        let (start, length) = match (bottom, Self::bit(status, self.cmp_bit)) {
+            (false, false) => (size - length, length),
+            (true, false) => (0, length),
+            (false, true) => (0, size - length),
+            (true, true) => (length, size - length),
+        };
+        ProtectedRange {
+            start: if length == 0 { 0 } else { start },
+            length,
+        }
+    }
+
+    /// Finds the value of the bits of the scheme, which protects `range` of a flash of `size`
+    /// bytes.  Settings without the `SEC` and `CMP` bits are preferred.
+    pub fn encode(&self, range: ProtectedRange, size: u32) -> Result<u16> {
+        // Values of an optional bit, clear first.
+        let options = |bit: Option<u8>| bit.map_or(vec![0], |bit| vec![0, 1u16 << bit]);
+        let target = if range.length == 0 {
+            ProtectedRange::default()
+        } else {
+            range
+        };
+        for cmp in options(self.cmp_bit) {
+            for sec in options(self.sec_bit) {
+                for tb in options(self.tb_bit) {
+                    for bp in 0..(1u16 << self.bp_bits) {
+                        let status = cmp | sec | tb | bp << self.bp_shift;
+                        if self.decode(status, size) == target {
+                            return Ok(status);
+                        }
+                    }
+                }
+            }
+        }
+        Err(Error::UnsupportedProtectionRange(range.start, range.length).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: u32 = 16 * 1024 * 1024;
+
+    fn range(start: u32, length: u32) -> ProtectedRange {
+        ProtectedRange { start, length }
+    }
+
+    #[test]
+    fn status_register_map() {
+        assert_eq!(
+            StatusRegisterMap::from_quad_enable_requirements(0b101),
+            StatusRegisterMap::Combined
+        );
+        assert_eq!(
+            StatusRegisterMap::from_quad_enable_requirements(0b010),
+            StatusRegisterMap::Single
+        );
+        assert_eq!(
+            StatusRegisterMap::from_sfdp(None),
+            StatusRegisterMap::Single
+        );
+    }
+
+    #[test]
+    fn winbond_protection() -> Result<()> {
+        let scheme = ProtectionScheme::from_jedec_id(&[0xef, 0x40, 0x18], SIZE)?;
+        assert_eq!(scheme.mask(), 0x407c);
+        // BP=1: upper 256KiB.
+        assert_eq!(scheme.decode(0x04, SIZE), range(SIZE - 0x40000, 0x40000));
+        // TB, BP=6: lower 8MiB.
+        assert_eq!(scheme.decode(0x38, SIZE), range(0, SIZE / 2));
+        // BP=7: everything.
+        assert_eq!(scheme.decode(0x1c, SIZE), range(0, SIZE));
+        // SEC, TB, BP=2: lower 8KiB.
+        assert_eq!(scheme.decode(0x68, SIZE), range(0, 0x2000));
+        // SEC, BP=6: upper 32KiB.
+        assert_eq!(scheme.decode(0x58, SIZE), range(SIZE - 0x8000, 0x8000));
+        // SEC, BP=7: everything.
+        assert_eq!(scheme.decode(0x5c, SIZE), range(0, SIZE));
+        // CMP, SEC, TB, BP=7: nothing.
+        assert_eq!(scheme.decode(0x407c, SIZE), range(0, 0));
+        // CMP, TB, BP=1: all but the lower 256KiB.
+        assert_eq!(scheme.decode(0x4024, SIZE), range(0x40000, SIZE - 0x40000));
+        // CMP, BP=0: everything.
+        assert_eq!(scheme.decode(0x4000, SIZE), range(0, SIZE));
+
+        for status in [0x00, 0x04, 0x38, 0x1c, 0x68, 0x5c, 0x4024] {
+            let protected = scheme.decode(status, SIZE);
+            assert_eq!(
+                scheme.decode(scheme.encode(protected, SIZE)?, SIZE),
+                protected
+            );
+        }
+        // TB, BP=2: lower 512KiB.
+        assert_eq!(scheme.encode(range(0, 0x80000), SIZE)?, 0x28);
+        // SEC, TB, BP=4: lower 32KiB.
+        assert_eq!(scheme.encode(range(0, 0x8000), SIZE)?, 0x70);
+        assert!(scheme.encode(range(0, 0x10000), SIZE).is_err());
+        assert_eq!(scheme.encode(range(0, 0), SIZE)?, 0x00);
+        assert!(scheme.encode(range(0x1000, 0x1000), SIZE).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn macronix_protection() -> Result<()> {
+        let size = 128 * 1024 * 1024;
+        let scheme = ProtectionScheme::from_jedec_id(&[0xc2, 0x20, 0x1b], size)?;
+        assert_eq!(scheme.decode(0x04, size), range(size - 0x10000, 0x10000));
+        assert_eq!(scheme.decode(0x3c, size), range(0, size));
+        assert!(scheme.encode(range(0, 0x10000), size).is_err());
+        assert!(ProtectionScheme::from_jedec_id(&[0x7f, 0x9d, 0x60], size).is_err());
+        Ok(())
+    }
+}
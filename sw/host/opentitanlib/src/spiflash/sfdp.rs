@@ -22,6 +22,12 @@ pub enum Error {
     SliceRange(usize, usize),
     #[error("SFDP header contains incorrect signature: {0:#010x}")]
     WrongHeaderSignature(u32),
+    #[error("the {0} parameter table is too short: {1} dwords")]
+    TableTooShort(&'static str, usize),
+    #[error("the sector map region size {0:#x} is too large")]
+    SectorMapRegionTooLarge(u32),
+    #[error("sector map configuration {0} covers {1:#x} bytes instead of {2:#x}")]
+    SectorMapSize(u8, u64, u32),
 
     // This is only needed to meet the error conversion requirements for the most
     // general case in the field! macro below.
@@ -91,18 +97,38 @@ pub struct SfdpPhdr {
     pub major: u8,
     pub dwords: u8,
     pub offset: u32,
+    pub id_msb: u8,
+}
+
+impl SfdpPhdr {
+    // Parameter IDs of the tables defined by JEDEC (JESD216F pg. 14).
+    pub const JEDEC_BASIC_ID: u16 = 0xff00;
+    pub const XSPI_PROFILE_ID: u16 = 0xff05;
+    pub const SECTOR_MAP_ID: u16 = 0xff81;
+    pub const FOUR_BYTE_ADDRESS_ID: u16 = 0xff84;
+
+    /// The full 16-bit parameter ID of the table.
+    pub fn parameter_id(&self) -> u16 {
+        u16::from_le_bytes([self.id, self.id_msb])
+    }
 }
 
 impl TryFrom<&[u8]> for SfdpPhdr {
     type Error = Error;
     fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
         let mut reader = std::io::Cursor::new(buf);
+        let id = reader.read_u8()?;
+        let minor = reader.read_u8()?;
+        let major = reader.read_u8()?;
+        let dwords = reader.read_u8()?;
+        let pointer = reader.read_u32::<LittleEndian>()?;
         Ok(SfdpPhdr {
-            id: reader.read_u8()?,
-            minor: reader.read_u8()?,
-            major: reader.read_u8()?,
-            dwords: reader.read_u8()?,
-            offset: reader.read_u32::<LittleEndian>()? & 0x00FFFFFFu32,
+            id,
+            minor,
+            major,
+            dwords,
+            offset: pointer & 0x00FFFFFFu32,
+            id_msb: (pointer >> 24) as u8,
         })
     }
 }
@@ -869,6 +895,347 @@ impl TryFrom<&[u8]> for JedecParams {
     }
 }
 
+// The 4-byte address instruction table is documented in JESD216F section 6.6.
+struct InternalFourByteParams {
+    pub data: Vec<u32>,
+}
+
+impl InternalFourByteParams {
+    field!(support_read_111 -> bool, 1, 0, 1);
+    field!(support_fast_read_111 -> bool, 1, 1, 1);
+    field!(support_fast_read_112 -> bool, 1, 2, 1);
+    field!(support_fast_read_122 -> bool, 1, 3, 1);
+    field!(support_fast_read_114 -> bool, 1, 4, 1);
+    field!(support_fast_read_144 -> bool, 1, 5, 1);
+    field!(support_page_program_111 -> bool, 1, 6, 1);
+    field!(support_page_program_114 -> bool, 1, 7, 1);
+    field!(support_page_program_144 -> bool, 1, 8, 1);
+    field!(support_erase_type1 -> bool, 1, 9, 1);
+    field!(support_erase_type2 -> bool, 1, 10, 1);
+    field!(support_erase_type3 -> bool, 1, 11, 1);
+    field!(support_erase_type4 -> bool, 1, 12, 1);
+    field!(support_fast_read_1s1d1d -> bool, 1, 13, 1);
+    field!(support_fast_read_1s2d2d -> bool, 1, 14, 1);
+    field!(support_fast_read_1s4d4d -> bool, 1, 15, 1);
+    field!(support_volatile_sector_lock_read -> bool, 1, 16, 1);
+    field!(support_volatile_sector_lock_write -> bool, 1, 17, 1);
+    field!(support_nonvolatile_sector_lock_read -> bool, 1, 18, 1);
+    field!(support_nonvolatile_sector_lock_write -> bool, 1, 19, 1);
+    field!(support_fast_read_118 -> bool, 1, 20, 1);
+    field!(support_fast_read_188 -> bool, 1, 21, 1);
+    field!(support_fast_read_1s8d8d -> bool, 1, 22, 1);
+    field!(support_page_program_118 -> bool, 1, 23, 1);
+    field!(support_page_program_188 -> bool, 1, 24, 1);
+
+    field!(erase_type1_opcode -> u8, 2, 0, 8);
+    field!(erase_type2_opcode -> u8, 2, 8, 8);
+    field!(erase_type3_opcode -> u8, 2, 16, 8);
+    field!(erase_type4_opcode -> u8, 2, 24, 8);
+}
+
+/// The 4-byte address instruction table lists the instructions taking a 4-byte address,
+/// which a device supports regardless of its current addressing mode.  The opcodes of these
+/// instructions are fixed, except for the erase instructions.
+#[derive(Default, Debug, Serialize, Annotate)]
+pub struct FourByteParams {
+    /// READ 4B (13h).
+    pub support_read_111: bool,
+    /// FAST READ 4B (0Ch).
+    pub support_fast_read_111: bool,
+    /// 1-1-2 FAST READ 4B (3Ch).
+    pub support_fast_read_112: bool,
+    /// 1-2-2 FAST READ 4B (BCh).
+    pub support_fast_read_122: bool,
+    /// 1-1-4 FAST READ 4B (6Ch).
+    pub support_fast_read_114: bool,
+    /// 1-4-4 FAST READ 4B (ECh).
+    pub support_fast_read_144: bool,
+    /// 1-1-8 FAST READ 4B (7Ch).
+    pub support_fast_read_118: bool,
+    /// 1-8-8 FAST READ 4B (CCh).
+    pub support_fast_read_188: bool,
+    /// 1S-1D-1D FAST READ 4B (0Eh).
+    pub support_fast_read_1s1d1d: bool,
+    /// 1S-2D-2D FAST READ 4B (BEh).
+    pub support_fast_read_1s2d2d: bool,
+    /// 1S-4D-4D FAST READ 4B (EEh).
+    pub support_fast_read_1s4d4d: bool,
+    /// 1S-8D-8D FAST READ 4B (FDh).
+    pub support_fast_read_1s8d8d: bool,
+    /// PAGE PROGRAM 4B (12h).
+    pub support_page_program_111: bool,
+    /// 1-1-4 PAGE PROGRAM 4B (34h).
+    pub support_page_program_114: bool,
+    /// 1-4-4 PAGE PROGRAM 4B (3Eh).
+    pub support_page_program_144: bool,
+    /// 1-1-8 PAGE PROGRAM 4B (84h).
+    pub support_page_program_118: bool,
+    /// 1-8-8 PAGE PROGRAM 4B (8Eh).
+    pub support_page_program_188: bool,
+    /// Volatile individual sector lock read (E0h).
+    pub support_volatile_sector_lock_read: bool,
+    /// Volatile individual sector lock write (E1h).
+    pub support_volatile_sector_lock_write: bool,
+    /// Non-volatile individual sector lock read (E2h).
+    pub support_nonvolatile_sector_lock_read: bool,
+    /// Non-volatile individual sector lock write (E3h).
+    pub support_nonvolatile_sector_lock_write: bool,
+    /// 4-byte address opcodes of the erase types of the JEDEC parameter table, if supported.
+    #[annotate(format=hex)]
+    pub erase: [Option<u8>; 4],
+}
+
+impl TryFrom<&[u8]> for FourByteParams {
+    type Error = Error;
+    fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
+        let p = InternalFourByteParams {
+            data: InternalJedecParams::try_from(buf)?.data,
+        };
+        if p.data.len() < 2 {
+            return Err(Error::TableTooShort(
+                "4-byte address instruction",
+                p.data.len(),
+            ));
+        }
+        let opcode = |supported: bool, opcode: u8| if supported { Some(opcode) } else { None };
+        Ok(FourByteParams {
+            support_read_111: p.support_read_111()?,
+            support_fast_read_111: p.support_fast_read_111()?,
+            support_fast_read_112: p.support_fast_read_112()?,
+            support_fast_read_122: p.support_fast_read_122()?,
+            support_fast_read_114: p.support_fast_read_114()?,
+            support_fast_read_144: p.support_fast_read_144()?,
+            support_fast_read_118: p.support_fast_read_118()?,
+            support_fast_read_188: p.support_fast_read_188()?,
+            support_fast_read_1s1d1d: p.support_fast_read_1s1d1d()?,
+            support_fast_read_1s2d2d: p.support_fast_read_1s2d2d()?,
+            support_fast_read_1s4d4d: p.support_fast_read_1s4d4d()?,
+            support_fast_read_1s8d8d: p.support_fast_read_1s8d8d()?,
+            support_page_program_111: p.support_page_program_111()?,
+            support_page_program_114: p.support_page_program_114()?,
+            support_page_program_144: p.support_page_program_144()?,
+            support_page_program_118: p.support_page_program_118()?,
+            support_page_program_188: p.support_page_program_188()?,
+            support_volatile_sector_lock_read: p.support_volatile_sector_lock_read()?,
+            support_volatile_sector_lock_write: p.support_volatile_sector_lock_write()?,
+            support_nonvolatile_sector_lock_read: p.support_nonvolatile_sector_lock_read()?,
+            support_nonvolatile_sector_lock_write: p.support_nonvolatile_sector_lock_write()?,
+            erase: [
+                opcode(p.support_erase_type1()?, p.erase_type1_opcode()?),
+                opcode(p.support_erase_type2()?, p.erase_type2_opcode()?),
+                opcode(p.support_erase_type3()?, p.erase_type3_opcode()?),
+                opcode(p.support_erase_type4()?, p.erase_type4_opcode()?),
+            ],
+        })
+    }
+}
+
+// The xSPI profile 1.0 table is documented in JESD216F section 6.10.
+struct InternalXspiParams {
+    pub data: Vec<u32>,
+}
+
+impl InternalXspiParams {
+    field!(read_fast_opcode -> u8, 1, 8, 8);
+    field!(read_status_dummy_8 -> bool, 1, 28, 1);
+    field!(read_status_address_4 -> bool, 1, 29, 1);
+
+    field!(dummy_cycles_200mhz -> u8, 4, 7, 5);
+
+    field!(dummy_cycles_100mhz -> u8, 5, 7, 5);
+    field!(dummy_cycles_133mhz -> u8, 5, 17, 5);
+    field!(dummy_cycles_166mhz -> u8, 5, 27, 5);
+}
+
+/// The xSPI profile 1.0 table describes the 8D-8D-8D (octal DTR) protocol of JESD251.
+#[derive(Default, Debug, Serialize, Annotate)]
+pub struct XspiProfileParams {
+    /// Opcode of the 8D-8D-8D Read Fast command.
+    #[annotate(format=hex)]
+    pub read_fast_opcode: u8,
+    /// Number of address bytes of the 8D-8D-8D Read Status Register command.
+    pub read_status_address_bytes: u8,
+    /// Number of dummy cycles of the 8D-8D-8D Read Status Register command.
+    pub read_status_dummy_cycles: u8,
+    /// Dummy cycles of the Read Fast command by operating frequency, zero if the frequency is
+    /// not supported.
+    pub dummy_cycles_100mhz: u8,
+    pub dummy_cycles_133mhz: u8,
+    pub dummy_cycles_166mhz: u8,
+    pub dummy_cycles_200mhz: u8,
+}
+
+impl TryFrom<&[u8]> for XspiProfileParams {
+    type Error = Error;
+    fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
+        let p = InternalXspiParams {
+            data: InternalJedecParams::try_from(buf)?.data,
+        };
+        if p.data.len() < 5 {
+            return Err(Error::TableTooShort("xSPI profile", p.data.len()));
+        }
+        Ok(XspiProfileParams {
+            read_fast_opcode: p.read_fast_opcode()?,
+            read_status_address_bytes: if p.read_status_address_4()? { 4 } else { 0 },
+            read_status_dummy_cycles: if p.read_status_dummy_8()? { 8 } else { 4 },
+            dummy_cycles_100mhz: p.dummy_cycles_100mhz()?,
+            dummy_cycles_133mhz: p.dummy_cycles_133mhz()?,
+            dummy_cycles_166mhz: p.dummy_cycles_166mhz()?,
+            dummy_cycles_200mhz: p.dummy_cycles_200mhz()?,
+        })
+    }
+}
+
+/// The address phase of a configuration detection command.
+#[derive(Default, Debug, Eq, PartialEq, FromPrimitive, Clone, Copy, Serialize)]
+#[repr(u32)]
+pub enum DetectAddressLength {
+    None = 0,
+    Mode3b = 1,
+    Mode4b = 2,
+    /// The current addressing mode of the device.
+    Current = 3,
+    #[default]
+    Invalid,
+}
+
+/// A configuration detection command of the sector map table.  Each command reads one byte,
+/// and contributes one bit to the ID of the current configuration: whether any of the bits in
+/// `read_mask` are set.
+#[derive(Clone, Default, Debug, Serialize, Annotate)]
+pub struct ConfigDetectCommand {
+    #[annotate(format=hex)]
+    pub opcode: u8,
+    pub address_length: DetectAddressLength,
+    /// Dummy cycles after the address, `None` if they are the same as for the read commands.
+    pub dummy_cycles: Option<u8>,
+    #[annotate(format=hex)]
+    pub read_mask: u8,
+    #[annotate(format=hex)]
+    pub address: u32,
+}
+
+/// A region of uniform erase types in a sector map.
+#[derive(Clone, Default, Debug, Serialize, Annotate)]
+pub struct SectorMapRegion {
+    #[annotate(format=hex)]
+    pub size: u32,
+    /// The erase types of the JEDEC parameter table usable in the region, bit 0 being the first
+    /// erase type.
+    #[annotate(format=bin)]
+    pub erase_types: u8,
+}
+
+/// The layout of the flash in one configuration.
+#[derive(Clone, Default, Debug, Serialize, Annotate)]
+pub struct SectorMapConfig {
+    pub id: u8,
+    pub regions: Vec<SectorMapRegion>,
+}
+
+/// The sector map table describes devices whose erase types differ between regions of the
+/// flash, and which may have several configurations, such as the boot sectors at the top or at
+/// the bottom.  It is documented in JESD216F section 6.5.
+#[derive(Default, Debug, Serialize, Annotate)]
+pub struct SectorMapParams {
+    /// Commands determining the ID of the current configuration.  If there are none, there is
+    /// only one configuration.
+    pub detect: Vec<ConfigDetectCommand>,
+    pub configs: Vec<SectorMapConfig>,
+}
+
+impl SectorMapParams {
+    const DESCRIPTOR_END: BitField = BitField::new(0, 1);
+    const DESCRIPTOR_MAP: BitField = BitField::new(1, 1);
+    const DETECT_OPCODE: BitField = BitField::new(8, 8);
+    const DETECT_DUMMY_CYCLES: BitField = BitField::new(16, 4);
+    const DETECT_ADDRESS_LENGTH: BitField = BitField::new(22, 2);
+    const DETECT_READ_MASK: BitField = BitField::new(24, 8);
+    const MAP_ID: BitField = BitField::new(8, 8);
+    const MAP_REGION_COUNT: BitField = BitField::new(16, 8);
+    const REGION_ERASE_TYPES: BitField = BitField::new(0, 4);
+    const REGION_SIZE: BitField = BitField::new(8, 24);
+
+    /// Returns the configuration with the given ID, as determined by the detection commands.
+    /// Without detection commands, the only configuration is returned.
+    pub fn config(&self, id: Option<u8>) -> Option<&SectorMapConfig> {
+        match id {
+            Some(id) => self.configs.iter().find(|c| c.id == id),
+            None if self.detect.is_empty() => self.configs.first(),
+            None => None,
+        }
+    }
+
+    /// Checks that the regions of every configuration cover exactly `density` bytes.
+    pub fn check_density(&self, density: u32) -> Result<(), Error> {
+        for config in self.configs.iter() {
+            let size = config.regions.iter().map(|r| u64::from(r.size)).sum();
+            if size != u64::from(density) {
+                return Err(Error::SectorMapSize(config.id, size, density));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&[u8]> for SectorMapParams {
+    type Error = Error;
+    fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
+        let data = InternalJedecParams::try_from(buf)?.data;
+        let dword = |i: usize| {
+            data.get(i)
+                .copied()
+                .ok_or(Error::TableTooShort("sector map", data.len()))
+        };
+        let mut params = SectorMapParams::default();
+        let mut i = 0;
+        // The configuration detection commands come first, followed by the maps.
+        while Self::DESCRIPTOR_MAP.extract(dword(i)?) == 0 {
+            let header = dword(i)?;
+            let dummy_cycles = Self::DETECT_DUMMY_CYCLES.extract(header) as u8;
+            params.detect.push(ConfigDetectCommand {
+                opcode: Self::DETECT_OPCODE.extract(header) as u8,
+                address_length: DetectAddressLength::from(
+                    Self::DETECT_ADDRESS_LENGTH.extract(header),
+                ),
+                dummy_cycles: if dummy_cycles == 0xf {
+                    None
+                } else {
+                    Some(dummy_cycles)
+                },
+                read_mask: Self::DETECT_READ_MASK.extract(header) as u8,
+                address: dword(i + 1)?,
+            });
+            i += 2;
+        }
+        loop {
+            let header = dword(i)?;
+            let count = Self::MAP_REGION_COUNT.extract(header) as usize + 1;
+            let regions = (i + 1..i + 1 + count)
+                .map(|j| {
+                    let region = dword(j)?;
+                    let size = Self::REGION_SIZE.extract(region);
+                    Ok(SectorMapRegion {
+                        size: (size + 1)
+                            .checked_mul(256)
+                            .ok_or(Error::SectorMapRegionTooLarge(size))?,
+                        erase_types: Self::REGION_ERASE_TYPES.extract(region) as u8,
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            params.configs.push(SectorMapConfig {
+                id: Self::MAP_ID.extract(header) as u8,
+                regions,
+            });
+            i += 1 + count;
+            if Self::DESCRIPTOR_END.extract(header) != 0 {
+                break;
+            }
+        }
+        Ok(params)
+    }
+}
+
 /// An `UnknownParams` structure represents SFDP parameter tables for which
 /// we don't have a specialized parser.
 #[derive(Debug, Serialize)]
@@ -892,6 +1259,9 @@ pub struct Sfdp {
     pub header: SfdpHeader,
     pub phdr: Vec<SfdpPhdr>,
     pub jedec: JedecParams,
+    pub sector_map: Option<SectorMapParams>,
+    pub four_byte: Option<FourByteParams>,
+    pub xspi: Option<XspiProfileParams>,
     pub params: Vec<UnknownParams>,
 }
 
@@ -944,19 +1314,48 @@ impl TryFrom<&[u8]> for Sfdp {
         let jedec =
             JedecParams::try_from(buf.get(start..end).ok_or(Error::SliceRange(start, end))?)?;
 
+        let mut sector_map = None;
+        let mut four_byte = None;
+        let mut xspi = None;
         let mut params = Vec::new();
         for ph in phdr.iter().take((header.nph as usize) + 1).skip(1) {
             let start = ph.offset as usize;
             let end = start + ph.dwords as usize * 4;
-            params.push(UnknownParams::try_from(
-                buf.get(start..end).ok_or(Error::SliceRange(start, end))?,
-            )?);
+            let table = buf.get(start..end).ok_or(Error::SliceRange(start, end))?;
+            // The optional tables are not needed to operate the flash, one we can't decode is
+            // kept undecoded.
+            let decoded = match ph.parameter_id() {
+                SfdpPhdr::SECTOR_MAP_ID => SectorMapParams::try_from(table)
+                    .and_then(|map| map.check_density(jedec.density).map(|_| map))
+                    .map(|map| sector_map = Some(map)),
+                SfdpPhdr::FOUR_BYTE_ADDRESS_ID => {
+                    FourByteParams::try_from(table).map(|p| four_byte = Some(p))
+                }
+                SfdpPhdr::XSPI_PROFILE_ID => {
+                    XspiProfileParams::try_from(table).map(|p| xspi = Some(p))
+                }
+                _ => {
+                    params.push(UnknownParams::try_from(table)?);
+                    continue;
+                }
+            };
+            if let Err(e) = decoded {
+                log::warn!(
+                    "Ignoring SFDP parameter table {:#06x}: {}",
+                    ph.parameter_id(),
+                    e
+                );
+                params.push(UnknownParams::try_from(table)?);
+            }
         }
 
         Ok(Sfdp {
             header,
             phdr,
             jedec,
+            sector_map,
+            four_byte,
+            xspi,
             params,
         })
     }
@@ -1055,6 +1454,125 @@ mod test {
         // The particular MX66L1G sampled doesn't have a RevD or RevF table.
         assert!(sfdp.jedec.rev_d.is_none());
         assert!(sfdp.jedec.rev_f.is_none());
+
+        assert_eq!(sfdp.phdr[2].parameter_id(), SfdpPhdr::FOUR_BYTE_ADDRESS_ID);
+        let four_byte = sfdp.four_byte.as_ref().expect("4-byte address parameters");
+        assert_eq!(four_byte.support_read_111, true);
+        assert_eq!(four_byte.support_page_program_114, false);
+        assert_eq!(four_byte.support_page_program_144, true);
+        assert_eq!(four_byte.erase, [Some(0x21), Some(0x5c), Some(0xdc), None]);
+        assert!(sfdp.sector_map.is_none());
+        assert!(sfdp.xspi.is_none());
+        // The Macronix vendor table.
+        assert_eq!(sfdp.params.len(), 1);
+        Ok(())
+    }
+
+    fn dwords(data: &[u32]) -> Vec<u8> {
+        data.iter().flat_map(|d| d.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_decode_sector_map() -> Result<()> {
+        let table = dwords(&[
+            // Read the configuration register (65h) at address 4 with 8 dummy cycles, 3-byte
+            // address, testing bit 2.
+            0x0448_6508,
+            0x0000_0004,
+            // Configuration 0: 4KiB sectors in the lower 32KiB, then 64KiB blocks.
+            0x0001_0002,
+            0x0000_7f01,
+            0x00ff_7f04,
+            // Configuration 1: 64KiB blocks, then 4KiB sectors in the upper 32KiB.
+            0x0001_0103,
+            0x00ff_7f04,
+            0x0000_7f01,
+        ]);
+        let map = SectorMapParams::try_from(&table[..])?;
+        assert_eq!(map.detect.len(), 1);
+        assert_eq!(map.detect[0].opcode, 0x65);
+        assert_eq!(map.detect[0].address_length, DetectAddressLength::Mode3b);
+        assert_eq!(map.detect[0].dummy_cycles, Some(8));
+        assert_eq!(map.detect[0].read_mask, 0x04);
+        assert_eq!(map.detect[0].address, 4);
+        assert!(map.config(None).is_none());
+        let config = map.config(Some(1)).expect("configuration 1");
+        assert_eq!(config.regions.len(), 2);
+        assert_eq!(config.regions[0].size, 0xff_8000);
+        assert_eq!(config.regions[0].erase_types, 0b0100);
+        assert_eq!(config.regions[1].size, 0x8000);
+        assert_eq!(config.regions[1].erase_types, 0b0001);
+        assert!(map.config(Some(2)).is_none());
+
+        // A truncated table.
+        assert!(SectorMapParams::try_from(&table[..24]).is_err());
+        // A region of 4GiB.
+        assert!(matches!(
+            SectorMapParams::try_from(&dwords(&[0x0000_0003, 0xffff_ff04])[..]),
+            Err(Error::SectorMapRegionTooLarge(0xff_ffff))
+        ));
+        Ok(())
+    }
+
+    /// The MX66L1G SFDP with its 4-byte address table replaced by the sector map `table`.
+    fn with_sector_map(table: &[u32]) -> Vec<u8> {
+        let mut sfdp = SFDP_MX66L1G.to_vec();
+        sfdp[0x18..0x20].copy_from_slice(&[
+            0x81,
+            0x00,
+            0x01,
+            table.len() as u8,
+            0x20,
+            0x01,
+            0x00,
+            0xff,
+        ]);
+        let table = dwords(table);
+        sfdp[0x120..0x120 + table.len()].copy_from_slice(&table);
+        sfdp
+    }
+
+    #[test]
+    fn test_decode_optional_table_errors() -> Result<()> {
+        // 4KiB sectors in the lower 32KiB, then 64KiB blocks up to 128MiB.
+        let sfdp = Sfdp::try_from(&with_sector_map(&[0x0001_0003, 0x0000_7f01, 0x07ff_7f04])[..])?;
+        let config = sfdp.sector_map.as_ref().and_then(|map| map.config(None));
+        assert_eq!(config.expect("configuration").regions.len(), 2);
+        assert_eq!(sfdp.params.len(), 1);
+
+        // The regions only cover 16MiB.
+        let sfdp = Sfdp::try_from(&with_sector_map(&[0x0001_0003, 0x0000_7f01, 0x00ff_7f04])[..])?;
+        assert!(sfdp.sector_map.is_none());
+        assert_eq!(sfdp.params.len(), 2);
+        assert_eq!(sfdp.params[1].data, [0x0001_0003, 0x0000_7f01, 0x00ff_7f04]);
+
+        // The regions are missing.
+        let sfdp = Sfdp::try_from(&with_sector_map(&[0x0001_0003])[..])?;
+        assert!(sfdp.sector_map.is_none());
+        assert_eq!(sfdp.params.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_xspi_profile() -> Result<()> {
+        let table = dwords(&[0x3000_ee00, 0, 0, 0x0000_0a00, 0x5028_0a00]);
+        let xspi = XspiProfileParams::try_from(&table[..])?;
+        assert_eq!(xspi.read_fast_opcode, 0xee);
+        assert_eq!(xspi.read_status_address_bytes, 4);
+        assert_eq!(xspi.read_status_dummy_cycles, 8);
+        assert_eq!(xspi.dummy_cycles_200mhz, 20);
+        assert_eq!(xspi.dummy_cycles_166mhz, 10);
+        assert_eq!(xspi.dummy_cycles_133mhz, 20);
+        assert_eq!(xspi.dummy_cycles_100mhz, 20);
+        assert!(XspiProfileParams::try_from(&table[..16]).is_err());
+
+        // A short xSPI profile table is kept undecoded.
+        let mut sfdp = SFDP_MX66L1G.to_vec();
+        sfdp[0x18..0x20].copy_from_slice(&[0x05, 0x00, 0x01, 0x04, 0x20, 0x01, 0x00, 0xff]);
+        sfdp[0x120..0x130].copy_from_slice(&table[..16]);
+        let sfdp = Sfdp::try_from(&sfdp[..])?;
+        assert!(sfdp.xspi.is_none());
+        assert_eq!(sfdp.params.len(), 2);
         Ok(())
     }
 
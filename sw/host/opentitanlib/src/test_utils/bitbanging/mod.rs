@@ -4,6 +4,7 @@
 
 pub mod i2c;
 pub mod spi;
+pub mod uart;
 
 #[repr(u8)]
 #[derive(Debug, PartialEq, Clone, Copy)]
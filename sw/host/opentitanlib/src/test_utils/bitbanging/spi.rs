@@ -4,6 +4,7 @@
 
 use super::Bit;
 use anyhow::{bail, Context, Result};
+use arrayvec::ArrayVec;
 
 pub enum SpiDataMode {
     Single,
@@ -11,7 +12,151 @@ pub enum SpiDataMode {
     Quad,
 }
 
-pub mod encoder {}
+pub mod encoder {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    pub enum Transfer<'w> {
+        /// Assert the chip select, with the clock at its idle level.
+        Select,
+        /// Return the clock to its idle level and deassert the chip select.
+        Deselect,
+        /// Bytes sent by the host on the data lines of the data mode, most significant bit
+        /// first.
+        Write(&'w [u8]),
+        /// Clock `n` bytes with the data lines released (high), as the host does when reading.
+        Read(usize),
+        /// A single mode transfer with both sides of the bus: `host` is sent on D0 and `device`
+        /// on D1. Both must have the same length.
+        Exchange { host: &'w [u8], device: &'w [u8] },
+        /// Clock cycles with the given levels on D0, typically less than a byte.
+        Broken(ArrayVec<Bit, 8>),
+        /// Bytes whose data lines only become valid on the sampling edge of the clock instead
+        /// of the shifting edge, violating the setup time.
+        WrongPhase(&'w [u8]),
+    }
+
+    pub struct Encoder<
+        const D0: u8,
+        const D1: u8,
+        const D2: u8,
+        const D3: u8,
+        const CLK: u8,
+        const CS: u8,
+    > {
+        pub cpol: bool,
+        pub cpha: bool,
+        pub data_mode: SpiDataMode,
+    }
+
+    impl<const D0: u8, const D1: u8, const D2: u8, const D3: u8, const CLK: u8, const CS: u8>
+        Encoder<D0, D1, D2, D3, CLK, CS>
+    {
+        fn clk(&self, active: bool) -> u8 {
+            ((self.cpol != active) as u8) << CLK
+        }
+
+        /// Levels of the data lines for `symbol`, bit `i` of the symbol driving `Di`.
+        fn data(symbol: u8) -> u8 {
+            (symbol & 0x01) << D0
+                | ((symbol >> 1) & 0x01) << D1
+                | ((symbol >> 2) & 0x01) << D2
+                | ((symbol >> 3) & 0x01) << D3
+        }
+
+        /// Number of data lines used by the data mode.
+        fn width(&self) -> u8 {
+            match self.data_mode {
+                SpiDataMode::Single => 1,
+                SpiDataMode::Dual => 2,
+                SpiDataMode::Quad => 4,
+            }
+        }
+
+        /// Splits a byte in the symbols shifted at each clock cycle, most significant first.
+        fn symbols(&self, byte: u8) -> impl Iterator<Item = u8> {
+            let width = self.width();
+            (0..8 / width)
+                .rev()
+                .map(move |i| (byte >> (i * width)) & ((1 << width) - 1))
+        }
+
+        /// One clock cycle with `symbol` on the data lines selected by `mask`, `cs` being the
+        /// level of the chip select.
+        fn cycle(&self, cs: u8, symbol: u8, mask: u8, wrong_phase: bool, samples: &mut Vec<u8>) {
+            let data = Self::data(symbol & mask);
+            let setup = if wrong_phase {
+                Self::data(!symbol & mask)
+            } else {
+                data
+            };
+            if self.cpha {
+                // Data is shifted on the leading edge and sampled on the trailing edge.
+                samples.extend([cs | self.clk(true) | setup, cs | self.clk(false) | data]);
+            } else {
+                samples.extend([cs | self.clk(false) | setup, cs | self.clk(true) | data]);
+            }
+        }
+
+        fn bitbanging(&self, transfer: &Transfer, selected: &mut bool, samples: &mut Vec<u8>) {
+            let mask = (1 << self.width()) - 1;
+            let cs = (!*selected as u8) << CS;
+            match transfer {
+                Transfer::Select => {
+                    samples.extend([cs | self.clk(false), self.clk(false)]);
+                    *selected = true;
+                }
+                Transfer::Deselect => {
+                    samples.extend([cs | self.clk(false), 0x01 << CS | self.clk(false)]);
+                    *selected = false;
+                }
+                Transfer::Write(bytes) => {
+                    for &byte in bytes.iter() {
+                        for symbol in self.symbols(byte) {
+                            self.cycle(cs, symbol, mask, false, samples);
+                        }
+                    }
+                }
+                Transfer::Read(len) => {
+                    for _ in 0..(8 / self.width()) as usize * len {
+                        self.cycle(cs, 0xff, mask, false, samples);
+                    }
+                }
+                Transfer::Exchange { host, device } => {
+                    for (&h, &d) in host.iter().zip(device.iter()) {
+                        for bit in (0..8).rev() {
+                            let symbol = (h >> bit) & 0x01 | ((d >> bit) & 0x01) << 1;
+                            self.cycle(cs, symbol, 0x03, false, samples);
+                        }
+                    }
+                }
+                Transfer::Broken(bits) => {
+                    for &bit in bits.iter() {
+                        self.cycle(cs, bit as u8, 0x01, false, samples);
+                    }
+                }
+                Transfer::WrongPhase(bytes) => {
+                    for &byte in bytes.iter() {
+                        for symbol in self.symbols(byte) {
+                            self.cycle(cs, symbol, mask, true, samples);
+                        }
+                    }
+                }
+            }
+        }
+
+        /// Returns the samples of `transfers`, two samples per clock cycle. The chip select is
+        /// deasserted until the first `Select`.
+        pub fn run(&self, transfers: &[Transfer]) -> Vec<u8> {
+            let mut samples = Vec::new();
+            let mut selected = false;
+            for transfer in transfers {
+                self.bitbanging(transfer, &mut selected, &mut samples);
+            }
+            samples
+        }
+    }
+}
 
 pub mod decoder {
     use super::*;
@@ -154,3 +299,142 @@ pub mod decoder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::decoder::Decoder;
+    use super::encoder::{Encoder, Transfer};
+    use super::*;
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let bytes = [0xa5, 0x3c, 0x01];
+        for (cpol, cpha) in [(false, false), (false, true), (true, false), (true, true)] {
+            for data_mode in [SpiDataMode::Single, SpiDataMode::Dual, SpiDataMode::Quad] {
+                let encoder = Encoder::<0, 1, 2, 3, 4, 5> {
+                    cpol,
+                    cpha,
+                    data_mode,
+                };
+                let samples = encoder.run(&[
+                    Transfer::Select,
+                    Transfer::Write(&bytes),
+                    Transfer::Deselect,
+                ]);
+                let mut decoder = Decoder::<0, 1, 2, 3, 4, 5> {
+                    cpol,
+                    cpha,
+                    data_mode: encoder.data_mode,
+                };
+                assert_eq!(decoder.run(samples)?, bytes, "mode {cpol} {cpha}");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn exchange() -> Result<()> {
+        let encoder = Encoder::<0, 1, 2, 3, 4, 5> {
+            cpol: false,
+            cpha: false,
+            data_mode: SpiDataMode::Single,
+        };
+        let samples = encoder.run(&[
+            Transfer::Select,
+            Transfer::Exchange {
+                host: &[0x9f],
+                device: &[0xef],
+            },
+            Transfer::Deselect,
+        ]);
+        // Decode the device side by mapping D1 to D0.
+        let mut decoder = Decoder::<1, 0, 2, 3, 4, 5> {
+            cpol: false,
+            cpha: false,
+            data_mode: SpiDataMode::Single,
+        };
+        assert_eq!(decoder.run(samples)?, [0xef]);
+        Ok(())
+    }
+
+    #[test]
+    fn read() -> Result<()> {
+        for data_mode in [SpiDataMode::Single, SpiDataMode::Dual, SpiDataMode::Quad] {
+            let encoder = Encoder::<0, 1, 2, 3, 4, 5> {
+                cpol: false,
+                cpha: false,
+                data_mode,
+            };
+            let samples = encoder.run(&[
+                Transfer::Select,
+                Transfer::Write(&[0x00]),
+                Transfer::Read(2),
+                Transfer::Deselect,
+            ]);
+            let mut decoder = Decoder::<0, 1, 2, 3, 4, 5> {
+                cpol: false,
+                cpha: false,
+                data_mode: encoder.data_mode,
+            };
+            assert_eq!(decoder.run(samples)?, [0x00, 0xff, 0xff]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn broken() -> Result<()> {
+        let encoder = Encoder::<0, 1, 2, 3, 4, 5> {
+            cpol: false,
+            cpha: true,
+            data_mode: SpiDataMode::Single,
+        };
+        let samples = encoder.run(&[
+            Transfer::Select,
+            Transfer::Write(&[0x5a]),
+            Transfer::Broken([Bit::High, Bit::Low, Bit::High].into_iter().collect()),
+            Transfer::Deselect,
+        ]);
+        // Select, one byte, three clock cycles and deselect.
+        assert_eq!(samples.len(), 2 + 16 + 6 + 2);
+        // The D0 levels of the three cycles, in both samples of each.
+        let d0: Vec<u8> = samples[18..24].iter().map(|s| s & 0x01).collect();
+        assert_eq!(d0, [1, 1, 0, 0, 1, 1]);
+        let mut decoder = Decoder::<0, 1, 2, 3, 4, 5> {
+            cpol: false,
+            cpha: true,
+            data_mode: SpiDataMode::Single,
+        };
+        // The incomplete byte is dropped.
+        assert_eq!(decoder.run(samples)?, [0x5a]);
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_phase() -> Result<()> {
+        for cpha in [false, true] {
+            let encoder = Encoder::<0, 1, 2, 3, 4, 5> {
+                cpol: false,
+                cpha,
+                data_mode: SpiDataMode::Dual,
+            };
+            let samples = encoder.run(&[
+                Transfer::Select,
+                Transfer::WrongPhase(&[0xa5, 0x3c]),
+                Transfer::Deselect,
+            ]);
+            // In each clock cycle, the data lines hold the inverted symbol until the sampling
+            // edge.
+            for cycle in samples[2..18].chunks(2) {
+                assert_eq!((cycle[0] ^ cycle[1]) & 0x03, 0x03, "cpha {cpha}");
+            }
+            // Sampling on the right edge still reads the bytes.
+            let mut decoder = Decoder::<0, 1, 2, 3, 4, 5> {
+                cpol: false,
+                cpha,
+                data_mode: SpiDataMode::Dual,
+            };
+            assert_eq!(decoder.run(samples)?, [0xa5, 0x3c], "cpha {cpha}");
+        }
+        Ok(())
+    }
+}
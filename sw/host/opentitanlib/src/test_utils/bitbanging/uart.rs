@@ -0,0 +1,407 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use super::Bit;
+use crate::io::uart::Parity;
+use anyhow::{ensure, Result};
+use arrayvec::ArrayVec;
+
+/// Framing of the characters, shared by the encoder and the decoder.
+#[derive(Clone, Debug)]
+pub struct UartFraming {
+    /// Number of data bits, between 5 and 8.
+    pub data_bits: u8,
+    pub parity: Parity,
+    /// Number of stop bits, 1 or 2.
+    pub stop_bits: u8,
+    /// Number of samples per bit: the bitbanging clock must run at `oversampling` times the baud
+    /// rate. Violations shorter than a bit, such as glitches, need an oversampling above 1.
+    pub oversampling: usize,
+}
+
+impl Default for UartFraming {
+    fn default() -> Self {
+        UartFraming {
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: 1,
+            oversampling: 1,
+        }
+    }
+}
+
+impl UartFraming {
+    fn check(&self) -> Result<()> {
+        ensure!(
+            (5..=8).contains(&self.data_bits),
+            "unsupported number of data bits {}",
+            self.data_bits
+        );
+        ensure!(
+            (1..=2).contains(&self.stop_bits),
+            "unsupported number of stop bits {}",
+            self.stop_bits
+        );
+        ensure!(self.oversampling > 0, "the oversampling must not be zero");
+        Ok(())
+    }
+
+    /// The level of the parity bit of `data`, if any.
+    fn parity_bit(&self, data: u8) -> Option<bool> {
+        let odd = data.count_ones() % 2 == 1;
+        match self.parity {
+            Parity::None => None,
+            Parity::Even => Some(odd),
+            Parity::Odd => Some(!odd),
+        }
+    }
+}
+
+pub mod encoder {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    pub enum Transfer<'w> {
+        /// Characters with a correct framing.
+        Write(&'w [u8]),
+        /// The line idle (high) for `n` bit times.
+        Idle(usize),
+        /// The line low for `n` bit times, `n` being larger than a character for a break.
+        Break(usize),
+        /// A character whose parity bit is inverted. Without parity, an extra bit is inserted in
+        /// place of the parity bit.
+        WrongParity(u8),
+        /// A character whose first stop bit is low.
+        FramingError(u8),
+        /// A character whose stop bits only last `samples` samples, after which the next
+        /// transfer starts.
+        ShortStop { data: u8, samples: usize },
+        /// The line low for `samples` samples, shorter than a start bit.
+        Glitch(usize),
+        /// A start bit followed by the given data bits, least significant first, after which the
+        /// line goes back to idle.
+        Broken(ArrayVec<Bit, 8>),
+    }
+
+    pub struct Encoder<const TX: u8> {
+        pub framing: UartFraming,
+    }
+
+    impl<const TX: u8> Encoder<TX> {
+        fn bit(&self, level: bool, samples: &mut Vec<u8>) {
+            self.samples(level, self.framing.oversampling, samples);
+        }
+
+        fn samples(&self, level: bool, count: usize, samples: &mut Vec<u8>) {
+            samples.resize(samples.len() + count, (level as u8) << TX);
+        }
+
+        /// Start bit, data bits and parity bit of a character.
+        fn character(&self, data: u8, parity: Option<bool>, samples: &mut Vec<u8>) {
+            self.bit(false, samples);
+            for bit in 0..self.framing.data_bits {
+                self.bit((data >> bit) & 0x01 == 1, samples);
+            }
+            if let Some(parity) = parity {
+                self.bit(parity, samples);
+            }
+        }
+
+        fn stop_bits(&self, level: bool, samples: &mut Vec<u8>) {
+            for _ in 0..self.framing.stop_bits {
+                self.bit(level, samples);
+            }
+        }
+
+        fn bitbanging(&self, transfer: &Transfer, samples: &mut Vec<u8>) {
+            let os = self.framing.oversampling;
+            match transfer {
+                Transfer::Write(bytes) => {
+                    for &byte in bytes.iter() {
+                        self.character(byte, self.framing.parity_bit(byte), samples);
+                        self.stop_bits(true, samples);
+                    }
+                }
+                Transfer::Idle(bits) => self.samples(true, bits * os, samples),
+                Transfer::Break(bits) => self.samples(false, bits * os, samples),
+                Transfer::WrongParity(byte) => {
+                    let parity = self.framing.parity_bit(*byte).is_some_and(|p| !p);
+                    self.character(*byte, Some(parity), samples);
+                    self.stop_bits(true, samples);
+                }
+                Transfer::FramingError(byte) => {
+                    self.character(*byte, self.framing.parity_bit(*byte), samples);
+                    self.bit(false, samples);
+                    // Return to idle so the next start bit can be detected.
+                    self.bit(true, samples);
+                }
+                Transfer::ShortStop {
+                    data,
+                    samples: count,
+                } => {
+                    self.character(*data, self.framing.parity_bit(*data), samples);
+                    self.samples(true, *count, samples);
+                }
+                Transfer::Glitch(count) => self.samples(false, *count, samples),
+                Transfer::Broken(bits) => {
+                    self.bit(false, samples);
+                    for &bit in bits.iter() {
+                        self.bit(bit == Bit::High, samples);
+                    }
+                }
+            }
+        }
+
+        /// Returns the samples of `transfers`, starting and ending with one idle bit.
+        pub fn run(&self, transfers: &[Transfer]) -> Result<Vec<u8>> {
+            self.framing.check()?;
+            let mut samples = Vec::new();
+            self.bit(true, &mut samples);
+            for transfer in transfers {
+                self.bitbanging(transfer, &mut samples);
+            }
+            self.bit(true, &mut samples);
+            Ok(samples)
+        }
+    }
+}
+
+pub mod decoder {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    pub enum Transfer {
+        Byte {
+            data: u8,
+            parity_error: bool,
+            framing_error: bool,
+        },
+        /// The line stayed low for more than a character, stop bits included.
+        Break,
+    }
+
+    pub struct Decoder<const RX: u8> {
+        pub framing: UartFraming,
+    }
+
+    impl<const RX: u8> Decoder<RX> {
+        /// Decodes the characters of `samples`. Each bit is sampled in its middle, relative to
+        /// the first low sample of the start bit. Start bits which are high in their middle are
+        /// glitches and are ignored. A break must keep the line low past the stop bits, so that
+        /// it is told apart from a null character with a framing error.
+        pub fn run(&self, samples: &[u8]) -> Result<Vec<Transfer>> {
+            self.framing.check()?;
+            let os = self.framing.oversampling;
+            let level = |index: usize| (samples[index] >> RX) & 0x01 == 1;
+            let parity_bits = (self.framing.parity != Parity::None) as usize;
+            let frame_bits = 1 + self.framing.data_bits as usize + parity_bits;
+            let stop_bits = self.framing.stop_bits as usize;
+            // Index of the middle of bit `n` of a character starting at `start`.
+            let middle = |start: usize, n: usize| start + n * os + os / 2;
+
+            let mut transfers = Vec::new();
+            let mut index = 0;
+            // Wait for the line to be idle.
+            while index < samples.len() && !level(index) {
+                index += 1;
+            }
+            while index < samples.len() {
+                if level(index) {
+                    index += 1;
+                    continue;
+                }
+                let start = index;
+                if middle(start, frame_bits) >= samples.len() {
+                    break;
+                }
+                if level(middle(start, 0)) {
+                    // Glitch.
+                    index += 1;
+                    continue;
+                }
+                let mut data = 0u8;
+                for bit in 0..self.framing.data_bits as usize {
+                    data |= (level(middle(start, 1 + bit)) as u8) << bit;
+                }
+                let parity_error = match self.framing.parity_bit(data) {
+                    Some(parity) => {
+                        level(middle(start, 1 + self.framing.data_bits as usize)) != parity
+                    }
+                    None => false,
+                };
+                let framing_error = !level(middle(start, frame_bits));
+                index = middle(start, frame_bits);
+                // Whether the line stayed low until the end of the stop bits, `end` being the
+                // first sample after them.
+                let end = start + (frame_bits + stop_bits) * os;
+                let low_frame = end < samples.len() && (start..end).all(|i| !level(i));
+                if low_frame && !level(end) {
+                    transfers.push(Transfer::Break);
+                } else {
+                    transfers.push(Transfer::Byte {
+                        data,
+                        parity_error,
+                        framing_error,
+                    });
+                }
+                if low_frame {
+                    // Wait for the line to be idle again.
+                    while index < samples.len() && !level(index) {
+                        index += 1;
+                    }
+                } else {
+                    // Skip the stop bit so a low stop bit is not taken as a start bit.
+                    index += 1;
+                }
+            }
+            Ok(transfers)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decoder::{Decoder, Transfer as Rx};
+    use super::encoder::{Encoder, Transfer as Tx};
+    use super::*;
+
+    fn framing(parity: Parity, oversampling: usize) -> UartFraming {
+        UartFraming {
+            parity,
+            oversampling,
+            ..Default::default()
+        }
+    }
+
+    fn byte(data: u8, parity_error: bool, framing_error: bool) -> Rx {
+        Rx::Byte {
+            data,
+            parity_error,
+            framing_error,
+        }
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        for oversampling in [1, 4, 5] {
+            let framing = framing(Parity::Odd, oversampling);
+            let encoder = Encoder::<2> {
+                framing: framing.clone(),
+            };
+            let samples =
+                encoder.run(&[Tx::Write(b"OT"), Tx::Idle(3), Tx::Write(&[0x00, 0xff])])?;
+            let decoded = Decoder::<2> { framing }.run(&samples)?;
+            assert_eq!(
+                decoded,
+                [
+                    byte(b'O', false, false),
+                    byte(b'T', false, false),
+                    byte(0x00, false, false),
+                    byte(0xff, false, false),
+                ]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn violations() -> Result<()> {
+        let framing = framing(Parity::Even, 4);
+        let encoder = Encoder::<0> {
+            framing: framing.clone(),
+        };
+        let samples = encoder.run(&[
+            Tx::Glitch(1),
+            Tx::Idle(1),
+            Tx::WrongParity(0x12),
+            Tx::FramingError(0x34),
+            Tx::Break(12),
+            Tx::Idle(1),
+            Tx::Broken([Bit::High, Bit::Low].into_iter().collect()),
+            Tx::Idle(12),
+            Tx::Write(b"A"),
+        ])?;
+        let decoded = Decoder::<0> { framing }.run(&samples)?;
+        assert_eq!(
+            decoded,
+            [
+                byte(0x12, true, false),
+                byte(0x34, false, true),
+                Rx::Break,
+                // The broken character ends with the line idle: its missing bits and its parity
+                // bit read as ones.
+                byte(0xfd, false, false),
+                byte(b'A', false, false),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn short_stop() -> Result<()> {
+        let encoder = Encoder::<0> {
+            framing: framing(Parity::None, 8),
+        };
+        let samples = encoder.run(&[Tx::ShortStop {
+            data: 0x55,
+            samples: 2,
+        }])?;
+        // Idle bit, start bit, 8 data bits, 2 stop samples, and the final idle bit.
+        assert_eq!(samples.len(), 8 + 8 + 8 * 8 + 2 + 8);
+
+        // The stop bit is sampled in its middle, which falls in the next start bit. The next
+        // character is still decoded, although its bits are no longer sampled in their middle.
+        let framing = framing(Parity::None, 8);
+        let encoder = Encoder::<0> {
+            framing: framing.clone(),
+        };
+        let samples = encoder.run(&[
+            Tx::ShortStop {
+                data: 0x55,
+                samples: 2,
+            },
+            Tx::Write(b"A"),
+        ])?;
+        let decoded = Decoder::<0> { framing }.run(&samples)?;
+        assert_eq!(decoded, [byte(0x55, false, true), byte(b'A', false, false)]);
+        Ok(())
+    }
+
+    #[test]
+    fn null_character_and_break() -> Result<()> {
+        for (stop_bits, oversampling) in [(1, 1), (1, 4), (2, 4)] {
+            let framing = UartFraming {
+                stop_bits,
+                oversampling,
+                ..Default::default()
+            };
+            let encoder = Encoder::<0> {
+                framing: framing.clone(),
+            };
+            // A character lasts 10 bits, and 11 with 2 stop bits.
+            let frame = 9 + stop_bits as usize;
+            let samples = encoder.run(&[
+                Tx::FramingError(0x00),
+                Tx::Idle(2),
+                Tx::Break(frame),
+                Tx::Idle(2),
+                Tx::Break(frame + 1),
+                Tx::Idle(2),
+                Tx::Write(&[0x00]),
+            ])?;
+            let decoded = Decoder::<0> { framing }.run(&samples)?;
+            assert_eq!(
+                decoded,
+                [
+                    byte(0x00, false, true),
+                    byte(0x00, false, true),
+                    Rx::Break,
+                    byte(0x00, false, false),
+                ],
+                "{stop_bits} stop bits, oversampling {oversampling}"
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{check_signal, edges};
+use crate::io::gpio::Edge;
+use crate::test_utils::gpio_monitor::Waves;
+
+/// An event on an I2C bus. `time_ns` is the time of the event in nanoseconds: the SDA edge of
+/// start and stop conditions, and the rising SCL edge of the acknowledge bit for bytes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum I2cEvent {
+    Start {
+        time_ns: u64,
+    },
+    /// A start condition while the bus is busy.
+    RepeatedStart {
+        time_ns: u64,
+    },
+    /// The first byte following a start condition.
+    Address {
+        time_ns: u64,
+        addr: u8,
+        read: bool,
+        ack: bool,
+    },
+    Data {
+        time_ns: u64,
+        data: u8,
+        ack: bool,
+    },
+    Stop {
+        time_ns: u64,
+    },
+    /// Bits interrupted by a start or stop condition, most significant bit first.
+    Broken {
+        time_ns: u64,
+        bits: Vec<bool>,
+    },
+}
+
+/// Decodes the I2C events of `waves`.
+///
+/// SDA is sampled on the rising edges of SCL, a bit being only accepted when SCL falls without a
+/// start or stop condition in between. A byte is made of 8 data bits followed by the acknowledge
+/// bit, low for an ACK and high for a NACK.
+pub fn decode(waves: &Waves, scl: usize, sda: usize) -> Result<Vec<I2cEvent>> {
+    check_signal(waves, scl)?;
+    check_signal(waves, sda)?;
+    let initial_levels = waves.initial_levels();
+    let mut scl_level = initial_levels[scl];
+    let mut sda_level = initial_levels[sda];
+
+    let mut events = Vec::new();
+    // Whether a start condition was seen since the last stop condition.
+    let mut busy = false;
+    // Whether the next byte is the first one after a start condition.
+    let mut address = false;
+    let mut bits: Vec<bool> = Vec::with_capacity(9);
+    // Time of the last rising edge of SCL and level of SDA at that time.
+    let mut sampled: Option<(u64, bool)> = None;
+    for edge in edges(waves) {
+        let rising = edge.edge == Edge::Rising;
+        if edge.signal == sda {
+            sda_level = rising;
+            if !scl_level {
+                continue;
+            }
+            sampled = None;
+            if !bits.is_empty() {
+                events.push(I2cEvent::Broken {
+                    time_ns: edge.ns,
+                    bits: std::mem::take(&mut bits),
+                });
+            }
+            if rising {
+                events.push(I2cEvent::Stop { time_ns: edge.ns });
+                busy = false;
+            } else {
+                events.push(if busy {
+                    I2cEvent::RepeatedStart { time_ns: edge.ns }
+                } else {
+                    I2cEvent::Start { time_ns: edge.ns }
+                });
+                busy = true;
+                address = true;
+            }
+        } else if edge.signal == scl {
+            scl_level = rising;
+            if rising {
+                sampled = busy.then_some((edge.ns, sda_level));
+                continue;
+            }
+            let Some((time_ns, bit)) = sampled.take() else {
+                continue;
+            };
+            bits.push(bit);
+            if bits.len() < 9 {
+                continue;
+            }
+            let byte = bits[..8]
+                .iter()
+                .fold(0u8, |byte, &bit| (byte << 1) | bit as u8);
+            let ack = !bits[8];
+            bits.clear();
+            events.push(if address {
+                I2cEvent::Address {
+                    time_ns,
+                    addr: byte >> 1,
+                    read: byte & 1 == 1,
+                    ack,
+                }
+            } else {
+                I2cEvent::Data {
+                    time_ns,
+                    data: byte,
+                    ack,
+                }
+            });
+            address = false;
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::gpio_decode::waves_from_samples;
+
+    const SCL: u8 = 1 << 0;
+    const SDA: u8 = 1 << 1;
+
+    fn start() -> [u8; 3] {
+        [SCL | SDA, SCL, 0]
+    }
+
+    fn stop() -> [u8; 3] {
+        [0, SCL, SCL | SDA]
+    }
+
+    // The samples of `byte` followed by an acknowledge bit, the clock ending low.
+    fn byte(byte: u8, ack: bool) -> Vec<u8> {
+        let bits = ((byte as u16) << 1) | !ack as u16;
+        (0..9)
+            .rev()
+            .flat_map(|i| {
+                let sda = if (bits >> i) & 1 == 1 { SDA } else { 0 };
+                [sda, sda | SCL, sda]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decode_write_then_read() -> Result<()> {
+        let mut samples = vec![SCL | SDA];
+        samples.extend(start());
+        samples.extend(byte(0x50 << 1, true));
+        samples.extend(byte(0x12, true));
+        // Repeated start: release SDA while SCL is low, then start.
+        samples.push(SDA);
+        samples.extend(start());
+        samples.extend(byte(0x50 << 1 | 1, true));
+        samples.extend(byte(0xab, false));
+        samples.extend(stop());
+        let waves = waves_from_samples(2, &samples, 10);
+        let events = decode(&waves, 0, 1)?
+            .into_iter()
+            .map(|e| match e {
+                I2cEvent::Start { .. } => "S".to_string(),
+                I2cEvent::RepeatedStart { .. } => "Sr".to_string(),
+                I2cEvent::Stop { .. } => "P".to_string(),
+                I2cEvent::Address {
+                    addr, read, ack, ..
+                } => format!("{addr:#x}{}{}", if read { 'R' } else { 'W' }, ack as u8),
+                I2cEvent::Data { data, ack, .. } => format!("{data:#x}/{}", ack as u8),
+                I2cEvent::Broken { bits, .. } => format!("broken {}", bits.len()),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            ["S", "0x50W1", "0x12/1", "Sr", "0x50R1", "0xab/0", "P"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decode_broken_byte() -> Result<()> {
+        let mut samples = vec![SCL | SDA];
+        samples.extend(start());
+        // Three bits, then a stop condition.
+        samples.extend(&byte(0xe0, true)[..9]);
+        samples.extend(stop());
+        let waves = waves_from_samples(2, &samples, 10);
+        let events = decode(&waves, 0, 1)?;
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[1], I2cEvent::Broken { bits, .. } if bits == &[true, true, true]));
+        assert!(matches!(events[2], I2cEvent::Stop { .. }));
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Protocol decoders working on edges captured through `GpioMonitoring`.
+//!
+//! Unlike the decoders of [`bitbanging`](super::bitbanging), which work on levels sampled at a
+//! fixed rate, these decoders work on the timestamped edges of [`Waves`], so a transport capable
+//! of monitoring GPIOs (such as HyperDebug) can be used as a simple logic analyzer.
+
+use anyhow::{ensure, Result};
+
+use crate::io::gpio::Edge;
+use crate::test_utils::gpio_monitor::Waves;
+
+pub mod i2c;
+pub mod pwm;
+pub mod spi;
+pub mod uart;
+
+/// An edge of one of the signals of a capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimedEdge {
+    /// Index of the signal in the capture.
+    pub signal: usize,
+    pub edge: Edge,
+    /// Time of the edge in nanoseconds since the start of the capture.
+    pub ns: u64,
+}
+
+/// Returns the edges of all signals of `waves`, ordered by time.
+pub fn edges(waves: &Waves) -> Vec<TimedEdge> {
+    let mut edges = waves
+        .all_events()
+        .map(|event| TimedEdge {
+            signal: event.signal_index as usize,
+            edge: event.edge,
+            ns: waves.timestamp_to_ns(event.timestamp),
+        })
+        .collect::<Vec<_>>();
+    // The sort is stable so simultaneous edges keep the order of the capture.
+    edges.sort_by_key(|edge| edge.ns);
+    edges
+}
+
+/// Checks that `waves` has a signal with index `index`.
+pub fn check_signal(waves: &Waves, index: usize) -> Result<()> {
+    let num_signals = waves.initial_levels().len();
+    ensure!(
+        index < num_signals,
+        "signal index {index} out of range, the capture has {num_signals} signals"
+    );
+    Ok(())
+}
+
+/// Levels of a single signal over time.
+#[derive(Clone, Debug)]
+pub struct Signal {
+    initial_level: bool,
+    // Time in nanoseconds and level after each edge, ordered by time.
+    transitions: Vec<(u64, bool)>,
+}
+
+impl Signal {
+    /// Extracts the signal with index `index` from `waves`.
+    pub fn new(waves: &Waves, index: usize) -> Result<Self> {
+        check_signal(waves, index)?;
+        let transitions = edges(waves)
+            .into_iter()
+            .filter(|edge| edge.signal == index)
+            .map(|edge| (edge.ns, edge.edge == Edge::Rising))
+            .collect();
+        Ok(Signal {
+            initial_level: waves.initial_levels()[index],
+            transitions,
+        })
+    }
+
+    /// Returns the level of the signal at the start of the capture.
+    pub fn initial_level(&self) -> bool {
+        self.initial_level
+    }
+
+    /// Returns the time and new level of every edge of the signal.
+    pub fn transitions(&self) -> &[(u64, bool)] {
+        &self.transitions
+    }
+
+    /// Returns the level of the signal at time `ns`, after any edge happening at that time.
+    pub fn level_at(&self, ns: u64) -> bool {
+        match self.transitions.partition_point(|&(t, _)| t <= ns) {
+            0 => self.initial_level,
+            n => self.transitions[n - 1].1,
+        }
+    }
+
+    /// Returns the time of the first `edge` at or after time `ns`.
+    pub fn next_edge(&self, ns: u64, edge: Edge) -> Option<u64> {
+        let level = edge == Edge::Rising;
+        let start = self.transitions.partition_point(|&(t, _)| t < ns);
+        self.transitions[start..]
+            .iter()
+            .find(|&&(_, l)| l == level)
+            .map(|&(t, _)| t)
+    }
+}
+
+/// Builds a capture with a 1ns resolution from a list of samples taken every `period_ns`. Bit `i`
+/// of a sample is the level of signal `i`.
+#[cfg(test)]
+pub(crate) fn waves_from_samples(num_signals: usize, samples: &[u8], period_ns: u64) -> Waves {
+    use crate::io::gpio::MonitoringEvent;
+
+    let level = |sample: u8, i: usize| (sample >> i) & 1 == 1;
+    let mut waves = Waves::new(
+        (0..num_signals).map(|i| format!("s{i}")).collect(),
+        (0..num_signals).map(|i| level(samples[0], i)).collect(),
+        0,
+        1_000_000_000,
+    );
+    for (n, pair) in samples.windows(2).enumerate() {
+        for i in 0..num_signals {
+            if level(pair[0], i) != level(pair[1], i) {
+                waves.add_event(MonitoringEvent {
+                    signal_index: i as u8,
+                    edge: if level(pair[1], i) {
+                        Edge::Rising
+                    } else {
+                        Edge::Falling
+                    },
+                    timestamp: (n as u64 + 1) * period_ns,
+                });
+            }
+        }
+    }
+    waves.set_final_timestamp(samples.len() as u64 * period_ns);
+    waves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_levels() -> Result<()> {
+        let waves = waves_from_samples(2, &[0b00, 0b01, 0b11, 0b10, 0b00], 10);
+        let s0 = Signal::new(&waves, 0)?;
+        assert_eq!(s0.transitions(), &[(10, true), (30, false)]);
+        assert!(!s0.level_at(9));
+        assert!(s0.level_at(10));
+        assert!(s0.level_at(29));
+        assert!(!s0.level_at(30));
+        assert!(!s0.initial_level());
+        assert_eq!(s0.next_edge(0, Edge::Rising), Some(10));
+        assert_eq!(s0.next_edge(10, Edge::Rising), Some(10));
+        assert_eq!(s0.next_edge(11, Edge::Rising), None);
+        assert_eq!(s0.next_edge(10, Edge::Falling), Some(30));
+        assert!(Signal::new(&waves, 2).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{ensure, Result};
+use serde::Serialize;
+
+use super::Signal;
+use crate::io::gpio::Edge;
+use crate::test_utils::gpio_monitor::Waves;
+
+/// A single period of a PWM signal, from a rising edge to the next one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct PwmPeriod {
+    /// Time of the rising edge starting the period, in nanoseconds.
+    pub time_ns: u64,
+    pub period_ns: u64,
+    /// Time spent high during the period, in nanoseconds.
+    pub high_ns: u64,
+}
+
+impl PwmPeriod {
+    pub fn frequency_hz(&self) -> f64 {
+        1e9 / self.period_ns as f64
+    }
+
+    pub fn duty_cycle(&self) -> f64 {
+        self.high_ns as f64 / self.period_ns as f64
+    }
+}
+
+/// Statistics over the complete periods of a PWM signal.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PwmMeasurement {
+    pub periods: usize,
+    /// Average frequency, computed from the total duration of the periods.
+    pub frequency_hz: f64,
+    pub min_frequency_hz: f64,
+    pub max_frequency_hz: f64,
+    /// Average duty cycle, between 0 and 1.
+    pub duty_cycle: f64,
+    pub min_duty_cycle: f64,
+    pub max_duty_cycle: f64,
+}
+
+/// Returns the complete periods of signal `pin` of `waves`.
+pub fn periods(waves: &Waves, pin: usize) -> Result<Vec<PwmPeriod>> {
+    let signal = Signal::new(waves, pin)?;
+    let rising = signal
+        .transitions()
+        .iter()
+        .filter(|&&(_, level)| level)
+        .map(|&(ns, _)| ns)
+        .collect::<Vec<_>>();
+    Ok(rising
+        .windows(2)
+        .filter(|pair| pair[1] > pair[0])
+        .map(|pair| {
+            // Without a falling edge the signal stayed high during the whole period.
+            let fall = signal
+                .next_edge(pair[0], Edge::Falling)
+                .filter(|&fall| fall < pair[1])
+                .unwrap_or(pair[1]);
+            PwmPeriod {
+                time_ns: pair[0],
+                period_ns: pair[1] - pair[0],
+                high_ns: fall - pair[0],
+            }
+        })
+        .collect())
+}
+
+/// Measures the frequency and duty cycle of signal `pin` of `waves`.
+pub fn measure(waves: &Waves, pin: usize) -> Result<PwmMeasurement> {
+    let periods = periods(waves, pin)?;
+    ensure!(
+        !periods.is_empty(),
+        "the capture does not contain a complete period"
+    );
+    let total_ns = periods.iter().map(|p| p.period_ns).sum::<u64>() as f64;
+    let high_ns = periods.iter().map(|p| p.high_ns).sum::<u64>() as f64;
+    let fold = |f: fn(&PwmPeriod) -> f64| {
+        periods
+            .iter()
+            .map(f)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+                (min.min(x), max.max(x))
+            })
+    };
+    let (min_frequency_hz, max_frequency_hz) = fold(PwmPeriod::frequency_hz);
+    let (min_duty_cycle, max_duty_cycle) = fold(PwmPeriod::duty_cycle);
+    Ok(PwmMeasurement {
+        periods: periods.len(),
+        frequency_hz: 1e9 * periods.len() as f64 / total_ns,
+        min_frequency_hz,
+        max_frequency_hz,
+        duty_cycle: high_ns / total_ns,
+        min_duty_cycle,
+        max_duty_cycle,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::gpio_decode::waves_from_samples;
+
+    #[test]
+    fn measure_duty_cycle() -> Result<()> {
+        // 25% duty cycle with a period of 4 samples of 250ns: 1MHz.
+        let samples = [0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0];
+        let waves = waves_from_samples(1, &samples, 250);
+        let periods = periods(&waves, 0)?;
+        assert_eq!(periods.len(), 3);
+        assert_eq!(
+            periods[0],
+            PwmPeriod {
+                time_ns: 250,
+                period_ns: 1000,
+                high_ns: 250,
+            }
+        );
+        let measurement = measure(&waves, 0)?;
+        assert_eq!(measurement.periods, 3);
+        assert_eq!(measurement.frequency_hz, 1e6);
+        assert_eq!(measurement.duty_cycle, 0.25);
+        assert_eq!(measurement.min_duty_cycle, 0.25);
+        assert_eq!(measurement.max_duty_cycle, 0.25);
+        Ok(())
+    }
+
+    #[test]
+    fn no_complete_period() {
+        let waves = waves_from_samples(1, &[0, 1, 1, 0], 10);
+        assert!(measure(&waves, 0).is_err());
+    }
+}
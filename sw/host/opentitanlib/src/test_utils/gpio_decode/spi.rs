@@ -0,0 +1,264 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{ensure, Result};
+use serde::Serialize;
+
+use super::{check_signal, edges, Signal};
+use crate::io::gpio::Edge;
+use crate::test_utils::bitbanging::spi::SpiDataMode;
+use crate::test_utils::gpio_monitor::Waves;
+
+/// Signals and settings of a SPI bus.
+pub struct SpiConfig {
+    /// Clock polarity: level of the clock when idle.
+    pub cpol: bool,
+    /// Clock phase: data is sampled on the second edge of the clock when set.
+    pub cpha: bool,
+    pub data_mode: SpiDataMode,
+    /// Index of the clock signal.
+    pub sck: usize,
+    /// Index of the active low chip select, if any. Without a chip select the whole capture is
+    /// decoded as a single transfer.
+    pub cs: Option<usize>,
+    /// Indices of the data signals: COPI and optionally CIPO in single mode, IO0 and IO1 in dual
+    /// mode and IO0 to IO3 in quad mode.
+    pub data: Vec<usize>,
+}
+
+/// The bytes exchanged while the chip select is asserted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SpiTransfer {
+    /// Time of the assertion of the chip select, in nanoseconds.
+    pub start_ns: u64,
+    /// Time of the deassertion of the chip select, in nanoseconds.
+    pub end_ns: u64,
+    /// Bytes sent by the host in single mode, or carried by the IO signals in dual and quad mode.
+    #[serde(with = "serde_bytes")]
+    pub data: Vec<u8>,
+    /// Bytes sent by the device in single mode, when CIPO is decoded.
+    #[serde(with = "serde_bytes")]
+    pub cipo: Vec<u8>,
+    /// Number of bits received after the last complete byte.
+    pub trailing_bits: u8,
+}
+
+// Accumulates bits into bytes, most significant bit first.
+#[derive(Default)]
+struct Shifter {
+    bytes: Vec<u8>,
+    current: u8,
+    bits: u8,
+}
+
+impl Shifter {
+    fn push(&mut self, bit: bool) {
+        self.current = (self.current << 1) | bit as u8;
+        self.bits += 1;
+        if self.bits == 8 {
+            self.bytes.push(self.current);
+            self.current = 0;
+            self.bits = 0;
+        }
+    }
+}
+
+/// Decodes the SPI transfers of `waves`.
+///
+/// The data signals are sampled on the rising edge of the clock in modes 0 and 3, and on the
+/// falling edge in modes 1 and 2, using their level just before the edge.
+pub fn decode(waves: &Waves, config: &SpiConfig) -> Result<Vec<SpiTransfer>> {
+    let lanes = match config.data_mode {
+        SpiDataMode::Single => 1..=2,
+        SpiDataMode::Dual => 2..=2,
+        SpiDataMode::Quad => 4..=4,
+    };
+    ensure!(
+        lanes.contains(&config.data.len()),
+        "wrong number of data signals ({}) for the data mode",
+        config.data.len()
+    );
+    let signals = config
+        .data
+        .iter()
+        .map(|&index| Signal::new(waves, index))
+        .collect::<Result<Vec<_>>>()?;
+    check_signal(waves, config.sck)?;
+    let cs = config.cs.map(|cs| Signal::new(waves, cs)).transpose()?;
+    let sample_edge = if config.cpol == config.cpha {
+        Edge::Rising
+    } else {
+        Edge::Falling
+    };
+    // Level of a data signal just before time `ns`.
+    let level_before = |signal: &Signal, ns: u64| match ns {
+        0 => signal.initial_level(),
+        ns => signal.level_at(ns - 1),
+    };
+
+    let mut transfers = Vec::new();
+    let mut start_ns = match &cs {
+        Some(cs) if cs.initial_level() => None,
+        _ => Some(0),
+    };
+    let mut shifters: Vec<Shifter> = Vec::new();
+    let mut finish = |start_ns: u64, end_ns: u64, shifters: &mut Vec<Shifter>| {
+        let mut shifters = std::mem::take(shifters).into_iter();
+        let data = shifters.next().unwrap_or_default();
+        let cipo = shifters.next().unwrap_or_default();
+        transfers.push(SpiTransfer {
+            start_ns,
+            end_ns,
+            data: data.bytes,
+            cipo: cipo.bytes,
+            trailing_bits: data.bits,
+        });
+    };
+    for edge in edges(waves) {
+        if Some(edge.signal) == config.cs {
+            match (edge.edge, start_ns) {
+                (Edge::Falling, None) => start_ns = Some(edge.ns),
+                (Edge::Rising, Some(start)) => {
+                    finish(start, edge.ns, &mut shifters);
+                    start_ns = None;
+                }
+                _ => (),
+            }
+        } else if edge.signal == config.sck && edge.edge == sample_edge && start_ns.is_some() {
+            match config.data_mode {
+                SpiDataMode::Single => {
+                    shifters.resize_with(signals.len(), Default::default);
+                    for (shifter, signal) in shifters.iter_mut().zip(&signals) {
+                        shifter.push(level_before(signal, edge.ns));
+                    }
+                }
+                SpiDataMode::Dual | SpiDataMode::Quad => {
+                    shifters.resize_with(1, Default::default);
+                    // The highest IO carries the most significant bit.
+                    for signal in signals.iter().rev() {
+                        shifters[0].push(level_before(signal, edge.ns));
+                    }
+                }
+            }
+        }
+    }
+    if let Some(start) = start_ns {
+        // The chip select is still asserted at the end of the capture.
+        if !shifters.is_empty() {
+            finish(start, waves.final_ns(), &mut shifters);
+        }
+    }
+    Ok(transfers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::gpio_decode::waves_from_samples;
+
+    const SCK: u8 = 0;
+    const CS: u8 = 1;
+
+    // Returns the samples of a transfer in mode `cpol`/`cpha`, shifting `symbols` on the data
+    // signals, which start at bit 2 of the samples.
+    fn transfer(cpol: bool, cpha: bool, symbols: &[u8]) -> Vec<u8> {
+        let idle = (cpol as u8) << SCK;
+        let active = ((!cpol) as u8) << SCK;
+        let mut samples = vec![idle | 1 << CS, idle];
+        for &symbol in symbols {
+            let data = symbol << 2;
+            if cpha {
+                // Data is shifted on the first edge and sampled on the second.
+                samples.extend([active | data, active | data, idle | data, idle | data]);
+            } else {
+                samples.extend([idle | data, active | data, active | data, idle | data]);
+            }
+        }
+        samples.extend([idle, idle | 1 << CS, idle | 1 << CS]);
+        samples
+    }
+
+    // Splits bytes into symbols of `width` bits, most significant first.
+    fn symbols(bytes: &[u8], width: u8) -> Vec<u8> {
+        bytes
+            .iter()
+            .flat_map(|&b| {
+                (0..8 / width)
+                    .rev()
+                    .map(move |i| (b >> (i * width)) & ((1 << width) - 1))
+            })
+            .collect()
+    }
+
+    fn config(cpol: bool, cpha: bool, data_mode: SpiDataMode, data: Vec<usize>) -> SpiConfig {
+        SpiConfig {
+            cpol,
+            cpha,
+            data_mode,
+            sck: SCK as usize,
+            cs: Some(CS as usize),
+            data,
+        }
+    }
+
+    #[test]
+    fn decode_all_modes() -> Result<()> {
+        for (cpol, cpha) in [(false, false), (false, true), (true, false), (true, true)] {
+            // COPI on bit 2 and CIPO on bit 3.
+            let copi = symbols(&[0xa5, 0x3c], 1);
+            let cipo = symbols(&[0x0f, 0xf0], 1);
+            let merged = copi
+                .iter()
+                .zip(&cipo)
+                .map(|(o, i)| o | i << 1)
+                .collect::<Vec<_>>();
+            let waves = waves_from_samples(4, &transfer(cpol, cpha, &merged), 10);
+            let transfers = decode(&waves, &config(cpol, cpha, SpiDataMode::Single, vec![2, 3]))?;
+            assert_eq!(transfers.len(), 1, "mode {cpol} {cpha}");
+            assert_eq!(transfers[0].data, [0xa5, 0x3c], "mode {cpol} {cpha}");
+            assert_eq!(transfers[0].cipo, [0x0f, 0xf0], "mode {cpol} {cpha}");
+            assert_eq!(transfers[0].trailing_bits, 0);
+            assert_eq!(transfers[0].start_ns, 10);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn decode_dual_quad() -> Result<()> {
+        let bytes = [0x12, 0x34, 0xfe];
+        let waves = waves_from_samples(4, &transfer(false, false, &symbols(&bytes, 2)), 10);
+        let transfers = decode(&waves, &config(false, false, SpiDataMode::Dual, vec![2, 3]))?;
+        assert_eq!(transfers[0].data, bytes);
+
+        let waves = waves_from_samples(6, &transfer(false, false, &symbols(&bytes, 4)), 10);
+        let transfers = decode(
+            &waves,
+            &config(false, false, SpiDataMode::Quad, vec![2, 3, 4, 5]),
+        )?;
+        assert_eq!(transfers[0].data, bytes);
+        assert!(transfers[0].cipo.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn decode_multiple_transfers() -> Result<()> {
+        let mut samples = transfer(false, false, &symbols(&[0x01], 1));
+        // A second transfer with only 3 bits.
+        samples.extend(transfer(false, false, &[1, 0, 1]));
+        let waves = waves_from_samples(3, &samples, 10);
+        let transfers = decode(&waves, &config(false, false, SpiDataMode::Single, vec![2]))?;
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0].data, [0x01]);
+        assert!(transfers[1].data.is_empty());
+        assert_eq!(transfers[1].trailing_bits, 3);
+        assert!(transfers[0].end_ns < transfers[1].start_ns);
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_number_of_lanes() {
+        let waves = waves_from_samples(3, &[0, 0], 10);
+        assert!(decode(&waves, &config(false, false, SpiDataMode::Quad, vec![2])).is_err());
+    }
+}
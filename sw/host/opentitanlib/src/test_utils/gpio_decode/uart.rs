@@ -0,0 +1,209 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{ensure, Result};
+use serde::Serialize;
+
+use super::Signal;
+use crate::io::gpio::Edge;
+use crate::io::uart::Parity;
+use crate::test_utils::gpio_monitor::Waves;
+
+/// Framing of a UART line.
+#[derive(Clone, Debug)]
+pub struct UartConfig {
+    pub baud_rate: u32,
+    /// Number of data bits, between 5 and 9.
+    pub data_bits: u8,
+    pub parity: Parity,
+    /// Number of stop bits, 1 or 2.
+    pub stop_bits: u8,
+}
+
+impl Default for UartConfig {
+    fn default() -> Self {
+        UartConfig {
+            baud_rate: 115200,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: 1,
+        }
+    }
+}
+
+/// A character received on a UART line.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct UartFrame {
+    /// Time of the beginning of the start bit, in nanoseconds.
+    pub time_ns: u64,
+    pub data: u16,
+    /// The parity bit does not match the data.
+    pub parity_error: bool,
+    /// A stop bit is low.
+    pub framing_error: bool,
+}
+
+/// Decodes the UART frames transmitted on signal `rx` of `waves`.
+///
+/// Every bit is sampled in its middle, the time of each bit being computed from the falling edge
+/// of the start bit. A falling edge which is not followed by a low level in the middle of the start
+/// bit is considered a glitch and ignored.
+pub fn decode(waves: &Waves, rx: usize, config: &UartConfig) -> Result<Vec<UartFrame>> {
+    ensure!(config.baud_rate > 0, "the baud rate must not be zero");
+    ensure!(
+        (5..=9).contains(&config.data_bits),
+        "unsupported number of data bits {}",
+        config.data_bits
+    );
+    ensure!(
+        (1..=2).contains(&config.stop_bits),
+        "unsupported number of stop bits {}",
+        config.stop_bits
+    );
+    let rx = Signal::new(waves, rx)?;
+    let end_ns = waves.final_ns();
+    let bit_ns = 1e9 / config.baud_rate as f64;
+    // Time of the middle of bit `n` of a frame starting at `start`, the start bit being bit 0.
+    let sample_time = |start: u64, n: u8| start + ((n as f64 + 0.5) * bit_ns) as u64;
+    let parity_bits = u8::from(config.parity != Parity::None);
+    let frame_bits = 1 + config.data_bits + parity_bits + config.stop_bits;
+
+    let mut frames = Vec::new();
+    // A line which is low at the start of the capture is in the middle of a frame: wait for it to
+    // become idle.
+    let mut search_from = if rx.initial_level() {
+        0
+    } else {
+        match rx.next_edge(0, Edge::Rising) {
+            Some(idle) => idle,
+            None => return Ok(frames),
+        }
+    };
+    while let Some(start) = rx.next_edge(search_from, Edge::Falling) {
+        if sample_time(start, frame_bits - 1) > end_ns {
+            // The capture ends in the middle of the frame.
+            break;
+        }
+        if rx.level_at(sample_time(start, 0)) {
+            // Glitch.
+            search_from = start + 1;
+            continue;
+        }
+        let mut data = 0u16;
+        for bit in 0..config.data_bits {
+            data |= (rx.level_at(sample_time(start, 1 + bit)) as u16) << bit;
+        }
+        let parity_error = match config.parity {
+            Parity::None => false,
+            parity => {
+                let bit = rx.level_at(sample_time(start, 1 + config.data_bits));
+                let odd = (data.count_ones() + bit as u32) % 2 == 1;
+                odd != (parity == Parity::Odd)
+            }
+        };
+        let framing_error = (1 + config.data_bits + parity_bits..frame_bits)
+            .any(|bit| !rx.level_at(sample_time(start, bit)));
+        frames.push(UartFrame {
+            time_ns: start,
+            data,
+            parity_error,
+            framing_error,
+        });
+        search_from = sample_time(start, frame_bits - 1);
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::gpio_decode::waves_from_samples;
+
+    // Returns the samples of `chars`, one sample per bit.
+    fn line(chars: &[u16], data_bits: u8, parity: Option<bool>, stop: &[bool]) -> Vec<u8> {
+        let mut samples = vec![1, 1];
+        for &c in chars {
+            samples.push(0);
+            for bit in 0..data_bits {
+                samples.push((c >> bit) as u8 & 1);
+            }
+            if let Some(odd) = parity {
+                samples.push(((c.count_ones() % 2 == 1) != odd) as u8);
+            }
+            samples.extend(stop.iter().map(|&b| b as u8));
+            samples.push(1);
+        }
+        samples.extend([1, 1]);
+        samples
+    }
+
+    #[test]
+    fn decode_8n1() -> Result<()> {
+        // 1 MBaud: one bit every 1000ns.
+        let config = UartConfig {
+            baud_rate: 1_000_000,
+            ..Default::default()
+        };
+        let waves = waves_from_samples(
+            1,
+            &line(b"Hi\x00\xff".map(u16::from).as_slice(), 8, None, &[true]),
+            1000,
+        );
+        let frames = decode(&waves, 0, &config)?;
+        let data = frames.iter().map(|f| f.data).collect::<Vec<_>>();
+        assert_eq!(data, [b'H' as u16, b'i' as u16, 0x00, 0xff]);
+        assert_eq!(frames[0].time_ns, 2000);
+        assert!(frames.iter().all(|f| !f.parity_error && !f.framing_error));
+        Ok(())
+    }
+
+    #[test]
+    fn decode_parity() -> Result<()> {
+        let config = UartConfig {
+            baud_rate: 1_000_000,
+            data_bits: 7,
+            parity: Parity::Even,
+            stop_bits: 2,
+        };
+        // Correct even parity, then a character sent with odd parity.
+        let mut samples = line(&[0x41], 7, Some(false), &[true, true]);
+        samples.extend(line(&[0x41], 7, Some(true), &[true, true]));
+        let waves = waves_from_samples(1, &samples, 1000);
+        let frames = decode(&waves, 0, &config)?;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].data, 0x41);
+        assert!(!frames[0].parity_error);
+        assert!(frames[1].parity_error);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_framing_error() -> Result<()> {
+        let config = UartConfig {
+            baud_rate: 1_000_000,
+            ..Default::default()
+        };
+        let waves = waves_from_samples(1, &line(&[0x55], 8, None, &[false]), 1000);
+        let frames = decode(&waves, 0, &config)?;
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].framing_error);
+        Ok(())
+    }
+
+    #[test]
+    fn ignore_glitches_and_truncated_frames() -> Result<()> {
+        let config = UartConfig {
+            baud_rate: 1_000_000,
+            ..Default::default()
+        };
+        // Sampled every 100ns: a 200ns glitch, then a frame cut by the end of the capture.
+        let mut samples = vec![1; 10];
+        samples.extend([0, 0, 1]);
+        samples.extend(vec![1; 10]);
+        samples.extend(vec![0; 30]);
+        let waves = waves_from_samples(1, &samples, 100);
+        assert_eq!(decode(&waves, 0, &config)?, []);
+        Ok(())
+    }
+}
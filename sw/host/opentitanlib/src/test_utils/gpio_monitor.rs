@@ -194,6 +194,14 @@ impl Waves {
         (timestamp - self.initial_timestamp) * 1000000000u64 / self.resolution
     }
 
+    /// Returns the time of the end of the capture in nanoseconds: the final timestamp, or the
+    /// last event if it is later.
+    pub fn final_ns(&self) -> u64 {
+        let last_event = self.events.iter().map(|e| e.timestamp).max();
+        let final_ts = std::cmp::max(last_event.unwrap_or(0), self.final_timestamp);
+        self.timestamp_to_ns(final_ts)
+    }
+
     pub fn ns_to_timestamp(&self, ns: u64) -> u64 {
         self.initial_timestamp + self.resolution * ns / 1000000000u64
     }
@@ -208,7 +216,7 @@ impl Waves {
 
     // This function assumes that the events are sorted.
     pub fn dump_vcd(&self) -> String {
-        const SYMBOLS: &[char] = &['!', '#', '$', '%', '&', '(', ')'];
+        const SYMBOLS: &[char] = &['!', '#', '$', '%', '&', '(', ')', '*', '+', ',', '-'];
         assert!(self.pin_names.len() < SYMBOLS.len());
         let mut vcd = String::new();
         vcd.push_str("$timescale 1ns $end\n");
@@ -234,7 +242,8 @@ impl Waves {
             ));
         }
         // Make sure that the final timestamp is after the last event.
-        let final_ts = std::cmp::max(self.events.last().unwrap().timestamp, self.final_timestamp);
+        let last_ts = self.events.last().map_or(0, |event| event.timestamp);
+        let final_ts = std::cmp::max(last_ts, self.final_timestamp);
         vcd.push_str(&format!("#{}", self.timestamp_to_ns(final_ts),));
 
         vcd
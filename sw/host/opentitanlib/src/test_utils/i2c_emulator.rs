@@ -0,0 +1,313 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::io::i2c::{Bus, DeviceTransfer, ReadStatus};
+use crate::util::parse_int::ParseInt;
+
+/// Behavior of an emulated I2C device, driven by an [`Emulator`].
+pub trait I2cDevice {
+    /// Handles a write transfer from the I2C host to device address `addr`.
+    fn write(&mut self, addr: u8, data: &[u8]) -> Result<()>;
+
+    /// Returns the data to send, when the I2C host starts reading from device address `addr`.
+    /// The host may read fewer bytes than returned, or more, in which case the debugger pads
+    /// the response.
+    fn prepare_read(&mut self, addr: u8) -> Result<Vec<u8>>;
+
+    /// Handles the completion of a read transfer, in which the I2C host read `len` of the bytes
+    /// previously returned by `prepare_read()`.
+    fn read_done(&mut self, addr: u8, len: usize) -> Result<()>;
+}
+
+/// Runs the loop answering the transfers of an I2C host, on a bus which has been put in device
+/// mode.
+pub struct Emulator<'a> {
+    bus: &'a dyn Bus,
+    device: &'a mut dyn I2cDevice,
+}
+
+impl<'a> Emulator<'a> {
+    pub fn new(bus: &'a dyn Bus, device: &'a mut dyn I2cDevice) -> Self {
+        Self { bus, device }
+    }
+
+    /// Waits up to `timeout` for the I2C host, handles the transfers it has performed, and
+    /// prepares the response if it is waiting to read.  Returns the handled transfers.
+    pub fn poll(&mut self, timeout: Duration) -> Result<Vec<DeviceTransfer>> {
+        let status = self.bus.get_device_status(timeout)?;
+        for transfer in &status.transfers {
+            match transfer {
+                DeviceTransfer::Write { addr, data } => {
+                    log::info!("Write to 0x{:02x}: {}", addr, hex::encode(data));
+                    self.device.write(*addr, data)?;
+                }
+                DeviceTransfer::Read {
+                    addr,
+                    timeout: true,
+                    len,
+                } => {
+                    // The host was not sent the data of the device, nothing was consumed.
+                    log::warn!("Read of {} bytes from 0x{:02x} timed out", len, addr);
+                }
+                DeviceTransfer::Read {
+                    addr,
+                    timeout: false,
+                    len,
+                } => {
+                    log::info!("Read of {} bytes from 0x{:02x}", len, addr);
+                    self.device.read_done(*addr, *len)?;
+                }
+            }
+        }
+        if let ReadStatus::WaitingForData(addr) = status.read_status {
+            let data = self.device.prepare_read(addr)?;
+            self.bus.prepare_read_data(&data, false)?;
+        }
+        Ok(status.transfers)
+    }
+}
+
+/// Script of a device with a register map, as in many EEPROMs and sensors: each write starts
+/// with the address of a register, optionally followed by data to store from that register on,
+/// and reads return data from the last addressed register on.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisterMapScript {
+    /// Number of bytes of the register address, in big endian order.
+    #[serde(default = "RegisterMapScript::default_address_width")]
+    pub address_width: usize,
+    /// Number of bytes prepared for each read.
+    #[serde(default = "RegisterMapScript::default_read_length")]
+    pub read_length: usize,
+    /// Value of the registers not listed in `registers`.
+    #[serde(default = "RegisterMapScript::default_fill")]
+    pub fill: u8,
+    /// Initial contents, as hex strings keyed by the address of their first register.
+    #[serde(default)]
+    pub registers: BTreeMap<String, String>,
+}
+
+impl RegisterMapScript {
+    fn default_address_width() -> usize {
+        1
+    }
+
+    fn default_read_length() -> usize {
+        32
+    }
+
+    fn default_fill() -> u8 {
+        0xff
+    }
+
+    /// Parses a script in JSON (or HJSON) format.
+    pub fn parse(script: &str) -> Result<Self> {
+        Ok(deser_hjson::from_str(script)?)
+    }
+}
+
+/// An emulated device with a register map, see [`RegisterMapScript`].
+pub struct RegisterMap {
+    address_width: usize,
+    read_length: usize,
+    fill: u8,
+    registers: BTreeMap<u32, u8>,
+    pointer: u32,
+}
+
+impl RegisterMap {
+    pub fn new(script: &RegisterMapScript) -> Result<Self> {
+        ensure!(
+            (1..=4).contains(&script.address_width),
+            "Unsupported register address width {}",
+            script.address_width
+        );
+        let mut map = Self {
+            address_width: script.address_width,
+            read_length: script.read_length,
+            fill: script.fill,
+            registers: BTreeMap::new(),
+            pointer: 0,
+        };
+        for (address, data) in &script.registers {
+            let address = u32::from_str(address)
+                .with_context(|| format!("Invalid register address {:?}", address))?;
+            let data = hex::decode(data)
+                .with_context(|| format!("Invalid data of register 0x{:x}", address))?;
+            if address > map.address_mask() {
+                bail!("Register address 0x{:x} out of range", address);
+            }
+            map.pointer = address;
+            map.store(&data);
+        }
+        map.pointer = 0;
+        Ok(map)
+    }
+
+    fn address_mask(&self) -> u32 {
+        (u64::MAX >> (64 - 8 * self.address_width)) as u32
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.pointer = (self.pointer as usize).wrapping_add(len) as u32 & self.address_mask();
+    }
+
+    fn store(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.registers.insert(self.pointer, byte);
+            self.advance(1);
+        }
+    }
+
+    /// Returns the value of register `address`.
+    pub fn get(&self, address: u32) -> u8 {
+        *self.registers.get(&address).unwrap_or(&self.fill)
+    }
+}
+
+impl I2cDevice for RegisterMap {
+    fn write(&mut self, _addr: u8, data: &[u8]) -> Result<()> {
+        if data.len() < self.address_width {
+            // A probe by the host, or an incomplete register address.
+            return Ok(());
+        }
+        let (address, data) = data.split_at(self.address_width);
+        self.pointer = address
+            .iter()
+            .fold(0u32, |pointer, &byte| pointer << 8 | byte as u32);
+        self.store(data);
+        Ok(())
+    }
+
+    fn prepare_read(&mut self, _addr: u8) -> Result<Vec<u8>> {
+        let mut address = self.pointer;
+        let mut data = Vec::with_capacity(self.read_length);
+        for _ in 0..self.read_length {
+            data.push(self.get(address));
+            address = address.wrapping_add(1) & self.address_mask();
+        }
+        Ok(data)
+    }
+
+    fn read_done(&mut self, _addr: u8, len: usize) -> Result<()> {
+        self.advance(len);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::i2c::{DeviceStatus, Transfer};
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    /// A bus replaying device statuses, and recording the prepared data.
+    #[derive(Default)]
+    struct ReplayBus {
+        statuses: RefCell<VecDeque<DeviceStatus>>,
+        prepared: RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl Bus for ReplayBus {
+        fn get_max_speed(&self) -> Result<u32> {
+            Ok(100_000)
+        }
+
+        fn set_max_speed(&self, _max_speed: u32) -> Result<()> {
+            Ok(())
+        }
+
+        fn set_default_address(&self, _addr: u8) -> Result<()> {
+            Ok(())
+        }
+
+        fn run_transaction(&self, _addr: Option<u8>, _transaction: &mut [Transfer]) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn get_device_status(&self, _timeout: Duration) -> Result<DeviceStatus> {
+            Ok(self.statuses.borrow_mut().pop_front().unwrap())
+        }
+
+        fn prepare_read_data(&self, data: &[u8], sticky: bool) -> Result<()> {
+            assert!(!sticky);
+            self.prepared.borrow_mut().push(data.to_vec());
+            Ok(())
+        }
+    }
+
+    fn script(text: &str) -> Result<RegisterMap> {
+        RegisterMap::new(&RegisterMapScript::parse(text)?)
+    }
+
+    #[test]
+    fn register_map_script() -> Result<()> {
+        let map = script(
+            r#"{
+                "address_width": 2,
+                "fill": 0,
+                "registers": { "0x10": "a5a6", "0xffff": "01" }
+            }"#,
+        )?;
+        assert_eq!(map.get(0x10), 0xa5);
+        assert_eq!(map.get(0x11), 0xa6);
+        assert_eq!(map.get(0x12), 0x00);
+        assert_eq!(map.get(0xffff), 0x01);
+        assert!(script(r#"{ "registers": { "0x100": "00" } }"#).is_err());
+        assert!(script(r#"{ "registers": { "0x10": "0" } }"#).is_err());
+        assert!(script(r#"{ "address_width": 0 }"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn emulate_register_map() -> Result<()> {
+        let mut map = script(
+            r#"{
+                "read_length": 4,
+                "registers": { "0x00": "00010203" }
+            }"#,
+        )?;
+        let bus = ReplayBus::default();
+        bus.statuses.borrow_mut().extend([
+            DeviceStatus {
+                transfers: vec![DeviceTransfer::Write {
+                    addr: 0x50,
+                    data: vec![0x02, 0x42],
+                }],
+                read_status: ReadStatus::Idle,
+            },
+            DeviceStatus {
+                transfers: vec![DeviceTransfer::Write {
+                    addr: 0x50,
+                    data: vec![0x01],
+                }],
+                read_status: ReadStatus::WaitingForData(0x50),
+            },
+            DeviceStatus {
+                transfers: vec![DeviceTransfer::Read {
+                    addr: 0x50,
+                    timeout: false,
+                    len: 2,
+                }],
+                read_status: ReadStatus::WaitingForData(0x50),
+            },
+        ]);
+        let mut emulator = Emulator::new(&bus, &mut map);
+        for _ in 0..3 {
+            emulator.poll(Duration::from_millis(10))?;
+        }
+        assert_eq!(
+            *bus.prepared.borrow(),
+            [vec![0x01, 0x42, 0x03, 0xff], vec![0x03, 0xff, 0xff, 0xff]]
+        );
+        assert_eq!(map.get(0x02), 0x42);
+        Ok(())
+    }
+}
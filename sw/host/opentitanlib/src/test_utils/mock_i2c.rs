@@ -0,0 +1,67 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! An in-memory I2C bus for unit tests.
+
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::io::i2c::{Bus, Transfer};
+
+/// Address and transfers of a transaction, with the data of writes, `None` for reads.
+pub type Transaction = (Option<u8>, Vec<Option<Vec<u8>>>);
+
+/// A bus recording the transfers of each transaction.
+///
+/// Reads are answered from `responses` in order, bytes beyond the end of a response read as
+/// from an idle bus.  Once `responses` is empty, reads return the address of the transaction.
+#[derive(Default)]
+pub struct RecordingBus {
+    pub transactions: RefCell<Vec<Transaction>>,
+    pub responses: RefCell<Vec<Vec<u8>>>,
+    pub clock_stretch_timeout: Cell<Option<Duration>>,
+}
+
+impl Bus for RecordingBus {
+    fn get_max_speed(&self) -> Result<u32> {
+        Ok(100_000)
+    }
+
+    fn set_max_speed(&self, _max_speed: u32) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_default_address(&self, _addr: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn run_transaction(&self, addr: Option<u8>, transaction: &mut [Transfer]) -> Result<()> {
+        let mut transfers = Vec::new();
+        for transfer in transaction {
+            match transfer {
+                Transfer::Write(wbuf) => transfers.push(Some(wbuf.to_vec())),
+                Transfer::Read(rbuf) => {
+                    let mut responses = self.responses.borrow_mut();
+                    if responses.is_empty() {
+                        rbuf.fill(addr.unwrap_or_default());
+                    } else {
+                        let data = responses.remove(0);
+                        rbuf.fill(0xff);
+                        rbuf[..data.len()].copy_from_slice(&data);
+                    }
+                    transfers.push(None);
+                }
+            }
+        }
+        self.transactions.borrow_mut().push((addr, transfers));
+        Ok(())
+    }
+
+    fn set_clock_stretch_timeout(&self, timeout: Duration) -> Result<()> {
+        self.clock_stretch_timeout.set(Some(timeout));
+        Ok(())
+    }
+}
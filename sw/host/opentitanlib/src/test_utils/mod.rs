@@ -11,7 +11,9 @@ pub mod epmp;
 #[cfg(not(feature = "english_breakfast"))]
 pub mod extclk;
 pub mod gpio;
+pub mod gpio_decode;
 pub mod gpio_monitor;
+pub mod i2c_emulator;
 pub mod i2c_target;
 pub mod init;
 pub mod lc;
@@ -19,6 +21,8 @@ pub mod lc_transition;
 pub mod load_bitstream;
 pub mod load_sram_program;
 pub mod mem;
+#[cfg(test)]
+pub mod mock_i2c;
 pub mod object;
 pub mod otp_ctrl;
 // The "english breakfast" variant of the chip doesn't have the same
@@ -3,13 +3,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use anyhow::{bail, ensure, Result};
+use std::borrow::Cow;
 use std::cell::Cell;
 use std::cmp;
 use std::rc::Rc;
 use std::time::Duration;
 use zerocopy::{AsBytes, FromBytes, FromZeroes};
 
-use crate::io::i2c::{self, Bus, DeviceStatus, DeviceTransfer, I2cError, ReadStatus, Transfer};
+use crate::io::i2c::{
+    self, Address, Bus, DeviceStatus, DeviceTransfer, I2cError, ReadStatus, Transfer,
+    TEN_BIT_ADDRESS_PREFIX,
+};
 use crate::transport::hyperdebug::{BulkInterface, Inner};
 use crate::transport::{TransportError, TransportInterfaceType};
 
@@ -269,6 +273,65 @@ impl HyperdebugI2cBus {
         Ok(())
     }
 
+    /// Runs the transfers of a transaction, each write or pair of a write followed by a read as
+    /// a single I2C operation.  `header` is sent at the start of every operation, ahead of the
+    /// data of any write.
+    fn run_transfers(
+        &self,
+        addr: u8,
+        header: &[u8],
+        mut transaction: &mut [Transfer],
+    ) -> Result<()> {
+        while !transaction.is_empty() {
+            match transaction {
+                [Transfer::Write(wbuf), Transfer::Read(rbuf), ..] => {
+                    // Hyperdebug can do I2C write followed by I2C read as a single USB
+                    // request/reply.  Take advantage of that by detecting pairs of
+                    // Transfer::Write followed by Transfer::Read.
+                    let wbuf = Self::with_header(header, wbuf);
+                    ensure!(
+                        wbuf.len() <= self.max_write_size,
+                        I2cError::InvalidDataLength(wbuf.len())
+                    );
+                    ensure!(
+                        rbuf.len() <= self.max_read_size,
+                        I2cError::InvalidDataLength(rbuf.len())
+                    );
+                    self.transmit_then_receive(addr, &wbuf, rbuf)?;
+                    // Skip two steps ahead, as two items were processed.
+                    transaction = &mut transaction[2..];
+                }
+                [Transfer::Write(wbuf), ..] => {
+                    let wbuf = Self::with_header(header, wbuf);
+                    ensure!(
+                        wbuf.len() <= self.max_write_size,
+                        I2cError::InvalidDataLength(wbuf.len())
+                    );
+                    self.transmit_then_receive(addr, &wbuf, &mut [])?;
+                    transaction = &mut transaction[1..];
+                }
+                [Transfer::Read(rbuf), ..] => {
+                    ensure!(
+                        rbuf.len() <= self.max_read_size,
+                        I2cError::InvalidDataLength(rbuf.len())
+                    );
+                    self.transmit_then_receive(addr, header, rbuf)?;
+                    transaction = &mut transaction[1..];
+                }
+                [] => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn with_header<'a>(header: &[u8], wbuf: &'a [u8]) -> Cow<'a, [u8]> {
+        if header.is_empty() {
+            Cow::Borrowed(wbuf)
+        } else {
+            Cow::Owned([header, wbuf].concat())
+        }
+    }
+
     /// Send one USB packet.
     fn usb_write_bulk(&self, buf: &[u8]) -> Result<()> {
         self.inner
@@ -341,48 +404,39 @@ impl Bus for HyperdebugI2cBus {
         Ok(())
     }
 
-    fn run_transaction(&self, addr: Option<u8>, mut transaction: &mut [Transfer]) -> Result<()> {
+    fn run_transaction(&self, addr: Option<u8>, transaction: &mut [Transfer]) -> Result<()> {
         let addr = addr
             .or(self.default_addr.get())
             .ok_or(I2cError::MissingAddress)?;
-        while !transaction.is_empty() {
-            match transaction {
-                [Transfer::Write(wbuf), Transfer::Read(rbuf), ..] => {
-                    // Hyperdebug can do I2C write followed by I2C read as a single USB
-                    // request/reply.  Take advantage of that by detecting pairs of
-                    // Transfer::Write followed by Transfer::Read.
-                    ensure!(
-                        wbuf.len() <= self.max_write_size,
-                        I2cError::InvalidDataLength(wbuf.len())
-                    );
-                    ensure!(
-                        rbuf.len() <= self.max_read_size,
-                        I2cError::InvalidDataLength(rbuf.len())
-                    );
-                    self.transmit_then_receive(addr, wbuf, rbuf)?;
-                    // Skip two steps ahead, as two items were processed.
-                    transaction = &mut transaction[2..];
-                }
-                [Transfer::Write(wbuf), ..] => {
-                    ensure!(
-                        wbuf.len() <= self.max_write_size,
-                        I2cError::InvalidDataLength(wbuf.len())
-                    );
-                    self.transmit_then_receive(addr, wbuf, &mut [])?;
-                    transaction = &mut transaction[1..];
-                }
-                [Transfer::Read(rbuf), ..] => {
-                    ensure!(
-                        rbuf.len() <= self.max_read_size,
-                        I2cError::InvalidDataLength(rbuf.len())
-                    );
-                    self.transmit_then_receive(addr, &[], rbuf)?;
-                    transaction = &mut transaction[1..];
-                }
-                [] => (),
+        self.run_transfers(addr, &[], transaction)
+    }
+
+    /// Hyperdebug issues every write and every read (together with the write preceding it) as
+    /// a separate I2C transaction.  For a 10-bit address, each of them starts with a write of
+    /// the low byte of the address, so that every read is properly addressed.
+    fn run_transaction_at(&self, addr: Address, transaction: &mut [Transfer]) -> Result<()> {
+        match addr {
+            Address::SevenBit(addr) => {
+                ensure!(addr < 0x80, I2cError::InvalidAddress(addr as u16));
+                self.run_transfers(addr, &[], transaction)
+            }
+            Address::TenBit(addr) => {
+                ensure!(addr < 0x400, I2cError::InvalidAddress(addr));
+                self.run_transfers(
+                    TEN_BIT_ADDRESS_PREFIX | (addr >> 8) as u8,
+                    &[addr as u8],
+                    transaction,
+                )
             }
         }
-        Ok(())
+    }
+
+    fn set_clock_stretch_timeout(&self, timeout: Duration) -> Result<()> {
+        self.inner.cmd_no_output(&format!(
+            "i2c set timeout {} {}",
+            &self.bus_idx,
+            timeout.as_micros()
+        ))
     }
 
     fn get_device_status(&self, timeout: Duration) -> Result<DeviceStatus> {
@@ -0,0 +1,356 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::app::config;
+use crate::app::TransportWrapper;
+use crate::io::gpio::{GpioError, PullMode};
+use crate::io::spi::{self, Target};
+
+use anyhow::{bail, ensure, Result};
+use std::rc::Rc;
+
+use super::Driver;
+
+/// Represents a particular MCP23S17 IO expander chip, with information about how to access it
+/// through a backend transport.
+pub struct Mcp23s17 {
+    spi: Rc<dyn Target>,
+    /// Hardware address, as set by the A2..A0 pins of the chip.
+    hardware_addr: u8,
+}
+
+/// Registers of the MCP23S17, with the default `IOCON.BANK = 0` layout.
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+enum Mcp23s17Registers {
+    IoDirA = 0x00,
+    IoDirB = 0x01,
+    IPolA = 0x02,
+    IPolB = 0x03,
+    GpIntEnA = 0x04,
+    GpIntEnB = 0x05,
+    DefValA = 0x06,
+    DefValB = 0x07,
+    IntConA = 0x08,
+    IntConB = 0x09,
+    IoCon = 0x0A,
+    GpPuA = 0x0C,
+    GpPuB = 0x0D,
+    IntFA = 0x0E,
+    IntFB = 0x0F,
+    IntCapA = 0x10,
+    IntCapB = 0x11,
+    GpioA = 0x12,
+    GpioB = 0x13,
+    OLatA = 0x14,
+    OLatB = 0x15,
+}
+
+/// Control byte of the SPI transactions, followed by the hardware address and the read bit.
+const OPCODE: u8 = 0x40;
+const OPCODE_READ: u8 = 0x01;
+
+/// `IOCON` bits: a single interrupt output for both ports, hardware addressing, and open-drain
+/// interrupt output, so that it can be shared with other chips.
+const IOCON_MIRROR: u8 = 0x40;
+const IOCON_HAEN: u8 = 0x08;
+const IOCON_ODR: u8 = 0x04;
+
+impl Mcp23s17 {
+    fn read_register(&self, addr: Mcp23s17Registers) -> Result<u8> {
+        let mut val: u8 = 0;
+        self.spi.run_transaction(&mut [
+            spi::Transfer::Write(&[OPCODE | self.hardware_addr << 1 | OPCODE_READ, addr as u8]),
+            spi::Transfer::Read(std::slice::from_mut(&mut val)),
+        ])?;
+        Ok(val)
+    }
+
+    fn write_register(&self, addr: Mcp23s17Registers, data: u8) -> Result<()> {
+        self.spi.run_transaction(&mut [spi::Transfer::Write(&[
+            OPCODE | self.hardware_addr << 1,
+            addr as u8,
+            data,
+        ])])?;
+        Ok(())
+    }
+
+    /// Reads a pair of registers of port A and B, as the pins of port A are the lower ones.
+    fn read_registers(
+        &self,
+        (addr_a, addr_b): (Mcp23s17Registers, Mcp23s17Registers),
+    ) -> Result<u16> {
+        let a = self.read_register(addr_a)?;
+        let b = self.read_register(addr_b)?;
+        Ok(u16::from_le_bytes([a, b]))
+    }
+
+    /// Sets or clears the bit of `pin_no` in the register of port A or B.
+    fn set_or_clear_bit(
+        &self,
+        (addr_a, addr_b): (Mcp23s17Registers, Mcp23s17Registers),
+        pin_no: u8,
+        value: bool,
+    ) -> Result<()> {
+        let addr = if pin_no < 8 { addr_a } else { addr_b };
+        let bit_no = pin_no & 0x07;
+        let val = self.read_register(addr)?;
+        let val = val & !(1 << bit_no) | (if value { 1 << bit_no } else { 0 });
+        self.write_register(addr, val)
+    }
+}
+
+impl Driver for Mcp23s17 {
+    fn init(&self) -> Result<()> {
+        // Until hardware addressing is enabled, all chips on the bus accept this write
+        // regardless of their address pins, so they must all use the same settings.
+        self.write_register(
+            Mcp23s17Registers::IoCon,
+            IOCON_MIRROR | IOCON_HAEN | IOCON_ODR,
+        )
+    }
+
+    fn read_pins(&self) -> Result<u16> {
+        self.read_registers((Mcp23s17Registers::GpioA, Mcp23s17Registers::GpioB))
+    }
+
+    fn write_pin(&self, pin_no: u8, value: bool) -> Result<()> {
+        self.set_or_clear_bit(
+            (Mcp23s17Registers::OLatA, Mcp23s17Registers::OLatB),
+            pin_no,
+            value,
+        )
+    }
+
+    fn set_direction(&self, pin_no: u8, input: bool) -> Result<()> {
+        self.set_or_clear_bit(
+            (Mcp23s17Registers::IoDirA, Mcp23s17Registers::IoDirB),
+            pin_no,
+            input,
+        )
+    }
+
+    fn set_pull_mode(&self, pin_no: u8, mode: PullMode) -> Result<()> {
+        let up = match mode {
+            PullMode::None => false,
+            PullMode::PullUp => true,
+            PullMode::PullDown => bail!(GpioError::UnsupportedPullMode(mode)),
+        };
+        self.set_or_clear_bit(
+            (Mcp23s17Registers::GpPuA, Mcp23s17Registers::GpPuB),
+            pin_no,
+            up,
+        )
+    }
+
+    fn set_interrupt_mask(&self, mask: u16) -> Result<()> {
+        let [a, b] = mask.to_le_bytes();
+        // Interrupt on any change from the previous value, rather than from `DEFVAL`.
+        self.write_register(Mcp23s17Registers::IntConA, 0)?;
+        self.write_register(Mcp23s17Registers::IntConB, 0)?;
+        self.write_register(Mcp23s17Registers::GpIntEnA, a)?;
+        self.write_register(Mcp23s17Registers::GpIntEnB, b)
+    }
+
+    fn take_interrupts(&self) -> Result<u16> {
+        let flags = self.read_registers((Mcp23s17Registers::IntFA, Mcp23s17Registers::IntFB))?;
+        // Reading the captured levels clears the interrupt.
+        self.read_registers((Mcp23s17Registers::IntCapA, Mcp23s17Registers::IntCapB))?;
+        Ok(flags)
+    }
+}
+
+/// Creates a driver for a MCP23S17 chip as specified in the given configuration declaration
+/// section.
+pub fn create(
+    conf: &config::IoExpander,
+    transport_wrapper: &TransportWrapper,
+) -> Result<Box<dyn Driver>> {
+    let Some(ref spi_bus) = conf.spi_bus else {
+        bail!("Missing spi bus name");
+    };
+    let hardware_addr = conf.spi_address.unwrap_or(0);
+    ensure!(
+        hardware_addr < 8,
+        "Invalid MCP23S17 hardware address {}",
+        hardware_addr
+    );
+    Ok(Box::new(Mcp23s17 {
+        spi: transport_wrapper.spi(spi_bus)?,
+        hardware_addr,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::create_io_expander;
+    use super::super::tests::LevelPin;
+    use super::*;
+    use crate::io::gpio::PinMode;
+    use crate::io::spi::{AssertChipSelect, MaxSizes, TransferMode};
+    use std::cell::RefCell;
+
+    /// A simulated MCP23S17 on a SPI bus, which logs the control byte, register address and
+    /// written value (if any) of every transaction.
+    struct SimulatedSpi {
+        registers: RefCell<[u8; 0x16]>,
+        log: RefCell<Vec<(u8, u8, Option<u8>)>>,
+    }
+
+    impl SimulatedSpi {
+        fn new() -> Rc<Self> {
+            let mut registers = [0u8; 0x16];
+            // All pins are inputs after reset.
+            registers[Mcp23s17Registers::IoDirA as usize] = 0xff;
+            registers[Mcp23s17Registers::IoDirB as usize] = 0xff;
+            Rc::new(Self {
+                registers: RefCell::new(registers),
+                log: RefCell::new(Vec::new()),
+            })
+        }
+
+        fn get(&self, reg: Mcp23s17Registers) -> u8 {
+            self.registers.borrow()[reg as usize]
+        }
+
+        fn set(&self, reg: Mcp23s17Registers, value: u8) {
+            self.registers.borrow_mut()[reg as usize] = value;
+        }
+
+        fn take_log(&self) -> Vec<(u8, u8, Option<u8>)> {
+            self.log.take()
+        }
+    }
+
+    impl Target for SimulatedSpi {
+        fn get_transfer_mode(&self) -> Result<TransferMode> {
+            unimplemented!()
+        }
+
+        fn set_transfer_mode(&self, _mode: TransferMode) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn get_bits_per_word(&self) -> Result<u32> {
+            unimplemented!()
+        }
+
+        fn set_bits_per_word(&self, _bits_per_word: u32) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn get_max_speed(&self) -> Result<u32> {
+            unimplemented!()
+        }
+
+        fn set_max_speed(&self, _max_speed: u32) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn supports_bidirectional_transfer(&self) -> Result<bool> {
+            Ok(false)
+        }
+
+        fn get_max_transfer_count(&self) -> Result<usize> {
+            unimplemented!()
+        }
+
+        fn get_max_transfer_sizes(&self) -> Result<MaxSizes> {
+            unimplemented!()
+        }
+
+        fn run_transaction(&self, transaction: &mut [spi::Transfer]) -> Result<()> {
+            let mut registers = self.registers.borrow_mut();
+            let mut log = self.log.borrow_mut();
+            match transaction {
+                [spi::Transfer::Write(&[control, reg]), spi::Transfer::Read(data)] => {
+                    assert_eq!(data.len(), 1);
+                    data[0] = registers[reg as usize];
+                    log.push((control, reg, None));
+                }
+                [spi::Transfer::Write(&[control, reg, value])] => {
+                    registers[reg as usize] = value;
+                    log.push((control, reg, Some(value)));
+                }
+                _ => panic!("Unexpected SPI transaction"),
+            }
+            Ok(())
+        }
+
+        fn assert_cs(self: Rc<Self>) -> Result<AssertChipSelect> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn pins_and_interrupts() -> Result<()> {
+        let spi = SimulatedSpi::new();
+        let io = create_io_expander(
+            Box::new(Mcp23s17 {
+                spi: spi.clone(),
+                hardware_addr: 5,
+            }),
+            None,
+            Some(Rc::new(LevelPin(false))),
+        );
+
+        io.pins[9].set(
+            Some(PinMode::PushPull),
+            Some(true),
+            Some(PullMode::PullUp),
+            None,
+        )?;
+        // Writes use control byte 0x4a and reads 0x4b, for hardware address 5. The first
+        // transaction sets `IOCON` to MIRROR | HAEN | ODR.
+        assert_eq!(
+            spi.take_log(),
+            [
+                (0x4a, 0x0a, Some(0x4c)),
+                (0x4b, 0x15, None),
+                (0x4a, 0x15, Some(0x02)),
+                (0x4b, 0x0d, None),
+                (0x4a, 0x0d, Some(0x02)),
+                (0x4b, 0x01, None),
+                (0x4a, 0x01, Some(0xfd)),
+            ]
+        );
+        assert_eq!(spi.get(Mcp23s17Registers::IoDirA), 0xff);
+        assert_eq!(spi.get(Mcp23s17Registers::OLatA), 0x00);
+        assert_eq!(spi.get(Mcp23s17Registers::GpPuA), 0x00);
+        assert!(io.pins[9].set_pull_mode(PullMode::PullDown).is_err());
+
+        spi.set(Mcp23s17Registers::GpioA, 0x01);
+        spi.set(Mcp23s17Registers::GpioB, 0x02);
+        assert!(io.pins[0].read()?);
+        assert!(!io.pins[8].read()?);
+        assert!(io.pins[9].read()?);
+
+        let interrupt = io.interrupt.as_ref().unwrap();
+        spi.take_log();
+        interrupt.set_interrupt_mask(0x8011)?;
+        assert_eq!(
+            spi.take_log(),
+            [
+                (0x4a, 0x08, Some(0x00)),
+                (0x4a, 0x09, Some(0x00)),
+                (0x4a, 0x04, Some(0x11)),
+                (0x4a, 0x05, Some(0x80)),
+            ]
+        );
+        assert!(interrupt.is_pending()?);
+        spi.set(Mcp23s17Registers::IntFB, 0x80);
+        assert_eq!(interrupt.take_interrupts()?, 0x8000);
+        // The captured levels are read to clear the interrupt.
+        assert_eq!(
+            spi.take_log(),
+            [
+                (0x4b, 0x0e, None),
+                (0x4b, 0x0f, None),
+                (0x4b, 0x10, None),
+                (0x4b, 0x11, None),
+            ]
+        );
+        Ok(())
+    }
+}
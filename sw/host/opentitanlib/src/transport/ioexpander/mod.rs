@@ -3,12 +3,232 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::app::config;
-use crate::app::TransportWrapper;
-use crate::io::ioexpander::IoExpander;
+use crate::app::{PinStrapping, TransportWrapper};
+use crate::io::gpio::{GpioError, GpioPin, PinMode, PullMode};
+use crate::io::i2c::{self, Bus};
+use crate::io::ioexpander::{IoExpander, IoExpanderInterrupt};
+use crate::transport::TransportError;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use std::cell::Cell;
+use std::rc::Rc;
 
+mod mcp23s17;
 mod sx1503;
+mod tca6416;
+
+/// Number of pins of the supported IO expanders.
+const NUM_PINS: u8 = 16;
+
+/// Register level operations of an IO expander chip, implemented by each driver.  All supported
+/// chips have 16 pins, pins 0 to 7 being on the first port of the chip and pins 8 to 15 on the
+/// second one.  In the masks, bit `n` corresponds to pin `n`.
+trait Driver {
+    /// Configures the chip, before its first access.
+    fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Reads the levels of all pins.
+    fn read_pins(&self) -> Result<u16>;
+
+    /// Sets the output level of a pin.
+    fn write_pin(&self, pin_no: u8, value: bool) -> Result<()>;
+
+    /// Configures a pin as input, or as push-pull output.
+    fn set_direction(&self, pin_no: u8, input: bool) -> Result<()>;
+
+    /// Configures the weak pull resistors of a pin.
+    fn set_pull_mode(&self, pin_no: u8, mode: PullMode) -> Result<()>;
+
+    /// Enables the interrupt on any change of the pins in `mask`, and disables it for the other
+    /// pins.
+    fn set_interrupt_mask(&self, mask: u16) -> Result<()>;
+
+    /// Returns the pins whose change caused an interrupt, and clears the interrupt.
+    fn take_interrupts(&self) -> Result<u16>;
+}
+
+/// Access to the 8-bit registers of an IO expander chip on an I2C bus.
+struct I2cRegisters {
+    i2c_bus: Rc<dyn Bus>,
+    i2c_addr: u8,
+}
+
+impl I2cRegisters {
+    /// Looks up the I2C bus and address of the chip in the configuration.
+    fn new(conf: &config::IoExpander, transport_wrapper: &TransportWrapper) -> Result<Self> {
+        let Some(ref i2c_bus) = conf.i2c_bus else {
+            bail!("Missing i2c bus number");
+        };
+        let Some(i2c_addr) = conf.i2c_address else {
+            bail!("Missing i2c address");
+        };
+        Ok(Self {
+            i2c_bus: transport_wrapper.i2c(i2c_bus)?,
+            i2c_addr,
+        })
+    }
+
+    fn read_register(&self, addr: u8) -> Result<u8> {
+        let mut val: u8 = 0;
+        self.i2c_bus.run_transaction(
+            Some(self.i2c_addr),
+            &mut [
+                i2c::Transfer::Write(&[addr]),
+                i2c::Transfer::Read(std::slice::from_mut(&mut val)),
+            ],
+        )?;
+        Ok(val)
+    }
+
+    fn write_register(&self, addr: u8, data: u8) -> Result<()> {
+        self.i2c_bus.run_transaction(
+            Some(self.i2c_addr),
+            &mut [i2c::Transfer::Write(&[addr, data])],
+        )?;
+        Ok(())
+    }
+
+    fn set_or_clear_bit(&self, addr: u8, bit_no: u8, value: bool) -> Result<()> {
+        let val = self.read_register(addr)?;
+        let val = val & !(1 << bit_no) | (if value { 1 << bit_no } else { 0 });
+        self.write_register(addr, val)?;
+        Ok(())
+    }
+}
+
+/// An IO expander chip, with the strapping to apply when accessing it.
+struct Expander {
+    driver: Box<dyn Driver>,
+    mux_strapping: Option<PinStrapping>,
+    initialized: Cell<bool>,
+}
+
+impl Expander {
+    /// Runs `f` with the bus to the chip MUXed from the transport.
+    fn access<T>(&self, f: impl FnOnce(&dyn Driver) -> Result<T>) -> Result<T> {
+        if let Some(ref strapping) = self.mux_strapping {
+            strapping.apply()?
+        }
+        if !self.initialized.get() {
+            self.driver.init()?;
+            self.initialized.set(true);
+        }
+        let result = f(self.driver.as_ref())?;
+        if let Some(ref strapping) = self.mux_strapping {
+            strapping.remove()?
+        }
+        Ok(result)
+    }
+}
+
+/// Represents a single pin of a particular IO expander chip.
+struct ExpanderPin {
+    expander: Rc<Expander>,
+    pin_no: u8,
+}
+
+impl GpioPin for ExpanderPin {
+    fn read(&self) -> Result<bool> {
+        let val = self.expander.access(|driver| driver.read_pins())?;
+        Ok(val & (1 << self.pin_no) != 0)
+    }
+
+    fn write(&self, value: bool) -> Result<()> {
+        self.set(None, Some(value), None, None)
+    }
+
+    fn set_mode(&self, mode: PinMode) -> Result<()> {
+        self.set(Some(mode), None, None, None)
+    }
+
+    fn set_pull_mode(&self, mode: PullMode) -> Result<()> {
+        self.set(None, None, Some(mode), None)
+    }
+
+    fn set(
+        &self,
+        mode: Option<PinMode>,
+        value: Option<bool>,
+        pull: Option<PullMode>,
+        analog_value: Option<f32>,
+    ) -> Result<()> {
+        if analog_value.is_some() {
+            bail!(TransportError::UnsupportedOperation);
+        }
+        if let (None, None, None) = (mode, value, pull) {
+            return Ok(());
+        }
+        let direction = match mode {
+            None => None,
+            Some(PinMode::Input) => Some(true),
+            Some(PinMode::PushPull) => Some(false),
+            Some(mode) => return Err(GpioError::UnsupportedPinMode(mode).into()),
+        };
+        self.expander.access(|driver| {
+            if let Some(value) = value {
+                driver.write_pin(self.pin_no, value)?;
+            }
+            if let Some(pull) = pull {
+                driver.set_pull_mode(self.pin_no, pull)?;
+            }
+            if let Some(input) = direction {
+                driver.set_direction(self.pin_no, input)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Interrupts of an IO expander chip, whose active low interrupt output is connected to a pin
+/// of the transport.
+struct ExpanderInterrupt {
+    expander: Rc<Expander>,
+    interrupt_pin: Rc<dyn GpioPin>,
+}
+
+impl IoExpanderInterrupt for ExpanderInterrupt {
+    fn set_interrupt_mask(&self, mask: u16) -> Result<()> {
+        self.expander
+            .access(|driver| driver.set_interrupt_mask(mask))
+    }
+
+    fn is_pending(&self) -> Result<bool> {
+        Ok(!self.interrupt_pin.read()?)
+    }
+
+    fn take_interrupts(&self) -> Result<u16> {
+        self.expander.access(|driver| driver.take_interrupts())
+    }
+}
+
+/// Creates an `IoExpander` from a driver, sharing the chip between all its pins.
+fn create_io_expander(
+    driver: Box<dyn Driver>,
+    mux_strapping: Option<PinStrapping>,
+    interrupt_pin: Option<Rc<dyn GpioPin>>,
+) -> IoExpander {
+    let expander = Rc::new(Expander {
+        driver,
+        mux_strapping,
+        initialized: Cell::new(false),
+    });
+    let mut pins: Vec<Rc<dyn GpioPin>> = Vec::new();
+    for pin_no in 0..NUM_PINS {
+        pins.push(Rc::new(ExpanderPin {
+            expander: expander.clone(),
+            pin_no,
+        }));
+    }
+    let interrupt = interrupt_pin.map(|interrupt_pin| {
+        Rc::new(ExpanderInterrupt {
+            expander,
+            interrupt_pin,
+        }) as Rc<dyn IoExpanderInterrupt>
+    });
+    IoExpander { pins, interrupt }
+}
 
 /// Creates an instance of `IoExpander` as specified in the given configuration declaration
 /// section.  The `driver` field will decide the implementing struct.
@@ -16,8 +236,135 @@ pub fn create(
     conf: &config::IoExpander,
     transport_wrapper: &TransportWrapper,
 ) -> Result<IoExpander> {
-    match conf.driver {
-        config::IoExpanderDriver::Sx1503 => sx1503::create(conf, transport_wrapper),
+    let driver = match conf.driver {
+        config::IoExpanderDriver::Sx1503 => sx1503::create(conf, transport_wrapper)?,
+        config::IoExpanderDriver::Tca6416 => tca6416::create(conf, transport_wrapper, false)?,
+        config::IoExpanderDriver::Pca9555 => tca6416::create(conf, transport_wrapper, true)?,
+        config::IoExpanderDriver::Mcp23s17 => mcp23s17::create(conf, transport_wrapper)?,
         // Add future drivers here
+    };
+    let mux_strapping = if let Some(ref name) = conf.mux_strapping {
+        Some(transport_wrapper.pin_strapping(name)?)
+    } else {
+        None
+    };
+    let interrupt_pin = if let Some(ref name) = conf.interrupt_pin {
+        Some(transport_wrapper.gpio_pin(name)?)
+    } else {
+        None
+    };
+    Ok(create_io_expander(driver, mux_strapping, interrupt_pin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A simulated I2C device with 8-bit registers, addressed by the first byte written.
+    pub struct SimulatedBus {
+        pub addr: u8,
+        pub registers: RefCell<[u8; 256]>,
+        pointer: RefCell<u8>,
+    }
+
+    impl SimulatedBus {
+        pub fn new(addr: u8) -> Rc<Self> {
+            Rc::new(Self {
+                addr,
+                registers: RefCell::new([0u8; 256]),
+                pointer: RefCell::new(0),
+            })
+        }
+
+        pub fn get(&self, reg: u8) -> u8 {
+            self.registers.borrow()[reg as usize]
+        }
+
+        pub fn set(&self, reg: u8, value: u8) {
+            self.registers.borrow_mut()[reg as usize] = value;
+        }
+    }
+
+    impl Bus for SimulatedBus {
+        fn get_max_speed(&self) -> Result<u32> {
+            Ok(100_000)
+        }
+
+        fn set_max_speed(&self, _max_speed: u32) -> Result<()> {
+            Ok(())
+        }
+
+        fn set_default_address(&self, _addr: u8) -> Result<()> {
+            Ok(())
+        }
+
+        fn run_transaction(
+            &self,
+            addr: Option<u8>,
+            transaction: &mut [i2c::Transfer],
+        ) -> Result<()> {
+            assert_eq!(addr, Some(self.addr));
+            let mut pointer = self.pointer.borrow_mut();
+            let mut registers = self.registers.borrow_mut();
+            for transfer in transaction {
+                match transfer {
+                    i2c::Transfer::Write(data) => {
+                        *pointer = data[0];
+                        for &byte in &data[1..] {
+                            registers[*pointer as usize] = byte;
+                            *pointer = pointer.wrapping_add(1);
+                        }
+                    }
+                    i2c::Transfer::Read(data) => {
+                        for byte in data.iter_mut() {
+                            *byte = registers[*pointer as usize];
+                            *pointer = pointer.wrapping_add(1);
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A pin with a fixed level, standing for the interrupt output of the chip.
+    pub struct LevelPin(pub bool);
+
+    impl GpioPin for LevelPin {
+        fn read(&self) -> Result<bool> {
+            Ok(self.0)
+        }
+
+        fn write(&self, _value: bool) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn set_mode(&self, _mode: PinMode) -> Result<()> {
+            unimplemented!()
+        }
+
+        fn set_pull_mode(&self, _mode: PullMode) -> Result<()> {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn unsupported_modes() -> Result<()> {
+        let bus = SimulatedBus::new(0x20);
+        let registers = I2cRegisters {
+            i2c_bus: bus,
+            i2c_addr: 0x20,
+        };
+        let io = create_io_expander(
+            Box::new(tca6416::Tca6416::new(registers, false)),
+            None,
+            None,
+        );
+        assert_eq!(io.pins.len(), 16);
+        assert!(io.interrupt.is_none());
+        assert!(io.pins[3].set_mode(PinMode::OpenDrain).is_err());
+        assert!(io.pins[3].analog_write(1.0).is_err());
+        Ok(())
     }
 }
@@ -3,22 +3,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::app::config;
-use crate::app::{PinStrapping, TransportWrapper};
-use crate::io::gpio::{GpioError, GpioPin, PinMode, PullMode};
-use crate::io::i2c::{self, Bus};
-use crate::io::ioexpander::IoExpander;
-use crate::transport::TransportError;
+use crate::app::TransportWrapper;
+use crate::io::gpio::PullMode;
 
-use anyhow::{bail, Result};
-use std::rc::Rc;
-use std::vec::Vec;
+use anyhow::Result;
+
+use super::{Driver, I2cRegisters};
 
 /// Represents a particular SX1503 IO expander chip, with information about how to access it
 /// through a backend transport.
-struct Sx1503 {
-    mux_strapping: Option<PinStrapping>,
-    i2c_bus: Rc<dyn Bus>,
-    i2c_addr: u8,
+pub struct Sx1503 {
+    registers: I2cRegisters,
 }
 
 #[allow(dead_code)]
@@ -58,183 +53,156 @@ enum Sx1503Registers {
 }
 
 impl Sx1503 {
-    fn apply_mux_strapping(&self) -> Result<()> {
-        if let Some(ref strapping) = self.mux_strapping {
-            strapping.apply()?
-        }
-        Ok(())
-    }
-
-    fn remove_mux_strapping(&self) -> Result<()> {
-        if let Some(ref strapping) = self.mux_strapping {
-            strapping.remove()?
-        }
-        Ok(())
+    pub fn new(registers: I2cRegisters) -> Self {
+        Self { registers }
     }
 
     fn read_register(&self, addr: Sx1503Registers) -> Result<u8> {
-        let mut val: u8 = 0;
-        self.i2c_bus.run_transaction(
-            Some(self.i2c_addr),
-            &mut [
-                i2c::Transfer::Write(&[addr as u8]),
-                i2c::Transfer::Read(std::slice::from_mut(&mut val)),
-            ],
-        )?;
-        Ok(val)
+        self.registers.read_register(addr as u8)
     }
 
     fn write_register(&self, addr: Sx1503Registers, data: u8) -> Result<()> {
-        self.i2c_bus.run_transaction(
-            Some(self.i2c_addr),
-            &mut [i2c::Transfer::Write(&[addr as u8, data])],
-        )?;
-        Ok(())
+        self.registers.write_register(addr as u8, data)
     }
 
-    fn set_or_clear_bit(&self, addr: Sx1503Registers, bit_no: u8, value: bool) -> Result<()> {
-        let val = self.read_register(addr)?;
-        let val = val & !(1 << bit_no) | (if value { 1 << bit_no } else { 0 });
-        self.write_register(addr, val)?;
-        Ok(())
+    /// Sets or clears the bit of `pin_no` in the register of port A or B.
+    fn set_or_clear_bit(
+        &self,
+        (addr_a, addr_b): (Sx1503Registers, Sx1503Registers),
+        pin_no: u8,
+        value: bool,
+    ) -> Result<()> {
+        let addr = if pin_no < 8 { addr_a } else { addr_b };
+        self.registers
+            .set_or_clear_bit(addr as u8, pin_no & 0x07, value)
     }
 }
 
-/// Represents a single pin of a particular SX1503 IO expander chip.
-struct Sx1503Pin {
-    sx1503: Rc<Sx1503>,
-    pin_no: u8,
-}
-
-impl Sx1503Pin {
-    fn new(sx1503: Rc<Sx1503>, pin_no: u8) -> Self {
-        Self { sx1503, pin_no }
+impl Driver for Sx1503 {
+    fn read_pins(&self) -> Result<u16> {
+        let a = self.read_register(Sx1503Registers::DataA)?;
+        let b = self.read_register(Sx1503Registers::DataB)?;
+        Ok(u16::from_le_bytes([a, b]))
     }
-}
 
-impl GpioPin for Sx1503Pin {
-    fn read(&self) -> Result<bool> {
-        self.sx1503.apply_mux_strapping()?;
-        let val = self.sx1503.read_register(if self.pin_no < 8 {
-            Sx1503Registers::DataA
-        } else {
-            Sx1503Registers::DataB
-        })?;
-        self.sx1503.remove_mux_strapping()?;
-        Ok(val & (1 << (self.pin_no & 0x07)) != 0)
+    fn write_pin(&self, pin_no: u8, value: bool) -> Result<()> {
+        self.set_or_clear_bit(
+            (Sx1503Registers::DataA, Sx1503Registers::DataB),
+            pin_no,
+            value,
+        )
     }
 
-    fn write(&self, value: bool) -> Result<()> {
-        self.set(None, Some(value), None, None)
+    fn set_direction(&self, pin_no: u8, input: bool) -> Result<()> {
+        self.set_or_clear_bit(
+            (Sx1503Registers::DirA, Sx1503Registers::DirB),
+            pin_no,
+            input,
+        )
     }
 
-    fn set_mode(&self, mode: PinMode) -> Result<()> {
-        self.set(Some(mode), None, None, None)
+    fn set_pull_mode(&self, pin_no: u8, mode: PullMode) -> Result<()> {
+        let (up, down) = match mode {
+            PullMode::None => (false, false),
+            PullMode::PullUp => (true, false),
+            PullMode::PullDown => (false, true),
+        };
+        self.set_or_clear_bit(
+            (Sx1503Registers::PullUpA, Sx1503Registers::PullUpB),
+            pin_no,
+            up,
+        )?;
+        self.set_or_clear_bit(
+            (Sx1503Registers::PullDownA, Sx1503Registers::PullDownB),
+            pin_no,
+            down,
+        )
     }
 
-    fn set_pull_mode(&self, mode: PullMode) -> Result<()> {
-        self.set(None, None, Some(mode), None)
+    fn set_interrupt_mask(&self, mask: u16) -> Result<()> {
+        // Each sense register holds two bits per pin, both set to detect rising and falling
+        // edges.
+        let sense = |pins: u16| -> u8 {
+            (0..4)
+                .filter(|i| pins & (1 << i) != 0)
+                .fold(0u8, |sense, i| sense | (0b11 << (2 * i)))
+        };
+        self.write_register(Sx1503Registers::SenseLowA, sense(mask))?;
+        self.write_register(Sx1503Registers::SenseHighA, sense(mask >> 4))?;
+        self.write_register(Sx1503Registers::SenseLowB, sense(mask >> 8))?;
+        self.write_register(Sx1503Registers::SenseHighB, sense(mask >> 12))?;
+        // A set bit of the mask registers disables the interrupt of the pin.
+        let [a, b] = (!mask).to_le_bytes();
+        self.write_register(Sx1503Registers::InterruptMaskA, a)?;
+        self.write_register(Sx1503Registers::InterruptMaskB, b)
     }
 
-    fn set(
-        &self,
-        mode: Option<PinMode>,
-        value: Option<bool>,
-        pull: Option<PullMode>,
-        analog_value: Option<f32>,
-    ) -> Result<()> {
-        if analog_value.is_some() {
-            bail!(TransportError::UnsupportedOperation);
-        }
-        if let (None, None, None) = (mode, value, pull) {
-            return Ok(());
-        }
-        self.sx1503.apply_mux_strapping()?;
-        if let Some(value) = value {
-            self.sx1503.set_or_clear_bit(
-                if self.pin_no < 8 {
-                    Sx1503Registers::DataA
-                } else {
-                    Sx1503Registers::DataB
-                },
-                self.pin_no & 0x07,
-                value,
-            )?;
-        }
-        if let Some(pull) = pull {
-            let (up, down) = match pull {
-                PullMode::None => (false, false),
-                PullMode::PullUp => (true, false),
-                PullMode::PullDown => (false, true),
-            };
-            self.sx1503.set_or_clear_bit(
-                if self.pin_no < 8 {
-                    Sx1503Registers::PullUpA
-                } else {
-                    Sx1503Registers::PullUpB
-                },
-                self.pin_no & 0x07,
-                up,
-            )?;
-            self.sx1503.set_or_clear_bit(
-                if self.pin_no < 8 {
-                    Sx1503Registers::PullDownA
-                } else {
-                    Sx1503Registers::PullDownB
-                },
-                self.pin_no & 0x07,
-                down,
-            )?;
-        }
-        if let Some(mode) = mode {
-            let direction = match mode {
-                PinMode::Input => true,
-                PinMode::PushPull => false,
-                _ => return Err(GpioError::UnsupportedPinMode(mode).into()),
-            };
-            self.sx1503.set_or_clear_bit(
-                if self.pin_no < 8 {
-                    Sx1503Registers::DirA
-                } else {
-                    Sx1503Registers::DirB
-                },
-                self.pin_no & 0x07,
-                direction,
-            )?;
-        }
-        self.sx1503.remove_mux_strapping()?;
-        Ok(())
+    fn take_interrupts(&self) -> Result<u16> {
+        let a = self.read_register(Sx1503Registers::InterruptSourceA)?;
+        let b = self.read_register(Sx1503Registers::InterruptSourceB)?;
+        // Writing ones clears the sources.
+        self.write_register(Sx1503Registers::InterruptSourceA, a)?;
+        self.write_register(Sx1503Registers::InterruptSourceB, b)?;
+        Ok(u16::from_le_bytes([a, b]))
     }
 }
 
-/// Creates an instance of `IoExpander` to represent a SX1503 chip as specified in the given
-/// configuration declaration section.
+/// Creates a driver for a SX1503 chip as specified in the given configuration declaration
+/// section.
 pub fn create(
     conf: &config::IoExpander,
     transport_wrapper: &TransportWrapper,
-) -> Result<IoExpander> {
-    // Look up I2C bus and strappings, create Sx1503 struct.
-    let Some(ref i2c_bus) = conf.i2c_bus else {
-        bail!("Missing i2c bus numer");
-    };
-    let Some(i2c_addr) = conf.i2c_address else {
-        bail!("Missing i2c addressr");
-    };
-    let mux_strapping = if let Some(ref name) = conf.mux_strapping {
-        Some(transport_wrapper.pin_strapping(name)?)
-    } else {
-        None
-    };
-    let sx1503 = Rc::new(Sx1503 {
-        mux_strapping,
-        i2c_bus: transport_wrapper.i2c(i2c_bus)?,
-        i2c_addr,
-    });
-
-    // Create 16 pins each with a shared reference to the struct created above.
-    let mut pins: Vec<Rc<dyn GpioPin>> = Vec::new();
-    for i in 0..16 {
-        pins.push(Rc::new(Sx1503Pin::new(sx1503.clone(), i)));
+) -> Result<Box<dyn Driver>> {
+    Ok(Box::new(Sx1503::new(I2cRegisters::new(
+        conf,
+        transport_wrapper,
+    )?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::create_io_expander;
+    use super::super::tests::{LevelPin, SimulatedBus};
+    use super::*;
+    use crate::io::gpio::PinMode;
+    use std::rc::Rc;
+
+    #[test]
+    fn pins_and_interrupts() -> Result<()> {
+        let bus = SimulatedBus::new(0x20);
+        let registers = I2cRegisters {
+            i2c_bus: bus.clone(),
+            i2c_addr: 0x20,
+        };
+        let io = create_io_expander(
+            Box::new(Sx1503::new(registers)),
+            None,
+            Some(Rc::new(LevelPin(false))),
+        );
+        bus.set(Sx1503Registers::DirA as u8, 0xff);
+        bus.set(Sx1503Registers::DirB as u8, 0xff);
+        io.pins[9].set(
+            Some(PinMode::PushPull),
+            Some(true),
+            Some(PullMode::PullDown),
+            None,
+        )?;
+        assert_eq!(bus.get(Sx1503Registers::DirB as u8), 0xfd);
+        assert_eq!(bus.get(Sx1503Registers::DataB as u8), 0x02);
+        assert_eq!(bus.get(Sx1503Registers::PullDownB as u8), 0x02);
+        assert!(io.pins[9].read()?);
+        assert!(!io.pins[1].read()?);
+
+        let interrupt = io.interrupt.as_ref().unwrap();
+        interrupt.set_interrupt_mask(0x8011)?;
+        assert_eq!(bus.get(Sx1503Registers::InterruptMaskA as u8), 0xee);
+        assert_eq!(bus.get(Sx1503Registers::InterruptMaskB as u8), 0x7f);
+        assert_eq!(bus.get(Sx1503Registers::SenseLowA as u8), 0x03);
+        assert_eq!(bus.get(Sx1503Registers::SenseHighA as u8), 0x03);
+        assert_eq!(bus.get(Sx1503Registers::SenseHighB as u8), 0xc0);
+        assert!(interrupt.is_pending()?);
+        bus.set(Sx1503Registers::InterruptSourceB as u8, 0x80);
+        assert_eq!(interrupt.take_interrupts()?, 0x8000);
+        Ok(())
     }
-    Ok(IoExpander { pins })
 }
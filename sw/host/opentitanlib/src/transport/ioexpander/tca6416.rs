@@ -0,0 +1,186 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::app::config;
+use crate::app::TransportWrapper;
+use crate::io::gpio::{GpioError, PullMode};
+
+use anyhow::Result;
+use std::cell::Cell;
+
+use super::{Driver, I2cRegisters};
+
+/// Represents a particular TCA6416 or PCA9555 IO expander chip, which share the same register
+/// map.  The PCA9555 has fixed pull-up resistors on all pins, while the TCA6416 has none.
+///
+/// These chips assert their interrupt output on any change of an input pin from the value last
+/// read, without per pin enables, so the mask is applied when reporting the changes.
+pub struct Tca6416 {
+    registers: I2cRegisters,
+    pull_ups: bool,
+    interrupt_mask: Cell<u16>,
+    // Levels of the pins when the interrupts were last taken.
+    last_levels: Cell<u16>,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+enum Tca6416Registers {
+    Input0 = 0x00,
+    Input1 = 0x01,
+    Output0 = 0x02,
+    Output1 = 0x03,
+    PolarityInversion0 = 0x04,
+    PolarityInversion1 = 0x05,
+    Configuration0 = 0x06,
+    Configuration1 = 0x07,
+}
+
+impl Tca6416 {
+    pub fn new(registers: I2cRegisters, pull_ups: bool) -> Self {
+        Self {
+            registers,
+            pull_ups,
+            interrupt_mask: Cell::new(0),
+            last_levels: Cell::new(0),
+        }
+    }
+
+    /// Sets or clears the bit of `pin_no` in the register of port 0 or 1.
+    fn set_or_clear_bit(
+        &self,
+        (addr_0, addr_1): (Tca6416Registers, Tca6416Registers),
+        pin_no: u8,
+        value: bool,
+    ) -> Result<()> {
+        let addr = if pin_no < 8 { addr_0 } else { addr_1 };
+        self.registers
+            .set_or_clear_bit(addr as u8, pin_no & 0x07, value)
+    }
+}
+
+impl Driver for Tca6416 {
+    fn read_pins(&self) -> Result<u16> {
+        let port0 = self
+            .registers
+            .read_register(Tca6416Registers::Input0 as u8)?;
+        let port1 = self
+            .registers
+            .read_register(Tca6416Registers::Input1 as u8)?;
+        Ok(u16::from_le_bytes([port0, port1]))
+    }
+
+    fn write_pin(&self, pin_no: u8, value: bool) -> Result<()> {
+        self.set_or_clear_bit(
+            (Tca6416Registers::Output0, Tca6416Registers::Output1),
+            pin_no,
+            value,
+        )
+    }
+
+    fn set_direction(&self, pin_no: u8, input: bool) -> Result<()> {
+        self.set_or_clear_bit(
+            (
+                Tca6416Registers::Configuration0,
+                Tca6416Registers::Configuration1,
+            ),
+            pin_no,
+            input,
+        )
+    }
+
+    fn set_pull_mode(&self, _pin_no: u8, mode: PullMode) -> Result<()> {
+        match (mode, self.pull_ups) {
+            (PullMode::None, false) | (PullMode::PullUp, true) => Ok(()),
+            _ => Err(GpioError::UnsupportedPullMode(mode).into()),
+        }
+    }
+
+    fn set_interrupt_mask(&self, mask: u16) -> Result<()> {
+        // Reading the inputs clears any pending interrupt.
+        self.last_levels.set(self.read_pins()?);
+        self.interrupt_mask.set(mask);
+        Ok(())
+    }
+
+    fn take_interrupts(&self) -> Result<u16> {
+        let levels = self.read_pins()?;
+        let changed = (levels ^ self.last_levels.replace(levels)) & self.interrupt_mask.get();
+        Ok(changed)
+    }
+}
+
+/// Creates a driver for a TCA6416 or PCA9555 chip as specified in the given configuration
+/// declaration section.
+pub fn create(
+    conf: &config::IoExpander,
+    transport_wrapper: &TransportWrapper,
+    pull_ups: bool,
+) -> Result<Box<dyn Driver>> {
+    Ok(Box::new(Tca6416::new(
+        I2cRegisters::new(conf, transport_wrapper)?,
+        pull_ups,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::create_io_expander;
+    use super::super::tests::{LevelPin, SimulatedBus};
+    use super::*;
+    use crate::io::gpio::PinMode;
+    use std::rc::Rc;
+
+    #[test]
+    fn pins_and_interrupts() -> Result<()> {
+        let bus = SimulatedBus::new(0x21);
+        let registers = I2cRegisters {
+            i2c_bus: bus.clone(),
+            i2c_addr: 0x21,
+        };
+        let io = create_io_expander(
+            Box::new(Tca6416::new(registers, false)),
+            None,
+            Some(Rc::new(LevelPin(true))),
+        );
+        bus.set(Tca6416Registers::Configuration0 as u8, 0xff);
+        bus.set(Tca6416Registers::Configuration1 as u8, 0xff);
+        io.pins[2].set(
+            Some(PinMode::PushPull),
+            Some(true),
+            Some(PullMode::None),
+            None,
+        )?;
+        assert_eq!(bus.get(Tca6416Registers::Configuration0 as u8), 0xfb);
+        assert_eq!(bus.get(Tca6416Registers::Output0 as u8), 0x04);
+        assert!(io.pins[2].set_pull_mode(PullMode::PullUp).is_err());
+
+        bus.set(Tca6416Registers::Input1 as u8, 0x10);
+        assert!(io.pins[12].read()?);
+        assert!(!io.pins[4].read()?);
+
+        let interrupt = io.interrupt.as_ref().unwrap();
+        assert!(!interrupt.is_pending()?);
+        interrupt.set_interrupt_mask(0x00ff)?;
+        bus.set(Tca6416Registers::Input0 as u8, 0x81);
+        bus.set(Tca6416Registers::Input1 as u8, 0x00);
+        assert_eq!(interrupt.take_interrupts()?, 0x0081);
+        assert_eq!(interrupt.take_interrupts()?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn pca9555_pull_ups() -> Result<()> {
+        let bus = SimulatedBus::new(0x20);
+        let registers = I2cRegisters {
+            i2c_bus: bus,
+            i2c_addr: 0x20,
+        };
+        let io = create_io_expander(Box::new(Tca6416::new(registers, true)), None, None);
+        io.pins[0].set_pull_mode(PullMode::PullUp)?;
+        assert!(io.pins[0].set_pull_mode(PullMode::None).is_err());
+        assert!(io.pins[0].set_pull_mode(PullMode::PullDown).is_err());
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use std::rc::Rc;
 use std::time::Duration;
 
 use super::ProxyError;
-use crate::io::i2c::{Bus, DeviceStatus, Mode, Transfer};
+use crate::io::i2c::{Address, Bus, DeviceStatus, Mode, Transfer};
 use crate::proxy::protocol::{
     I2cRequest, I2cResponse, I2cTransferRequest, I2cTransferResponse, Request, Response,
 };
@@ -40,6 +40,46 @@ impl ProxyI2c {
             _ => bail!(ProxyError::UnexpectedReply()),
         }
     }
+
+    // Convenience method for running I2C transactions via proxy protocol, `request` builds the
+    // command from the list of transfers.
+    fn execute_transaction(
+        &self,
+        transaction: &mut [Transfer],
+        request: impl FnOnce(Vec<I2cTransferRequest>) -> I2cRequest,
+    ) -> Result<()> {
+        let mut req: Vec<I2cTransferRequest> = Vec::new();
+        for transfer in &*transaction {
+            // &* to treat as non-mutable in this loop
+            match transfer {
+                Transfer::Read(rbuf) => req.push(I2cTransferRequest::Read {
+                    len: rbuf.len() as u32,
+                }),
+                Transfer::Write(wbuf) => req.push(I2cTransferRequest::Write {
+                    data: wbuf.to_vec(),
+                }),
+            }
+        }
+        match self.execute_command(request(req))? {
+            I2cResponse::RunTransaction { transaction: resp } => {
+                ensure!(
+                    resp.len() == transaction.len(),
+                    ProxyError::UnexpectedReply()
+                );
+                for pair in resp.iter().zip(transaction.iter_mut()) {
+                    match pair {
+                        (I2cTransferResponse::Read { data }, Transfer::Read(rbuf)) => {
+                            rbuf.clone_from_slice(data);
+                        }
+                        (I2cTransferResponse::Write, Transfer::Write(_)) => (),
+                        _ => bail!(ProxyError::UnexpectedReply()),
+                    }
+                }
+                Ok(())
+            }
+            _ => bail!(ProxyError::UnexpectedReply()),
+        }
+    }
 }
 
 impl Bus for ProxyI2c {
@@ -77,38 +117,25 @@ impl Bus for ProxyI2c {
     }
 
     fn run_transaction(&self, address: Option<u8>, transaction: &mut [Transfer]) -> Result<()> {
-        let mut req: Vec<I2cTransferRequest> = Vec::new();
-        for transfer in &*transaction {
-            // &* to treat as non-mutable in this loop
-            match transfer {
-                Transfer::Read(rbuf) => req.push(I2cTransferRequest::Read {
-                    len: rbuf.len() as u32,
-                }),
-                Transfer::Write(wbuf) => req.push(I2cTransferRequest::Write {
-                    data: wbuf.to_vec(),
-                }),
-            }
-        }
-        match self.execute_command(I2cRequest::RunTransaction {
-            address: address.or(self.default_address.get()),
-            transaction: req,
+        let address = address.or(self.default_address.get());
+        self.execute_transaction(transaction, |transaction| I2cRequest::RunTransaction {
+            address,
+            transaction,
+        })
+    }
+
+    fn run_transaction_at(&self, address: Address, transaction: &mut [Transfer]) -> Result<()> {
+        self.execute_transaction(transaction, |transaction| I2cRequest::RunTransactionAt {
+            address,
+            transaction,
+        })
+    }
+
+    fn set_clock_stretch_timeout(&self, timeout: Duration) -> Result<()> {
+        match self.execute_command(I2cRequest::SetClockStretchTimeout {
+            timeout_micros: timeout.as_micros().try_into()?,
         })? {
-            I2cResponse::RunTransaction { transaction: resp } => {
-                ensure!(
-                    resp.len() == transaction.len(),
-                    ProxyError::UnexpectedReply()
-                );
-                for pair in resp.iter().zip(transaction.iter_mut()) {
-                    match pair {
-                        (I2cTransferResponse::Read { data }, Transfer::Read(rbuf)) => {
-                            rbuf.clone_from_slice(data);
-                        }
-                        (I2cTransferResponse::Write, Transfer::Write(_)) => (),
-                        _ => bail!(ProxyError::UnexpectedReply()),
-                    }
-                }
-                Ok(())
-            }
+            I2cResponse::SetClockStretchTimeout => Ok(()),
             _ => bail!(ProxyError::UnexpectedReply()),
         }
     }
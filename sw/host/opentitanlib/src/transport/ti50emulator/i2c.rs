@@ -6,7 +6,7 @@ use anyhow::{bail, Context, Result};
 use log;
 
 use std::cell::{Cell, RefCell};
-use std::io::{Read, Write};
+use std::io::{ErrorKind, Read, Write};
 use std::os::unix::net::UnixStream;
 use std::path::PathBuf;
 use std::rc::Rc;
@@ -18,8 +18,9 @@ use crate::transport::ti50emulator::emu::EMULATOR_INVALID_ID;
 use crate::transport::ti50emulator::Inner;
 use crate::transport::TransportError;
 
-const MAX_READ_TIMEOUT: Duration = Duration::from_millis(35);
-const MAX_WRITE_TIMEOUT: Duration = Duration::from_millis(35);
+/// Default time allowed to the emulated device to complete a transfer, as the emulated bus has
+/// no clock to stretch.
+const DEFAULT_TRANSFER_TIMEOUT: Duration = Duration::from_millis(35);
 
 const TI50_I2C_BUS_WRITE_REQ: u8 = b'W';
 const TI50_I2C_BUS_READ_REQ: u8 = b'R';
@@ -127,6 +128,8 @@ pub struct Ti50I2cBus {
     // last SubProcess ID
     last_id: Cell<u64>,
     default_address: Cell<Option<u8>>,
+    // Time allowed to the emulated device to complete a transfer.
+    transfer_timeout: Cell<Duration>,
 }
 
 impl Ti50I2cBus {
@@ -139,6 +142,7 @@ impl Ti50I2cBus {
             path: soc_path,
             last_id: Cell::new(EMULATOR_INVALID_ID),
             default_address: Cell::new(None),
+            transfer_timeout: Cell::new(DEFAULT_TRANSFER_TIMEOUT),
         })
     }
 
@@ -173,13 +177,19 @@ impl Ti50I2cBus {
     /// Function try to receive data from unix socket.
     pub fn rx(fd: &mut UnixStream, data: &mut [u8], deadline: Instant) -> Result<usize> {
         let mut rx_count: usize = 0;
-        while rx_count <= data.len() {
+        while rx_count < data.len() {
             let ts = Instant::now();
-            if ts > deadline {
+            if ts >= deadline {
                 bail!(I2cError::Timeout);
             }
             fd.set_read_timeout(Some(deadline - ts))?;
-            rx_count += fd.read(&mut data[rx_count..])?;
+            rx_count += match fd.read(&mut data[rx_count..]) {
+                Ok(n) => n,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    bail!(I2cError::Timeout)
+                }
+                Err(e) => return Err(e.into()),
+            };
         }
         Ok(rx_count)
     }
@@ -187,13 +197,19 @@ impl Ti50I2cBus {
     /// Function send contents of slice to unix socket.
     pub fn tx(fd: &mut UnixStream, data: &[u8], deadline: Instant) -> Result<()> {
         let mut tx_count: usize = 0;
-        while tx_count <= data.len() {
+        while tx_count < data.len() {
             let ts = Instant::now();
-            if ts > deadline {
+            if ts >= deadline {
                 bail!(I2cError::Timeout);
             }
             fd.set_write_timeout(Some(deadline - ts))?;
-            tx_count += fd.write(&data[tx_count..])?;
+            tx_count += match fd.write(&data[tx_count..]) {
+                Ok(n) => n,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    bail!(I2cError::Timeout)
+                }
+                Err(e) => return Err(e.into()),
+            };
         }
         Ok(())
     }
@@ -201,15 +217,7 @@ impl Ti50I2cBus {
     /// Function perform write transaction on emulated bus.
     pub fn write(&self, addr: u8, data: &[u8]) -> Result<()> {
         if let Some(ref mut fd) = *self.socket.borrow_mut() {
-            let deadline = Instant::now() + MAX_WRITE_TIMEOUT;
-            log::debug!(
-                "I2C Transmit transaction write request addr: {:02X} len: {}",
-                addr,
-                data.len()
-            );
-            Ti50BusControl::send_write_request(fd, deadline, addr, data.len() as u16)?;
-            Ti50I2cBus::tx(fd, data, deadline)?;
-            return Ti50BusControl::recv_write_response(fd, deadline);
+            return Self::write_transfer(fd, self.transfer_timeout.get(), addr, data);
         }
         bail!(I2cError::Generic("Invalid socket".to_string()));
     }
@@ -217,19 +225,42 @@ impl Ti50I2cBus {
     /// Function perform read transaction on emulated BUS.
     pub fn read(&self, addr: u8, data: &mut [u8]) -> Result<()> {
         if let Some(ref mut fd) = *self.socket.borrow_mut() {
-            let deadline = Instant::now() + MAX_READ_TIMEOUT;
-            log::debug!(
-                "I2C Transmit transaction read request addr: {:02X} len: {}",
-                addr,
-                data.len()
-            );
-            Ti50BusControl::send_read_request(fd, deadline, addr, data.len() as u16)?;
-            Ti50BusControl::recv_read_response(fd, deadline)?;
-            Ti50I2cBus::rx(fd, &mut data[..], deadline)?;
-            return Ok(());
+            return Self::read_transfer(fd, self.transfer_timeout.get(), addr, data);
         }
         bail!(I2cError::Generic("Invalid socket".to_string()));
     }
+
+    /// Function perform write transaction on socket, which has to complete within `timeout`.
+    fn write_transfer(fd: &mut UnixStream, timeout: Duration, addr: u8, data: &[u8]) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        log::debug!(
+            "I2C Transmit transaction write request addr: {:02X} len: {}",
+            addr,
+            data.len()
+        );
+        Ti50BusControl::send_write_request(fd, deadline, addr, data.len() as u16)?;
+        Ti50I2cBus::tx(fd, data, deadline)?;
+        Ti50BusControl::recv_write_response(fd, deadline)
+    }
+
+    /// Function perform read transaction on socket, which has to complete within `timeout`.
+    fn read_transfer(
+        fd: &mut UnixStream,
+        timeout: Duration,
+        addr: u8,
+        data: &mut [u8],
+    ) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        log::debug!(
+            "I2C Transmit transaction read request addr: {:02X} len: {}",
+            addr,
+            data.len()
+        );
+        Ti50BusControl::send_read_request(fd, deadline, addr, data.len() as u16)?;
+        Ti50BusControl::recv_read_response(fd, deadline)?;
+        Ti50I2cBus::rx(fd, &mut data[..], deadline)?;
+        Ok(())
+    }
 }
 
 impl Bus for Ti50I2cBus {
@@ -249,6 +280,12 @@ impl Bus for Ti50I2cBus {
         Ok(())
     }
 
+    /// The emulated bus has no clock, the timeout applies to each transfer as a whole.
+    fn set_clock_stretch_timeout(&self, timeout: Duration) -> Result<()> {
+        self.transfer_timeout.set(timeout);
+        Ok(())
+    }
+
     fn run_transaction(&self, addr: Option<u8>, transaction: &mut [Transfer]) -> Result<()> {
         let addr = addr.or(self.default_address.get()).unwrap(); // TODO: Handle None
         self.check_state()?;
@@ -266,3 +303,69 @@ impl Bus for Ti50I2cBus {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Answers the transfers requested on `fd` like the emulated bus, with a device which
+    /// responds to reads with its address.  Returns the requests received.
+    fn emulate_device(mut fd: UnixStream, transfers: usize) -> Vec<(u8, u8, Vec<u8>)> {
+        let mut requests = Vec::new();
+        for _ in 0..transfers {
+            let mut header = [0u8; 4];
+            fd.read_exact(&mut header).unwrap();
+            let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+            match header[0] {
+                TI50_I2C_BUS_WRITE_REQ => {
+                    let mut data = vec![0u8; len];
+                    fd.read_exact(&mut data).unwrap();
+                    fd.write_all(&[TI50_I2C_BUS_WRITE_RES]).unwrap();
+                    requests.push((header[0], header[1], data));
+                }
+                _ => {
+                    fd.write_all(&[TI50_I2C_BUS_READ_RES]).unwrap();
+                    fd.write_all(&vec![header[1]; len]).unwrap();
+                    requests.push((header[0], header[1], Vec::new()));
+                }
+            }
+        }
+        requests
+    }
+
+    #[test]
+    fn transfers() -> Result<()> {
+        let (mut fd, device_fd) = UnixStream::pair()?;
+        let device = thread::spawn(move || emulate_device(device_fd, 2));
+
+        Ti50I2cBus::write_transfer(&mut fd, DEFAULT_TRANSFER_TIMEOUT, 0x50, &[0x01, 0x02])?;
+        let mut rbuf = [0u8; 3];
+        Ti50I2cBus::read_transfer(&mut fd, DEFAULT_TRANSFER_TIMEOUT, 0x50, &mut rbuf)?;
+        assert_eq!(rbuf, [0x50; 3]);
+
+        assert_eq!(
+            device.join().unwrap(),
+            [
+                (TI50_I2C_BUS_WRITE_REQ, 0x50, vec![0x01, 0x02]),
+                (TI50_I2C_BUS_READ_REQ, 0x50, Vec::new()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn transfer_timeout() -> Result<()> {
+        // The device never responds.
+        let (mut fd, _device_fd) = UnixStream::pair()?;
+        let timeout = Duration::from_millis(50);
+        let start = Instant::now();
+        let err = Ti50I2cBus::write_transfer(&mut fd, timeout, 0x50, &[0x01]).unwrap_err();
+        assert!(start.elapsed() >= timeout);
+        assert!(matches!(
+            err.downcast_ref::<I2cError>(),
+            Some(I2cError::Timeout)
+        ));
+        Ok(())
+    }
+}
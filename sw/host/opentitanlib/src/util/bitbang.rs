@@ -2,13 +2,15 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::{bail, ensure, Result};
+use anyhow::{bail, ensure, Context, Result};
 use std::collections::HashMap;
 use std::iter::Peekable;
 use std::ops::Mul;
+use std::path::Path;
 use std::time::Duration;
 
-use crate::io::gpio::{BitbangEntry, DacBangEntry};
+use crate::io::gpio::{BitbangEntry, DacBangEntry, Edge, MonitoringEvent};
+use crate::test_utils::gpio_monitor::Waves;
 
 #[derive(Debug, Eq, PartialEq)]
 enum Token<'a> {
@@ -446,6 +448,480 @@ pub fn parse_dac_sequence<'a, 'wr>(
     Ok(result.into())
 }
 
+/// File formats of waveforms which can be imported as bitbang or dac-bang sequences.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaveformFormat {
+    /// Value Change Dump, as produced by `Waves::dump_vcd()`, simulators or GTKWave.  Logic
+    /// signals must be single bit variables, analog signals `real` variables.
+    Vcd,
+    /// Comma separated values with a header line naming the columns.  The first column is the
+    /// time in seconds, each of the other columns holds the values of one signal from that time
+    /// until the next line: `0` or `1` for logic signals, volts for analog signals.
+    Csv,
+}
+
+impl WaveformFormat {
+    /// Determines the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("vcd") => Ok(Self::Vcd),
+            Some("csv") => Ok(Self::Csv),
+            _ => bail!("Unknown waveform file format: {}", path.display()),
+        }
+    }
+}
+
+/// Value of a signal of an imported waveform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Level {
+    Logic(bool),
+    Real(f64),
+}
+
+/// The values of the signals of an imported waveform, at each time (in seconds) at which at
+/// least one of them changes, and the time of the end of the waveform.
+struct Changes {
+    changes: Vec<(f64, Vec<Level>)>,
+    end: f64,
+}
+
+impl Changes {
+    fn push(&mut self, time: f64, values: &[Option<Level>], signals: &[&str]) -> Result<()> {
+        let values = values
+            .iter()
+            .zip(signals)
+            .map(|(value, signal)| value.with_context(|| format!("No value for signal `{signal}`")))
+            .collect::<Result<Vec<_>>>()?;
+        match self.changes.last() {
+            Some((_, last)) if *last == values => (),
+            _ => self.changes.push((time, values)),
+        }
+        self.end = time;
+        Ok(())
+    }
+}
+
+/// Returns the tokens up to the next `$end`.
+fn vcd_until_end<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Vec<&'a str>> {
+    let mut result = Vec::new();
+    loop {
+        match tokens.next() {
+            Some("$end") => return Ok(result),
+            Some(token) => result.push(token),
+            None => bail!("Missing $end"),
+        }
+    }
+}
+
+/// Parses a VCD timescale such as "10ps" into seconds.
+fn parse_vcd_timescale(spec: &str) -> Result<f64> {
+    let unit_start = spec
+        .find(|ch: char| !ch.is_ascii_digit())
+        .context("Missing timescale unit")?;
+    let number = match &spec[..unit_start] {
+        "1" => 1.0,
+        "10" => 10.0,
+        "100" => 100.0,
+        other => bail!("Unsupported timescale: {}", other),
+    };
+    let unit = match &spec[unit_start..] {
+        "s" => 1.0,
+        "ms" => 1e-3,
+        "us" => 1e-6,
+        "ns" => 1e-9,
+        "ps" => 1e-12,
+        "fs" => 1e-15,
+        unit => bail!("Unknown unit: {}", unit),
+    };
+    Ok(number * unit)
+}
+
+fn parse_vcd_value(value: &str, signal: &str) -> Result<Level> {
+    let value = value.to_ascii_lowercase();
+    if let Some(real) = value.strip_prefix('r') {
+        return Ok(Level::Real(real.parse().with_context(|| {
+            format!("Invalid real value `{real}` of signal `{signal}`")
+        })?));
+    }
+    match value.strip_prefix('b').unwrap_or(&value) {
+        "0" => Ok(Level::Logic(false)),
+        "1" => Ok(Level::Logic(true)),
+        // A released line, as if pulled up.
+        "z" => Ok(Level::Logic(true)),
+        other => bail!("Unsupported value `{}` of signal `{}`", other, signal),
+    }
+}
+
+fn parse_vcd(input: &str, signals: &[&str]) -> Result<Changes> {
+    let mut timescale = 1e-9;
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let mut values: Vec<Option<Level>> = vec![None; signals.len()];
+    let mut time: Option<u64> = None;
+    let mut changes = Changes {
+        changes: Vec::new(),
+        end: 0.0,
+    };
+    let mut tokens = input.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "$timescale" => {
+                timescale = parse_vcd_timescale(&vcd_until_end(&mut tokens)?.concat())?;
+            }
+            "$var" => {
+                // Type, size, identifier, reference and optional bit range.
+                let var = vcd_until_end(&mut tokens)?;
+                ensure!(var.len() >= 4, "Malformed $var declaration");
+                if let Some(index) = signals.iter().position(|&s| s == var[3]) {
+                    ensure!(
+                        var[0] == "real" || var[1] == "1",
+                        "Signal `{}` is not a single bit",
+                        var[3]
+                    );
+                    ids.insert(var[2], index);
+                }
+            }
+            // Value changes follow these keywords, up to the next `$end`.
+            "$dumpvars" | "$dumpall" | "$dumpon" | "$dumpoff" | "$end" => (),
+            keyword if keyword.starts_with('$') => {
+                vcd_until_end(&mut tokens)?;
+            }
+            _ => {
+                if let Some(timestamp) = token.strip_prefix('#') {
+                    let timestamp = timestamp
+                        .parse::<u64>()
+                        .with_context(|| format!("Invalid timestamp `{token}`"))?;
+                    if let Some(previous) = time {
+                        ensure!(
+                            timestamp >= previous,
+                            "Timestamps must be increasing: {}",
+                            token
+                        );
+                        if timestamp > previous {
+                            changes.push(previous as f64 * timescale, &values, signals)?;
+                        }
+                    }
+                    time = Some(timestamp);
+                    continue;
+                }
+                let (value, id) = match token.as_bytes()[0] {
+                    b'0' | b'1' | b'x' | b'X' | b'z' | b'Z' => (&token[..1], &token[1..]),
+                    b'b' | b'B' | b'r' | b'R' => {
+                        (token, tokens.next().context("Missing identifier")?)
+                    }
+                    _ => bail!("Unexpected VCD token `{}`", token),
+                };
+                if let Some(&index) = ids.get(id) {
+                    // Values dumped before the first timestamp are the initial values.
+                    time.get_or_insert(0);
+                    values[index] = Some(parse_vcd_value(value, signals[index])?);
+                }
+            }
+        }
+    }
+    for (index, signal) in signals.iter().enumerate() {
+        ensure!(
+            ids.values().any(|&i| i == index),
+            "Signal `{}` not found in VCD",
+            signal
+        );
+    }
+    let time = time.context("Waveform has no data")?;
+    changes.push(time as f64 * timescale, &values, signals)?;
+    Ok(changes)
+}
+
+fn parse_csv(input: &str, signals: &[&str], analog: bool) -> Result<Changes> {
+    let mut lines = input.lines().filter(|line| !line.trim().is_empty());
+    let header = lines
+        .next()
+        .context("Missing CSV header")?
+        .split(',')
+        .map(|column| column.trim().trim_matches('"'))
+        .collect::<Vec<_>>();
+    let columns = signals
+        .iter()
+        .map(|&signal| {
+            header
+                .iter()
+                .skip(1)
+                .position(|&column| column == signal)
+                .map(|position| position + 1)
+                .with_context(|| format!("Signal `{signal}` not found in CSV header"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut changes = Changes {
+        changes: Vec::new(),
+        end: 0.0,
+    };
+    for line in lines {
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        ensure!(
+            fields.len() == header.len(),
+            "Wrong number of columns in CSV line `{}`",
+            line
+        );
+        let time = fields[0]
+            .parse::<f64>()
+            .with_context(|| format!("Invalid time `{}`", fields[0]))?;
+        ensure!(
+            changes.changes.is_empty() || time > changes.end,
+            "Times must be increasing: {}",
+            time
+        );
+        let values = columns
+            .iter()
+            .zip(signals)
+            .map(|(&column, signal)| {
+                let value = fields[column];
+                Ok(Some(match value {
+                    _ if analog => Level::Real(value.parse().with_context(|| {
+                        format!("Invalid value `{value}` of signal `{signal}`")
+                    })?),
+                    "0" => Level::Logic(false),
+                    "1" => Level::Logic(true),
+                    _ => bail!("Invalid value `{}` of signal `{}`", value, signal),
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        changes.push(time, &values, signals)?;
+    }
+    ensure!(!changes.changes.is_empty(), "Waveform has no data");
+    Ok(changes)
+}
+
+fn parse_waveform(
+    input: &str,
+    format: WaveformFormat,
+    signals: &[&str],
+    analog: bool,
+) -> Result<Changes> {
+    match format {
+        WaveformFormat::Vcd => parse_vcd(input, signals),
+        WaveformFormat::Csv => parse_csv(input, signals, analog),
+    }
+}
+
+/// Converts the changes of a waveform into runs of identical values, with their lengths in
+/// ticks of `clock`.  The last values are held for at least one tick.
+fn waveform_runs(changes: &Changes, clock: Duration) -> Result<Vec<(&[Level], u32)>> {
+    let start = changes.changes[0].0;
+    let tick = |time: f64| ((time - start) / clock.as_secs_f64()).round() as u64;
+    let mut runs: Vec<(&[Level], u64)> = Vec::new();
+    let mut dropped = 0usize;
+    for (i, (time, values)) in changes.changes.iter().enumerate() {
+        let next = match changes.changes.get(i + 1) {
+            Some((next, _)) => *next,
+            None => changes.end,
+        };
+        let mut ticks = tick(next) - tick(*time);
+        if i + 1 == changes.changes.len() {
+            ticks = ticks.max(1);
+        }
+        if ticks == 0 {
+            dropped += 1;
+            continue;
+        }
+        match runs.last_mut() {
+            Some((last, length)) if *last == values.as_slice() => *length += ticks,
+            _ => runs.push((values, ticks)),
+        }
+    }
+    if dropped > 0 {
+        log::warn!(
+            "{} changes shorter than half a clock tick were dropped, try increasing clock frequency",
+            dropped
+        );
+    }
+    runs.into_iter()
+        .map(|(values, ticks)| {
+            ensure!(
+                ticks <= u32::MAX as u64,
+                "Waveform exceeds range, try lower clock frequency"
+            );
+            Ok((values, ticks as u32))
+        })
+        .collect()
+}
+
+/// Groups runs of samples into blocks written at consecutive clock ticks, each followed by the
+/// delay holding its last sample, if longer than one tick.
+fn waveform_blocks(runs: &[(&[Level], u32)]) -> Vec<(usize, Option<u32>)> {
+    let mut blocks = Vec::new();
+    let mut samples = 0;
+    for (i, &(_, ticks)) in runs.iter().enumerate() {
+        samples += 1;
+        if i + 1 == runs.len() {
+            blocks.push((samples, None));
+        } else if ticks > 1 {
+            blocks.push((samples, Some(ticks)));
+            samples = 0;
+        }
+    }
+    blocks
+}
+
+/// This function imports a waveform file as a list of `BitbangEntry`, for replaying it with
+/// `opentitantool gpio bit-bang`.  `signals` gives the name of the waveform signal to apply to
+/// each pin.  Values are resampled at each tick of `clock`, and held values are turned into
+/// delays.  As with `parse_sequence()`, the slices in the entries refer to the "accumulator
+/// vectors" provided by the caller.
+pub fn import_sequence<'rd, 'wr>(
+    input: &str,
+    format: WaveformFormat,
+    signals: &[&str],
+    clock: Duration,
+    accumulator_rd: &'rd mut Vec<u8>,
+    accumulator_wr: &'wr mut Vec<u8>,
+) -> Result<Box<[BitbangEntry<'rd, 'wr>]>> {
+    ensure!(
+        !signals.is_empty(),
+        "Must specify at least one GPIO pin for bitbanging"
+    );
+    ensure!(signals.len() <= 8, "At most 8 GPIO pins can be bitbanged");
+    let changes = parse_waveform(input, format, signals, false)?;
+    let runs = waveform_runs(&changes, clock)?;
+
+    accumulator_wr.clear();
+    for (values, _) in &runs {
+        let mut sample = 0u8;
+        for (pin_no, value) in values.iter().enumerate() {
+            match value {
+                Level::Logic(false) => (),
+                Level::Logic(true) => sample |= 1 << pin_no,
+                Level::Real(_) => bail!("Signal `{}` is not a logic signal", signals[pin_no]),
+            }
+        }
+        accumulator_wr.push(sample);
+    }
+    accumulator_rd.clear();
+    accumulator_rd.resize(runs.len(), 0u8);
+    let mut slice_wr: &'wr [u8] = accumulator_wr;
+    let mut slice_rd: &'rd mut [u8] = accumulator_rd;
+
+    let mut result = Vec::new();
+    for (samples, delay) in waveform_blocks(&runs) {
+        let (left_wr, right_wr) = slice_wr.split_at(samples);
+        let (left_rd, right_rd) = slice_rd.split_at_mut(samples);
+        result.push(BitbangEntry::Both(left_wr, left_rd));
+        if let Some(delay) = delay {
+            result.push(BitbangEntry::Delay(delay));
+        }
+        slice_wr = right_wr;
+        slice_rd = right_rd;
+    }
+    Ok(result.into())
+}
+
+/// This function imports a waveform file as a list of `DacBangEntry`, for replaying it with
+/// `opentitantool gpio dac-bang`.  `signals` gives the name of the waveform signal to apply to
+/// each pin.  Values are resampled at each tick of `clock`, without interpolation, and held
+/// values are turned into delays.
+pub fn import_dac_sequence<'wr>(
+    input: &str,
+    format: WaveformFormat,
+    signals: &[&str],
+    clock: Duration,
+    accumulator: &'wr mut Vec<f32>,
+) -> Result<Box<[DacBangEntry<'wr>]>> {
+    ensure!(
+        !signals.is_empty(),
+        "Must specify at least one analog pin for dac-banging"
+    );
+    let changes = parse_waveform(input, format, signals, true)?;
+    let runs = waveform_runs(&changes, clock)?;
+
+    accumulator.clear();
+    for (values, _) in &runs {
+        for (pin_no, value) in values.iter().enumerate() {
+            match value {
+                Level::Real(voltage) => accumulator.push(*voltage as f32),
+                Level::Logic(_) => bail!("Signal `{}` is not an analog signal", signals[pin_no]),
+            }
+        }
+    }
+    let mut slice_wr: &'wr [f32] = accumulator;
+
+    let mut result = Vec::new();
+    for (samples, delay) in waveform_blocks(&runs) {
+        let (left_wr, right_wr) = slice_wr.split_at(samples * signals.len());
+        result.push(DacBangEntry::Write(left_wr));
+        if let Some(delay) = delay {
+            result.push(DacBangEntry::Delay(delay));
+        }
+        slice_wr = right_wr;
+    }
+    Ok(result.into())
+}
+
+/// This function converts the samples captured by the `Both` entries of a sequence, as returned
+/// by `GpioBitbanging::run()`, into a VCD dump with one signal per pin.  Each sample is placed
+/// at the clock tick at which it was taken, that is just before the corresponding output levels
+/// were applied.  The time spent in `Await` entries is unknown, and not accounted for.
+pub fn export_vcd(sequence: &[BitbangEntry], signals: &[&str], clock: Duration) -> Result<String> {
+    ensure!(
+        !signals.is_empty() && signals.len() <= 8,
+        "Unsupported number of signals: {}",
+        signals.len()
+    );
+    let mut samples: Vec<(u64, u8)> = Vec::new();
+    let mut tick = 0u64;
+    for entry in sequence {
+        match entry {
+            BitbangEntry::Write(data) => tick += data.len() as u64,
+            BitbangEntry::WriteOwned(data) => tick += data.len() as u64,
+            BitbangEntry::Both(_, data) => {
+                for &sample in data.iter() {
+                    samples.push((tick, sample));
+                    tick += 1;
+                }
+            }
+            BitbangEntry::BothOwned(data) => {
+                for &sample in data.iter() {
+                    samples.push((tick, sample));
+                    tick += 1;
+                }
+            }
+            // The last sample already accounts for one tick of the delay.
+            BitbangEntry::Delay(ticks) => tick += (*ticks as u64).saturating_sub(1),
+            BitbangEntry::Await { .. } => (),
+        }
+    }
+    let Some(&(_, first)) = samples.first() else {
+        bail!("The sequence did not capture any samples");
+    };
+
+    let clock_ns = clock.as_nanos() as u64;
+    let mut waves = Waves::new(
+        signals.iter().map(|s| s.to_string()).collect(),
+        (0..signals.len()).map(|i| first & (1 << i) != 0).collect(),
+        0,
+        1_000_000_000,
+    );
+    let mut previous = first;
+    for &(tick, sample) in &samples[1..] {
+        for i in 0..signals.len() {
+            if (sample ^ previous) & (1 << i) != 0 {
+                waves.add_event(MonitoringEvent {
+                    signal_index: i as u8,
+                    edge: if sample & (1 << i) != 0 {
+                        Edge::Rising
+                    } else {
+                        Edge::Falling
+                    },
+                    timestamp: tick * clock_ns,
+                });
+            }
+        }
+        previous = sample;
+    }
+    waves.set_final_timestamp(tick * clock_ns);
+    Ok(waves.dump_vcd())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -521,4 +997,114 @@ mod tests {
             ],
         );
     }
+
+    /// Describes bitbang entries as "w<samples>" and "d<ticks>".
+    fn describe(entries: &[BitbangEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| match entry {
+                BitbangEntry::Both(wr, _) => format!("w{wr:?}"),
+                BitbangEntry::Delay(ticks) => format!("d{ticks}"),
+                _ => "?".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_import_vcd() {
+        let vcd = "$timescale 100ns $end\n\
+                   $scope module top $end\n\
+                   $var wire 1 ! clk $end\n\
+                   $var wire 1 # data $end\n\
+                   $var wire 8 $ bus [7:0] $end\n\
+                   $upscope $end\n\
+                   $enddefinitions $end\n\
+                   $dumpvars 0! 1# b10100101 $ $end\n\
+                   #10 1!\n\
+                   #20 0! 0#\n\
+                   #30 1! b0 $\n\
+                   #60 z#\n\
+                   #70\n";
+        let mut rd = Vec::new();
+        let mut wr = Vec::new();
+        let clock = Duration::from_micros(1);
+        let entries = import_sequence(
+            vcd,
+            WaveformFormat::Vcd,
+            &["data", "clk"],
+            clock,
+            &mut rd,
+            &mut wr,
+        )
+        .unwrap();
+        assert_eq!(describe(&entries), ["w[1, 3, 0, 2]", "d3", "w[3]"],);
+        // Multi-bit signals are not supported.
+        assert!(
+            import_sequence(vcd, WaveformFormat::Vcd, &["bus"], clock, &mut rd, &mut wr).is_err()
+        );
+        assert!(import_sequence(
+            vcd,
+            WaveformFormat::Vcd,
+            &["missing"],
+            clock,
+            &mut rd,
+            &mut wr
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_import_csv() {
+        let csv = "Time [s],\"vref\",vdd\n\
+                   0.000,0.5,3.3\n\
+                   0.001,0.5,3.0\n\
+                   0.004,1.25,3.0\n";
+        let mut accumulator = Vec::new();
+        let entries = import_dac_sequence(
+            csv,
+            WaveformFormat::Csv,
+            &["vdd", "vref"],
+            Duration::from_millis(1),
+            &mut accumulator,
+        )
+        .unwrap();
+        let entries = entries
+            .iter()
+            .map(|entry| match entry {
+                DacBangEntry::Write(data) => format!("w{data:?}"),
+                DacBangEntry::Delay(ticks) => format!("d{ticks}"),
+                _ => "?".to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(entries, ["w[3.3, 0.5, 3.0, 0.5]", "d3", "w[3.0, 1.25]"]);
+    }
+
+    #[test]
+    fn test_export_vcd() {
+        let wr = [0u8; 4];
+        let mut rd1 = [0b01, 0b11, 0b11];
+        let mut rd2 = [0b10];
+        let sequence = [
+            BitbangEntry::Both(&wr[..3], &mut rd1),
+            BitbangEntry::Delay(5),
+            BitbangEntry::Both(&wr[3..], &mut rd2),
+        ];
+        let clock = Duration::from_micros(2);
+        let vcd = export_vcd(&sequence, &["a", "b"], clock).unwrap();
+        assert!(vcd.ends_with("#0 1! 0#\n#2000 1#\n#14000 0!\n#16000"));
+
+        // Importing the dump gives back the captured samples.
+        let mut rd = Vec::new();
+        let mut wr = Vec::new();
+        let entries = import_sequence(
+            &vcd,
+            WaveformFormat::Vcd,
+            &["a", "b"],
+            clock,
+            &mut rd,
+            &mut wr,
+        )
+        .unwrap();
+        assert_eq!(describe(&entries), ["w[1, 3]", "d6", "w[2]"]);
+    }
 }
@@ -50,6 +50,13 @@ pub enum CertFormat {
     X509,
 }
 
+/// Language of the generated code.
+#[derive(Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CodegenLang {
+    C,
+    Rust,
+}
+
 /// Generate a certificate template.
 #[derive(Debug, Args)]
 pub struct CodegenCommand {
@@ -59,27 +66,32 @@ pub struct CodegenCommand {
     /// Certificate format
     #[arg(long, value_enum, default_value_t = CertFormat::X509)]
     cert_format: CertFormat,
+    /// Language of the generated code.
+    #[arg(long, value_enum, default_value_t = CodegenLang::C)]
+    lang: CodegenLang,
     /// Output directory path.
-    #[arg(long, required_unless_present_any(["output_c", "output_h"]))]
+    #[arg(long, required_unless_present_any(["output_c", "output_h", "output_rs"]))]
     output_dir: Option<PathBuf>,
     /// Output file for C source.
-    #[arg(long, required_unless_present = "output_dir")]
+    #[arg(long, required_unless_present_any(["output_dir", "output_rs"]))]
     output_c: Option<PathBuf>,
     /// Output file for H header.
-    #[arg(long, required_unless_present = "output_dir")]
+    #[arg(long, required_unless_present_any(["output_dir", "output_rs"]))]
     output_h: Option<PathBuf>,
-    /// Output file for C++ unittest.
+    /// Output file for the Rust module.
+    #[arg(long, conflicts_with_all(["output_c", "output_h"]))]
+    output_rs: Option<PathBuf>,
+    /// Output file for the unittest: C++ source, or Rust crate root when
+    /// generating Rust.
     #[arg(long)]
     output_unittest: Option<PathBuf>,
 }
 
-impl CommandDispatch for CodegenCommand {
-    fn run(
-        &self,
-        _context: &dyn Any,
-        _transport: &TransportWrapper,
-    ) -> Result<Option<Box<dyn Annotate>>> {
-        let template = load_template(&self.template)?;
+impl CodegenCommand {
+    fn run_c(&self, template: &Template) -> Result<()> {
+        if self.output_rs.is_some() {
+            bail!("--output-rs can only be used with --lang=rust");
+        }
         // Generate C and header files.
         let (output_c, output_h) = if let Some(output_dir) = &self.output_dir {
             (
@@ -99,7 +111,7 @@ impl CommandDispatch for CodegenCommand {
         let mut output_c = File::create(output_c)?;
         let mut output_h = File::create(output_h)?;
 
-        let codegen = codegen::generate_cert(&self.template.display().to_string(), &template)?;
+        let codegen = codegen::generate_cert(&self.template.display().to_string(), template)?;
         writeln!(output_c, "{}", codegen.source_c)?;
         writeln!(output_h, "{}", codegen.source_h)?;
 
@@ -107,7 +119,42 @@ impl CommandDispatch for CodegenCommand {
             let mut output_unittest = File::create(output_unittest)?;
             writeln!(output_unittest, "{}", codegen.source_unittest)?;
         }
+        Ok(())
+    }
+
+    fn run_rust(&self, template: &Template) -> Result<()> {
+        if self.output_c.is_some() || self.output_h.is_some() {
+            bail!("--output-c and --output-h cannot be used with --lang=rust");
+        }
+        let output_rs = match (&self.output_rs, &self.output_dir) {
+            (Some(output_rs), _) => output_rs.clone(),
+            (None, Some(output_dir)) => output_dir.join(format!("{}.rs", &template.name)),
+            (None, None) => bail!("--output-rs must be specified when --output-dir is not used"),
+        };
+        let mut output_rs = File::create(output_rs)?;
+
+        let codegen = codegen::rust::generate_cert(&self.template.display().to_string(), template)?;
+        write!(output_rs, "{}", codegen.source_rs)?;
 
+        if let Some(output_unittest) = &self.output_unittest {
+            let mut output_unittest = File::create(output_unittest)?;
+            write!(output_unittest, "{}", codegen.source_unittest)?;
+        }
+        Ok(())
+    }
+}
+
+impl CommandDispatch for CodegenCommand {
+    fn run(
+        &self,
+        _context: &dyn Any,
+        _transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        let template = load_template(&self.template)?;
+        match self.lang {
+            CodegenLang::C => self.run_c(&template)?,
+            CodegenLang::Rust => self.run_rust(&template)?,
+        }
         Ok(None)
     }
 }
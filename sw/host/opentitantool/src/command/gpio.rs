@@ -2,22 +2,29 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::Result;
-use clap::{Args, Subcommand};
+use anyhow::{ensure, Context, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use humantime::parse_duration;
 use serde_annotate::Annotate;
 use std::any::Any;
 use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::str::FromStr;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use opentitanlib::app::command::CommandDispatch;
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::io::gpio::{ClockNature, Edge, GpioPin, PinMode, PullMode};
+use opentitanlib::io::uart::Parity;
+use opentitanlib::test_utils::bitbanging::spi::SpiDataMode;
+use opentitanlib::test_utils::gpio_decode::{i2c, pwm, spi, uart};
+use opentitanlib::test_utils::gpio_monitor::{GpioMon, Waves};
 use opentitanlib::transport::Capability;
+use opentitanlib::util::bitbang::{self, WaveformFormat};
 use opentitanlib::util::file;
 use opentitanlib::util::raw_tty::RawTty;
 use opentitanlib::util::voltage::Voltage;
@@ -235,6 +242,7 @@ pub enum GpioMonitoringCommand {
     Start(GpioMonitoringStart),
     Read(GpioMonitoringRead),
     Vcd(GpioMonitoringVcd),
+    Decode(GpioMonitoringDecode),
 }
 
 #[derive(Debug, Args)]
@@ -513,6 +521,211 @@ impl CommandDispatch for GpioMonitoringVcd {
     }
 }
 
+#[derive(Clone, Debug, ValueEnum)]
+pub enum DecodeProtocol {
+    Uart,
+    Spi,
+    I2c,
+    Pwm,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum DecodeParity {
+    None,
+    Even,
+    Odd,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum DecodeSpiWidth {
+    Single,
+    Dual,
+    Quad,
+}
+
+#[derive(Debug, Args)]
+/// Capture the edges on a set of pins, and decode them according to a protocol, turning the
+/// transport into a simple logic analyzer.  The capture lasts for `--duration`, or until the user
+/// presses Ctrl-C.  The pins are given in the following order: `RX` for UART; `SCK CS` followed
+/// by `COPI [CIPO]`, `IO0 IO1` or `IO0 IO1 IO2 IO3` for SPI; `SCL SDA` for I2C; the measured pin
+/// for PWM.
+pub struct GpioMonitoringDecode {
+    /// Protocol to decode.
+    #[arg(long, value_enum)]
+    pub protocol: DecodeProtocol,
+
+    /// The list of GPIO pins to monitor, in the order expected by the protocol.
+    #[arg(long, num_args = 1.., required = true)]
+    pub pins: Vec<String>,
+
+    /// Duration of the capture.
+    #[arg(long, value_parser = parse_duration)]
+    pub duration: Option<Duration>,
+
+    /// UART baud rate.
+    #[arg(long, default_value_t = 115200)]
+    pub baud_rate: u32,
+
+    /// UART number of data bits.
+    #[arg(long, default_value_t = 8)]
+    pub data_bits: u8,
+
+    /// UART parity.
+    #[arg(long, value_enum, default_value_t = DecodeParity::None)]
+    pub parity: DecodeParity,
+
+    /// UART number of stop bits.
+    #[arg(long, default_value_t = 1)]
+    pub stop_bits: u8,
+
+    /// SPI mode, from 0 to 3.
+    #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..4))]
+    pub spi_mode: u8,
+
+    /// Number of SPI data lines.
+    #[arg(long, value_enum, default_value_t = DecodeSpiWidth::Single)]
+    pub spi_width: DecodeSpiWidth,
+}
+
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+pub enum GpioMonitoringDecodeResult {
+    Uart {
+        frames: Vec<uart::UartFrame>,
+        /// The received characters, when they are 8 bits wide.
+        text: Option<String>,
+    },
+    Spi {
+        transfers: Vec<spi::SpiTransfer>,
+    },
+    I2c {
+        events: Vec<i2c::I2cEvent>,
+    },
+    Pwm(pwm::PwmMeasurement),
+}
+
+impl GpioMonitoringDecode {
+    fn num_pins(&self) -> std::ops::RangeInclusive<usize> {
+        match (&self.protocol, &self.spi_width) {
+            (DecodeProtocol::Uart | DecodeProtocol::Pwm, _) => 1..=1,
+            (DecodeProtocol::I2c, _) => 2..=2,
+            (DecodeProtocol::Spi, DecodeSpiWidth::Single) => 3..=4,
+            (DecodeProtocol::Spi, DecodeSpiWidth::Dual) => 4..=4,
+            (DecodeProtocol::Spi, DecodeSpiWidth::Quad) => 6..=6,
+        }
+    }
+
+    // Monitor the pins for the requested duration, or until the user presses Ctrl-C.
+    fn capture(&self, transport: &TransportWrapper) -> Result<Waves> {
+        let pins = self
+            .pins
+            .iter()
+            .map(|pin| (pin.as_str(), ""))
+            .collect::<Vec<_>>();
+        let mut gpio_mon = GpioMon::start(transport, &pins, false)?;
+        let mut stdin = match self.duration {
+            Some(_) => None,
+            None => {
+                eprint!("[CTRL+C] to stop capturing  ");
+                // See `GpioMonitoringVcd` for why the terminal is put in raw mode.
+                Some(RawTty::new(std::io::stdin())?)
+            }
+        };
+        let start = Instant::now();
+        loop {
+            let events = gpio_mon.read(true)?;
+            if let Some(duration) = self.duration {
+                if start.elapsed() >= duration {
+                    break;
+                }
+            }
+            let delay = if events.is_empty() {
+                Duration::from_millis(10)
+            } else {
+                Duration::from_millis(0)
+            };
+            if let Some(stdin) = stdin.as_mut() {
+                if file::wait_read_timeout(&*stdin, delay).is_ok() {
+                    let mut buf = [0u8; 1];
+                    if stdin.read(&mut buf)? == 1 && buf[0] == 3 {
+                        // CtrlC
+                        break;
+                    }
+                }
+            } else {
+                std::thread::sleep(delay);
+            }
+        }
+        if stdin.is_some() {
+            eprintln!("\r");
+        }
+        // Make one final reading and stop monitoring.
+        Waves::try_from(gpio_mon)
+    }
+}
+
+impl CommandDispatch for GpioMonitoringDecode {
+    fn run(
+        &self,
+        _context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport
+            .capabilities()?
+            .request(Capability::GPIO | Capability::GPIO_MONITORING)
+            .ok()?;
+        ensure!(
+            self.num_pins().contains(&self.pins.len()),
+            "wrong number of pins for {:?}: expected {:?}",
+            self.protocol,
+            self.num_pins()
+        );
+        let waves = self.capture(transport)?;
+        let result = match self.protocol {
+            DecodeProtocol::Uart => {
+                let config = uart::UartConfig {
+                    baud_rate: self.baud_rate,
+                    data_bits: self.data_bits,
+                    parity: match self.parity {
+                        DecodeParity::None => Parity::None,
+                        DecodeParity::Even => Parity::Even,
+                        DecodeParity::Odd => Parity::Odd,
+                    },
+                    stop_bits: self.stop_bits,
+                };
+                let frames = uart::decode(&waves, 0, &config)?;
+                let text = (self.data_bits == 8).then(|| {
+                    let bytes = frames.iter().map(|f| f.data as u8).collect::<Vec<_>>();
+                    String::from_utf8_lossy(&bytes).into_owned()
+                });
+                GpioMonitoringDecodeResult::Uart { frames, text }
+            }
+            DecodeProtocol::Spi => {
+                let config = spi::SpiConfig {
+                    cpol: self.spi_mode & 2 != 0,
+                    cpha: self.spi_mode & 1 != 0,
+                    data_mode: match self.spi_width {
+                        DecodeSpiWidth::Single => SpiDataMode::Single,
+                        DecodeSpiWidth::Dual => SpiDataMode::Dual,
+                        DecodeSpiWidth::Quad => SpiDataMode::Quad,
+                    },
+                    sck: 0,
+                    cs: Some(1),
+                    data: (2..self.pins.len()).collect(),
+                };
+                GpioMonitoringDecodeResult::Spi {
+                    transfers: spi::decode(&waves, &config)?,
+                }
+            }
+            DecodeProtocol::I2c => GpioMonitoringDecodeResult::I2c {
+                events: i2c::decode(&waves, 0, 1)?,
+            },
+            DecodeProtocol::Pwm => GpioMonitoringDecodeResult::Pwm(pwm::measure(&waves, 0)?),
+        };
+        Ok(Some(Box::new(result)))
+    }
+}
+
 #[derive(Debug, Args)]
 /// Remove a configuration-named pin strapping
 pub struct GpioRemoveStrapping {
@@ -557,11 +770,23 @@ pub struct GpioBitbang {
     #[arg(long, value_parser = opentitanlib::util::bitbang::parse_clock_frequency)]
     pub clock: Duration,
 
-    #[arg(short, long)]
-    pub sequence: String,
+    #[arg(short, long, required_unless_present = "waveform")]
+    pub sequence: Option<String>,
+
+    /// VCD or CSV file to replay instead of `--sequence`, resampled at the clock frequency.
+    #[arg(long, conflicts_with = "sequence")]
+    pub waveform: Option<PathBuf>,
+
+    /// Names of the waveform signals to apply to each pin, defaults to the pin names.
+    #[arg(long, num_args = 1.., requires = "waveform")]
+    pub signals: Vec<String>,
 
     #[arg(short, long)]
     pub all: bool,
+
+    /// Write the logic levels of all affected pins at each clock tick to a VCD file.
+    #[arg(long)]
+    pub dump_vcd: Option<PathBuf>,
 }
 
 #[derive(serde::Serialize)]
@@ -586,6 +811,26 @@ fn binary_string(data: u8, num_digits: usize) -> String {
     val
 }
 
+/// Reads a waveform file to replay on `pins`, returning its content, its format and the names of
+/// the signals to apply to each pin.
+fn read_waveform<'a>(
+    path: &Path,
+    pins: &'a [String],
+    signals: &'a [String],
+) -> Result<(String, WaveformFormat, Vec<&'a str>)> {
+    let format = WaveformFormat::from_path(path)?;
+    let input = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let signals = if signals.is_empty() { pins } else { signals };
+    ensure!(
+        signals.len() == pins.len(),
+        "Expected {} signal names, one per pin, got {}",
+        pins.len(),
+        signals.len()
+    );
+    Ok((input, format, signals.iter().map(String::as_str).collect()))
+}
+
 impl CommandDispatch for GpioBitbang {
     fn run(
         &self,
@@ -600,14 +845,29 @@ impl CommandDispatch for GpioBitbang {
         let gpio_pins = transport.gpio_pins(&self.pins)?;
         let mut outbound_data_accumulator: Vec<u8> = Vec::new();
         let mut inbound_data_accumulator: Vec<u8> = Vec::new();
-        let (sequence, output_map) = opentitanlib::util::bitbang::parse_sequence(
-            &self.sequence,
-            gpio_pins.len(),
-            self.clock,
-            &mut inbound_data_accumulator,
-            &mut outbound_data_accumulator,
-        )?;
-        gpio_bitbanging.run(
+        let (sequence, output_map) = match (&self.sequence, &self.waveform) {
+            (Some(sequence), _) => bitbang::parse_sequence(
+                sequence,
+                gpio_pins.len(),
+                self.clock,
+                &mut inbound_data_accumulator,
+                &mut outbound_data_accumulator,
+            )?,
+            (None, Some(path)) => {
+                let (input, format, signals) = read_waveform(path, &self.pins, &self.signals)?;
+                let sequence = bitbang::import_sequence(
+                    &input,
+                    format,
+                    &signals,
+                    self.clock,
+                    &mut inbound_data_accumulator,
+                    &mut outbound_data_accumulator,
+                )?;
+                (sequence, HashMap::new())
+            }
+            (None, None) => unreachable!(),
+        };
+        let sequence = gpio_bitbanging.run(
             &gpio_pins
                 .iter()
                 .map(Rc::borrow)
@@ -615,6 +875,12 @@ impl CommandDispatch for GpioBitbang {
             self.clock,
             sequence,
         )?;
+        if let Some(path) = &self.dump_vcd {
+            let pins = self.pins.iter().map(String::as_str).collect::<Vec<_>>();
+            let vcd = bitbang::export_vcd(&sequence, &pins, self.clock)?;
+            std::fs::write(path, vcd)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
         let mut samples = HashMap::new();
         for (label, byte_index) in output_map {
             let sampled_data = inbound_data_accumulator[byte_index];
@@ -643,8 +909,16 @@ pub struct GpioDacBang {
     #[arg(long, value_parser = opentitanlib::util::bitbang::parse_clock_frequency)]
     pub clock: Duration,
 
-    #[arg(short, long)]
-    pub sequence: String,
+    #[arg(short, long, required_unless_present = "waveform")]
+    pub sequence: Option<String>,
+
+    /// VCD or CSV file to replay instead of `--sequence`, resampled at the clock frequency.
+    #[arg(long, conflicts_with = "sequence")]
+    pub waveform: Option<PathBuf>,
+
+    /// Names of the waveform signals to apply to each pin, defaults to the pin names.
+    #[arg(long, num_args = 1.., requires = "waveform")]
+    pub signals: Vec<String>,
 }
 
 impl CommandDispatch for GpioDacBang {
@@ -660,12 +934,25 @@ impl CommandDispatch for GpioDacBang {
         let gpio_bitbanging = transport.gpio_bitbanging()?;
         let gpio_pins = transport.gpio_pins(&self.pins)?;
         let mut data_accumulator: Vec<f32> = Vec::new();
-        let sequence = opentitanlib::util::bitbang::parse_dac_sequence(
-            &self.sequence,
-            gpio_pins.len(),
-            self.clock,
-            &mut data_accumulator,
-        )?;
+        let sequence = match (&self.sequence, &self.waveform) {
+            (Some(sequence), _) => bitbang::parse_dac_sequence(
+                sequence,
+                gpio_pins.len(),
+                self.clock,
+                &mut data_accumulator,
+            )?,
+            (None, Some(path)) => {
+                let (input, format, signals) = read_waveform(path, &self.pins, &self.signals)?;
+                bitbang::import_dac_sequence(
+                    &input,
+                    format,
+                    &signals,
+                    self.clock,
+                    &mut data_accumulator,
+                )?
+            }
+            (None, None) => unreachable!(),
+        };
         gpio_bitbanging.dac_run(
             &gpio_pins
                 .iter()
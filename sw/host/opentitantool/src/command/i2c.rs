@@ -2,19 +2,24 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Context, Result};
 use clap::{Args, Subcommand, ValueEnum};
 use serde_annotate::Annotate;
 use std::any::Any;
 use std::convert::From;
-use std::time::Duration;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use opentitanlib::app::command::CommandDispatch;
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::io::i2c::{self, DeviceStatus, I2cParams, Transfer};
+use opentitanlib::test_utils::i2c_emulator::{Emulator, RegisterMap, RegisterMapScript};
 use opentitanlib::tpm;
 use opentitanlib::transport::Capability;
+use opentitanlib::util::file;
 use opentitanlib::util::parse_int::ParseInt;
+use opentitanlib::util::raw_tty::RawTty;
 
 /// Read plain data bytes from a I2C device.
 #[derive(Debug, Args)]
@@ -107,6 +112,83 @@ impl CommandDispatch for I2cRawWriteRead {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ScanProbe {
+    /// Read a byte from addresses typically used by EEPROMs (0x30-0x37 and 0x50-0x5f), which may
+    /// react to writes, and write zero bytes to the others.
+    Auto,
+    /// Write zero bytes.
+    Write,
+    /// Read a single byte.
+    Read,
+}
+
+/// Probe the 7 bit addresses on the I2C bus, and list those acknowledged by a device.
+#[derive(Debug, Args)]
+pub struct I2cScan {
+    /// Transaction used to probe each address.
+    #[arg(long, value_enum, default_value = "auto")]
+    probe: ScanProbe,
+
+    /// First address to probe.
+    #[arg(long, default_value = "0x08", value_parser = u8::from_str)]
+    first: u8,
+
+    /// Last address to probe.
+    #[arg(long, default_value = "0x77", value_parser = u8::from_str)]
+    last: u8,
+}
+
+#[derive(Debug, serde::Serialize, Annotate)]
+pub struct I2cScanResponse {
+    #[annotate(format = hex)]
+    addresses: Vec<u8>,
+}
+
+impl I2cScan {
+    fn probe(&self, addr: u8) -> ScanProbe {
+        match self.probe {
+            ScanProbe::Auto if matches!(addr, 0x30..=0x37 | 0x50..=0x5f) => ScanProbe::Read,
+            ScanProbe::Auto => ScanProbe::Write,
+            probe => probe,
+        }
+    }
+}
+
+impl CommandDispatch for I2cScan {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::I2C).ok()?;
+        ensure!(
+            self.first <= self.last && self.last <= 0x7f,
+            "Invalid address range 0x{:02x}-0x{:02x}",
+            self.first,
+            self.last
+        );
+        let context = context.downcast_ref::<I2cCommand>().unwrap();
+        let i2c_bus = context.params.create(transport, "DEFAULT")?;
+        let mut addresses = Vec::new();
+        for addr in self.first..=self.last {
+            let mut byte = [0u8; 1];
+            let result = match self.probe(addr) {
+                ScanProbe::Read => {
+                    i2c_bus.run_transaction(Some(addr), &mut [Transfer::Read(&mut byte)])
+                }
+                _ => i2c_bus.run_transaction(Some(addr), &mut [Transfer::Write(&[])]),
+            };
+            // Transports do not tell a missing acknowledge from other errors.
+            match result {
+                Ok(()) => addresses.push(addr),
+                Err(e) => log::debug!("No device at 0x{:02x}: {}", addr, e),
+            }
+        }
+        Ok(Some(Box::new(I2cScanResponse { addresses })))
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
 pub enum Mode {
     Host,
@@ -189,22 +271,24 @@ pub struct I2cGetDeviceStatusResponse {
     pub read_status: ReadStatus,
 }
 
+impl From<i2c::DeviceTransfer> for DeviceTransfer {
+    fn from(t: i2c::DeviceTransfer) -> Self {
+        match t {
+            i2c::DeviceTransfer::Read { addr, timeout, len } => {
+                DeviceTransfer::Read { addr, timeout, len }
+            }
+            i2c::DeviceTransfer::Write { addr, data } => DeviceTransfer::Write {
+                addr,
+                hexdata: hex::encode(data),
+            },
+        }
+    }
+}
+
 impl From<DeviceStatus> for I2cGetDeviceStatusResponse {
     fn from(ds: DeviceStatus) -> Self {
-        let mut transfers = Vec::new();
-        for t in ds.transfers {
-            transfers.push(match t {
-                i2c::DeviceTransfer::Read { addr, timeout, len } => {
-                    DeviceTransfer::Read { addr, timeout, len }
-                }
-                i2c::DeviceTransfer::Write { addr, data } => DeviceTransfer::Write {
-                    addr,
-                    hexdata: hex::encode(data),
-                },
-            });
-        }
         Self {
-            transfers,
+            transfers: ds.transfers.into_iter().map(DeviceTransfer::from).collect(),
             read_status: match ds.read_status {
                 i2c::ReadStatus::WaitingForData(_) => ReadStatus::WaitingForData,
                 i2c::ReadStatus::Idle => ReadStatus::Idle,
@@ -257,6 +341,92 @@ impl CommandDispatch for I2cPrepareRead {
     }
 }
 
+/// Emulate an I2C device following a register map script, answering the reads of the I2C host
+/// and logging its writes, for the requested duration or until the user presses Ctrl-C.  The
+/// script is a JSON object such as `{"address_width": 1, "registers": {"0x10": "a5a6"}}`, see
+/// `RegisterMapScript` for all fields.
+#[derive(Debug, Args)]
+pub struct I2cEmulate {
+    /// 7 bit I2C address of the emulated device.
+    #[arg(
+        short,
+        long,
+        value_parser = u8::from_str
+    )]
+    addr: u8,
+
+    /// Register map script.
+    script: PathBuf,
+
+    /// For how long to emulate the device, by default until Ctrl-C.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    duration: Option<Duration>,
+
+    /// For how long to wait for the I2C host, before checking for Ctrl-C.
+    #[arg(long, default_value = "100ms", value_parser = humantime::parse_duration)]
+    poll: Duration,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct I2cEmulateResponse {
+    pub transfers: Vec<DeviceTransfer>,
+}
+
+impl CommandDispatch for I2cEmulate {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::I2C).ok()?;
+        let context = context.downcast_ref::<I2cCommand>().unwrap();
+        let i2c_bus = context.params.create(transport, "DEFAULT")?;
+        let script = std::fs::read_to_string(&self.script)
+            .with_context(|| format!("Failed to read {}", self.script.display()))?;
+        let mut device = RegisterMap::new(&RegisterMapScript::parse(&script)?)?;
+
+        let mut stdin = match self.duration {
+            Some(_) => None,
+            None => {
+                eprint!("[CTRL+C] to stop emulating  ");
+                // See `GpioMonitoringVcd` for why the terminal is put in raw mode.
+                Some(RawTty::new(std::io::stdin())?)
+            }
+        };
+        i2c_bus.set_mode(i2c::Mode::Device(self.addr))?;
+        let mut emulator = Emulator::new(&*i2c_bus, &mut device);
+        let mut transfers = Vec::new();
+        let start = Instant::now();
+        let result = loop {
+            match emulator.poll(self.poll) {
+                Ok(polled) => transfers.extend(polled.into_iter().map(DeviceTransfer::from)),
+                Err(e) => break Err(e),
+            }
+            if let Some(duration) = self.duration {
+                if start.elapsed() >= duration {
+                    break Ok(());
+                }
+            }
+            if let Some(stdin) = stdin.as_mut() {
+                if file::wait_read_timeout(&*stdin, Duration::from_millis(0)).is_ok() {
+                    let mut buf = [0u8; 1];
+                    if stdin.read(&mut buf)? == 1 && buf[0] == 3 {
+                        // CtrlC
+                        break Ok(());
+                    }
+                }
+            }
+        };
+        if stdin.is_some() {
+            eprintln!("\r");
+        }
+        // Stop answering as a device, even if the emulation failed.
+        i2c_bus.set_mode(i2c::Mode::Host)?;
+        result?;
+        Ok(Some(Box::new(I2cEmulateResponse { transfers })))
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct I2cTpm {
     #[command(subcommand)]
@@ -292,8 +462,10 @@ pub enum InternalI2cCommand {
     RawRead(I2cRawRead),
     RawWrite(I2cRawWrite),
     RawWriteRead(I2cRawWriteRead),
+    Scan(I2cScan),
     GetDeviceStatus(I2cGetDeviceStatus),
     PrepareRead(I2cPrepareRead),
+    Emulate(I2cEmulate),
     SetMode(I2cSetMode),
     Tpm(I2cTpm),
 }
@@ -17,6 +17,7 @@ pub mod lc;
 pub mod load_bitstream;
 pub mod otp;
 pub mod ownership;
+pub mod perso;
 pub mod rescue;
 pub mod rsa;
 pub mod sam3x;
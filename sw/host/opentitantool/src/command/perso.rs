@@ -0,0 +1,113 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use num_bigint_dig::BigUint;
+use serde_annotate::Annotate;
+use std::any::Any;
+use std::fs;
+use std::path::PathBuf;
+
+use opentitanlib::app::command::CommandDispatch;
+use opentitanlib::app::TransportWrapper;
+use ot_certs::template::{Certificate, EcdsaSignature, Signature, Value};
+use ot_certs::x509;
+use perso_tlv_lib::PersoObject;
+
+/// Parses a certificate or, for a TBS certificate, parses it as if it had
+/// been signed.
+fn parse_object_certificate(object: &PersoObject) -> Result<Option<Certificate>> {
+    match object {
+        PersoObject::EndorsedX509Cert { cert, .. } => Ok(Some(x509::parse_certificate(cert)?)),
+        PersoObject::UnendorsedX509Cert { tbs, .. } => {
+            let placeholder = Signature::EcdsaWithSha256 {
+                value: Some(EcdsaSignature {
+                    r: Value::Literal(BigUint::from(1u32)),
+                    s: Value::Literal(BigUint::from(1u32)),
+                }),
+            };
+            let cert = x509::generate_certificate_from_tbs(tbs.clone(), &placeholder)?;
+            let mut cert = x509::parse_certificate(&cert)?;
+            cert.signature = Signature::EcdsaWithSha256 { value: None };
+            Ok(Some(cert))
+        }
+        PersoObject::DevSeed(_) => Ok(None),
+    }
+}
+
+#[derive(serde::Serialize, Annotate)]
+pub struct PersoObjectInfo {
+    #[annotate(format = hex)]
+    pub offset: usize,
+    pub kind: String,
+    pub name: Option<String>,
+    #[serde(with = "serde_bytes")]
+    #[annotate(format = hexstr)]
+    pub payload: Vec<u8>,
+    pub certificate: Option<Certificate>,
+    #[annotate(comment = "Set if the certificate could not be parsed")]
+    pub error: Option<String>,
+}
+
+#[derive(serde::Serialize, Annotate)]
+pub struct PersoDumpResult {
+    pub objects: Vec<PersoObjectInfo>,
+    #[annotate(comment = "Set if the blob contains a malformed object")]
+    pub error: Option<String>,
+}
+
+/// Decode and print the objects of a personalization blob.
+#[derive(Debug, Args)]
+pub struct PersoDump {
+    /// Filename of the raw perso blob body.  Trailing zeros (the free space of
+    /// the blob) are ignored.
+    blob: PathBuf,
+}
+
+impl CommandDispatch for PersoDump {
+    fn run(
+        &self,
+        _context: &dyn Any,
+        _transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        let data = fs::read(&self.blob)
+            .with_context(|| format!("Could not read the perso blob {}", self.blob.display()))?;
+        let mut result = PersoDumpResult {
+            objects: Vec::new(),
+            error: None,
+        };
+        let mut objects = perso_tlv_lib::objects(&data);
+        while objects.remainder().iter().any(|&b| b != 0) {
+            let offset = objects.offset();
+            let object = match objects.next() {
+                Some(Ok(object)) => object,
+                Some(Err(e)) => {
+                    result.error = Some(format!("{e:#}"));
+                    break;
+                }
+                None => break,
+            };
+            let (certificate, error) = match parse_object_certificate(&object) {
+                Ok(cert) => (cert, None),
+                Err(e) => (None, Some(format!("{e:#}"))),
+            };
+            result.objects.push(PersoObjectInfo {
+                offset,
+                kind: format!("{:?}", object.obj_type()),
+                name: object.name().map(str::to_string),
+                payload: object.payload().to_vec(),
+                certificate,
+                error,
+            });
+        }
+        Ok(Some(Box::new(result)))
+    }
+}
+
+/// Commands for inspecting personalization data.
+#[derive(Debug, Subcommand, CommandDispatch)]
+pub enum PersoCommand {
+    Dump(PersoDump),
+}
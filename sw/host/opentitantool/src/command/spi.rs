@@ -2,7 +2,7 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use clap::{Args, Subcommand};
 use serde_annotate::Annotate;
 use std::any::Any;
@@ -14,8 +14,11 @@ use std::time::Duration;
 use opentitanlib::app::command::CommandDispatch;
 use opentitanlib::app::{StagedProgressBar, TransportWrapper};
 use opentitanlib::io::eeprom::{AddressMode, Transaction, MODE_111};
-use opentitanlib::io::spi::{SpiParams, Transfer};
-use opentitanlib::spiflash::{EraseMode, ReadMode, SpiFlash};
+use opentitanlib::io::spi::{SpiParams, Target, Transfer};
+use opentitanlib::spiflash::{
+    EraseMode, FlashLayout, FlashRegion, ProtectedRange, ProtectionScheme, ReadMode, SpiFlash,
+    StatusRegisterMap,
+};
 use opentitanlib::tpm;
 use opentitanlib::transport::Capability;
 use opentitanlib::transport::ProgressIndicator;
@@ -89,6 +92,83 @@ impl CommandDispatch for SpiReadId {
     }
 }
 
+/// Selects a region of the flash by name.
+#[derive(Debug, Args)]
+pub struct LayoutParams {
+    /// Flash layout file, in JSON or HJSON format.
+    #[arg(long, value_parser = FlashLayout::load)]
+    layout: Option<FlashLayout>,
+    /// Region of the flash layout, which is read from the FMAP of the flash when `--layout` is
+    /// not given.
+    #[arg(long, conflicts_with = "start")]
+    region: Option<String>,
+}
+
+impl LayoutParams {
+    /// Returns the layout file, or the layout read from the FMAP of the `flash`.
+    fn layout(&self, flash: &SpiFlash, spi: &dyn Target) -> Result<FlashLayout> {
+        match &self.layout {
+            Some(layout) => {
+                layout.validate(Some(flash.size))?;
+                Ok(layout.clone())
+            }
+            None => FlashLayout::read_fmap(flash, spi),
+        }
+    }
+
+    /// Returns the selected region, if any.
+    fn region(&self, flash: &SpiFlash, spi: &dyn Target) -> Result<Option<FlashRegion>> {
+        match &self.region {
+            Some(name) => Ok(Some(self.layout(flash, spi)?.region(name)?.clone())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the offset at which to write `buffer`: that of the selected region, checking that
+    /// `buffer` fits in it, or `start` if no region is selected.
+    fn start(&self, flash: &SpiFlash, spi: &dyn Target, start: u32, buffer: &[u8]) -> Result<u32> {
+        let region = match self.region(flash, spi)? {
+            Some(region) => region,
+            None => return Ok(start),
+        };
+        ensure!(
+            buffer.len() <= region.size as usize,
+            "File of {} bytes does not fit in region {:?} of {} bytes",
+            buffer.len(),
+            region.name,
+            region.size
+        );
+        Ok(region.offset)
+    }
+}
+
+/// Show the layout of a SPI EEPROM.
+#[derive(Debug, Args)]
+pub struct SpiLayout {
+    /// Flash layout file to check against the flash, instead of reading its FMAP.
+    #[arg(long, value_parser = FlashLayout::load)]
+    layout: Option<FlashLayout>,
+}
+
+impl CommandDispatch for SpiLayout {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::SPI).ok()?;
+        let context = context.downcast_ref::<SpiCommand>().unwrap();
+        let spi = context.params.create(transport, "BOOTSTRAP")?;
+        let mut flash = SpiFlash::from_spi(&*spi)?;
+        flash.set_address_mode_auto(&*spi)?;
+        let params = LayoutParams {
+            layout: self.layout.clone(),
+            region: None,
+        };
+        Ok(Some(Box::new(params.layout(&flash, &*spi)?)))
+    }
+}
+
 /// Read data from a SPI EEPROM.
 #[derive(Debug, Args)]
 pub struct SpiRead {
@@ -96,8 +176,10 @@ pub struct SpiRead {
     #[arg(short, long, default_value = "0")]
     start: u32,
     /// Number of bytes to read.
-    #[arg(short = 'n', long, default_value = "4096")]
+    #[arg(short = 'n', long, default_value = "4096", conflicts_with = "region")]
     length: usize,
+    #[command(flatten)]
+    layout: LayoutParams,
     /// Read mode.
     #[arg(
         short,
@@ -146,15 +228,13 @@ impl CommandDispatch for SpiRead {
         flash.set_address_mode_auto(&*spi)?;
         flash.read_mode = self.mode;
 
-        let mut buffer = vec![0u8; self.length];
+        let (start, length) = match self.layout.region(&flash, &*spi)? {
+            Some(region) => (region.offset, region.size as usize),
+            None => (self.start, self.length),
+        };
+        let mut buffer = vec![0u8; length];
         let progress = StagedProgressBar::new();
-        flash.read_with_progress(
-            &*spi,
-            self.start,
-            &mut buffer,
-            &progress,
-            self.use_4b_opcodes,
-        )?;
+        flash.read_with_progress(&*spi, start, &mut buffer, &progress, self.use_4b_opcodes)?;
 
         if self.filename.to_str() == Some("-") {
             self.write_file(io::stdout(), &buffer)?;
@@ -244,6 +324,8 @@ pub struct SpiProgram {
     /// Start offset.
     #[arg(short, long, default_value = "0")]
     start: u32,
+    #[command(flatten)]
+    layout: LayoutParams,
     #[arg(value_name = "FILE")]
     filename: PathBuf,
 }
@@ -267,8 +349,9 @@ impl CommandDispatch for SpiProgram {
         flash.set_address_mode_auto(&*spi)?;
 
         let buffer = fs::read(&self.filename)?;
+        let start = self.layout.start(&flash, &*spi, self.start, &buffer)?;
         let progress = StagedProgressBar::new();
-        flash.program_with_progress(&*spi, self.start, &buffer, &progress)?;
+        flash.program_with_progress(&*spi, start, &buffer, &progress)?;
 
         Ok(Some(Box::new(SpiProgramResponse {
             length: buffer.len(),
@@ -277,6 +360,63 @@ impl CommandDispatch for SpiProgram {
     }
 }
 
+/// Program data into a SPI EEPROM, erasing and programming only the sectors whose contents
+/// differ, then verifying them.
+#[derive(Debug, Args)]
+pub struct SpiSync {
+    /// Start offset.
+    #[arg(short, long, default_value = "0")]
+    start: u32,
+    #[command(flatten)]
+    layout: LayoutParams,
+    /// Erase mode.
+    #[arg(
+        short,
+        long,
+        value_enum,
+        ignore_case = true,
+        default_value = "standard"
+    )]
+    pub mode: EraseMode,
+    #[arg(value_name = "FILE")]
+    filename: PathBuf,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SpiSyncResponse {
+    length: usize,
+    sectors: usize,
+    changed_sectors: usize,
+    erased_sectors: usize,
+}
+
+impl CommandDispatch for SpiSync {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::SPI).ok()?;
+        let context = context.downcast_ref::<SpiCommand>().unwrap();
+        let spi = context.params.create(transport, "BOOTSTRAP")?;
+        let mut flash = SpiFlash::from_spi(&*spi)?;
+        flash.set_address_mode_auto(&*spi)?;
+        flash.erase_mode = self.mode;
+
+        let buffer = fs::read(&self.filename)?;
+        let start = self.layout.start(&flash, &*spi, self.start, &buffer)?;
+        let progress = StagedProgressBar::new();
+        let summary = flash.sync_with_progress(&*spi, start, &buffer, &progress)?;
+
+        Ok(Some(Box::new(SpiSyncResponse {
+            length: buffer.len(),
+            sectors: summary.sectors,
+            changed_sectors: summary.changed,
+            erased_sectors: summary.erased,
+        })))
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct SpiTpm {
     #[command(subcommand)]
@@ -306,6 +446,227 @@ impl CommandDispatch for SpiTpm {
     }
 }
 
+/// Read the range protected by the block protection bits of a SPI EEPROM.
+#[derive(Debug, Args)]
+pub struct SpiGetProtection {}
+
+#[derive(Debug, serde::Serialize, Annotate)]
+pub struct SpiProtectionResponse {
+    #[annotate(format = hex)]
+    status: u16,
+    status_register_map: StatusRegisterMap,
+    protected: ProtectedRange,
+}
+
+impl SpiProtectionResponse {
+    fn read(flash: &SpiFlash, spi: &dyn Target, scheme: &ProtectionScheme) -> Result<Self> {
+        Ok(SpiProtectionResponse {
+            status: flash.read_status_registers(spi)?,
+            status_register_map: flash.status_register_map(),
+            protected: flash.protection(spi, scheme)?,
+        })
+    }
+}
+
+impl CommandDispatch for SpiGetProtection {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::SPI).ok()?;
+        let context = context.downcast_ref::<SpiCommand>().unwrap();
+        let spi = context.params.create(transport, "BOOTSTRAP")?;
+        let flash = SpiFlash::from_spi(&*spi)?;
+        let scheme = flash.protection_scheme(&*spi)?;
+        Ok(Some(Box::new(SpiProtectionResponse::read(
+            &flash, &*spi, &scheme,
+        )?)))
+    }
+}
+
+/// Set the range protected by the block protection bits of a SPI EEPROM.
+#[derive(Debug, Args)]
+pub struct SpiSetProtection {
+    /// Start offset of the protected range.
+    #[arg(short, long, default_value = "0")]
+    start: u32,
+    /// Length of the protected range, zero to remove the protection.
+    #[arg(short = 'n', long)]
+    length: u32,
+}
+
+impl CommandDispatch for SpiSetProtection {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::SPI).ok()?;
+        let context = context.downcast_ref::<SpiCommand>().unwrap();
+        let spi = context.params.create(transport, "BOOTSTRAP")?;
+        let flash = SpiFlash::from_spi(&*spi)?;
+        let scheme = flash.protection_scheme(&*spi)?;
+        let range = ProtectedRange {
+            start: self.start,
+            length: self.length,
+        };
+        flash.set_protection(&*spi, &scheme, range)?;
+        Ok(Some(Box::new(SpiProtectionResponse::read(
+            &flash, &*spi, &scheme,
+        )?)))
+    }
+}
+
+/// Read a security register of a SPI EEPROM.
+#[derive(Debug, Args)]
+pub struct SpiSecurityRegisterRead {
+    /// Security register number, starting at 1.
+    index: u8,
+    /// Offset within the register.
+    #[arg(short, long, default_value = "0")]
+    offset: u32,
+    /// Number of bytes to read.
+    #[arg(short = 'n', long, default_value = "256")]
+    length: usize,
+}
+
+#[derive(Debug, serde::Serialize, Annotate)]
+pub struct SpiSecurityRegisterReadResponse {
+    #[annotate(format = hex)]
+    data: Vec<u8>,
+}
+
+impl CommandDispatch for SpiSecurityRegisterRead {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::SPI).ok()?;
+        let context = context.downcast_ref::<SpiCommand>().unwrap();
+        let spi = context.params.create(transport, "BOOTSTRAP")?;
+        let mut flash = SpiFlash::from_spi(&*spi)?;
+        flash.set_address_mode_auto(&*spi)?;
+        let mut data = vec![0u8; self.length];
+        flash.read_security_register(&*spi, self.index, self.offset, &mut data)?;
+        Ok(Some(Box::new(SpiSecurityRegisterReadResponse { data })))
+    }
+}
+
+/// Program a security register of a SPI EEPROM, without erasing it first.
+#[derive(Debug, Args)]
+pub struct SpiSecurityRegisterProgram {
+    /// Security register number, starting at 1.
+    index: u8,
+    /// Offset within the register.
+    #[arg(short, long, default_value = "0")]
+    offset: u32,
+    #[arg(value_name = "FILE")]
+    filename: PathBuf,
+}
+
+impl CommandDispatch for SpiSecurityRegisterProgram {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::SPI).ok()?;
+        let context = context.downcast_ref::<SpiCommand>().unwrap();
+        let spi = context.params.create(transport, "BOOTSTRAP")?;
+        let mut flash = SpiFlash::from_spi(&*spi)?;
+        flash.set_address_mode_auto(&*spi)?;
+        let buffer = fs::read(&self.filename)?;
+        flash.program_security_register(&*spi, self.index, self.offset, &buffer)?;
+        Ok(None)
+    }
+}
+
+/// Erase a security register of a SPI EEPROM.
+#[derive(Debug, Args)]
+pub struct SpiSecurityRegisterErase {
+    /// Security register number, starting at 1.
+    index: u8,
+}
+
+impl CommandDispatch for SpiSecurityRegisterErase {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::SPI).ok()?;
+        let context = context.downcast_ref::<SpiCommand>().unwrap();
+        let spi = context.params.create(transport, "BOOTSTRAP")?;
+        let mut flash = SpiFlash::from_spi(&*spi)?;
+        flash.set_address_mode_auto(&*spi)?;
+        flash.erase_security_register(&*spi, self.index)?;
+        Ok(None)
+    }
+}
+
+/// Permanently lock a security register of a SPI EEPROM.  This cannot be undone.
+#[derive(Debug, Args)]
+pub struct SpiSecurityRegisterLock {
+    /// Security register number, starting at 1.
+    index: u8,
+}
+
+impl CommandDispatch for SpiSecurityRegisterLock {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::SPI).ok()?;
+        let context = context.downcast_ref::<SpiCommand>().unwrap();
+        let spi = context.params.create(transport, "BOOTSTRAP")?;
+        let flash = SpiFlash::from_spi(&*spi)?;
+        flash.lock_security_register(&*spi, self.index)?;
+        Ok(None)
+    }
+}
+
+/// Commands for accessing the security registers of a SPI EEPROM.
+#[derive(Debug, Subcommand, CommandDispatch)]
+pub enum SpiSecurityRegister {
+    Read(SpiSecurityRegisterRead),
+    Program(SpiSecurityRegisterProgram),
+    Erase(SpiSecurityRegisterErase),
+    Lock(SpiSecurityRegisterLock),
+}
+
+/// Read the factory programmed unique ID of a SPI EEPROM.
+#[derive(Debug, Args)]
+pub struct SpiReadUniqueId {
+    /// Number of unique ID bytes to read.
+    #[arg(short = 'n', long, default_value = "8")]
+    length: usize,
+}
+
+#[derive(Debug, serde::Serialize, Annotate)]
+pub struct SpiReadUniqueIdResponse {
+    #[annotate(format = hex)]
+    unique_id: Vec<u8>,
+}
+
+impl CommandDispatch for SpiReadUniqueId {
+    fn run(
+        &self,
+        context: &dyn Any,
+        transport: &TransportWrapper,
+    ) -> Result<Option<Box<dyn Annotate>>> {
+        transport.capabilities()?.request(Capability::SPI).ok()?;
+        let context = context.downcast_ref::<SpiCommand>().unwrap();
+        let spi = context.params.create(transport, "BOOTSTRAP")?;
+        let mut flash = SpiFlash::from_spi(&*spi)?;
+        flash.set_address_mode_auto(&*spi)?;
+        let unique_id = flash.read_unique_id(&*spi, self.length)?;
+        Ok(Some(Box::new(SpiReadUniqueIdResponse { unique_id })))
+    }
+}
+
 /// Read plain data bytes from a SPI device (not necessarily SPI EEPROM/flash).
 #[derive(Debug, Args)]
 pub struct SpiRawRead {
@@ -424,6 +785,13 @@ pub enum InternalSpiCommand {
     Read(SpiRead),
     Erase(SpiErase),
     Program(SpiProgram),
+    Sync(SpiSync),
+    Layout(SpiLayout),
+    GetProtection(SpiGetProtection),
+    SetProtection(SpiSetProtection),
+    #[command(subcommand)]
+    SecurityRegister(SpiSecurityRegister),
+    ReadUniqueId(SpiReadUniqueId),
     RawRead(SpiRawRead),
     RawWrite(SpiRawWrite),
     RawWriteRead(SpiRawWriteRead),
@@ -52,6 +52,8 @@ enum RootCommandHierarchy {
     #[command(subcommand)]
     Ownership(command::ownership::OwnershipCommand),
     #[command(subcommand)]
+    Perso(command::perso::PersoCommand),
+    #[command(subcommand)]
     Rescue(command::rescue::RescueCommand),
     #[command(subcommand)]
     Rsa(command::rsa::Rsa),
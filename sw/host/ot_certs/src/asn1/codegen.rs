@@ -11,6 +11,8 @@ use crate::asn1::builder::Builder;
 use crate::asn1::{Oid, Tag};
 use crate::template::{Conversion, Value, Variable, VariableType};
 
+pub mod rust;
+
 struct ConstantEntry {
     var_name: String,
     c_decl: String,
@@ -41,6 +43,18 @@ impl ConstantPool {
         self.constants.get(data).map(|ent| ent.var_name.clone())
     }
 
+    /// Return a variable name based on `name` that no constant uses yet.
+    pub fn unique_var_name(&self, name: String) -> String {
+        let in_use = |name: &str| self.constants.values().any(|ent| ent.var_name == name);
+        if !in_use(&name) {
+            return name;
+        }
+        (1..)
+            .map(|idx| format!("{name}_{idx}"))
+            .find(|name| !in_use(name))
+            .unwrap()
+    }
+
     pub fn add_entry(&mut self, data: Vec<u8>, var_name: String, c_decl: String) {
         self.constants
             .insert(data, ConstantEntry { var_name, c_decl });
@@ -168,12 +182,14 @@ impl Codegen<'_> {
             return name;
         }
 
-        let const_name = format!(
+        // Different constants can have the same name hint, for example the
+        // digests of several DICE TCB infos.
+        let const_name = self.constants.unique_var_name(format!(
             "kConstant{}",
             name_hint
                 .map(|x| x.to_upper_camel_case())
                 .unwrap_or("".into())
-        );
+        ));
         let bytes = data
             .iter()
             .map(|b| format!("{:#04x}", b))
@@ -269,6 +285,7 @@ impl Tag {
             Tag::Boolean => "kAsn1TagNumberBoolean".into(),
             Tag::Integer => "kAsn1TagNumberInteger".into(),
             Tag::GeneralizedTime => "kAsn1TagNumberGeneralizedTime".into(),
+            Tag::Ia5String => "kAsn1TagNumberIa5String".into(),
             Tag::PrintableString => "kAsn1TagNumberPrintableString".into(),
             Tag::Utf8String => "kAsn1TagNumberUtf8String".into(),
             Tag::Sequence => "kAsn1TagNumberSequence".into(),
@@ -429,6 +446,10 @@ impl Builder for Codegen<'_> {
         match val {
             Value::Literal(x) => {
                 let data = &x.to_bytes_be();
+                ensure!(
+                    data.len() <= size,
+                    "integer {x:#x} does not fit in {size} bytes"
+                );
                 let data = [vec![0; size - data.len()], data.clone()].concat();
                 let const_name = self.add_constant_byte_array(name_hint, &data);
                 self.push_str_with_indent(&format!("RETURN_IF_ERROR(asn1_push_bytes(&state, {const_name}, sizeof({const_name})));\n"));
@@ -647,3 +668,50 @@ impl Builder for Codegen<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_variables(name: &str) -> Result<VariableInfo> {
+        bail!("unexpected variable {name}")
+    }
+
+    #[test]
+    fn integer_pad_literal() -> Result<()> {
+        let value = Value::Literal(BigUint::from(0x1234u32));
+        let mut constants = ConstantPool::new();
+        let (_, size) = Codegen::generate(
+            "buf",
+            "size",
+            "  ",
+            0,
+            &mut constants,
+            &no_variables,
+            |builder| builder.push_integer_pad(None, &value, 4),
+        )?;
+        assert_eq!(size, 4);
+        assert!(Codegen::generate(
+            "buf",
+            "size",
+            "  ",
+            0,
+            &mut constants,
+            &no_variables,
+            |builder| { builder.push_integer_pad(None, &value, 1) }
+        )
+        .is_err());
+
+        let (_, size) = rust::Codegen::generate("buf", "    ", 0, &no_variables, |builder| {
+            builder.push_integer_pad(None, &value, 4)
+        })?;
+        assert_eq!(size, 4);
+        assert!(
+            rust::Codegen::generate("buf", "    ", 0, &no_variables, |builder| {
+                builder.push_integer_pad(None, &value, 1)
+            })
+            .is_err()
+        );
+        Ok(())
+    }
+}
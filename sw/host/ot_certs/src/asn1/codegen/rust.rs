@@ -0,0 +1,456 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module provides an implementation of the `Builder` trait that produces
+//! Rust code. The generated code uses the DER writer of
+//! [`crate::codegen::rust`] and refers to variables through the expressions
+//! given by the variable information.
+
+use anyhow::{bail, ensure, Result};
+use heck::ToSnakeCase;
+use num_bigint_dig::BigUint;
+
+use crate::asn1::builder::Builder;
+use crate::asn1::codegen::{Codegen as CCodegen, VariableCodegenInfo, VariableInfo};
+use crate::asn1::{Oid, Tag};
+use crate::template::{Conversion, Value, Variable, VariableType};
+
+/// ASN1 Rust code generator.
+pub struct Codegen<'a> {
+    /// Output buffer.
+    output: String,
+    /// Indentation string.
+    indent: String,
+    /// Current indentation level.
+    indent_lvl: usize,
+    /// Variable types: return information about a variable by name.
+    variable_info: &'a dyn Fn(&str) -> Result<VariableInfo>,
+    /// Index of next tag (to guarantee unique names).
+    tag_idx: usize,
+    /// Maximum size of the output.
+    max_out_size: usize,
+}
+
+/// Return a Rust expression for a byte slice literal.
+pub fn bytes_literal(data: &[u8]) -> String {
+    format!(
+        "&[{}]",
+        data.iter()
+            .map(|b| format!("{:#04x}", b))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+impl Codegen<'_> {
+    /// Generate code that corresponds to an ASN1 document described by a closure acting on a Builder.
+    /// Returns the generated code and the maximum possible size of the output.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_name` - Name of the variable holding the output buffer slice.
+    /// * `indent` - Identation string (one level).
+    /// * `indent_lvl` - Initial identation level.
+    /// * `variables` - Description of the variable types used when producing the output.
+    /// * `gen` - Closure generating the ASN1 document.
+    ///
+    /// The generated code evaluates to the size of the output.
+    pub fn generate(
+        buf_name: &str,
+        indent: &str,
+        indent_lvl: usize,
+        variable_info: &dyn Fn(&str) -> Result<VariableInfo>,
+        gen: impl FnOnce(&mut Codegen) -> Result<()>,
+    ) -> Result<(String, usize)> {
+        let mut builder = Codegen {
+            output: String::new(),
+            indent: indent.to_string(),
+            indent_lvl,
+            variable_info,
+            tag_idx: 0,
+            max_out_size: 0,
+        };
+        builder.push_str_with_indent(&format!("let mut w = asn1::Writer::new({buf_name});\n"));
+        gen(&mut builder)?;
+        builder.push_str_with_indent("Ok(w.finish())\n");
+        Ok((builder.output, builder.max_out_size))
+    }
+
+    /// Push indentation into the output buffer.
+    fn push_indent(&mut self) {
+        let indent = self.indent.repeat(self.indent_lvl);
+        self.output.push_str(&indent);
+    }
+
+    /// Push raw string with indentation into the output buffer.
+    fn push_str_with_indent(&mut self, s: &str) {
+        self.push_indent();
+        self.output.push_str(s);
+    }
+
+    /// Push a bit in a bitstring.
+    fn push_bit(&mut self, bitstring_name: &str, val: &Value<bool>) -> Result<()> {
+        match val {
+            Value::Literal(x) => {
+                self.push_str_with_indent(&format!("w.push_bit(&mut {bitstring_name}, {x})?;\n"));
+            }
+            Value::Variable(Variable { name, convert }) => {
+                let VariableInfo {
+                    codegen,
+                    var_type: source_type,
+                } = (self.variable_info)(name)?;
+                match source_type {
+                    VariableType::Boolean => {
+                        ensure!(
+                            convert.is_none(),
+                            "cannot use a convertion from boolean to boolean"
+                        );
+                        let VariableCodegenInfo::Boolean { value_expr } = codegen else {
+                            bail!("internal error: boolean not represented by a VariableCodegenInfo::Boolean");
+                        };
+                        self.push_str_with_indent(&format!(
+                            "w.push_bit(&mut {bitstring_name}, {value_expr})?;\n"
+                        ));
+                    }
+                    _ => bail!(
+                        "conversion from to {:?} to boolean is not supported",
+                        source_type
+                    ),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Tag {
+    // Return the Rust expression of the identifier octet of a tag.
+    fn rust_codestring(&self) -> String {
+        match self {
+            Tag::Oid => "tag::OID".into(),
+            Tag::Boolean => "tag::BOOLEAN".into(),
+            Tag::Integer => "tag::INTEGER".into(),
+            Tag::GeneralizedTime => "tag::GENERALIZED_TIME".into(),
+            Tag::Ia5String => "tag::IA5_STRING".into(),
+            Tag::PrintableString => "tag::PRINTABLE_STRING".into(),
+            Tag::Utf8String => "tag::UTF8_STRING".into(),
+            Tag::Sequence => "tag::SEQUENCE".into(),
+            Tag::Set => "tag::SET".into(),
+            Tag::OctetString => "tag::OCTET_STRING".into(),
+            Tag::BitString => "tag::BIT_STRING".into(),
+            &Tag::Context { constructed, value } => {
+                let mut tag = String::from("tag::CLASS_CONTEXT");
+                if constructed {
+                    tag.push_str(" | tag::FORM_CONSTRUCTED");
+                }
+                // Avoid a no-op `| 0` in the generated code.
+                if value != 0 {
+                    tag.push_str(&format!(" | {value}"));
+                }
+                tag
+            }
+        }
+    }
+}
+
+impl Builder for Codegen<'_> {
+    /// Push a byte into the ASN1 output.
+    fn push_byte(&mut self, val: u8) -> Result<()> {
+        self.push_str_with_indent(&format!("w.push_byte({val:#04x})?;\n"));
+        self.max_out_size += 1;
+        Ok(())
+    }
+
+    /// Push a tagged boolean into the ASN1 output.
+    fn push_boolean(&mut self, tag: &Tag, val: &Value<bool>) -> Result<()> {
+        let value_expr = match val {
+            Value::Literal(x) => x.to_string(),
+            Value::Variable(Variable { name, convert }) => {
+                let VariableInfo {
+                    codegen,
+                    var_type: source_type,
+                } = (self.variable_info)(name)?;
+                match source_type {
+                    VariableType::Boolean =>
+                        ensure!(convert.is_none(), "using an boolean variable for an boolean field cannot specify a conversion"),
+                    _ => bail!(
+                        "using a variable of type {source_type:?} for a boolean field is not supported"
+                        ),
+                }
+                let VariableCodegenInfo::Boolean { value_expr } = codegen else {
+                    bail!("internal error: boolean represented by a {source_type:?}");
+                };
+                value_expr
+            }
+        };
+        self.push_str_with_indent(&format!(
+            "w.push_bool({}, {value_expr})?;\n",
+            tag.rust_codestring()
+        ));
+        // A boolean only requires one byte of data (plus the tag).
+        self.max_out_size += CCodegen::tag_and_content_size(1);
+        Ok(())
+    }
+
+    /// Push a tagged integer into the ASN1 output.
+    fn push_integer(
+        &mut self,
+        _name_hint: Option<String>,
+        tag: &Tag,
+        val: &Value<BigUint>,
+    ) -> Result<()> {
+        let tag = tag.rust_codestring();
+        match val {
+            // See the C generator for the size estimates: an unsigned integer may need
+            // one more byte of padding.
+            Value::Literal(x) => {
+                if x.bits() <= 32 {
+                    self.push_str_with_indent(&format!("w.push_uint32({tag}, {x})?;\n"));
+                    self.max_out_size += CCodegen::tag_and_content_size(1 + (x.bits() + 7) / 8);
+                } else {
+                    let bytes = x.to_bytes_be();
+                    self.push_str_with_indent(&format!(
+                        "w.push_integer({tag}, {})?;\n",
+                        bytes_literal(&bytes)
+                    ));
+                    self.max_out_size += CCodegen::tag_and_content_size(1 + bytes.len());
+                }
+            }
+            Value::Variable(Variable { name, convert }) => {
+                let VariableInfo {
+                    codegen,
+                    var_type: source_type,
+                } = (self.variable_info)(name)?;
+                let size = match source_type {
+                    VariableType::Integer { size } => {
+                        ensure!(convert.is_none(), "using an integer variable for an integer field cannot specify a conversion");
+                        size
+                    }
+                    VariableType::ByteArray { size } => {
+                        match convert {
+                            None => bail!("using a byte array variable for an integer field must specify a conversion"),
+                            Some(Conversion::BigEndian) => (),
+                            _ => bail!("conversion {:?} from byte array to integer is not supported", convert),
+                        }
+                        size
+                    }
+                    _ => bail!(
+                        "using a variable of type {source_type:?} for an integer field is not supported"
+                        ),
+                };
+                self.max_out_size += CCodegen::tag_and_content_size(1 + size);
+                match codegen {
+                    VariableCodegenInfo::Int32 { value_expr } => self
+                        .push_str_with_indent(&format!("w.push_uint32({tag}, {value_expr})?;\n")),
+                    VariableCodegenInfo::Pointer { ptr_expr, .. } => {
+                        self.push_str_with_indent(&format!("w.push_integer({tag}, {ptr_expr})?;\n"))
+                    }
+                    _ => bail!("internal error: integer represented by a {source_type:?}"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Push a byte array into the ASN1 output, represeting an integer. If the provided buffer is too small,
+    /// it will be padded with zeroes. Note that this function does not add a tag to the ASN1 output.
+    fn push_integer_pad(
+        &mut self,
+        _name_hint: Option<String>,
+        val: &Value<BigUint>,
+        size: usize,
+    ) -> Result<()> {
+        match val {
+            Value::Literal(x) => {
+                let data = &x.to_bytes_be();
+                ensure!(
+                    data.len() <= size,
+                    "integer {x:#x} does not fit in {size} bytes"
+                );
+                let data = [vec![0; size - data.len()], data.clone()].concat();
+                self.push_str_with_indent(&format!("w.push_bytes({})?;\n", bytes_literal(&data)));
+                // There is not tag, we are just pushing the data itself.
+                self.max_out_size += data.len();
+            }
+            Value::Variable(Variable { name, convert }) => {
+                let VariableInfo {
+                    codegen,
+                    var_type: source_type,
+                } = (self.variable_info)(name)?;
+                match source_type {
+                    VariableType::Integer { size } => {
+                        ensure!(convert.is_none(), "using an integer variable for an integer field cannot specify a conversion");
+                        let VariableCodegenInfo::Pointer { ptr_expr, .. } = codegen else {
+                            bail!("the codegen backend does not support small integers for padded integer fields");
+                        };
+                        // There is not tag, we are just pushing the data itself.
+                        self.max_out_size += size;
+                        self.push_str_with_indent(&format!(
+                            "w.push_integer_pad({ptr_expr}, {size})?;\n"
+                        ))
+                    }
+                    _ => bail!(
+                        "using a variable of type {source_type:?} for a padded integer field is not supported"
+                    ),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Push a byte array of fixed length into the ASN1 output. Note that this function does not add a tag to
+    /// the ASN1 output.
+    fn push_byte_array(&mut self, _name_hint: Option<String>, val: &Value<Vec<u8>>) -> Result<()> {
+        match val {
+            Value::Literal(x) => {
+                self.push_str_with_indent(&format!("w.push_bytes({})?;\n", bytes_literal(x)));
+                // There is not tag, we are just pushing the data itself.
+                self.max_out_size += x.len();
+            }
+            Value::Variable(Variable { name, convert }) => {
+                let VariableInfo {
+                    codegen,
+                    var_type: source_type,
+                } = (self.variable_info)(name)?;
+                match source_type {
+                    VariableType::ByteArray { size } => {
+                        ensure!(convert.is_none(), "using a byte-array variable for a byte-array field cannot specify a conversion");
+                        let VariableCodegenInfo::Pointer { ptr_expr, .. } = codegen else {
+                            bail!("internal error: byte-array represented by a VariableCodegenInfo::Int32");
+                        };
+                        // There is not tag, we are just pushing the data itself.
+                        self.max_out_size += size;
+                        self.push_str_with_indent(&format!("w.push_bytes({ptr_expr})?;\n"))
+                    }
+                    _ => bail!(
+                        "using a variable of type {source_type:?} for a byte-array field is not supported",
+                    ),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Push an optionally tagged string into the ASN1 output.
+    fn push_string(
+        &mut self,
+        _name_hint: Option<String>,
+        str_type: &Tag,
+        val: &Value<String>,
+    ) -> Result<()> {
+        let str_type = str_type.rust_codestring();
+        match val {
+            Value::Literal(x) => {
+                self.push_str_with_indent(&format!(
+                    "w.push_string({str_type}, {x:?}.as_bytes())?;\n"
+                ));
+                self.max_out_size += CCodegen::tag_and_content_size(x.len());
+            }
+            Value::Variable(Variable { name, convert }) => {
+                let VariableInfo {
+                    codegen,
+                    var_type: source_type,
+                } = (self.variable_info)(name)?;
+                let VariableCodegenInfo::Pointer { ptr_expr, .. } = codegen else {
+                    bail!(
+                        "internal error: string not represented by a VariableCodegenInfo::Pointer"
+                    );
+                };
+                // When pushing a variable, it can either a string or a byte array that needs
+                // to be converted.
+                match source_type {
+                    VariableType::String { size } => {
+                        ensure!(
+                            convert.is_none(),
+                            "cannot use a convertion from string to string"
+                        );
+                        self.push_str_with_indent(&format!(
+                            "w.push_string({str_type}, {ptr_expr})?;\n"
+                        ));
+                        self.max_out_size += CCodegen::tag_and_content_size(size);
+                    }
+                    VariableType::ByteArray { size } => match convert {
+                        None => bail!("using a byte array variable for an string field must to specify a conversion"),
+                        Some(Conversion::LowercaseHex) => {
+                            // The conversion doubles the size.
+                            self.max_out_size += CCodegen::tag_and_content_size(2 * size);
+                            self.push_str_with_indent(&format!(
+                                "w.push_hexstring({str_type}, {ptr_expr})?;\n"
+                            ))
+                        }
+                        _ => bail!(
+                            "conversion {convert:?} from byte array to string is not supported"
+                        ),
+                    },
+                    _ => bail!("conversion from to {source_type:?} to string is not supported",),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push_bitstring(
+        &mut self,
+        name_hint: Option<String>,
+        tag: &Tag,
+        bits: &[Value<bool>],
+    ) -> Result<()> {
+        self.push_tag(name_hint.clone(), tag, |builder| {
+            let bitstring_name = format!(
+                "bit{}_{}",
+                builder.tag_idx,
+                name_hint.map(|x| x.to_snake_case()).unwrap_or("".into())
+            );
+            builder.tag_idx += 1;
+            builder.push_str_with_indent(&format!(
+                "let mut {bitstring_name} = w.start_bitstring()?;\n"
+            ));
+            for bit in bits {
+                builder.push_bit(&bitstring_name, bit)?;
+            }
+            // One byte for the unused bits and then one byte per 8 bits.
+            builder.max_out_size += 1 + (bits.len() + 7) / 8;
+            builder.push_str_with_indent(&format!("w.finish_bitstring({bitstring_name})?;\n"));
+            Ok(())
+        })
+    }
+
+    fn push_oid(&mut self, oid: &Oid) -> Result<()> {
+        let bytes = oid.to_der()?;
+        self.push_str_with_indent(&format!("// {}\n", oid.oid()));
+        self.push_str_with_indent(&format!("w.push_oid_raw({})?;\n", bytes_literal(&bytes)));
+        self.max_out_size += CCodegen::tag_and_content_size(bytes.len());
+        Ok(())
+    }
+
+    // Helper function for outputting ASN1 tags.
+    fn push_tag(
+        &mut self,
+        name_hint: Option<String>,
+        tag: &Tag,
+        gen: impl FnOnce(&mut Self) -> Result<()>,
+    ) -> Result<()> {
+        let tag_name = format!(
+            "tag{}_{}",
+            self.tag_idx,
+            name_hint.map(|x| x.to_snake_case()).unwrap_or("".into())
+        );
+        self.tag_idx += 1;
+        self.push_str_with_indent(&format!(
+            "let {tag_name} = w.start_tag({})?;\n",
+            tag.rust_codestring()
+        ));
+        self.push_str_with_indent("{\n");
+        self.indent_lvl += 1;
+        // See the C generator: the size of the content gives a bound on the size of the tag.
+        let old_max_size = self.max_out_size;
+        gen(self)?;
+        let max_size = self.max_out_size - old_max_size;
+        self.max_out_size += CCodegen::tag_size(max_size);
+        self.indent_lvl -= 1;
+        self.push_str_with_indent("}\n");
+        self.push_str_with_indent(&format!("w.finish_tag({tag_name})?;\n"));
+        Ok(())
+    }
+}
@@ -26,6 +26,7 @@ impl Tag {
             Tag::Boolean => 0x01,
             Tag::BitString => 0x03,
             Tag::GeneralizedTime => 0x018,
+            Tag::Ia5String => 0x16,
             Tag::Integer => 0x02,
             Tag::OctetString => 0x04,
             Tag::Oid => 0x06,
@@ -122,6 +123,10 @@ impl Builder for Der {
     ) -> Result<()> {
         let val = Self::get_value_or_error(val)?;
         let mut bytes = val.to_bytes_be();
+        ensure!(
+            bytes.len() <= size,
+            "integer {val:#x} does not fit in {size} bytes"
+        );
         while bytes.len() < size {
             bytes.insert(0, 0x0);
         }
@@ -297,6 +302,10 @@ mod tests {
             0x00, 0x00, 0x12, 0x34,
         ];
         assert_eq!(&der, RESULT);
+        assert!(Der::generate(|builder| {
+            builder.push_integer_pad(None, &Value::Literal(BigUint::from_u32(0x1234).unwrap()), 1)
+        })
+        .is_err());
         Ok(())
     }
 
@@ -7,7 +7,30 @@ use anyhow::Result;
 use crate::asn1::builder::Builder;
 use crate::asn1::x509::X509;
 use crate::asn1::{Oid, Tag};
-use crate::template::DiceTcbInfoExtension;
+use crate::template::{
+    DiceMultiTcbInfoExtension, DiceTcbInfoExtension, DiceUeidExtension, FirmwareId,
+};
+
+// Push a FWIDLIST with the given tag. The name hint of each digest is the given
+// hint followed by the index of the digest.
+fn push_fwid_list<B: Builder>(
+    builder: &mut B,
+    name_hint: &str,
+    tag: &Tag,
+    fwids: &[FirmwareId],
+) -> Result<()> {
+    builder.push_tag(Some(name_hint.into()), tag, |builder| {
+        for (idx, fwid) in fwids.iter().enumerate() {
+            builder.push_seq(Some("fwid".into()), |builder| {
+                builder.push_oid(&fwid.hash_algorithm.oid())?;
+                builder.push_octet_string(Some(format!("{name_hint}_{idx}")), |builder| {
+                    builder.push_byte_array(Some(format!("{name_hint}_{idx}")), &fwid.digest)
+                })
+            })?;
+        }
+        Ok(())
+    })
+}
 
 impl DiceTcbInfoExtension {
     // From the DICE specification:
@@ -26,7 +49,9 @@ impl DiceTcbInfoExtension {
     //     fwids [6] IMPLICIT FWIDLIST OPTIONAL,
     //     flags [7] IMPLICIT OperationalFlags OPTIONAL,
     //     vendorInfo [8] IMPLICIT OCTET STRING OPTIONAL,
-    //     type [9] IMPLICIT OCTET STRING OPTIONAL
+    //     type [9] IMPLICIT OCTET STRING OPTIONAL,
+    //     flagsMask [10] IMPLICIT OperationalFlagsMask OPTIONAL,
+    //     integrityRegisters [11] IMPLICIT IrList OPTIONAL
     // }
     // FWIDLIST ::== SEQUENCE SIZE (1..MAX) OF FWID
     //     FWID ::== SEQUENCE {
@@ -39,6 +64,14 @@ impl DiceTcbInfoExtension {
     //     recovery (2),
     //     debug (3)
     // }
+    // IrList ::= SEQUENCE SIZE (1..MAX) OF IntegrityRegister
+    // IntegrityRegister ::= SEQUENCE {
+    //     registerName IA5String OPTIONAL,
+    //     registerNum INTEGER OPTIONAL,
+    //     registerDigests FWIDLIST
+    // }
+    //
+    // The flags mask is not supported.
 
     // Push a raw DICE TCB Info extension data, without the X509 extension header.
     pub fn push_extension_raw<B: Builder>(&self, builder: &mut B) -> Result<()> {
@@ -93,30 +126,25 @@ impl DiceTcbInfoExtension {
                     layer,
                 )?;
             }
+            if let Some(index) = &self.index {
+                builder.push_integer(
+                    Some("dice_index".into()),
+                    &Tag::Context {
+                        constructed: false,
+                        value: 5,
+                    },
+                    index,
+                )?;
+            }
             if let Some(fwids) = &self.fw_ids {
-                builder.push_tag(
-                    Some("dice_fwids".into()),
+                push_fwid_list(
+                    builder,
+                    "dice_fwids",
                     &Tag::Context {
                         constructed: true,
                         value: 6,
                     },
-                    |builder| {
-                        for (idx, fwid) in fwids.iter().enumerate() {
-                            builder.push_seq(Some("fwid".into()), |builder| {
-                                builder.push_oid(&fwid.hash_algorithm.oid())?;
-                                builder.push_octet_string(
-                                    Some(format!("dice_fwids_{}", idx)),
-                                    |builder| {
-                                        builder.push_byte_array(
-                                            Some(format!("dice_fwids_{}", idx)),
-                                            &fwid.digest,
-                                        )
-                                    },
-                                )
-                            })?;
-                        }
-                        Ok(())
-                    },
+                    fwids,
                 )?;
             }
             if let Some(flags) = &self.flags {
@@ -134,6 +162,62 @@ impl DiceTcbInfoExtension {
                     ],
                 )?;
             }
+            if let Some(vendor_info) = &self.vendor_info {
+                builder.push_tag(
+                    Some("dice_vendor_info".into()),
+                    &Tag::Context {
+                        constructed: false,
+                        value: 8,
+                    },
+                    |builder| builder.push_byte_array(Some("dice_vendor_info".into()), vendor_info),
+                )?;
+            }
+            if let Some(tcb_type) = &self.tcb_type {
+                builder.push_tag(
+                    Some("dice_type".into()),
+                    &Tag::Context {
+                        constructed: false,
+                        value: 9,
+                    },
+                    |builder| builder.push_byte_array(Some("dice_type".into()), tcb_type),
+                )?;
+            }
+            if let Some(registers) = &self.integrity_registers {
+                builder.push_tag(
+                    Some("dice_integrity_registers".into()),
+                    &Tag::Context {
+                        constructed: true,
+                        value: 11,
+                    },
+                    |builder| {
+                        for (idx, register) in registers.iter().enumerate() {
+                            builder.push_seq(Some("integrity_register".into()), |builder| {
+                                if let Some(name) = &register.name {
+                                    builder.push_string(
+                                        Some(format!("dice_ir_{idx}_name")),
+                                        &Tag::Ia5String,
+                                        name,
+                                    )?;
+                                }
+                                if let Some(number) = &register.number {
+                                    builder.push_integer(
+                                        Some(format!("dice_ir_{idx}_number")),
+                                        &Tag::Integer,
+                                        number,
+                                    )?;
+                                }
+                                push_fwid_list(
+                                    builder,
+                                    &format!("dice_ir_{idx}_digests"),
+                                    &Tag::Sequence,
+                                    &register.digests,
+                                )
+                            })?;
+                        }
+                        Ok(())
+                    },
+                )?;
+            }
             Ok(())
         })
     }
@@ -146,3 +230,56 @@ impl DiceTcbInfoExtension {
         })
     }
 }
+
+impl DiceMultiTcbInfoExtension {
+    // From the DICE specification:
+    // https://trustedcomputinggroup.org/wp-content/uploads/DICE-Attestation-Architecture-r23-final.pdf
+    //
+    // tcg-dice-MultiTcbInfo OBJECT IDENTIFIER ::= {tcg-dice 5}
+    // DiceTcbInfoSeq ::= SEQUENCE SIZE (1..MAX) OF DiceTcbInfo
+
+    // Push a raw DICE multi-TCB Info extension data, without the X509 extension header.
+    pub fn push_extension_raw<B: Builder>(&self, builder: &mut B) -> Result<()> {
+        builder.push_seq(Some("dice_multi_tcb_info".into()), |builder| {
+            for tcb_info in &self.tcb_infos {
+                tcb_info.push_extension_raw(builder)?;
+            }
+            Ok(())
+        })
+    }
+
+    // Push a DICE multi-TCB Info X509 extension.
+    pub fn push_extension<B: Builder>(&self, builder: &mut B) -> Result<()> {
+        // Like DiceTcbInfo, the DiceMultiTcbInfo extension SHOULD be marked critical.
+        X509::push_extension(builder, &Oid::DiceMultiTcbInfo, true, |builder| {
+            self.push_extension_raw(builder)
+        })
+    }
+}
+
+impl DiceUeidExtension {
+    // From the DICE specification:
+    // https://trustedcomputinggroup.org/wp-content/uploads/DICE-Attestation-Architecture-r23-final.pdf
+    //
+    // tcg-dice-Ueid OBJECT IDENTIFIER ::= {tcg-dice 4}
+    // TcgUeid ::== SEQUENCE {
+    //     ueid OCTET STRING
+    // }
+
+    // Push a raw DICE UEID extension data, without the X509 extension header.
+    pub fn push_extension_raw<B: Builder>(&self, builder: &mut B) -> Result<()> {
+        builder.push_seq(Some("dice_ueid".into()), |builder| {
+            builder.push_octet_string(Some("dice_ueid".into()), |builder| {
+                builder.push_byte_array(Some("dice_ueid".into()), &self.ueid)
+            })
+        })
+    }
+
+    // Push a DICE UEID X509 extension.
+    pub fn push_extension<B: Builder>(&self, builder: &mut B) -> Result<()> {
+        // Per the DICE specification, the Ueid extension SHOULD be marked critical.
+        X509::push_extension(builder, &Oid::DiceUeid, true, |builder| {
+            self.push_extension_raw(builder)
+        })
+    }
+}
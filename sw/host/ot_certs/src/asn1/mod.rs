@@ -17,6 +17,7 @@ pub enum Tag {
     Boolean,
     BitString,
     GeneralizedTime,
+    Ia5String,
     Integer,
     OctetString,
     Oid,
@@ -38,8 +39,19 @@ pub enum Oid {
     AuthorityKeyIdentifier,
     BasicConstraints,
     DiceTcbInfo,
+    DiceMultiTcbInfo,
+    DiceUeid,
+    ExtendedKeyUsage,
     KeyUsage,
     SubjectKeyIdentifier,
+    // Extended key usage purposes.
+    ServerAuth,
+    ClientAuth,
+    CodeSigning,
+    EmailProtection,
+    TimeStamping,
+    OcspSigning,
+    TcgKpEkCertificate,
     // Name attributes.
     CommonName,
     Country,
@@ -82,12 +94,39 @@ impl Oid {
             Oid::AuthorityKeyIdentifier => "2.5.29.35",
             // id-ce-subjectKeyIdentifier OBJECT IDENTIFIER ::=  { id-ce 14 }
             Oid::SubjectKeyIdentifier => "2.5.29.14",
+            // id-ce-extKeyUsage OBJECT IDENTIFIER ::= { id-ce 37 }
+            Oid::ExtendedKeyUsage => "2.5.29.37",
+
+            // From https://datatracker.ietf.org/doc/html/rfc5280#section-4.2.1.12
+            // id-kp OBJECT IDENTIFIER ::= { id-pkix 3 }
+            // (id-pkix is 1.3.6.1.5.5.7)
+            //
+            // id-kp-serverAuth             OBJECT IDENTIFIER ::= { id-kp 1 }
+            Oid::ServerAuth => "1.3.6.1.5.5.7.3.1",
+            // id-kp-clientAuth             OBJECT IDENTIFIER ::= { id-kp 2 }
+            Oid::ClientAuth => "1.3.6.1.5.5.7.3.2",
+            // id-kp-codeSigning            OBJECT IDENTIFIER ::= { id-kp 3 }
+            Oid::CodeSigning => "1.3.6.1.5.5.7.3.3",
+            // id-kp-emailProtection        OBJECT IDENTIFIER ::= { id-kp 4 }
+            Oid::EmailProtection => "1.3.6.1.5.5.7.3.4",
+            // id-kp-timeStamping           OBJECT IDENTIFIER ::= { id-kp 8 }
+            Oid::TimeStamping => "1.3.6.1.5.5.7.3.8",
+            // id-kp-OCSPSigning            OBJECT IDENTIFIER ::= { id-kp 9 }
+            Oid::OcspSigning => "1.3.6.1.5.5.7.3.9",
+            // From the TCG EK Credential Profile, section 4:
+            // tcg-kp-EKCertificate OBJECT IDENTIFIER ::= {tcg-kp 1}
+            // (tcg-kp is 2.23.133.8)
+            Oid::TcgKpEkCertificate => "2.23.133.8.1",
 
             // https://trustedcomputinggroup.org/wp-content/uploads/TCG_DICE_Attestation_Architecture_r22_02dec2020.pdf
             // tcg OBJECT IDENTIFIER ::= {2 23 133}
             // tcg-dice OBJECT IDENTIFIER ::= { tcg platformClass(5) 4 }
             // tcg-dice-TcbInfo OBJECT IDENTIFIER ::= {tcg-dice 1}
             Oid::DiceTcbInfo => "2.23.133.5.4.1",
+            // tcg-dice-Ueid OBJECT IDENTIFIER ::= {tcg-dice 4}
+            Oid::DiceUeid => "2.23.133.5.4.4",
+            // tcg-dice-MultiTcbInfo OBJECT IDENTIFIER ::= {tcg-dice 5}
+            Oid::DiceMultiTcbInfo => "2.23.133.5.4.5",
 
             // From https://www.itu.int/rec/T-REC-X.501/en
             // ID ::= OBJECT IDENTIFIER
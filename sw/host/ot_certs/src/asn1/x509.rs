@@ -10,7 +10,8 @@ use crate::asn1::builder::{concat_suffix, Builder};
 use crate::asn1::{Oid, Tag};
 use crate::template::{
     AttributeType, BasicConstraints, Certificate, CertificateExtension, EcCurve, EcPublicKeyInfo,
-    EcdsaSignature, HashAlgorithm, KeyUsage, Name, Signature, SubjectPublicKeyInfo, Value,
+    EcdsaSignature, HashAlgorithm, KeyPurpose, KeyUsage, Name, RawExtension, Signature,
+    SubjectPublicKeyInfo, Value,
 };
 
 impl HashAlgorithm {
@@ -22,6 +23,22 @@ impl HashAlgorithm {
     }
 }
 
+impl KeyPurpose {
+    // Return the object identifier of this key purpose.
+    pub fn oid(&self) -> Oid {
+        match self {
+            KeyPurpose::ServerAuth => Oid::ServerAuth,
+            KeyPurpose::ClientAuth => Oid::ClientAuth,
+            KeyPurpose::CodeSigning => Oid::CodeSigning,
+            KeyPurpose::EmailProtection => Oid::EmailProtection,
+            KeyPurpose::TimeStamping => Oid::TimeStamping,
+            KeyPurpose::OcspSigning => Oid::OcspSigning,
+            KeyPurpose::TcgKpEkCertificate => Oid::TcgKpEkCertificate,
+            KeyPurpose::Oid(oid) => Oid::Custom(oid.clone()),
+        }
+    }
+}
+
 impl EcCurve {
     pub fn oid(&self) -> Oid {
         match self {
@@ -153,6 +170,7 @@ impl X509 {
                         if let Some(key_usage) = &cert.key_usage {
                             Self::push_key_usage_ext(builder, key_usage)?;
                         }
+                        Self::push_ext_key_usage_ext(builder, &cert.extended_key_usage)?;
                         Self::push_auth_key_id_ext(builder, &cert.authority_key_identifier)?;
                         Self::push_subject_key_id_ext(builder, &cert.subject_key_identifier)?;
                         for ext in &cert.private_extensions {
@@ -171,6 +189,9 @@ impl X509 {
     ) -> Result<()> {
         match ext {
             CertificateExtension::DiceTcbInfo(dice_ext) => dice_ext.push_extension(builder),
+            CertificateExtension::DiceMultiTcbInfo(multi_ext) => multi_ext.push_extension(builder),
+            CertificateExtension::DiceUeid(ueid_ext) => ueid_ext.push_extension(builder),
+            CertificateExtension::Raw(raw_ext) => Self::push_raw_ext(builder, raw_ext),
         }
     }
 
@@ -230,7 +251,15 @@ impl X509 {
         // Retrieve the configured value of the extension.
         Self::push_extension(builder, &Oid::BasicConstraints, true, |builder| {
             builder.push_seq(Some("basic_constraints".into()), |builder| {
-                builder.push_boolean(&Tag::Boolean, &constraints.ca)
+                builder.push_boolean(&Tag::Boolean, &constraints.ca)?;
+                if let Some(path_len) = &constraints.path_len_constraint {
+                    builder.push_integer(
+                        Some("path_len_constraint".into()),
+                        &Tag::Integer,
+                        path_len,
+                    )?;
+                }
+                Ok(())
             })
         })
     }
@@ -291,26 +320,42 @@ impl X509 {
                 Some("key_usage".into()),
                 &Tag::BitString,
                 &[
-                    key_usage
-                        .digital_signature
-                        .clone()
-                        .unwrap_or(Value::literal(false)),
-                    /* nonRepudiation */ Value::Literal(false),
-                    /* keyEncipherment */ Value::Literal(false),
-                    /* dataEncipherment */ Value::Literal(false),
-                    key_usage
-                        .key_agreement
-                        .clone()
-                        .unwrap_or(Value::literal(false)),
-                    key_usage.cert_sign.clone().unwrap_or(Value::literal(false)),
-                    /* cRLSign */ Value::Literal(false),
-                    /* encipherOnly */ Value::Literal(false),
-                    /* decipherOnly */ Value::Literal(false),
+                    key_usage.digital_signature.clone(),
+                    key_usage.non_repudiation.clone(),
+                    key_usage.key_encipherment.clone(),
+                    key_usage.data_encipherment.clone(),
+                    key_usage.key_agreement.clone(),
+                    key_usage.cert_sign.clone(),
+                    key_usage.crl_sign.clone(),
+                    key_usage.encipher_only.clone(),
+                    key_usage.decipher_only.clone(),
                 ],
             )
         })
     }
 
+    pub fn push_ext_key_usage_ext<B: Builder>(
+        builder: &mut B,
+        purposes: &[KeyPurpose],
+    ) -> Result<()> {
+        // From https://datatracker.ietf.org/doc/html/rfc5280#section-4.2.1.12
+        // ExtKeyUsageSyntax ::= SEQUENCE SIZE (1..MAX) OF KeyPurposeId
+        //
+        // KeyPurposeId ::= OBJECT IDENTIFIER
+        if purposes.is_empty() {
+            // Extended Key Usage is an optional extension, it's ok not to be present.
+            return Ok(());
+        }
+        Self::push_extension(builder, &Oid::ExtendedKeyUsage, false, |builder| {
+            builder.push_seq(Some("ext_key_usage".into()), |builder| {
+                for purpose in purposes {
+                    builder.push_oid(&purpose.oid())?;
+                }
+                Ok(())
+            })
+        })
+    }
+
     pub fn push_auth_key_id_ext<B: Builder>(
         builder: &mut B,
         auth_key_id: &Value<Vec<u8>>,
@@ -352,6 +397,15 @@ impl X509 {
         })
     }
 
+    pub fn push_raw_ext<B: Builder>(builder: &mut B, ext: &RawExtension) -> Result<()> {
+        Self::push_extension(
+            builder,
+            &Oid::Custom(ext.oid.clone()),
+            ext.critical,
+            |builder| builder.push_byte_array(Some("raw_ext_value".into()), &ext.value),
+        )
+    }
+
     pub fn push_extension<B: Builder>(
         builder: &mut B,
         oid: &Oid,
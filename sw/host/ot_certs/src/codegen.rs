@@ -4,6 +4,7 @@
 
 //! This module is capable of generating C code for generating a binary X.509
 //! certificate according to a [`Template`](crate::template::Template).
+//! The [`rust`] submodule generates the equivalent Rust code.
 
 use anyhow::{bail, Context, Result};
 use heck::ToUpperCamelCase;
@@ -16,6 +17,8 @@ use crate::template::subst::{Subst, SubstValue};
 use crate::template::{EcdsaSignature, Signature, Template, Value, Variable, VariableType};
 use crate::x509;
 
+pub mod rust;
+
 const INDENT: &str = "  ";
 
 pub struct Codegen {
@@ -84,15 +87,7 @@ pub fn generate_cert(from_file: &str, tmpl: &Template) -> Result<Codegen> {
     source_h.push_str("#include \"sw/device/lib/base/status.h\"\n\n");
 
     // Partition variables between TBS and signature.
-    let mut tbs_vars = IndexMap::<String, VariableType>::new();
-    let mut sig_vars = IndexMap::<String, VariableType>::new();
-    for (var_name, var) in tmpl.variables.clone() {
-        if var_appears_in_sig(&var_name, &tmpl.certificate.signature) {
-            sig_vars.insert(var_name, var);
-        } else {
-            tbs_vars.insert(var_name, var);
-        }
-    }
+    let (tbs_vars, mut sig_vars) = partition_variables(tmpl);
 
     // Structure containing the TBS variables.
     let tbs_value_struct_name = format!("{}_tbs_values", tmpl.name);
@@ -302,6 +297,25 @@ fn generate_value_struct_assignment(variables: &IndexMap<String, VariableType>)
     Ok(source)
 }
 
+// Partition the variables of a template between the TBS and the signature.
+fn partition_variables(
+    tmpl: &Template,
+) -> (
+    IndexMap<String, VariableType>,
+    IndexMap<String, VariableType>,
+) {
+    let mut tbs_vars = IndexMap::<String, VariableType>::new();
+    let mut sig_vars = IndexMap::<String, VariableType>::new();
+    for (var_name, var) in tmpl.variables.clone() {
+        if var_appears_in_sig(&var_name, &tmpl.certificate.signature) {
+            sig_vars.insert(var_name, var);
+        } else {
+            tbs_vars.insert(var_name, var);
+        }
+    }
+    (tbs_vars, sig_vars)
+}
+
 // Decide if a variable appears in a signature field (if not, it is in the TBS).
 fn var_appears_in_sig(var_name: &str, sig: &Signature) -> bool {
     match sig {
@@ -0,0 +1,473 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module is capable of generating Rust code for generating a binary
+//! X.509 certificate according to a [`Template`](crate::template::Template).
+//!
+//! The generated module only depends on `core` so that it can be used by
+//! `no_std` firmware: it embeds a minimal DER writer.
+
+use anyhow::{bail, Context, Result};
+use heck::ToSnakeCase;
+use indexmap::IndexMap;
+use std::fmt::Write;
+
+use crate::asn1::codegen::rust::{bytes_literal, Codegen as RustCodegen};
+use crate::asn1::codegen::{VariableCodegenInfo, VariableInfo};
+use crate::asn1::x509::X509;
+use crate::template::subst::{Subst, SubstValue};
+use crate::template::{Template, Value, Variable, VariableType};
+use crate::x509;
+
+use super::partition_variables;
+
+const INDENT: &str = "    ";
+
+/// DER writer embedded in the generated code.
+const RUNTIME: &str = include_str!("rust_runtime.rs");
+
+pub struct Codegen {
+    /// Module containing the generator.
+    pub source_rs: String,
+    /// Code containing the unittest.
+    pub source_unittest: String,
+}
+
+/// Generate the certificate generator module and its unittest.
+///
+/// The generated files will indicate that they have been automatically
+/// generated from `from_file`. The generated module contains the following
+/// elements. Below `<name>` refers to `tmpl.name`.
+/// 1. License header and warning.
+/// 2. Two constants giving the maximum size of the TBS given the variables
+///    sizes defined in the template, and the maximum size of the whole
+///    certificate. They are named `MAX_TBS_SIZE_BYTES` and
+///    `MAX_CERT_SIZE_BYTES` respectively.
+/// 3. Definition of a structure holding the values of the variables used in
+///    the TBS, named `TbsValues`.
+/// 4. Definition of a structure holding the values of the variables used in
+///    the signature, named `SigValues`. It contains an extra `tbs` field that
+///    must hold the TBS.
+/// 5. A function `build_tbs` that takes a `TbsValues` and a buffer and
+///    writes the TBS in the buffer.
+/// 6. A function `build_cert` that takes a `SigValues` and a buffer and
+///    writes the certificate in the buffer.
+/// 7. The `asn1` module holding the DER writer and the `Error` type, which is
+///    re-exported.
+///
+/// The unittest is the root of a test crate: it declares the module with
+/// `mod <name>;` so the module must be in a file named `<name>.rs` next to it.
+pub fn generate_cert(from_file: &str, tmpl: &Template) -> Result<Codegen> {
+    let mut source_rs = String::new();
+    let mut source_unittest = String::new();
+
+    let license_and_warning = indoc::formatdoc! { r#"
+    // Copyright lowRISC contributors (OpenTitan project).
+    // Licensed under the Apache License, Version 2.0, see LICENSE for details.
+    // SPDX-License-Identifier: Apache-2.0
+
+    // This file was automatically generated using opentitantool from:
+    // {from_file}
+    "#};
+    source_rs.push_str(&license_and_warning);
+    source_rs.push('\n');
+    source_rs.push_str("use self::asn1::tag;\n");
+    source_rs.push_str("pub use self::asn1::Error;\n\n");
+
+    // Partition variables between TBS and signature.
+    let (tbs_vars, mut sig_vars) = partition_variables(tmpl);
+
+    // Generate TBS function.
+    let (tbs_struct, generate_tbs_fn, max_tbs_size) = generate_builder(
+        "TbsValues",
+        "Values of the variables of the TBS certificate.",
+        indoc::indoc! {"
+        /// Generates a TBS certificate.
+        ///
+        /// Writes the TBS portion of the certificate in `tbs` using the variables
+        /// in `values`, and returns the size of the TBS.
+        "},
+        "build_tbs",
+        "tbs",
+        &tbs_vars,
+        |builder| X509::push_tbs_certificate(builder, &tmpl.certificate),
+    )?;
+
+    // Create a special variable to hold the TBS binary.
+    let tbs_binary_val_name = "tbs";
+    sig_vars.insert(
+        tbs_binary_val_name.to_string(),
+        VariableType::ByteArray { size: max_tbs_size },
+    );
+    let tbs_binary_val = Value::Variable(Variable {
+        name: tbs_binary_val_name.to_string(),
+        convert: None,
+    });
+
+    // Generate sig function.
+    let (sig_struct, generate_cert_fn, max_cert_size) = generate_builder(
+        "SigValues",
+        "Values of the variables of the certificate signature, and the TBS.",
+        indoc::indoc! {"
+        /// Generates an endorsed certificate from a TBS certificate and a signature.
+        ///
+        /// Writes the certificate in `cert` using the variables in `values`, and
+        /// returns the size of the certificate.
+        "},
+        "build_cert",
+        "cert",
+        &sig_vars,
+        |builder| X509::push_certificate(builder, &tbs_binary_val, &tmpl.certificate.signature),
+    )?;
+
+    // Create two constants for the maximum possible size of TBS and cert.
+    // Also generate a comment stating how this size was computed.
+    source_rs.push_str("// Maximum possible size of a TBS and a certificate assuming:\n");
+    for (var_name, var_type) in tbs_vars.iter().chain(sig_vars.iter()) {
+        // Only consider variables whose size can vary, ie slices.
+        let (codegen, _) = rust_variable_info(var_name, "", var_type);
+        if let VariableCodegenInfo::Pointer { .. } = codegen {
+            let size = match var_type {
+                VariableType::ByteArray { size }
+                | VariableType::Integer { size }
+                | VariableType::String { size } => *size,
+                VariableType::Boolean => bail!("internal error: boolean represented by a slice"),
+            };
+            writeln!(
+                source_rs,
+                "// - {var_name} is of size at most {size} bytes."
+            )?;
+        }
+    }
+    writeln!(
+        source_rs,
+        "pub const MAX_TBS_SIZE_BYTES: usize = {max_tbs_size};"
+    )?;
+    writeln!(
+        source_rs,
+        "pub const MAX_CERT_SIZE_BYTES: usize = {max_cert_size};\n"
+    )?;
+
+    source_rs.push_str(&tbs_struct);
+    source_rs.push_str(&sig_struct);
+    source_rs.push_str(&generate_tbs_fn);
+    source_rs.push_str(&generate_cert_fn);
+
+    // Embed the DER writer, without its license header.
+    source_rs.push_str("mod asn1 {\n");
+    let (_, runtime) = RUNTIME
+        .split_once("\n\n")
+        .context("internal error: the DER writer has no license header")?;
+    for line in runtime.lines() {
+        if !line.is_empty() {
+            source_rs.push_str(INDENT);
+        }
+        source_rs.push_str(line);
+        source_rs.push('\n');
+    }
+    source_rs.push_str("}\n");
+
+    // Generate unittest.
+    let unittest_data = tmpl.random_test()?;
+    let subst_tmpl = tmpl.subst(&unittest_data)?;
+    let expected_tbs = x509::generate_tbs_certificate(&subst_tmpl)?;
+    let expected_cert = x509::generate_certificate(&subst_tmpl)?;
+
+    source_unittest.push_str(&license_and_warning);
+    source_unittest.push('\n');
+    writeln!(source_unittest, "mod {};\n", tmpl.name)?;
+    writeln!(source_unittest, "use {}::*;\n", tmpl.name)?;
+    writeln!(
+        source_unittest,
+        "const EXPECTED_TBS: &[u8] = {};\n",
+        bytes_literal(&expected_tbs)
+    )?;
+    writeln!(
+        source_unittest,
+        "const EXPECTED_CERT: &[u8] = {};\n",
+        bytes_literal(&expected_cert)
+    )?;
+    // The TBS variable is not part of the substitution data.
+    let value_expr = |var_name: &str| -> Result<String> {
+        if var_name == tbs_binary_val_name {
+            return Ok(format!("&{tbs_binary_val_name}[..tbs_size]"));
+        }
+        Ok(
+            match unittest_data
+                .values
+                .get(var_name)
+                .with_context(|| format!("no test data for variable '{var_name}'"))?
+            {
+                SubstValue::ByteArray(bytes) => bytes_literal(bytes),
+                SubstValue::String(s) => format!("{s:?}"),
+                SubstValue::Int32(val) => val.to_string(),
+                SubstValue::Boolean(val) => val.to_string(),
+            },
+        )
+    };
+    let struct_assignment = |struct_name: &str, vars: &IndexMap<String, VariableType>| {
+        let mut source = format!(
+            "{INDENT}let {} = {struct_name} {{\n",
+            struct_name.to_snake_case()
+        );
+        for var_name in vars.keys() {
+            writeln!(
+                source,
+                "{INDENT}{INDENT}{var_name}: {},",
+                value_expr(var_name)?
+            )?;
+        }
+        writeln!(source, "{INDENT}}};")?;
+        Ok::<_, anyhow::Error>(source)
+    };
+    source_unittest.push_str("#[test]\nfn verify() {\n");
+    source_unittest.push_str(&struct_assignment("TbsValues", &tbs_vars)?);
+    source_unittest.push_str(&indoc::formatdoc! { r#"
+        {INDENT}let mut {tbs_binary_val_name} = [0u8; MAX_TBS_SIZE_BYTES];
+        {INDENT}let tbs_size = build_tbs(&tbs_values, &mut {tbs_binary_val_name}).unwrap();
+        {INDENT}assert_eq!(&{tbs_binary_val_name}[..tbs_size], EXPECTED_TBS);
+        {INDENT}assert_eq!(
+        {INDENT}{INDENT}build_tbs(&tbs_values, &mut [0u8; MAX_TBS_SIZE_BYTES][..tbs_size - 1]),
+        {INDENT}{INDENT}Err(Error::BufferExhausted)
+        {INDENT});
+        "#
+    });
+    source_unittest.push_str(&struct_assignment("SigValues", &sig_vars)?);
+    source_unittest.push_str(&indoc::formatdoc! { r#"
+        {INDENT}let mut cert = [0u8; MAX_CERT_SIZE_BYTES];
+        {INDENT}let cert_size = build_cert(&sig_values, &mut cert).unwrap();
+        {INDENT}assert_eq!(&cert[..cert_size], EXPECTED_CERT);
+        }}
+        "#
+    });
+
+    Ok(Codegen {
+        source_rs,
+        source_unittest,
+    })
+}
+
+// Generate the structure holding the value of the variables and the function
+// that generates a TBS/cert. This functions returns three elements: the
+// structure definition, the function and the maximum size of the produced
+// TBS/cert.
+fn generate_builder(
+    struct_name: &str,
+    struct_doc: &str,
+    fn_doc: &str,
+    fn_name: &str,
+    buf_name: &str,
+    variables: &IndexMap<String, VariableType>,
+    gen: impl FnOnce(&mut RustCodegen) -> Result<()>,
+) -> Result<(String, String, usize)> {
+    // The structure only needs a lifetime if it holds slices.
+    let mut fields = String::new();
+    let mut has_lifetime = false;
+    for (var_name, var_type) in variables {
+        let (codegen, field_def) = rust_variable_info(var_name, "", var_type);
+        has_lifetime |= matches!(codegen, VariableCodegenInfo::Pointer { .. });
+        fields.push_str(&field_def);
+    }
+    let lifetime = if has_lifetime { "<'a>" } else { "" };
+    let value_struct = indoc::formatdoc! {r#"
+        /// {struct_doc}
+        pub struct {struct_name}{lifetime} {{
+        {fields}}}
+
+        "#
+    };
+
+    let get_var_info = |var_name: &str| -> Result<VariableInfo> {
+        let var_type = variables
+            .get(var_name)
+            .with_context(|| format!("could not find variable '{var_name}'"))
+            .copied()?;
+        let (codegen, _) = rust_variable_info(var_name, "values.", &var_type);
+        Ok(VariableInfo { var_type, codegen })
+    };
+    let (implementation, max_size) =
+        RustCodegen::generate(buf_name, INDENT, 1, &get_var_info, gen)?;
+
+    let lifetime = if has_lifetime { "<'_>" } else { "" };
+    let values_name = if variables.is_empty() {
+        "_values"
+    } else {
+        "values"
+    };
+    let mut generate_fn = String::new();
+    generate_fn.push_str(fn_doc);
+    writeln!(
+        generate_fn,
+        "pub fn {fn_name}({values_name}: &{struct_name}{lifetime}, {buf_name}: &mut [u8]) -> Result<usize, Error> {{"
+    )?;
+    generate_fn.push_str(&implementation);
+    generate_fn.push_str("}\n\n");
+
+    Ok((value_struct, generate_fn, max_size))
+}
+
+// Decide whether a integer should use a Rust integer type instead
+// of being represented by a big-endian byte slice.
+fn rust_integer_for_length(size: usize) -> Option<&'static str> {
+    match size {
+        4 => Some("u32"),
+        _ => None,
+    }
+}
+
+// Return information about a variable (codegen info, definition in struct).
+fn rust_variable_info(
+    name: &str,
+    struct_expr: &str,
+    var_type: &VariableType,
+) -> (VariableCodegenInfo, String) {
+    let slice_info = |ptr_expr: String| VariableCodegenInfo::Pointer {
+        size_expr: format!("{ptr_expr}.len()"),
+        ptr_expr,
+    };
+    match var_type {
+        VariableType::ByteArray { size } => (
+            slice_info(format!("{struct_expr}{name}")),
+            indoc::formatdoc! {r#"
+                {INDENT}/// Array of at most {size} bytes.
+                {INDENT}pub {name}: &'a [u8],
+                "#
+            },
+        ),
+        VariableType::Integer { size } => match rust_integer_for_length(*size) {
+            Some(rust_type) => (
+                VariableCodegenInfo::Int32 {
+                    value_expr: format!("{struct_expr}{name}"),
+                },
+                format!("{INDENT}pub {name}: {rust_type},\n"),
+            ),
+            None => (
+                slice_info(format!("{struct_expr}{name}")),
+                indoc::formatdoc! {r#"
+                    {INDENT}/// Unsigned big-endian integer of at most {size} bytes.
+                    {INDENT}pub {name}: &'a [u8],
+                    "#
+                },
+            ),
+        },
+        VariableType::String { size } => (
+            slice_info(format!("{struct_expr}{name}.as_bytes()")),
+            indoc::formatdoc! {r#"
+                {INDENT}/// String of at most {size} bytes.
+                {INDENT}pub {name}: &'a str,
+                "#
+            },
+        ),
+        VariableType::Boolean => (
+            VariableCodegenInfo::Boolean {
+                value_expr: format!("{struct_expr}{name}"),
+            },
+            format!("{INDENT}pub {name}: bool,\n"),
+        ),
+    }
+}
+
+// Compile the embedded DER writer to test it against the DER builder.
+#[cfg(test)]
+#[path = "rust_runtime.rs"]
+mod asn1_writer;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asn1::builder::Builder;
+    use crate::asn1::der::Der;
+    use crate::asn1::Tag;
+    use num_bigint_dig::BigUint;
+
+    use super::asn1_writer as asn1;
+
+    #[test]
+    fn writer_matches_der() -> Result<()> {
+        let long = vec![0x5a; 300];
+        let der = Der::generate(|builder| {
+            builder.push_seq(None, |builder| {
+                builder.push_integer(None, &Tag::Integer, &Value::Literal(BigUint::from(0u32)))?;
+                builder.push_integer(
+                    None,
+                    &Tag::Integer,
+                    &Value::Literal(BigUint::from(0x80u32)),
+                )?;
+                builder.push_boolean(&Tag::Boolean, &Value::Literal(true))?;
+                builder.push_tag(None, &Tag::OctetString, |builder| {
+                    builder.push_byte_array(None, &Value::Literal(long[..200].to_vec()))
+                })?;
+                builder.push_tag(None, &Tag::OctetString, |builder| {
+                    builder.push_byte_array(None, &Value::Literal(long.clone()))
+                })?;
+                builder.push_bitstring(
+                    None,
+                    &Tag::BitString,
+                    &[true, false, true].map(Value::Literal),
+                )
+            })
+        })?;
+
+        let mut buf = [0u8; 1024];
+        let mut w = asn1::Writer::new(&mut buf);
+        let seq = w.start_tag(asn1::tag::SEQUENCE).unwrap();
+        w.push_uint32(asn1::tag::INTEGER, 0).unwrap();
+        w.push_integer(asn1::tag::INTEGER, &[0x00, 0x00, 0x80])
+            .unwrap();
+        w.push_bool(asn1::tag::BOOLEAN, true).unwrap();
+        w.push_string(asn1::tag::OCTET_STRING, &long[..200])
+            .unwrap();
+        w.push_string(asn1::tag::OCTET_STRING, &long).unwrap();
+        let bits = w.start_tag(asn1::tag::BIT_STRING).unwrap();
+        let mut bitstring = w.start_bitstring().unwrap();
+        for bit in [true, false, true] {
+            w.push_bit(&mut bitstring, bit).unwrap();
+        }
+        w.finish_bitstring(bitstring).unwrap();
+        w.finish_tag(bits).unwrap();
+        w.finish_tag(seq).unwrap();
+        let size = w.finish();
+        assert_eq!(&buf[..size], der);
+
+        // The length of a tag must fit in the buffer once the tag is finished:
+        // the content fits in 510 bytes but the encoding takes 511 bytes.
+        let mut buf = [0u8; 510];
+        let mut w = asn1::Writer::new(&mut buf);
+        let seq = w.start_tag(asn1::tag::SEQUENCE).unwrap();
+        w.push_string(asn1::tag::OCTET_STRING, &long[..200])
+            .unwrap();
+        w.push_string(asn1::tag::OCTET_STRING, &long).unwrap();
+        assert_eq!(w.finish_tag(seq), Err(asn1::Error::BufferExhausted));
+        Ok(())
+    }
+
+    #[test]
+    fn writer_integer_pad() {
+        let mut buf = [0u8; 4];
+        let mut w = asn1::Writer::new(&mut buf);
+        w.push_integer_pad(&[0x12, 0x34], 4).unwrap();
+        assert_eq!(w.finish(), 4);
+        assert_eq!(buf, [0x00, 0x00, 0x12, 0x34]);
+
+        let mut w = asn1::Writer::new(&mut buf);
+        assert_eq!(
+            w.push_integer_pad(&[1, 2, 3], 2),
+            Err(asn1::Error::InvalidArgument)
+        );
+        assert_eq!(
+            w.push_integer(asn1::tag::INTEGER, &[]),
+            Err(asn1::Error::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn writer_hexstring() {
+        let mut buf = [0u8; 6];
+        let mut w = asn1::Writer::new(&mut buf);
+        w.push_hexstring(asn1::tag::UTF8_STRING, &[0xab, 0x01])
+            .unwrap();
+        assert_eq!(w.finish(), 6);
+        assert_eq!(&buf, b"\x0c\x04ab01");
+    }
+}
@@ -0,0 +1,222 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Minimal DER writer used by the generated Rust certificate generators. This
+// file is embedded as is in every generated module so it must only depend on
+// `core`. It follows the semantics of the C asn1 library
+// (sw/device/silicon_creator/lib/cert/asn1.c).
+#![allow(dead_code)]
+
+/// Errors returned by the certificate generators.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The output buffer is too small.
+    BufferExhausted,
+    /// An integer is empty or larger than its padded size.
+    InvalidArgument,
+    /// The content of a tag is larger than 0xffff bytes.
+    LengthTooLarge,
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Identifier octets of the tags used by the generators.
+pub mod tag {
+    pub const BOOLEAN: u8 = 0x01;
+    pub const INTEGER: u8 = 0x02;
+    pub const BIT_STRING: u8 = 0x03;
+    pub const OCTET_STRING: u8 = 0x04;
+    pub const OID: u8 = 0x06;
+    pub const UTF8_STRING: u8 = 0x0c;
+    pub const PRINTABLE_STRING: u8 = 0x13;
+    pub const IA5_STRING: u8 = 0x16;
+    pub const GENERALIZED_TIME: u8 = 0x18;
+    pub const SEQUENCE: u8 = 0x30;
+    pub const SET: u8 = 0x31;
+    pub const CLASS_CONTEXT: u8 = 0x80;
+    pub const FORM_CONSTRUCTED: u8 = 0x20;
+}
+
+/// A tag started by [`Writer::start_tag`].
+#[must_use]
+pub struct Tag {
+    len_offset: usize,
+}
+
+/// A bit string started by [`Writer::start_bitstring`].
+#[must_use]
+pub struct BitString {
+    unused_bits_offset: usize,
+    used_bits: u8,
+    current_byte: u8,
+}
+
+/// DER writer into a caller-provided buffer.
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    offset: usize,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Writer { buf, offset: 0 }
+    }
+
+    /// Return the size of the output.
+    pub fn finish(self) -> usize {
+        self.offset
+    }
+
+    pub fn push_byte(&mut self, byte: u8) -> Result<()> {
+        let slot = self
+            .buf
+            .get_mut(self.offset)
+            .ok_or(Error::BufferExhausted)?;
+        *slot = byte;
+        self.offset += 1;
+        Ok(())
+    }
+
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.offset + bytes.len();
+        self.buf
+            .get_mut(self.offset..end)
+            .ok_or(Error::BufferExhausted)?
+            .copy_from_slice(bytes);
+        self.offset = end;
+        Ok(())
+    }
+
+    /// Start a tag. A single byte is reserved for the length, the content is
+    /// moved by [`Writer::finish_tag`] if the length needs more.
+    pub fn start_tag(&mut self, tag: u8) -> Result<Tag> {
+        self.push_byte(tag)?;
+        let len_offset = self.offset;
+        self.push_byte(0)?;
+        Ok(Tag { len_offset })
+    }
+
+    pub fn finish_tag(&mut self, tag: Tag) -> Result<()> {
+        let content_offset = tag.len_offset + 1;
+        let length = self.offset - content_offset;
+        let len_size = match length {
+            0..=0x7f => 1,
+            0x80..=0xff => 2,
+            0x100..=0xffff => 3,
+            _ => return Err(Error::LengthTooLarge),
+        };
+        let end = self.offset + len_size - 1;
+        if end > self.buf.len() {
+            return Err(Error::BufferExhausted);
+        }
+        self.buf
+            .copy_within(content_offset..self.offset, tag.len_offset + len_size);
+        let len = &mut self.buf[tag.len_offset..tag.len_offset + len_size];
+        match len_size {
+            1 => len[0] = length as u8,
+            2 => len.copy_from_slice(&[0x81, length as u8]),
+            _ => len.copy_from_slice(&[0x82, (length >> 8) as u8, length as u8]),
+        }
+        self.offset = end;
+        Ok(())
+    }
+
+    pub fn push_bool(&mut self, tag: u8, value: bool) -> Result<()> {
+        let tag = self.start_tag(tag)?;
+        self.push_byte(if value { 0xff } else { 0x00 })?;
+        self.finish_tag(tag)
+    }
+
+    pub fn push_uint32(&mut self, tag: u8, value: u32) -> Result<()> {
+        self.push_integer(tag, &value.to_be_bytes())
+    }
+
+    /// Push an unsigned big-endian integer using its minimal encoding.
+    pub fn push_integer(&mut self, tag: u8, bytes_be: &[u8]) -> Result<()> {
+        if bytes_be.is_empty() {
+            return Err(Error::InvalidArgument);
+        }
+        let mut bytes = bytes_be;
+        while bytes.len() >= 2 && bytes[0] == 0 && (bytes[1] >> 7) == 0 {
+            bytes = &bytes[1..];
+        }
+        let tag = self.start_tag(tag)?;
+        // Integers are in two's complement: pad if the MSB is set.
+        if (bytes[0] >> 7) == 1 {
+            self.push_byte(0)?;
+        }
+        self.push_bytes(bytes)?;
+        self.finish_tag(tag)
+    }
+
+    /// Push an unsigned big-endian integer, padded with zeroes to
+    /// `padded_size` bytes, without a tag.
+    pub fn push_integer_pad(&mut self, bytes_be: &[u8], padded_size: usize) -> Result<()> {
+        if bytes_be.is_empty() || bytes_be.len() > padded_size {
+            return Err(Error::InvalidArgument);
+        }
+        for _ in bytes_be.len()..padded_size {
+            self.push_byte(0)?;
+        }
+        self.push_bytes(bytes_be)
+    }
+
+    /// Push the DER encoding of an object identifier, without its tag.
+    pub fn push_oid_raw(&mut self, oid: &[u8]) -> Result<()> {
+        let tag = self.start_tag(tag::OID)?;
+        self.push_bytes(oid)?;
+        self.finish_tag(tag)
+    }
+
+    pub fn push_string(&mut self, tag: u8, s: &[u8]) -> Result<()> {
+        let tag = self.start_tag(tag)?;
+        self.push_bytes(s)?;
+        self.finish_tag(tag)
+    }
+
+    /// Push a string holding the lowercase hexadecimal encoding of `bytes`.
+    pub fn push_hexstring(&mut self, tag: u8, bytes: &[u8]) -> Result<()> {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+        let tag = self.start_tag(tag)?;
+        for byte in bytes {
+            self.push_byte(HEX[(byte >> 4) as usize])?;
+            self.push_byte(HEX[(byte & 0xf) as usize])?;
+        }
+        self.finish_tag(tag)
+    }
+
+    /// Start the content of a bit string: the number of unused bits followed
+    /// by the bits.
+    pub fn start_bitstring(&mut self) -> Result<BitString> {
+        let unused_bits_offset = self.offset;
+        self.push_byte(0)?;
+        Ok(BitString {
+            unused_bits_offset,
+            used_bits: 0,
+            current_byte: 0,
+        })
+    }
+
+    pub fn push_bit(&mut self, bitstring: &mut BitString, bit: bool) -> Result<()> {
+        // Bits are added from MSB to LSB.
+        if bit {
+            bitstring.current_byte |= 1 << (7 - bitstring.used_bits);
+        }
+        bitstring.used_bits += 1;
+        if bitstring.used_bits == 8 {
+            self.push_byte(bitstring.current_byte)?;
+            bitstring.current_byte = 0;
+            bitstring.used_bits = 0;
+        }
+        Ok(())
+    }
+
+    pub fn finish_bitstring(&mut self, bitstring: BitString) -> Result<()> {
+        if bitstring.used_bits != 0 {
+            self.push_byte(bitstring.current_byte)?;
+            self.buf[bitstring.unused_bits_offset] = 8 - bitstring.used_bits;
+        }
+        Ok(())
+    }
+}
@@ -76,6 +76,10 @@ pub struct Certificate {
     // X509 basic constraints extension, optional.
     pub basic_constraints: Option<BasicConstraints>,
     pub key_usage: Option<KeyUsage>,
+    /// X509 extended key usage extension, optional: the extension is only
+    /// present if at least one purpose is listed.
+    #[serde(default)]
+    pub extended_key_usage: Vec<KeyPurpose>,
     /// X509 Subject Alternative Name extension, optional.
     #[serde(default)]
     pub subject_alt_name: Name,
@@ -98,25 +102,74 @@ pub type Name = Vec<IndexMap<AttributeType, Value<String>>>;
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct BasicConstraints {
     pub ca: Value<bool>,
+    /// Maximum number of intermediate CA certificates that may follow this
+    /// certificate in a path, optional.
+    pub path_len_constraint: Option<Value<BigUint>>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+/// X509 key usage extension. Usages that are not listed are not allowed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct KeyUsage {
-    pub digital_signature: Option<Value<bool>>,
-    pub key_agreement: Option<Value<bool>>,
-    pub cert_sign: Option<Value<bool>>,
+    pub digital_signature: Value<bool>,
+    pub non_repudiation: Value<bool>,
+    pub key_encipherment: Value<bool>,
+    pub data_encipherment: Value<bool>,
+    pub key_agreement: Value<bool>,
+    pub cert_sign: Value<bool>,
+    pub crl_sign: Value<bool>,
+    pub encipher_only: Value<bool>,
+    pub decipher_only: Value<bool>,
 }
 
+/// Purpose of the certified key, as listed by the X509 extended key usage
+/// extension.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyPurpose {
+    ServerAuth,
+    ClientAuth,
+    CodeSigning,
+    EmailProtection,
+    TimeStamping,
+    OcspSigning,
+    /// TCG endorsement key certificate.
+    TcgKpEkCertificate,
+    /// Any other purpose, given by its object identifier in dotted notation.
+    Oid(String),
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum CertificateExtension {
     /// DICE TCB extension.
     DiceTcbInfo(DiceTcbInfoExtension),
+    /// DICE multi-TCB extension.
+    DiceMultiTcbInfo(DiceMultiTcbInfoExtension),
+    /// DICE UEID extension.
+    DiceUeid(DiceUeidExtension),
+    /// Any other extension, kept as its raw DER value.
+    Raw(RawExtension),
 }
 
-/// DICE TCB extension.
+/// An X509 extension that the template does not model.
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
+pub struct RawExtension {
+    /// Object identifier of the extension in dotted notation.
+    pub oid: String,
+    /// Critical marker.
+    #[serde(default)]
+    pub critical: bool,
+    /// DER encoding of the extension value, i.e. the content of the
+    /// `extnValue` octet string.
+    pub value: Value<Vec<u8>>,
+}
+
+/// DICE TCB extension.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct DiceTcbInfoExtension {
     /// TCB model.
     pub model: Option<Value<String>>,
@@ -128,10 +181,47 @@ pub struct DiceTcbInfoExtension {
     pub svn: Option<Value<BigUint>>,
     /// TCB layer.
     pub layer: Option<Value<BigUint>>,
+    /// TCB index, distinguishes TCBs of the same layer.
+    pub index: Option<Value<BigUint>>,
     /// TCB firmware IDs.
     pub fw_ids: Option<Vec<FirmwareId>>,
     /// TCB flags.
     pub flags: Option<DiceTcbInfoFlags>,
+    /// Vendor-defined information.
+    pub vendor_info: Option<Value<Vec<u8>>>,
+    /// TCB type, vendor-defined.
+    pub tcb_type: Option<Value<Vec<u8>>>,
+    /// TCB integrity registers.
+    pub integrity_registers: Option<Vec<IntegrityRegister>>,
+}
+
+/// DICE TCB integrity register: a register (such as a PCR) holding
+/// measurements of the TCB.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct IntegrityRegister {
+    /// Register name.
+    pub name: Option<Value<String>>,
+    /// Register number.
+    pub number: Option<Value<BigUint>>,
+    /// Register digests.
+    pub digests: Vec<FirmwareId>,
+}
+
+/// DICE multi-TCB extension: the TCB info of several layers or components.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiceMultiTcbInfoExtension {
+    /// TCB infos, there must be at least one.
+    pub tcb_infos: Vec<DiceTcbInfoExtension>,
+}
+
+/// DICE UEID extension.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiceUeidExtension {
+    /// Universal entity ID of the device.
+    pub ueid: Value<Vec<u8>>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Hash, strum::Display, Serialize)]
@@ -173,6 +263,12 @@ pub struct Variable {
     pub convert: Option<Conversion>,
 }
 
+impl<T: Default> Default for Value<T> {
+    fn default() -> Self {
+        Value::Literal(T::default())
+    }
+}
+
 impl<T> Value<T> {
     /// Create a variable with the given name. No conversion applied.
     pub fn variable(name: &str) -> Self {
@@ -572,10 +668,10 @@ mod tests {
             subject_key_identifier: Value::variable("owner_pub_key_id"),
             basic_constraints: None,
             key_usage: Some(KeyUsage {
-                digital_signature: None,
-                key_agreement: Some(Value::literal(true)),
-                cert_sign: None,
+                key_agreement: Value::literal(true),
+                ..Default::default()
             }),
+            extended_key_usage: vec![],
             subject_alt_name: vec![],
             private_extensions: vec![CertificateExtension::DiceTcbInfo(DiceTcbInfoExtension {
                 vendor: Some(Value::literal("OpenTitan")),
@@ -599,6 +695,7 @@ mod tests {
                     recovery: Value::Literal(true),
                     debug: Value::Literal(false),
                 }),
+                ..Default::default()
             })],
             signature: Signature::EcdsaWithSha256 {
                 value: Some(EcdsaSignature {
@@ -13,9 +13,10 @@ use num_traits::Num;
 use serde::{Deserialize, Serialize};
 
 use crate::template::{
-    BasicConstraints, Certificate, CertificateExtension, Conversion, DiceTcbInfoExtension,
-    DiceTcbInfoFlags, EcPublicKey, EcPublicKeyInfo, EcdsaSignature, FirmwareId, KeyUsage,
-    Signature, SubjectPublicKeyInfo, Template, Value, Variable, VariableType,
+    BasicConstraints, Certificate, CertificateExtension, Conversion, DiceMultiTcbInfoExtension,
+    DiceTcbInfoExtension, DiceTcbInfoFlags, DiceUeidExtension, EcPublicKey, EcPublicKeyInfo,
+    EcdsaSignature, FirmwareId, IntegrityRegister, KeyUsage, RawExtension, Signature,
+    SubjectPublicKeyInfo, Template, Value, Variable, VariableType,
 };
 
 /// Substitution value: this is the raw value loaded from a hjson/json file
@@ -366,6 +367,7 @@ impl Subst for Certificate {
                 .key_usage
                 .subst(data)
                 .context("cannot substitute key usage")?,
+            extended_key_usage: self.extended_key_usage.clone(),
             private_extensions: self
                 .private_extensions
                 .iter()
@@ -389,6 +391,10 @@ impl Subst for BasicConstraints {
     fn subst(&self, data: &SubstData) -> Result<BasicConstraints> {
         Ok(BasicConstraints {
             ca: self.ca.subst(data)?,
+            path_len_constraint: self
+                .path_len_constraint
+                .subst(data)
+                .context("cannot substitute path length constraint")?,
         })
     }
 }
@@ -400,6 +406,30 @@ impl Subst for CertificateExtension {
                 dice.subst(data)
                     .context("cannot substitute in DICE extension")?,
             )),
+            CertificateExtension::DiceMultiTcbInfo(multi) => Ok(
+                CertificateExtension::DiceMultiTcbInfo(DiceMultiTcbInfoExtension {
+                    tcb_infos: multi
+                        .tcb_infos
+                        .subst(data)
+                        .context("cannot substitute in DICE multi-TCB extension")?,
+                }),
+            ),
+            CertificateExtension::DiceUeid(ueid) => {
+                Ok(CertificateExtension::DiceUeid(DiceUeidExtension {
+                    ueid: ueid
+                        .ueid
+                        .subst(data)
+                        .context("cannot substitute DICE UEID")?,
+                }))
+            }
+            CertificateExtension::Raw(raw) => Ok(CertificateExtension::Raw(RawExtension {
+                oid: raw.oid.clone(),
+                critical: raw.critical,
+                value: raw
+                    .value
+                    .subst(data)
+                    .with_context(|| format!("cannot substitute in extension {}", raw.oid))?,
+            })),
         }
     }
 }
@@ -424,6 +454,10 @@ impl Subst for DiceTcbInfoExtension {
                 .layer
                 .subst(data)
                 .context("cannot substitute DICE layer")?,
+            index: self
+                .index
+                .subst(data)
+                .context("cannot substitute DICE index")?,
             fw_ids: self
                 .fw_ids
                 .subst(data)
@@ -432,6 +466,28 @@ impl Subst for DiceTcbInfoExtension {
                 .flags
                 .subst(data)
                 .context("cannot substitute DICE flags")?,
+            vendor_info: self
+                .vendor_info
+                .subst(data)
+                .context("cannot substitute DICE vendor info")?,
+            tcb_type: self
+                .tcb_type
+                .subst(data)
+                .context("cannot substitute DICE type")?,
+            integrity_registers: self
+                .integrity_registers
+                .subst(data)
+                .context("cannot substitute DICE integrity registers")?,
+        })
+    }
+}
+
+impl Subst for IntegrityRegister {
+    fn subst(&self, data: &SubstData) -> Result<IntegrityRegister> {
+        Ok(IntegrityRegister {
+            name: self.name.subst(data)?,
+            number: self.number.subst(data)?,
+            digests: self.digests.subst(data)?,
         })
     }
 }
@@ -475,6 +531,18 @@ impl Subst for KeyUsage {
                 .digital_signature
                 .subst(data)
                 .context("cannot substitute digital signature key usage")?,
+            non_repudiation: self
+                .non_repudiation
+                .subst(data)
+                .context("cannot substitute non repudiation")?,
+            key_encipherment: self
+                .key_encipherment
+                .subst(data)
+                .context("cannot substitute key encipherment")?,
+            data_encipherment: self
+                .data_encipherment
+                .subst(data)
+                .context("cannot substitute data encipherment")?,
             key_agreement: self
                 .key_agreement
                 .subst(data)
@@ -483,6 +551,18 @@ impl Subst for KeyUsage {
                 .cert_sign
                 .subst(data)
                 .context("cannot substitute cert sign")?,
+            crl_sign: self
+                .crl_sign
+                .subst(data)
+                .context("cannot substitute CRL sign")?,
+            encipher_only: self
+                .encipher_only
+                .subst(data)
+                .context("cannot substitute encipher only")?,
+            decipher_only: self
+                .decipher_only
+                .subst(data)
+                .context("cannot substitute decipher only")?,
         })
     }
 }
@@ -7,9 +7,7 @@ use indexmap::IndexMap;
 use num_bigint_dig::BigUint;
 
 use foreign_types::ForeignTypeRef;
-use openssl::asn1::{
-    Asn1IntegerRef, Asn1ObjectRef, Asn1OctetStringRef, Asn1StringRef, Asn1TimeRef,
-};
+use openssl::asn1::{Asn1IntegerRef, Asn1ObjectRef, Asn1StringRef, Asn1TimeRef};
 use openssl::bn::{BigNum, BigNumContext, BigNumRef};
 use openssl::ec::{EcGroupRef, EcKey};
 use openssl::ecdsa::EcdsaSig;
@@ -22,7 +20,7 @@ use crate::asn1::der;
 use crate::asn1::x509;
 
 use crate::template::{
-    self, AttributeType, EcCurve, EcPublicKeyInfo, EcdsaSignature, KeyUsage, Name, Signature,
+    self, AttributeType, EcCurve, EcPublicKeyInfo, EcdsaSignature, Name, Signature,
     SubjectPublicKeyInfo, Value,
 };
 
@@ -84,31 +82,36 @@ fn asn1str_to_str(field: &str, s: &Asn1StringRef) -> Result<Value<String>> {
     ))
 }
 
-fn asn1octets_to_vec(s: &Asn1OctetStringRef) -> Value<Vec<u8>> {
-    Value::literal(s.as_slice().to_vec())
-}
-
 fn asn1time_to_string(time: &Asn1TimeRef) -> Result<Value<String>> {
     // OpenSSL guarantees that an ASN1_TIME is in fact just a typedef for ASN1_STRING
     // https://www.openssl.org/docs/man1.1.1/man3/ASN1_TIME_to_generalizedtime.html
     let time_str = time.as_ptr() as *mut openssl_sys::ASN1_STRING;
     // SAFETY: the above pointer is guaranteed to be valid since `time` is valid reference.
     let time_type = unsafe { openssl_sys::ASN1_STRING_type(time_str) };
-    // FIXME: we only support GeneralizedTime and openssl_sys does not export the OpenSSL method to
-    // convert to a GeneralizedTime so we just error out.
-    if time_type == openssl_sys::V_ASN1_UTCTIME {
-        bail!("time uses UtcTime but only GeneralizedTime is supported")
-    }
-    if time_type != openssl_sys::V_ASN1_GENERALIZEDTIME {
-        bail!("time uses type {time_type} but only GeneralizedTime is supported")
+    if time_type != openssl_sys::V_ASN1_UTCTIME && time_type != openssl_sys::V_ASN1_GENERALIZEDTIME
+    {
+        bail!("time uses type {time_type} but only UtcTime and GeneralizedTime are supported")
     }
     // SAFETY: the above pointer is guaranteed to be valid since `time` is valid reference.
     let time_str = unsafe { Asn1StringRef::from_ptr(time_str) }.as_slice();
-    Ok(Value::literal(
-        std::str::from_utf8(time_str)
-            .context("GeneralizedTime is not a valid UTF8 string")?
-            .to_string(),
-    ))
+    let time_str = std::str::from_utf8(time_str).context("time is not a valid UTF8 string")?;
+    if time_type == openssl_sys::V_ASN1_UTCTIME {
+        Ok(Value::literal(utc_time_to_generalized_time(time_str)?))
+    } else {
+        Ok(Value::literal(time_str.to_string()))
+    }
+}
+
+/// Converts a DER UtcTime (`YYMMDDHHMMSSZ`) to a GeneralizedTime
+/// (`YYYYMMDDHHMMSSZ`).  As specified by RFC 5280, years 50 to 99 are in the
+/// 20th century and years 00 to 49 are in the 21st century.
+fn utc_time_to_generalized_time(time: &str) -> Result<String> {
+    ensure!(
+        time.len() == 13 && time.ends_with('Z') && time[..12].bytes().all(|b| b.is_ascii_digit()),
+        "invalid UtcTime {time:?}"
+    );
+    let century = if &time[..2] >= "50" { "19" } else { "20" };
+    Ok(format!("{century}{time}"))
 }
 
 fn asn1name_to_name(field: &str, name: &X509NameRef) -> Result<Name> {
@@ -180,14 +183,18 @@ pub fn generate_certificate_from_tbs(tbs: Vec<u8>, signature: &Signature) -> Res
     Ok(cert)
 }
 
+/// Generate the X509 TBS certificate from a template. The template must specify
+/// all variables used by the TBS; the values of the signature are not needed.
+pub fn generate_tbs_certificate(tmpl: &template::Template) -> Result<Vec<u8>> {
+    der::Der::generate(|builder| x509::X509::push_tbs_certificate(builder, &tmpl.certificate))
+}
+
 /// Generate a X509 certificate from a template that specifies all variables.
 /// If the template does not specify the values of the signature, a signature
 /// with "zero" values will be generated.
 pub fn generate_certificate(tmpl: &template::Template) -> Result<Vec<u8>> {
     // Generate TBS.
-    let tbs =
-        der::Der::generate(|builder| x509::X509::push_tbs_certificate(builder, &tmpl.certificate))?;
-    let tbs = Value::Literal(tbs);
+    let tbs = Value::Literal(generate_tbs_certificate(tmpl)?);
     // Generate certificate.
     let cert = der::Der::generate(|builder| {
         x509::X509::push_certificate(builder, &tbs, &tmpl.certificate.signature)
@@ -214,35 +221,74 @@ fn get_subject_alt_name(x509: &X509) -> Result<Name> {
     asn1name_to_name("Subject Alternative Names", x509_name_ref)
 }
 
-/// Parse a X509 certificate
+/// Record the value of an extension that must appear at most once.
+fn set_once<T>(slot: &mut Option<T>, name: &str, value: T) -> Result<()> {
+    ensure!(
+        slot.is_none(),
+        "certificate contains several {name} extensions"
+    );
+    *slot = Some(value);
+    Ok(())
+}
+
+/// Parse a X509 certificate.
+///
+/// The standard extensions that the template models are parsed into their
+/// respective fields and all other extensions are preserved as raw extensions,
+/// so that parsing a certificate generated from a template gives back the
+/// template (with all variables substituted). Note that the critical marker of
+/// the standard extensions is not recorded: the generator always uses the
+/// values recommended by RFC 5280.
 pub fn parse_certificate(cert: &[u8]) -> Result<template::Certificate> {
     let x509 = X509::from_der(cert).context("could not parse certificate with openssl")?;
     let raw_extensions =
         extension::x509_get_extensions(&x509).context("could not parse X509 extensions")?;
     let mut private_extensions = Vec::new();
     let mut basic_constraints = None;
-    let mut key_usage: Option<KeyUsage> = None;
+    let mut key_usage = None;
+    let mut extended_key_usage = None;
+    let mut subject_alt_name_ext = false;
+    let mut authority_key_identifier = None;
+    let mut subject_key_identifier = None;
     for ext in raw_extensions {
         match ext.object.nid() {
-            // Ignore extensions that are standard and handled by openssl.
-            Nid::BASIC_CONSTRAINTS => {
+            Nid::BASIC_CONSTRAINTS => set_once(
+                &mut basic_constraints,
+                "basic constraints",
+                extension::parse_basic_constraints(&ext)
+                    .context("could not parse X509 basic constraints")?,
+            )?,
+            Nid::KEY_USAGE => set_once(
+                &mut key_usage,
+                "key usage",
+                extension::parse_key_usage(&ext).context("could not parse X509 key usage")?,
+            )?,
+            Nid::EXT_KEY_USAGE => set_once(
+                &mut extended_key_usage,
+                "extended key usage",
+                extension::parse_extended_key_usage(&ext)
+                    .context("could not parse X509 extended key usage")?,
+            )?,
+            Nid::SUBJECT_ALT_NAME => {
+                // The subject alt name is parsed by openssl below.
                 ensure!(
-                    basic_constraints.is_none(),
-                    "certificate contains several basic constraints extensions"
-                );
-                basic_constraints = Some(
-                    extension::parse_basic_constraints(&ext)
-                        .context("could not parse X509 basic constraints")?,
-                );
-            }
-            Nid::KEY_USAGE => {
-                key_usage = Some(
-                    extension::parse_key_usage(&ext).context("could not parse X509 key usage")?,
+                    !subject_alt_name_ext,
+                    "certificate contains several subject alt name extensions"
                 );
+                subject_alt_name_ext = true;
             }
-            Nid::AUTHORITY_KEY_IDENTIFIER => (),
-            Nid::SUBJECT_ALT_NAME => (),
-            Nid::SUBJECT_KEY_IDENTIFIER => (),
+            Nid::AUTHORITY_KEY_IDENTIFIER => set_once(
+                &mut authority_key_identifier,
+                "authority key identifier",
+                extension::parse_authority_key_identifier(&ext)
+                    .context("could not parse X509 authority key identifier")?,
+            )?,
+            Nid::SUBJECT_KEY_IDENTIFIER => set_once(
+                &mut subject_key_identifier,
+                "subject key identifier",
+                extension::parse_subject_key_identifier(&ext)
+                    .context("could not parse X509 subject key identifier")?,
+            )?,
             _ => private_extensions
                 .push(extension::parse_extension(&ext).context("could not parse X509 extension")?),
         }
@@ -262,16 +308,13 @@ pub fn parse_certificate(cert: &[u8]) -> Result<template::Certificate> {
             .context("cannot parse not_before time")?,
         not_after: asn1time_to_string(x509.not_after()).context("cannot parse not_after time")?,
         subject_public_key_info,
-        authority_key_identifier: asn1octets_to_vec(
-            x509.authority_key_id()
-                .context("the certificate has not authority key id")?,
-        ),
-        subject_key_identifier: asn1octets_to_vec(
-            x509.subject_key_id()
-                .context("the certificate has not subject key id")?,
-        ),
+        authority_key_identifier: authority_key_identifier
+            .context("the certificate has not authority key id")?,
+        subject_key_identifier: subject_key_identifier
+            .context("the certificate has not subject key id")?,
         basic_constraints,
         key_usage,
+        extended_key_usage: extended_key_usage.unwrap_or_default(),
         subject_alt_name: get_subject_alt_name(&x509)?,
         private_extensions,
         signature: extract_signature(&x509)?,
@@ -11,8 +11,9 @@ use openssl::x509::X509;
 
 use crate::asn1::Oid;
 use crate::template::{
-    BasicConstraints, CertificateExtension, DiceTcbInfoExtension, DiceTcbInfoFlags, FirmwareId,
-    HashAlgorithm, KeyUsage, Value,
+    BasicConstraints, CertificateExtension, DiceMultiTcbInfoExtension, DiceTcbInfoExtension,
+    DiceTcbInfoFlags, DiceUeidExtension, FirmwareId, HashAlgorithm, IntegrityRegister, KeyPurpose,
+    KeyUsage, RawExtension, Value,
 };
 
 /// X509 extension reference.
@@ -94,7 +95,9 @@ pub fn x509_get_extensions(x509: &X509) -> Result<Vec<X509ExtensionRef>> {
 //     fwids [6] IMPLICIT FWIDLIST OPTIONAL,
 //     flags [7] IMPLICIT OperationalFlags OPTIONAL,
 //     vendorInfo [8] IMPLICIT OCTET STRING OPTIONAL,
-//     type [9] IMPLICIT OCTET STRING OPTIONAL
+//     type [9] IMPLICIT OCTET STRING OPTIONAL,
+//     flagsMask [10] IMPLICIT OperationalFlagsMask OPTIONAL,
+//     integrityRegisters [11] IMPLICIT IrList OPTIONAL
 // }
 // FWIDLIST ::== SEQUENCE SIZE (1..MAX) OF FWID
 //     FWID ::== SEQUENCE {
@@ -107,6 +110,20 @@ pub fn x509_get_extensions(x509: &X509) -> Result<Vec<X509ExtensionRef>> {
 //     recovery (2),
 //     debug (3)
 // }
+// IrList ::= SEQUENCE SIZE (1..MAX) OF IntegrityRegister
+// IntegrityRegister ::= SEQUENCE {
+//     registerName IA5String OPTIONAL,
+//     registerNum INTEGER OPTIONAL,
+//     registerDigests FWIDLIST
+// }
+//
+// tcg-dice-MultiTcbInfo OBJECT IDENTIFIER ::= {tcg-dice 5}
+// DiceTcbInfoSeq ::= SEQUENCE SIZE (1..MAX) OF DiceTcbInfo
+//
+// tcg-dice-Ueid OBJECT IDENTIFIER ::= {tcg-dice 4}
+// TcgUeid ::== SEQUENCE {
+//     ueid OCTET STRING
+// }
 
 // See DiceTcbInfo.
 #[derive(asn1::Asn1Read)]
@@ -115,6 +132,14 @@ struct Fwid<'a> {
     pub digest: &'a [u8],
 }
 
+// See DiceTcbInfo.
+#[derive(asn1::Asn1Read)]
+struct IntegrityRegisterInternal<'a> {
+    pub name: Option<asn1::IA5String<'a>>,
+    pub number: Option<asn1::BigInt<'a>>,
+    pub digests: asn1::SequenceOf<'a, Fwid<'a>>,
+}
+
 // This is an internal structure used to parse a DiceTcbInfo extension using the `asn1`
 // crate. We cannot use the `DiceTcbInfoExtension` in `template` since we
 // need to use specific annotations and types so that the `asn` library can
@@ -141,6 +166,16 @@ struct DiceTcbInfo<'a> {
     pub vendor_info: Option<&'a [u8]>,
     #[implicit(9)]
     pub tcb_type: Option<&'a [u8]>,
+    #[implicit(10)]
+    pub flags_mask: Option<asn1::BitString<'a>>,
+    #[implicit(11)]
+    pub integrity_registers: Option<asn1::SequenceOf<'a, IntegrityRegisterInternal<'a>>>,
+}
+
+// See DiceTcbInfo.
+#[derive(asn1::Asn1Read)]
+struct TcgUeid<'a> {
+    pub ueid: &'a [u8],
 }
 
 fn convert_hash_algorithm(objid: &asn1::ObjectIdentifier) -> Result<HashAlgorithm> {
@@ -163,38 +198,56 @@ fn asn1bigint_to_bn(bn: &asn1::BigInt) -> Value<BigUint> {
     Value::literal(BigUint::from_bytes_be(bn.as_bytes()))
 }
 
+fn convert_fwids<'a>(fwids: &asn1::SequenceOf<'a, Fwid<'a>>) -> Result<Vec<FirmwareId>> {
+    fwids
+        .clone()
+        .map(|fwid| {
+            Ok(FirmwareId {
+                hash_algorithm: convert_hash_algorithm(&fwid.hash_alg)
+                    .context("unknown hash algorithm")?,
+                digest: Value::literal(fwid.digest.to_vec()),
+            })
+        })
+        .collect()
+}
+
+impl IntegrityRegisterInternal<'_> {
+    fn to_integrity_register(&self) -> Result<IntegrityRegister> {
+        Ok(IntegrityRegister {
+            name: self
+                .name
+                .as_ref()
+                .map(|name| Value::literal(name.as_str().to_string())),
+            number: self.number.as_ref().map(asn1bigint_to_bn),
+            digests: convert_fwids(&self.digests)?,
+        })
+    }
+}
+
 impl DiceTcbInfo<'_> {
     fn to_dice_extension(&self) -> Result<DiceTcbInfoExtension> {
         let fw_ids = self
             .fwids
             .as_ref()
-            .map(|fwids| {
-                fwids
+            .map(convert_fwids)
+            .transpose()
+            .context("cannot parse DICE TCB firmware IDs")?;
+        let integrity_registers = self
+            .integrity_registers
+            .as_ref()
+            .map(|registers| {
+                registers
                     .clone()
-                    .map(|fwid| {
-                        Ok(FirmwareId {
-                            hash_algorithm: convert_hash_algorithm(&fwid.hash_alg)
-                                .context("unknown hash algorithm")?,
-                            digest: Value::literal(fwid.digest.to_vec()),
-                        })
-                    })
+                    .map(|register| register.to_integrity_register())
                     .collect::<Result<Vec<_>>>()
             })
             .transpose()
-            .context("cannot parse DICE TCB firmware IDs")?;
+            .context("cannot parse DICE TCB integrity registers")?;
 
-        // Vendor info is not supported.
+        // Flags mask is not supported.
         ensure!(
-            self.index.is_none(),
-            "the parser does not support DICE TCB index"
-        );
-        ensure!(
-            self.vendor_info.is_none(),
-            "the parser does not support DICE TCB vendor info"
-        );
-        ensure!(
-            self.tcb_type.is_none(),
-            "the parser does not support DICE TCB type"
+            self.flags_mask.is_none(),
+            "the parser does not support DICE TCB flags mask"
         );
 
         Ok(DiceTcbInfoExtension {
@@ -203,8 +256,14 @@ impl DiceTcbInfo<'_> {
             version: self.version.as_ref().map(asn1utf8_to_str),
             svn: self.svn.as_ref().map(asn1bigint_to_bn),
             layer: self.layer.as_ref().map(asn1bigint_to_bn),
+            index: self.index.as_ref().map(asn1bigint_to_bn),
             fw_ids,
             flags: self.flags.clone(),
+            vendor_info: self.vendor_info.map(|info| Value::literal(info.to_vec())),
+            tcb_type: self
+                .tcb_type
+                .map(|tcb_type| Value::literal(tcb_type.to_vec())),
+            integrity_registers,
         })
     }
 }
@@ -245,23 +304,24 @@ impl<'a> asn1::SimpleAsn1Readable<'a> for KeyUsage {
     fn parse_data(_data: &'a [u8]) -> asn1::ParseResult<Self> {
         let result = asn1::OwnedBitString::parse_data(_data)?;
         let bs = result.as_bitstring();
-        // List of bits that this parser supports. Any bit set outside of
-        // these will be an error because we cannot record it.
-        const PARSED_BITS: &[usize] = &[0, 4, 5];
+        // Any bit set after decipherOnly is an error because we cannot record it.
+        const NR_BITS: usize = 9;
         let len = bs.as_bytes().len() * 8 - bs.padding_bits() as usize;
-        for i in 0..len {
-            if bs.has_bit_set(i) && !PARSED_BITS.contains(&i) {
-                // FIXME This will not return a very readable error message but the asn1
-                // does not support arbitrary string errors.
-                return asn1::ParseResult::Err(asn1::ParseError::new(
-                    asn1::ParseErrorKind::ExtraData,
-                ));
-            }
+        if (NR_BITS..len).any(|i| bs.has_bit_set(i)) {
+            // FIXME This will not return a very readable error message but the asn1
+            // does not support arbitrary string errors.
+            return asn1::ParseResult::Err(asn1::ParseError::new(asn1::ParseErrorKind::ExtraData));
         }
         Ok(KeyUsage {
-            digital_signature: Some(Value::Literal(bs.has_bit_set(0))),
-            key_agreement: Some(Value::Literal(bs.has_bit_set(4))),
-            cert_sign: Some(Value::Literal(bs.has_bit_set(5))),
+            digital_signature: Value::Literal(bs.has_bit_set(0)),
+            non_repudiation: Value::Literal(bs.has_bit_set(1)),
+            key_encipherment: Value::Literal(bs.has_bit_set(2)),
+            data_encipherment: Value::Literal(bs.has_bit_set(3)),
+            key_agreement: Value::Literal(bs.has_bit_set(4)),
+            cert_sign: Value::Literal(bs.has_bit_set(5)),
+            crl_sign: Value::Literal(bs.has_bit_set(6)),
+            encipher_only: Value::Literal(bs.has_bit_set(7)),
+            decipher_only: Value::Literal(bs.has_bit_set(8)),
         })
     }
 }
@@ -273,6 +333,23 @@ pub fn parse_dice_tcb_info_extension(ext: &[u8]) -> Result<DiceTcbInfoExtension>
         .to_dice_extension()
 }
 
+/// Try to parse an X509 extension as a DICE multi-TCB info extension.
+pub fn parse_dice_multi_tcb_info_extension(ext: &[u8]) -> Result<DiceMultiTcbInfoExtension> {
+    let tcb_infos = asn1::parse_single::<asn1::SequenceOf<DiceTcbInfo>>(ext)
+        .context("cannot parse DICE multi-TCB info extension")?
+        .map(|tcb_info| tcb_info.to_dice_extension())
+        .collect::<Result<Vec<_>>>()?;
+    Ok(DiceMultiTcbInfoExtension { tcb_infos })
+}
+
+/// Try to parse an X509 extension as a DICE UEID extension.
+pub fn parse_dice_ueid_extension(ext: &[u8]) -> Result<DiceUeidExtension> {
+    let ueid = asn1::parse_single::<TcgUeid>(ext).context("cannot parse DICE UEID extension")?;
+    Ok(DiceUeidExtension {
+        ueid: Value::literal(ueid.ueid.to_vec()),
+    })
+}
+
 // This is an internal structure used to parse a Basic Constraints extension using the `asn1`
 // crate. We cannot use the `BasicConstraints` in `template` since we
 // need to use specific annotations and types so that the `asn` library can
@@ -280,35 +357,122 @@ pub fn parse_dice_tcb_info_extension(ext: &[u8]) -> Result<DiceTcbInfoExtension>
 #[derive(asn1::Asn1Read)]
 struct BasicConstraintsInternal {
     ca: bool,
+    path_len_constraint: Option<u64>,
 }
 
 impl BasicConstraintsInternal {
     fn to_basic_constraints(&self) -> Result<BasicConstraints> {
         Ok(BasicConstraints {
             ca: Value::Literal(self.ca),
+            path_len_constraint: self
+                .path_len_constraint
+                .map(|len| Value::Literal(BigUint::from(len))),
         })
     }
 }
 
+// From https://datatracker.ietf.org/doc/html/rfc5280#section-4.2.1.1
+// AuthorityKeyIdentifier ::= SEQUENCE {
+//   keyIdentifier             [0] KeyIdentifier           OPTIONAL,
+//   authorityCertIssuer       [1] GeneralNames            OPTIONAL,
+//   authorityCertSerialNumber [2] CertificateSerialNumber OPTIONAL  }
+//
+// KeyIdentifier ::= OCTET STRING
+#[derive(asn1::Asn1Read)]
+struct AuthorityKeyIdentifier<'a> {
+    #[implicit(0)]
+    key_identifier: Option<&'a [u8]>,
+    #[implicit(1)]
+    #[allow(dead_code)]
+    authority_cert_issuer: Option<asn1::Sequence<'a>>,
+    #[implicit(2)]
+    #[allow(dead_code)]
+    authority_cert_serial_number: Option<asn1::BigInt<'a>>,
+}
+
 pub fn parse_key_usage(ext: &X509ExtensionRef) -> Result<KeyUsage> {
     Ok(asn1::parse_single::<KeyUsage>(ext.data.as_slice())?)
 }
 
 pub fn parse_basic_constraints(ext: &X509ExtensionRef) -> Result<BasicConstraints> {
     asn1::parse_single::<BasicConstraintsInternal>(ext.data.as_slice())
-        .context("cannot parse DICE extension")?
+        .context("cannot parse basic constraints extension")?
         .to_basic_constraints()
 }
 
-/// Try to parse an X509 extension.
+fn key_purpose_from_oid(oid: &asn1::ObjectIdentifier) -> KeyPurpose {
+    let oid = oid.to_string();
+    for purpose in [
+        KeyPurpose::ServerAuth,
+        KeyPurpose::ClientAuth,
+        KeyPurpose::CodeSigning,
+        KeyPurpose::EmailProtection,
+        KeyPurpose::TimeStamping,
+        KeyPurpose::OcspSigning,
+        KeyPurpose::TcgKpEkCertificate,
+    ] {
+        if purpose.oid().oid() == oid {
+            return purpose;
+        }
+    }
+    KeyPurpose::Oid(oid)
+}
+
+// From https://datatracker.ietf.org/doc/html/rfc5280#section-4.2.1.12
+// ExtKeyUsageSyntax ::= SEQUENCE SIZE (1..MAX) OF KeyPurposeId
+//
+// KeyPurposeId ::= OBJECT IDENTIFIER
+pub fn parse_extended_key_usage(ext: &X509ExtensionRef) -> Result<Vec<KeyPurpose>> {
+    let purposes =
+        asn1::parse_single::<asn1::SequenceOf<asn1::ObjectIdentifier>>(ext.data.as_slice())
+            .context("cannot parse extended key usage extension")?;
+    Ok(purposes.map(|oid| key_purpose_from_oid(&oid)).collect())
+}
+
+pub fn parse_authority_key_identifier(ext: &X509ExtensionRef) -> Result<Value<Vec<u8>>> {
+    let aki = asn1::parse_single::<AuthorityKeyIdentifier>(ext.data.as_slice())
+        .context("cannot parse authority key identifier extension")?;
+    // The template only records the key identifier: the issuer and serial number of the
+    // authority certificate, which some CAs also include, are ignored.
+    let key_id = aki
+        .key_identifier
+        .context("the authority key identifier has no key identifier")?;
+    Ok(Value::literal(key_id.to_vec()))
+}
+
+// From https://datatracker.ietf.org/doc/html/rfc5280#section-4.2.1.2
+// SubjectKeyIdentifier ::= KeyIdentifier
+pub fn parse_subject_key_identifier(ext: &X509ExtensionRef) -> Result<Value<Vec<u8>>> {
+    let key_id = asn1::parse_single::<&[u8]>(ext.data.as_slice())
+        .context("cannot parse subject key identifier extension")?;
+    Ok(Value::literal(key_id.to_vec()))
+}
+
+/// Try to parse an X509 extension. Extensions that the template does not model
+/// are returned as raw extensions.
 pub fn parse_extension(ext: &X509ExtensionRef) -> Result<CertificateExtension> {
-    let dice_oid =
-        Asn1Object::from_str(Oid::DiceTcbInfo.oid()).expect("cannot create object ID from string");
+    let object =
+        |oid: Oid| Asn1Object::from_str(oid.oid()).expect("cannot create object ID from string");
+    let dice_oid = object(Oid::DiceTcbInfo);
+    let dice_multi_oid = object(Oid::DiceMultiTcbInfo);
+    let dice_ueid_oid = object(Oid::DiceUeid);
     // The openssl library does not provide a way to compare between two Asn1Object so compare the raw DER.
     Ok(match ext.object.to_owned().as_slice() {
         obj if obj == dice_oid.as_slice() => {
             CertificateExtension::DiceTcbInfo(parse_dice_tcb_info_extension(ext.data.as_slice())?)
         }
-        _ => bail!("unknown extension type {}", ext.object,),
+        obj if obj == dice_multi_oid.as_slice() => CertificateExtension::DiceMultiTcbInfo(
+            parse_dice_multi_tcb_info_extension(ext.data.as_slice())?,
+        ),
+        obj if obj == dice_ueid_oid.as_slice() => {
+            CertificateExtension::DiceUeid(parse_dice_ueid_extension(ext.data.as_slice())?)
+        }
+        obj => CertificateExtension::Raw(RawExtension {
+            oid: asn1::ObjectIdentifier::from_der(obj)
+                .with_context(|| format!("invalid extension type {}", ext.object))?
+                .to_string(),
+            critical: ext.critical,
+            value: Value::literal(ext.data.as_slice().to_vec()),
+        }),
     })
 }
@@ -10,7 +10,7 @@ use num_traits::Num;
 
 use ot_certs::asn1::der::Der;
 use ot_certs::template::{
-    DiceTcbInfoExtension, DiceTcbInfoFlags, FirmwareId, HashAlgorithm, Value,
+    DiceTcbInfoExtension, DiceTcbInfoFlags, FirmwareId, HashAlgorithm, IntegrityRegister, Value,
 };
 use ot_certs::x509::extension::parse_dice_tcb_info_extension;
 
@@ -47,6 +47,7 @@ fn main() -> Result<()> {
             recovery: Value::Literal(false),
             debug: Value::Literal(true),
         }),
+        ..Default::default()
     })?;
 
     check_dice_tcb_info(DiceTcbInfoExtension {
@@ -75,6 +76,40 @@ fn main() -> Result<()> {
             },
         ]),
         flags: None,
+        ..Default::default()
+    })?;
+
+    check_dice_tcb_info(DiceTcbInfoExtension {
+        vendor: Some(Value::Literal("Vendor".into())),
+        layer: Some(Value::Literal(BigUint::from_u32(2).unwrap())),
+        index: Some(Value::Literal(BigUint::from_u32(1).unwrap())),
+        vendor_info: Some(Value::Literal(vec![0x01, 0x02, 0x03])),
+        tcb_type: Some(Value::Literal(b"ROM_EXT".to_vec())),
+        integrity_registers: Some(vec![
+            IntegrityRegister {
+                name: Some(Value::Literal("PCR0".into())),
+                number: None,
+                digests: vec![FirmwareId {
+                    hash_algorithm: HashAlgorithm::Sha256,
+                    digest: Value::Literal(vec![0x11; 32]),
+                }],
+            },
+            IntegrityRegister {
+                name: None,
+                number: Some(Value::Literal(BigUint::from_u32(7).unwrap())),
+                digests: vec![
+                    FirmwareId {
+                        hash_algorithm: HashAlgorithm::Sha256,
+                        digest: Value::Literal(vec![0x22; 32]),
+                    },
+                    FirmwareId {
+                        hash_algorithm: HashAlgorithm::Sha256,
+                        digest: Value::Literal(vec![0x33; 32]),
+                    },
+                ],
+            },
+        ]),
+        ..Default::default()
     })?;
 
     Ok(())
@@ -2,35 +2,105 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
+//! Check that parsing a certificate generated from a template gives back the
+//! template, for every example template.
+
 use anyhow::{bail, Result};
 use base64ct::Encoding;
+use num_bigint_dig::BigUint;
+use openssl::x509::X509;
 
 use ot_certs::template::subst::Subst;
-use ot_certs::template::Template;
+use ot_certs::template::{Template, Value};
 use ot_certs::x509;
 
 const GENERIC_CERT: &str = include_str!("generic.hjson");
+const EXAMPLE_CERT: &str = include_str!("example.hjson");
+const EXTENSIONS_CERT: &str = include_str!("extensions.hjson");
+const UDS_CERT: &str = include_str!("../../../device/silicon_creator/lib/cert/uds.hjson");
+const CDI_0_CERT: &str = include_str!("../../../device/silicon_creator/lib/cert/cdi_0.hjson");
+const CDI_1_CERT: &str = include_str!("../../../device/silicon_creator/lib/cert/cdi_1.hjson");
+const TPM_EK_CERT: &str = include_str!("../../../device/silicon_creator/lib/cert/tpm_ek.hjson");
+const FAKE_CA_PEM: &[u8] =
+    include_bytes!("../../../device/silicon_creator/manuf/keys/fake/fake_ca.pem");
 
-#[test]
-fn main() -> Result<()> {
-    // Parse generic certificate.
-    let generic_tmpl =
-        Template::from_hjson_str(GENERIC_CERT).expect("failed to parse generic template");
+fn round_trip(template: &str) -> Result<()> {
+    // Parse the template.
+    let tmpl = Template::from_hjson_str(template).expect("failed to parse template");
     // Generate some random test data.
-    let test_data = generic_tmpl.random_test()?;
+    let test_data = tmpl.random_test()?;
     // Substitute data into the template.
-    let cert = generic_tmpl.subst(&test_data)?;
+    let cert = tmpl.subst(&test_data)?;
     // Use DER to generate a binary certificate.
     let der_cert = x509::generate_certificate(&cert)?;
-    // Use openssl to parse the binary certificate.
+    // Parse the binary certificate.
     let parsed_cert = x509::parse_certificate(&der_cert)?;
     // Check that this is exactly what we started with.
-    println!("expected: {:#?}", cert.certificate);
-    println!("got: {parsed_cert:#?}");
-    println!("DER: {}", base64ct::Base64::encode_string(&der_cert));
     if cert.certificate != parsed_cert {
-        bail!("parsed certificate does not match the expected one")
+        println!("expected: {:#?}", cert.certificate);
+        println!("got: {parsed_cert:#?}");
+        println!("DER: {}", base64ct::Base64::encode_string(&der_cert));
+        bail!(
+            "parsed {} certificate does not match the expected one",
+            tmpl.name
+        )
     }
+    Ok(())
+}
+
+#[test]
+fn generic() -> Result<()> {
+    round_trip(GENERIC_CERT)
+}
+
+#[test]
+fn example() -> Result<()> {
+    round_trip(EXAMPLE_CERT)
+}
+
+#[test]
+fn extensions() -> Result<()> {
+    round_trip(EXTENSIONS_CERT)
+}
 
+#[test]
+fn uds() -> Result<()> {
+    round_trip(UDS_CERT)
+}
+
+#[test]
+fn cdi_0() -> Result<()> {
+    round_trip(CDI_0_CERT)
+}
+
+#[test]
+fn cdi_1() -> Result<()> {
+    round_trip(CDI_1_CERT)
+}
+
+#[test]
+fn tpm_ek() -> Result<()> {
+    round_trip(TPM_EK_CERT)
+}
+
+#[test]
+fn fake_ca() -> Result<()> {
+    // This CA certificate was not generated from a template: its authority key
+    // identifier also has the issuer and serial number of the authority.
+    let der_cert = X509::from_pem(FAKE_CA_PEM)?.to_der()?;
+    let cert = x509::parse_certificate(&der_cert)?;
+    assert_eq!(cert.authority_key_identifier, cert.subject_key_identifier);
+    let basic_constraints = cert.basic_constraints.expect("no basic constraints");
+    assert_eq!(basic_constraints.ca, Value::literal(true));
+    assert_eq!(
+        basic_constraints.path_len_constraint,
+        Some(Value::literal(BigUint::from(0u32)))
+    );
+    let key_usage = cert.key_usage.expect("no key usage");
+    assert_eq!(key_usage.digital_signature, Value::literal(true));
+    assert_eq!(key_usage.cert_sign, Value::literal(true));
+    assert_eq!(key_usage.crl_sign, Value::literal(true));
+    assert_eq!(key_usage.key_agreement, Value::literal(false));
+    assert!(cert.private_extensions.is_empty());
     Ok(())
 }
@@ -0,0 +1,71 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use heck::ToUpperCamelCase;
+
+use ot_certs::codegen;
+use ot_certs::template::Template;
+
+const TEMPLATES: &[&str] = &[
+    include_str!("generic.hjson"),
+    include_str!("example.hjson"),
+    include_str!("extensions.hjson"),
+    include_str!("../../../device/silicon_creator/lib/cert/cdi_0.hjson"),
+    include_str!("../../../device/silicon_creator/lib/cert/cdi_1.hjson"),
+    include_str!("../../../device/silicon_creator/lib/cert/tpm_ek.hjson"),
+    include_str!("../../../device/silicon_creator/lib/cert/uds.hjson"),
+];
+
+// Return the value of the first line of `source` of the form
+// `<prefix><value><suffix>`.
+fn find_value(source: &str, prefix: &str, suffix: &str) -> usize {
+    source
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(prefix)?.strip_suffix(suffix))
+        .unwrap_or_else(|| panic!("no line starting with {prefix:?}"))
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn max_sizes_match_c() -> Result<()> {
+    for tmpl in TEMPLATES {
+        let tmpl = Template::from_hjson_str(tmpl)?;
+        let c = codegen::generate_cert("test", &tmpl)?;
+        let rust = codegen::rust::generate_cert("test", &tmpl)?;
+
+        let name = tmpl.name.to_upper_camel_case();
+        for (c_name, rust_name) in [
+            ("MaxTbsSizeBytes", "MAX_TBS_SIZE_BYTES"),
+            ("MaxCertSizeBytes", "MAX_CERT_SIZE_BYTES"),
+        ] {
+            assert_eq!(
+                find_value(&c.source_h, &format!("k{name}{c_name} = "), ","),
+                find_value(
+                    &rust.source_rs,
+                    &format!("pub const {rust_name}: usize = "),
+                    ";"
+                ),
+                "{} for {}",
+                rust_name,
+                tmpl.name
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn self_contained() -> Result<()> {
+    let tmpl = Template::from_hjson_str(TEMPLATES[3])?;
+    let rust = codegen::rust::generate_cert("test", &tmpl)?;
+    // The generated module embeds its DER writer and only depends on `core`.
+    assert!(rust.source_rs.contains("mod asn1 {"));
+    assert!(!rust.source_rs.contains("std::"));
+    assert!(rust
+        .source_unittest
+        .contains(&format!("mod {};", tmpl.name)));
+    Ok(())
+}
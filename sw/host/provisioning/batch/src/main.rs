@@ -0,0 +1,75 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+
+mod tray;
+
+use tray::{RunOptions, Tray};
+
+/// Runs the CP or FT provisioning program on every device of a tray, in
+/// parallel.
+#[derive(Debug, Parser)]
+struct Opts {
+    /// Tray description (hjson) listing the program to run and the devices.
+    #[arg(long)]
+    tray: PathBuf,
+
+    /// Directory receiving the log of every device.
+    #[arg(long, default_value = "provisioning_logs")]
+    log_dir: PathBuf,
+
+    /// File to write the results of all devices to, as JSON.
+    #[arg(long)]
+    results: Option<PathBuf>,
+
+    /// Maximum number of devices provisioned at the same time.  Defaults to
+    /// all devices of the tray.
+    #[arg(long)]
+    jobs: Option<usize>,
+
+    /// Time after which the provisioning of a device is aborted.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Logging level.
+    #[arg(long, default_value = "info")]
+    logging: log::LevelFilter,
+}
+
+fn main() -> Result<()> {
+    let opts = Opts::parse();
+    env_logger::Builder::from_default_env()
+        .format_timestamp_millis()
+        .filter(None, opts.logging)
+        .init();
+
+    let tray = Tray::read(&opts.tray)?;
+    let run_opts = RunOptions {
+        log_dir: opts.log_dir,
+        jobs: opts.jobs.unwrap_or(tray.devices.len()),
+        timeout: opts.timeout,
+    };
+    let results = tray::run(&tray, &run_opts)?;
+
+    if let Some(path) = &opts.results {
+        fs::write(path, serde_json::to_string_pretty(&results)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    log::info!(
+        "{} of {} devices provisioned",
+        results.len() - failed,
+        results.len()
+    );
+    if failed != 0 {
+        bail!("{failed} devices failed provisioning");
+    }
+    Ok(())
+}
@@ -0,0 +1,357 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Description and execution of a tray of devices under test.
+//!
+//! Every DUT is provisioned by a separate run of the provisioning program
+//! (`cp` or `ft`), connected to the DUT through its own backend.  Running each
+//! DUT in its own process keeps transports, logs and failures isolated: a DUT
+//! that fails or hangs does not affect the others.
+
+use std::collections::{HashSet, VecDeque};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Interval at which running programs are polled for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A device under test and the backend used to reach it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Device {
+    /// Unique name of the device, such as its socket in the tray.  Used to
+    /// name the log of the device.
+    pub name: String,
+    /// Debug interface, overriding the interface of the tray.
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// USB serial number of the debug interface of the device.
+    #[serde(default)]
+    pub usb_serial: Option<String>,
+    /// Additional backend configuration files.
+    #[serde(default)]
+    pub conf: Vec<PathBuf>,
+    /// Device specific arguments of the provisioning program, such as its
+    /// device ID.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// A tray of devices to provision.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tray {
+    /// The provisioning program to run for every device.
+    pub program: PathBuf,
+    /// Debug interface of the devices.
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// Arguments of the provisioning program common to all devices.
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub devices: Vec<Device>,
+}
+
+impl Tray {
+    /// Reads a tray description from an hjson file.
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let tray: Tray = deser_hjson::from_str(&text)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        tray.validate()?;
+        Ok(tray)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for device in &self.devices {
+            let name = device.name.as_str();
+            if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+                bail!("invalid device name {name:?}");
+            }
+            if !names.insert(name) {
+                bail!("duplicate device name {name:?}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the arguments of the provisioning program for `device`.
+    ///
+    /// `{name}` and `{usb_serial}` are replaced in all arguments, which allows
+    /// common arguments to name per-device files, such as provisioning
+    /// records.
+    pub fn device_args(&self, device: &Device) -> Vec<String> {
+        let mut args = self.args.clone();
+        if let Some(interface) = device.interface.as_ref().or(self.interface.as_ref()) {
+            args.push(format!("--interface={interface}"));
+        }
+        if let Some(serial) = &device.usb_serial {
+            args.push(format!("--usb-serial={serial}"));
+        }
+        for conf in &device.conf {
+            args.push(format!("--conf={}", conf.display()));
+        }
+        args.extend(device.args.iter().cloned());
+        let serial = device.usb_serial.as_deref().unwrap_or_default();
+        args.iter()
+            .map(|arg| {
+                arg.replace("{name}", &device.name)
+                    .replace("{usb_serial}", serial)
+            })
+            .collect()
+    }
+}
+
+/// Outcome of the provisioning of a device.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Passed,
+    /// The program exited with an error, or was killed by a signal when there
+    /// is no exit code.
+    Failed {
+        exit_code: Option<i32>,
+    },
+    /// The program did not complete in time and was killed.
+    TimedOut,
+    /// The program could not be started.
+    Error {
+        message: String,
+    },
+}
+
+/// The result of the provisioning of a device.
+#[derive(Clone, Debug, Serialize)]
+pub struct DeviceResult {
+    pub name: String,
+    pub usb_serial: Option<String>,
+    pub status: Status,
+    /// Run time in seconds.
+    pub duration: f64,
+    /// Log of the provisioning program.
+    pub log: PathBuf,
+}
+
+impl DeviceResult {
+    pub fn passed(&self) -> bool {
+        self.status == Status::Passed
+    }
+}
+
+/// Settings of a tray run.
+#[derive(Clone, Debug)]
+pub struct RunOptions {
+    /// Directory receiving the log of every device.
+    pub log_dir: PathBuf,
+    /// Maximum number of devices provisioned at the same time.
+    pub jobs: usize,
+    /// Time after which a device run is killed.
+    pub timeout: Option<Duration>,
+}
+
+/// Waits for `child` to exit, killing it after `timeout`.
+fn wait(child: &mut Child, timeout: Option<Duration>) -> Result<Option<ExitStatus>> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn run_device(tray: &Tray, device: &Device, opts: &RunOptions) -> DeviceResult {
+    let log = opts.log_dir.join(format!("{}.log", device.name));
+    let start = Instant::now();
+    let status = (|| -> Result<Status> {
+        let stdout =
+            File::create(&log).with_context(|| format!("failed to create {}", log.display()))?;
+        let stderr = stdout.try_clone()?;
+        let mut child = Command::new(&tray.program)
+            .args(tray.device_args(device))
+            .stdin(Stdio::null())
+            .stdout(stdout)
+            .stderr(stderr)
+            .spawn()
+            .with_context(|| format!("failed to run {}", tray.program.display()))?;
+        Ok(match wait(&mut child, opts.timeout)? {
+            Some(status) if status.success() => Status::Passed,
+            Some(status) => Status::Failed {
+                exit_code: status.code(),
+            },
+            None => Status::TimedOut,
+        })
+    })()
+    .unwrap_or_else(|e| Status::Error {
+        message: format!("{e:#}"),
+    });
+    let result = DeviceResult {
+        name: device.name.clone(),
+        usb_serial: device.usb_serial.clone(),
+        status,
+        duration: start.elapsed().as_secs_f64(),
+        log,
+    };
+    if result.passed() {
+        log::info!("{}: passed in {:.1}s", result.name, result.duration);
+    } else {
+        log::error!(
+            "{}: {:?}, see {}",
+            result.name,
+            result.status,
+            result.log.display()
+        );
+    }
+    result
+}
+
+/// Provisions all devices of `tray`, running up to `opts.jobs` devices at
+/// the same time.  Returns the results in the order of the devices.
+pub fn run(tray: &Tray, opts: &RunOptions) -> Result<Vec<DeviceResult>> {
+    fs::create_dir_all(&opts.log_dir)
+        .with_context(|| format!("failed to create {}", opts.log_dir.display()))?;
+    let queue = Mutex::new(tray.devices.iter().enumerate().collect::<VecDeque<_>>());
+    let results = Mutex::new(Vec::with_capacity(tray.devices.len()));
+    thread::scope(|s| {
+        for _ in 0..opts.jobs.clamp(1, tray.devices.len().max(1)) {
+            s.spawn(|| loop {
+                let Some((index, device)) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                log::info!("{}: provisioning", device.name);
+                let result = run_device(tray, device, opts);
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tray(devices: &[(&str, &str)]) -> Tray {
+        Tray {
+            program: "/bin/sh".into(),
+            interface: None,
+            args: vec!["-c".into()],
+            devices: devices
+                .iter()
+                .map(|(name, script)| Device {
+                    name: name.to_string(),
+                    interface: None,
+                    usb_serial: None,
+                    conf: Vec::new(),
+                    args: vec![script.to_string()],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn device_args() -> Result<()> {
+        let tray: Tray = deser_hjson::from_str(
+            r#"{
+                program: ft
+                interface: hyper340
+                args: ["--record=records/{name}.json"]
+                devices: [
+                    {
+                        name: slot0
+                        usb_serial: "1234"
+                        conf: ["extra.json"]
+                        args: ["--device-id=0x01"]
+                    }
+                    {
+                        name: slot1
+                        interface: hyper310
+                    }
+                ]
+            }"#,
+        )?;
+        tray.validate()?;
+        assert_eq!(
+            tray.device_args(&tray.devices[0]),
+            [
+                "--record=records/slot0.json",
+                "--interface=hyper340",
+                "--usb-serial=1234",
+                "--conf=extra.json",
+                "--device-id=0x01",
+            ]
+        );
+        assert_eq!(
+            tray.device_args(&tray.devices[1]),
+            ["--record=records/slot1.json", "--interface=hyper310"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_names() {
+        assert!(tray(&[("a", ""), ("a", "")]).validate().is_err());
+        assert!(tray(&[("../a", "")]).validate().is_err());
+        assert!(tray(&[("", "")]).validate().is_err());
+    }
+
+    #[test]
+    fn failures_are_isolated() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let tray = tray(&[
+            ("pass", "echo hello {name}"),
+            ("fail", "echo oops >&2; exit 3"),
+            ("hang", "sleep 10"),
+            ("pass2", "exit 0"),
+        ]);
+        let opts = RunOptions {
+            log_dir: dir.path().join("logs"),
+            jobs: 4,
+            timeout: Some(Duration::from_secs(2)),
+        };
+        let results = run(&tray, &opts)?;
+        let statuses = results.iter().map(|r| r.status.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                Status::Passed,
+                Status::Failed { exit_code: Some(3) },
+                Status::TimedOut,
+                Status::Passed,
+            ]
+        );
+        assert_eq!(fs::read_to_string(&results[0].log)?, "hello pass\n");
+        assert_eq!(fs::read_to_string(&results[1].log)?, "oops\n");
+        Ok(())
+    }
+
+    #[test]
+    fn missing_program() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut tray = tray(&[("slot0", "")]);
+        tray.program = dir.path().join("missing");
+        let opts = RunOptions {
+            log_dir: dir.path().to_path_buf(),
+            jobs: 1,
+            timeout: None,
+        };
+        let results = run(&tray, &opts)?;
+        assert!(matches!(results[0].status, Status::Error { .. }));
+        Ok(())
+    }
+}
@@ -0,0 +1,466 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-process validation of X.509 certificate chains.
+//!
+//! The certificates produced during personalization are ECDSA P256 / SHA256
+//! certificates issued directly by a CA, so this module only implements the
+//! subset of RFC 5280 path validation needed for them: issuer matching by name
+//! and key identifier, CA and key usage checks on the issuer, signature
+//! verification, the validity period and the rejection of unrecognized
+//! critical extensions.
+
+use std::fmt;
+
+use chrono::{NaiveDateTime, Utc};
+use der::{Decode, Reader, SliceReader};
+use num_bigint_dig::BigUint;
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature as P256Signature, VerifyingKey};
+use thiserror::Error;
+
+use ot_certs::template::{
+    Certificate, CertificateExtension, EcCurve, EcPublicKeyInfo, Signature, SubjectPublicKeyInfo,
+    Value,
+};
+use ot_certs::x509::parse_certificate;
+
+/// Identifies a certificate in a chain being validated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CertRef {
+    /// The trusted CA certificate.
+    Ca,
+    /// The certificate at the given index in the list being validated.
+    Index(usize),
+}
+
+impl fmt::Display for CertRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertRef::Ca => write!(f, "CA certificate"),
+            CertRef::Index(index) => write!(f, "certificate {index}"),
+        }
+    }
+}
+
+/// Reasons why a certificate chain fails to validate.
+#[derive(Debug, Error)]
+pub enum ChainError {
+    #[error("{cert}: malformed certificate: {reason}")]
+    Malformed { cert: CertRef, reason: String },
+    #[error("{cert}: unsupported {what}")]
+    Unsupported { cert: CertRef, what: String },
+    #[error("{cert}: unrecognized critical extension {oid}")]
+    UnknownCriticalExtension { cert: CertRef, oid: String },
+    #[error("{cert}: not issued by the CA")]
+    NotIssuedByCa { cert: CertRef },
+    #[error("the CA certificate is not allowed to sign certificates")]
+    CaNotAllowedToSign,
+    #[error("{cert}: invalid public key")]
+    BadPublicKey { cert: CertRef },
+    #[error("{cert}: signature verification failed")]
+    BadSignature { cert: CertRef },
+    #[error("{cert}: not valid before {not_before}")]
+    NotYetValid { cert: CertRef, not_before: String },
+    #[error("{cert}: expired on {not_after}")]
+    Expired { cert: CertRef, not_after: String },
+}
+
+/// A parsed certificate along with its DER encoded TBS.
+struct ParsedCert {
+    tbs: Vec<u8>,
+    cert: Certificate,
+}
+
+impl ParsedCert {
+    fn parse(cert_ref: CertRef, der: &[u8]) -> Result<Self, ChainError> {
+        let malformed = |reason: String| ChainError::Malformed {
+            cert: cert_ref,
+            reason,
+        };
+        let tbs = tbs_certificate(der).map_err(|e| malformed(e.to_string()))?;
+        let cert = parse_certificate(der).map_err(|e| malformed(format!("{e:#}")))?;
+        // The extensions that `ot_certs` does not recognize are returned as
+        // raw extensions.
+        for ext in cert.private_extensions.iter() {
+            if let CertificateExtension::Raw(raw) = ext {
+                if raw.critical {
+                    return Err(ChainError::UnknownCriticalExtension {
+                        cert: cert_ref,
+                        oid: raw.oid.clone(),
+                    });
+                }
+            }
+        }
+        Ok(ParsedCert {
+            tbs: tbs.to_vec(),
+            cert,
+        })
+    }
+
+    /// Returns true if `self` is the issuer named by `cert`.
+    fn issued(&self, cert: &ParsedCert) -> bool {
+        self.cert.subject == cert.cert.issuer
+            && self.cert.subject_key_identifier == cert.cert.authority_key_identifier
+    }
+
+    /// Returns true if the basic constraints and key usage of `self` allow it
+    /// to sign certificates.
+    fn is_ca(&self) -> bool {
+        let ca = self
+            .cert
+            .basic_constraints
+            .as_ref()
+            .is_some_and(|bc| bc.ca == Value::Literal(true));
+        let cert_sign = match &self.cert.key_usage {
+            Some(key_usage) => key_usage.cert_sign == Value::Literal(true),
+            None => true,
+        };
+        ca && cert_sign
+    }
+
+    fn check_validity(&self, cert_ref: CertRef, now: NaiveDateTime) -> Result<(), ChainError> {
+        let not_before = literal(cert_ref, "not before", &self.cert.not_before)?;
+        let not_after = literal(cert_ref, "not after", &self.cert.not_after)?;
+        if now < parse_time(cert_ref, not_before)? {
+            return Err(ChainError::NotYetValid {
+                cert: cert_ref,
+                not_before: not_before.clone(),
+            });
+        }
+        if now > parse_time(cert_ref, not_after)? {
+            return Err(ChainError::Expired {
+                cert: cert_ref,
+                not_after: not_after.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn verifying_key(&self, cert_ref: CertRef) -> Result<VerifyingKey, ChainError> {
+        // P256 is the only key type that `ot_certs` parses.
+        let SubjectPublicKeyInfo::EcPublicKey(EcPublicKeyInfo {
+            curve: EcCurve::Prime256v1,
+            public_key,
+        }) = &self.cert.subject_public_key_info;
+        let mut sec1 = vec![0x04];
+        sec1.extend(
+            scalar_bytes(literal(cert_ref, "public key", &public_key.x)?)
+                .ok_or(ChainError::BadPublicKey { cert: cert_ref })?,
+        );
+        sec1.extend(
+            scalar_bytes(literal(cert_ref, "public key", &public_key.y)?)
+                .ok_or(ChainError::BadPublicKey { cert: cert_ref })?,
+        );
+        VerifyingKey::from_sec1_bytes(&sec1)
+            .map_err(|_| ChainError::BadPublicKey { cert: cert_ref })
+    }
+
+    fn signature(&self, cert_ref: CertRef) -> Result<P256Signature, ChainError> {
+        let Signature::EcdsaWithSha256 { value: Some(sig) } = &self.cert.signature else {
+            return Err(ChainError::Unsupported {
+                cert: cert_ref,
+                what: "signature".into(),
+            });
+        };
+        let bad_signature = ChainError::BadSignature { cert: cert_ref };
+        let r = scalar_bytes(literal(cert_ref, "signature", &sig.r)?);
+        let s = scalar_bytes(literal(cert_ref, "signature", &sig.s)?);
+        match (r, s) {
+            (Some(r), Some(s)) => P256Signature::from_scalars(r, s).map_err(|_| bad_signature),
+            _ => Err(bad_signature),
+        }
+    }
+}
+
+/// Extracts the DER encoded TBS from a DER encoded certificate.
+pub fn tbs_certificate(cert: &[u8]) -> der::Result<&[u8]> {
+    let mut reader = SliceReader::new(cert)?;
+    let tbs = reader.sequence(|seq| {
+        let tbs = seq.tlv_bytes()?;
+        // signatureAlgorithm and signatureValue.
+        der::asn1::AnyRef::decode(seq)?;
+        der::asn1::AnyRef::decode(seq)?;
+        Ok(tbs)
+    })?;
+    reader.finish(tbs)
+}
+
+fn literal<'a, T>(cert: CertRef, field: &str, value: &'a Value<T>) -> Result<&'a T, ChainError> {
+    match value {
+        Value::Literal(x) => Ok(x),
+        Value::Variable(_) => Err(ChainError::Malformed {
+            cert,
+            reason: format!("{field} is not a literal"),
+        }),
+    }
+}
+
+/// Returns the 32-byte big-endian representation of a P256 scalar or
+/// coordinate, or `None` if it does not fit.
+fn scalar_bytes(value: &BigUint) -> Option<[u8; 32]> {
+    let bytes = value.to_bytes_be();
+    if bytes.len() > 32 {
+        return None;
+    }
+    let mut res = [0u8; 32];
+    res[32 - bytes.len()..].copy_from_slice(&bytes);
+    Some(res)
+}
+
+/// Parses a GeneralizedTime as produced by `ot_certs`.
+fn parse_time(cert: CertRef, time: &str) -> Result<NaiveDateTime, ChainError> {
+    NaiveDateTime::parse_from_str(time, "%Y%m%d%H%M%SZ").map_err(|_| ChainError::Malformed {
+        cert,
+        reason: format!("invalid time {time:?}"),
+    })
+}
+
+/// Validates DER encoded certificates against a DER encoded CA certificate
+/// at the time `now`.
+///
+/// Each certificate must be issued by the CA.  The CA is trusted as is: its
+/// own signature is not checked, but it must be within its validity period and
+/// allowed to sign certificates.
+pub fn validate_chain_at(ca: &[u8], certs: &[&[u8]], now: NaiveDateTime) -> Result<(), ChainError> {
+    let ca = ParsedCert::parse(CertRef::Ca, ca)?;
+    ca.check_validity(CertRef::Ca, now)?;
+    if !ca.is_ca() {
+        return Err(ChainError::CaNotAllowedToSign);
+    }
+    let key = ca.verifying_key(CertRef::Ca)?;
+    for (index, der) in certs.iter().enumerate() {
+        let cert_ref = CertRef::Index(index);
+        let cert = ParsedCert::parse(cert_ref, der)?;
+        cert.check_validity(cert_ref, now)?;
+        if !ca.issued(&cert) {
+            return Err(ChainError::NotIssuedByCa { cert: cert_ref });
+        }
+        let signature = cert.signature(cert_ref)?;
+        key.verify(&cert.tbs, &signature)
+            .map_err(|_| ChainError::BadSignature { cert: cert_ref })?;
+    }
+    Ok(())
+}
+
+/// Validates DER encoded certificates against a DER encoded CA certificate at
+/// the current time.  See [`validate_chain_at`].
+pub fn validate_chain(ca: &[u8], certs: &[&[u8]]) -> Result<(), ChainError> {
+    validate_chain_at(ca, certs, Utc::now().naive_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::NaiveDate;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+
+    use ot_certs::template::{EcPublicKey, EcdsaSignature, RawExtension, Template};
+    use ot_certs::x509::{generate_certificate_from_tbs, generate_tbs_certificate};
+
+    const CA_TEMPLATE: &str = r#"{
+        name: "test_ca",
+        variables: {},
+        certificate: {
+            serial_number: 1,
+            issuer: [ { common_name: "Test CA" } ],
+            subject: [ { common_name: "Test CA" } ],
+            not_before: "20230101000000Z",
+            not_after: "20491231235959Z",
+            subject_public_key_info: {
+                algorithm: "ec-public-key",
+                curve: "prime256v1",
+                public_key: { x: 0, y: 0 },
+            },
+            authority_key_identifier: "0101",
+            subject_key_identifier: "0101",
+            basic_constraints: { ca: true },
+            key_usage: { cert_sign: true },
+            signature: { algorithm: "ecdsa-with-sha256" },
+        }
+    }"#;
+
+    const DEVICE_TEMPLATE: &str = r#"{
+        name: "test_device",
+        variables: {},
+        certificate: {
+            serial_number: 2,
+            issuer: [ { common_name: "Test CA" } ],
+            subject: [ { common_name: "Test Device" } ],
+            not_before: "20230101000000Z",
+            not_after: "99991231235959Z",
+            subject_public_key_info: {
+                algorithm: "ec-public-key",
+                curve: "prime256v1",
+                public_key: { x: 0, y: 0 },
+            },
+            authority_key_identifier: "0101",
+            subject_key_identifier: "0202",
+            key_usage: { digital_signature: true },
+            signature: { algorithm: "ecdsa-with-sha256" },
+        }
+    }"#;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_slice(&[seed; 32]).unwrap()
+    }
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    /// Generates the certificate described by `template`, after applying
+    /// `edit`, for the key `subject` and signed by `issuer`.
+    fn issue(
+        template: &str,
+        subject: &SigningKey,
+        issuer: &SigningKey,
+        edit: impl FnOnce(&mut Certificate),
+    ) -> Vec<u8> {
+        let mut tmpl = Template::from_hjson_str(template).unwrap();
+        let point = subject.verifying_key().to_encoded_point(false);
+        tmpl.certificate.subject_public_key_info =
+            SubjectPublicKeyInfo::EcPublicKey(EcPublicKeyInfo {
+                curve: EcCurve::Prime256v1,
+                public_key: EcPublicKey {
+                    x: Value::literal(BigUint::from_bytes_be(point.x().unwrap())),
+                    y: Value::literal(BigUint::from_bytes_be(point.y().unwrap())),
+                },
+            });
+        edit(&mut tmpl.certificate);
+        let tbs = generate_tbs_certificate(&tmpl).unwrap();
+        let signature: P256Signature = issuer.sign(&tbs);
+        let signature = Signature::EcdsaWithSha256 {
+            value: Some(EcdsaSignature {
+                r: Value::literal(BigUint::from_bytes_be(&signature.r().to_bytes())),
+                s: Value::literal(BigUint::from_bytes_be(&signature.s().to_bytes())),
+            }),
+        };
+        generate_certificate_from_tbs(tbs, &signature).unwrap()
+    }
+
+    fn ca_cert(edit: impl FnOnce(&mut Certificate)) -> Vec<u8> {
+        issue(CA_TEMPLATE, &key(1), &key(1), edit)
+    }
+
+    fn device_cert(edit: impl FnOnce(&mut Certificate)) -> Vec<u8> {
+        issue(DEVICE_TEMPLATE, &key(2), &key(1), edit)
+    }
+
+    fn raw_extension(critical: bool) -> CertificateExtension {
+        CertificateExtension::Raw(RawExtension {
+            oid: "2.999.1".into(),
+            critical,
+            value: Value::literal(vec![0x05, 0x00]),
+        })
+    }
+
+    #[test]
+    fn valid_chain() {
+        let ca = ca_cert(|_| {});
+        let device = device_cert(|_| {});
+        let other = issue(DEVICE_TEMPLATE, &key(3), &key(1), |_| {});
+        assert!(validate_chain_at(&ca, &[&device, &other], now()).is_ok());
+        assert!(validate_chain_at(&ca, &[], now()).is_ok());
+    }
+
+    #[test]
+    fn validity_period() {
+        let ca = ca_cert(|_| {});
+        let device = device_cert(|cert| cert.not_after = Value::literal("20231231235959Z"));
+        let before = NaiveDate::from_ymd_opt(2022, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert!(matches!(
+            validate_chain_at(&ca, &[], before),
+            Err(ChainError::NotYetValid {
+                cert: CertRef::Ca,
+                ..
+            })
+        ));
+        assert!(matches!(
+            validate_chain_at(&ca, &[&device], now()),
+            Err(ChainError::Expired {
+                cert: CertRef::Index(0),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn not_issued_by_ca() {
+        let ca = ca_cert(|_| {});
+        let good = device_cert(|_| {});
+        let other_issuer = device_cert(|cert| cert.issuer = cert.subject.clone());
+        let other_key_id =
+            device_cert(|cert| cert.authority_key_identifier = Value::literal(vec![3, 3]));
+        for cert in [&other_issuer, &other_key_id] {
+            assert!(matches!(
+                validate_chain_at(&ca, &[&good, cert], now()),
+                Err(ChainError::NotIssuedByCa {
+                    cert: CertRef::Index(1)
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn bad_signature() {
+        let ca = ca_cert(|_| {});
+        let device = issue(DEVICE_TEMPLATE, &key(2), &key(2), |_| {});
+        assert!(matches!(
+            validate_chain_at(&ca, &[&device], now()),
+            Err(ChainError::BadSignature {
+                cert: CertRef::Index(0)
+            })
+        ));
+    }
+
+    #[test]
+    fn ca_not_allowed_to_sign() {
+        let device = device_cert(|_| {});
+        let no_cert_sign = ca_cert(|cert| {
+            cert.key_usage.as_mut().unwrap().cert_sign = Value::literal(false);
+        });
+        let not_ca = ca_cert(|cert| cert.basic_constraints = None);
+        for ca in [&no_cert_sign, &not_ca] {
+            assert!(matches!(
+                validate_chain_at(ca, &[&device], now()),
+                Err(ChainError::CaNotAllowedToSign)
+            ));
+        }
+    }
+
+    #[test]
+    fn critical_extensions() {
+        let ca = ca_cert(|_| {});
+        let non_critical = device_cert(|cert| cert.private_extensions.push(raw_extension(false)));
+        assert!(validate_chain_at(&ca, &[&non_critical], now()).is_ok());
+
+        let critical = device_cert(|cert| cert.private_extensions.push(raw_extension(true)));
+        let err = validate_chain_at(&ca, &[&critical], now()).unwrap_err();
+        assert!(matches!(
+            &err,
+            ChainError::UnknownCriticalExtension {
+                cert: CertRef::Index(0),
+                oid,
+            } if oid == "2.999.1"
+        ));
+
+        let critical_ca = ca_cert(|cert| cert.private_extensions.push(raw_extension(true)));
+        assert!(matches!(
+            validate_chain_at(&critical_ca, &[&non_critical], now()),
+            Err(ChainError::UnknownCriticalExtension {
+                cert: CertRef::Ca,
+                ..
+            })
+        ));
+    }
+}
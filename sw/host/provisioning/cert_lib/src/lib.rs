@@ -2,37 +2,29 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
-use std::fs::{self, OpenOptions};
-use std::io::{Read, Write};
-use std::process::Command;
-
-use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use cryptoki::mechanism::Mechanism;
+use cryptoki::object::{Attribute, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
 use elliptic_curve::SecretKey;
 use num_bigint_dig::BigUint;
-use openssl::ecdsa::EcdsaSig;
 use p256::ecdsa::SigningKey;
 use p256::NistP256;
 
+use hsmtool::module::Module;
+use hsmtool::profile::Profile;
+use hsmtool::util::helper;
 use opentitanlib::crypto::sha256::sha256;
-use opentitanlib::util::tmpfilename;
 use ot_certs::template::{EcdsaSignature, Signature, Value};
 use ot_certs::x509::generate_certificate_from_tbs;
 
-/// Execute an openssl invocation, passing the args[] as command line parameters.
-///
-/// The intended use is openssl x509 certificate verification. cert_num is the
-/// number of the certificate in the list of certificates being validated.
-fn openssl_command(args: &[&str]) -> Result<()> {
-    let o = Command::new("openssl").args(args).output()?;
-    if !o.status.success() {
-        log::error!(
-            "openssl output:\n{}",
-            std::str::from_utf8(&o.stderr).unwrap()
-        );
-        bail!("openssl command {} failed", args[0]);
-    }
-    Ok(())
-}
+pub mod chain;
+
+pub use chain::{CertRef, ChainError};
 
 /// Given a u8 blob containing an x509 certificate perform some rudimentary
 /// header correctness checks and return the actual certificate size based on the
@@ -56,20 +48,140 @@ pub fn get_cert_size(cert: &[u8]) -> Result<usize> {
     Ok(size)
 }
 
+/// Parameters for connecting to the PKCS#11 token holding a CA key.
+///
+/// The token and credentials are given either directly or through the name of
+/// an `hsmtool` profile.
+#[derive(Clone, Debug)]
+pub struct Pkcs11Config {
+    /// Path to the PKCS#11 shared library.
+    pub module: String,
+    /// The label of the token holding the key.
+    pub token: Option<String>,
+    /// The user type to authenticate to the token.
+    pub user: Option<UserType>,
+    /// The pin for the user.
+    pub pin: Option<String>,
+    /// The name of an `hsmtool` profile to use instead of `token`, `user` and
+    /// `pin`.
+    pub profile: Option<String>,
+    /// Filename of the `hsmtool` profiles, relative to
+    /// `$XDG_CONFIG_HOME/hsmtool`.
+    pub profiles: PathBuf,
+}
+
+/// PKCS#11 token command-line parameters.
+#[derive(Debug, Args, Clone)]
+pub struct Pkcs11Input {
+    /// Path to the PKCS#11 shared library.
+    #[arg(long, env = "HSMTOOL_MODULE")]
+    pub hsm_module: Option<String>,
+
+    /// PKCS#11 token holding the key.
+    #[arg(long, env = "HSMTOOL_TOKEN")]
+    pub hsm_token: Option<String>,
+
+    /// User type ('so' or 'user') for the PKCS#11 token.
+    #[arg(long, env = "HSMTOOL_USER", value_parser = hsmtool::module::parse_user_type)]
+    pub hsm_user: Option<UserType>,
+
+    /// Pin for the PKCS#11 token.
+    #[arg(long, env = "HSMTOOL_PIN")]
+    pub hsm_pin: Option<String>,
+
+    /// The name of an hsmtool profile to use instead of the token, user and pin.
+    #[arg(long)]
+    pub hsm_profile: Option<String>,
+
+    /// Filename of hsmtool profiles.  Relative to $XDG_CONFIG_HOME/hsmtool.
+    #[arg(long, default_value = "profiles.json")]
+    pub hsm_profiles: PathBuf,
+}
+
+impl Pkcs11Input {
+    /// Returns the token configuration, which requires `--hsm-module`.
+    pub fn config(&self) -> Result<Pkcs11Config> {
+        let Some(module) = self.hsm_module.clone() else {
+            bail!("PKCS#11 keys require --hsm-module");
+        };
+        Ok(Pkcs11Config {
+            module,
+            token: self.hsm_token.clone(),
+            user: self.hsm_user,
+            pin: self.hsm_pin.clone(),
+            profile: self.hsm_profile.clone(),
+            profiles: self.hsm_profiles.clone(),
+        })
+    }
+}
+
+/// An ECC P256 CA private key held in a PKCS#11 token.
+pub struct Pkcs11Key {
+    session: Session,
+    key: ObjectHandle,
+}
+
+impl Pkcs11Key {
+    /// Connects to the token described by `config` and finds the private key
+    /// with the given `label`.
+    pub fn open(config: &Pkcs11Config, label: &str) -> Result<Self> {
+        let mut hsm = Module::initialize(&config.module, None)
+            .with_context(|| format!("failed to load PKCS#11 module {}", config.module))?;
+        let session = if let Some(name) = &config.profile {
+            let profiles = Profile::load(&config.profiles)?;
+            let profile = profiles
+                .get(name)
+                .ok_or_else(|| anyhow!("HSM profile {name:?} not found"))?;
+            hsm.connect(&profile.token, Some(profile.user), profile.pin.as_deref())?
+        } else if let Some(token) = &config.token {
+            hsm.connect(token, config.user, config.pin.as_deref())?
+        } else {
+            bail!("A PKCS#11 token or profile is required");
+        };
+        Self::from_session(session, label)
+    }
+
+    /// Finds the private key with the given `label` in an open `session`.
+    pub fn from_session(session: Session, label: &str) -> Result<Self> {
+        let mut search = helper::search_spec(None, Some(label))?;
+        search.push(Attribute::Class(ObjectClass::PRIVATE_KEY));
+        search.push(Attribute::Sign(true));
+        let key = helper::find_one_object(&session, &search)
+            .with_context(|| format!("failed to find CA key {label:?}"))?;
+        Ok(Pkcs11Key { session, key })
+    }
+
+    /// Signs the SHA256 digest of `data`, returning the big-endian `r` and
+    /// `s` values of the ECDSA signature.
+    pub fn sign(&self, data: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        let digest = sha256(data).to_be_bytes();
+        let mut sig = self
+            .session
+            .sign(&Mechanism::Ecdsa, self.key, &digest)
+            .context("PKCS#11 signing failed")?;
+        if sig.is_empty() || sig.len() % 2 != 0 {
+            bail!("Unexpected ECDSA signature length {}", sig.len());
+        }
+        let s = sig.split_off(sig.len() / 2);
+        Ok((sig, s))
+    }
+}
+
 /// This provides two different certificate signing key representations:
-///   1. a SecretKey object in case of fake key or a String object, or
-///   2. the ID of the key used by Google Cloud KMS.
+///   1. a SecretKey object in case of fake key, or
+///   2. a key held in a PKCS#11 token, such as an on-prem HSM or Google
+///      Cloud KMS through its PKCS#11 library.
 pub enum CertEndorsementKey {
     LocalKey(SecretKey<NistP256>),
-    CkmsKey(String),
+    Pkcs11Key(Pkcs11Key),
 }
 
 /// Parses an X.509 ASN.1 DER encoded certificate, signs it with the specified
 /// key, and attaches a signature to it.
 pub fn parse_and_endorse_x509_cert(tbs: Vec<u8>, key: &CertEndorsementKey) -> Result<Vec<u8>> {
     match key {
-        CertEndorsementKey::CkmsKey(key_id) => parse_and_endorse_x509_cert_ckms(tbs, key_id),
         CertEndorsementKey::LocalKey(ca_sk) => parse_and_endorse_x509_cert_local(tbs, ca_sk),
+        CertEndorsementKey::Pkcs11Key(key) => parse_and_endorse_x509_cert_pkcs11(tbs, key),
     }
 }
 
@@ -92,115 +204,97 @@ fn parse_and_endorse_x509_cert_local(tbs: Vec<u8>, ca_sk: &SecretKey<NistP256>)
     generate_certificate_from_tbs(tbs, &signature)
 }
 
-fn parse_and_endorse_x509_cert_ckms(tbs: Vec<u8>, ckms_key_id: &str) -> Result<Vec<u8>> {
-    // Let openssl hash and sign the TBS.
-    let base_name = tmpfilename("cert_signing");
-    let binding_tbs = base_name.to_owned() + ".tbs";
-    let binding_sig = base_name.to_owned() + ".sig";
-    let tbs_filename = binding_tbs.as_str();
-    let sig_filename = binding_sig.as_str();
-
-    // Save TBS in a file.
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .create(true)
-        .open(tbs_filename)
-        .context("failed to open tbs file")?;
-    file.write_all(&tbs)?;
-    drop(file);
-
-    let binding_key = String::from("pkcs11:object=") + ckms_key_id;
-    openssl_command(&[
-        "dgst",
-        "-sha256",
-        "-engine",
-        "pkcs11",
-        "-keyform",
-        "engine",
-        "-sign",
-        binding_key.as_str(),
-        "-out",
-        sig_filename,
-        tbs_filename,
-    ])
-    .context("openssl failed to sign certificate digest")?;
-
-    // Read the signature represented as an ASN.1 object.
-    file = OpenOptions::new().read(true).open(sig_filename)?;
-    let mut asn1_sig = Vec::new();
-    file.read_to_end(&mut asn1_sig)?;
-    drop(file);
-
-    // Parse the ASN.1 string into signature components.
-    let ecdsa_sig =
-        EcdsaSig::from_der(&asn1_sig).context("cannot extract ECDSA signature from blob")?;
+fn parse_and_endorse_x509_cert_pkcs11(tbs: Vec<u8>, key: &Pkcs11Key) -> Result<Vec<u8>> {
+    // Hash and sign the TBS in the token.
+    let (r, s) = key.sign(&tbs)?;
 
+    // Reformat the signature.
     let signature = Signature::EcdsaWithSha256 {
         value: Some(EcdsaSignature {
-            r: Value::Literal(BigUint::from_bytes_be(&ecdsa_sig.r().to_vec())),
-            s: Value::Literal(BigUint::from_bytes_be(&ecdsa_sig.s().to_vec())),
+            r: Value::Literal(BigUint::from_bytes_be(&r)),
+            s: Value::Literal(BigUint::from_bytes_be(&s)),
         }),
     };
 
-    fs::remove_file(tbs_filename).context("failed to remove tbs file")?;
-    fs::remove_file(sig_filename).context("failed to remove signature file")?;
-
     // Generate the (endorsed) certificate.
     generate_certificate_from_tbs(tbs, &signature)
 }
 
-/// Validate a collection of X.509 certificates using 'openssl verify ...' command.
+/// Validates a collection of X.509 certificates against a CA certificate.
 ///
 /// Each certificate in the collection is validated against the provided CA.
+/// Errors identify the offending certificate with a [`ChainError`].
 ///
 /// Arguments:
 /// * ca_pem - The file name of the CA certificate saved in PEM format.
 /// * certs  - A vector of certificate binary blobs.
 pub fn validate_certs_chain(ca_pem: &str, certs: &[Vec<u8>]) -> Result<()> {
-    let base_name = tmpfilename("cert_validation");
-    let binding_der = base_name.to_owned() + ".der";
-    let binding_pem = base_name.to_owned() + ".pem";
-
-    let der_filename = binding_der.as_str();
-    let pem_filename = binding_pem.as_str();
-
-    for cert in certs.iter() {
-        let size = get_cert_size(cert)?;
-        let mut file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(der_filename)
-            .context("failed to open temporary der file")?;
-        file.write_all(&cert[0..size])?;
-        drop(file);
-
-        openssl_command(&[
-            "x509",
-            "-out",
-            pem_filename,
-            "-in",
-            der_filename,
-            "-inform",
-            "der",
-        ])
-        .context("failed to covert DER to PEM")?;
-
-        // Validate with the fake CA certificate.
-        openssl_command(&["verify", "-CAfile", ca_pem, pem_filename])
-            .context("failed to verify a certificate chain")?;
+    let pem = fs::read(ca_pem).with_context(|| format!("failed to read {ca_pem}"))?;
+    let (label, ca_der) =
+        pem_rfc7468::decode_vec(&pem).with_context(|| format!("failed to decode {ca_pem}"))?;
+    if label != "CERTIFICATE" {
+        bail!("{ca_pem} contains a {label}, not a CERTIFICATE");
     }
-
-    fs::remove_file(der_filename).context("failed to remove der file")?;
-    fs::remove_file(pem_filename).context("failed to remove pem file")?;
-
+    let certs = certs
+        .iter()
+        .map(|cert| Ok(&cert[..get_cert_size(cert)?]))
+        .collect::<Result<Vec<_>>>()?;
+    chain::validate_chain(&ca_der, &certs).context("failed to verify a certificate chain")?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cryptoki::object::KeyType;
+    use elliptic_curve::pkcs8::DecodePrivateKey;
+    use p256::ecdsa::signature::Verifier;
+    use p256::ecdsa::Signature as EcdsaSig256;
+
+    const CA_KEY: &str = "./sw/device/silicon_creator/manuf/keys/fake/cert_endorsement_key.sk.der";
+
+    // DER encoding of the prime256v1 curve OID.
+    const P256_EC_PARAMS: [u8; 10] = [0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+
+    /// Imports the fake CA key into the SoftHSM token `fake_keys` and checks
+    /// that the signatures made through PKCS#11 verify under the CA public
+    /// key.  `HSMTOOL_MODULE` and `SOFTHSM2_CONF` are set by the Bazel test
+    /// rule.
+    #[test]
+    fn endorse_pkcs11() -> Result<()> {
+        let module = std::env::var("HSMTOOL_MODULE").context("HSMTOOL_MODULE is not set")?;
+        std::env::var("SOFTHSM2_CONF").context("SOFTHSM2_CONF is not set")?;
+
+        let mut hsm = Module::initialize(&module, None)?;
+        let session = hsm.connect("fake_keys", Some(UserType::User), Some("123456"))?;
+        let ca_sk = SecretKey::<NistP256>::read_pkcs8_der_file(CA_KEY)?;
+        session.create_object(&[
+            Attribute::Token(false),
+            Attribute::Class(ObjectClass::PRIVATE_KEY),
+            Attribute::KeyType(KeyType::EC),
+            Attribute::EcParams(P256_EC_PARAMS.to_vec()),
+            Attribute::Value(ca_sk.to_bytes().to_vec()),
+            Attribute::Label(b"ca_key".to_vec()),
+            Attribute::Sign(true),
+        ])?;
+
+        let key = Pkcs11Key::from_session(session, "ca_key")?;
+        let data = b"to be signed";
+        let (r, s) = key.sign(data)?;
+        let sig = EcdsaSig256::from_scalars(
+            <[u8; 32]>::try_from(r.as_slice())?,
+            <[u8; 32]>::try_from(s.as_slice())?,
+        )?;
+        let signing_key = SigningKey::from(&ca_sk);
+        let verifying_key = signing_key.verifying_key();
+        assert!(verifying_key.verify(data, &sig).is_ok());
+        assert!(verifying_key.verify(b"something else", &sig).is_err());
+
+        // Looking up a missing key fails.
+        let session = hsm.connect("fake_keys", None, None)?;
+        assert!(Pkcs11Key::from_session(session, "no_such_key").is_err());
+        Ok(())
+    }
 
     #[test]
     fn validate_good() {
@@ -301,12 +395,37 @@ mod tests {
             validate_certs_chain(ca_pem, &[cert0.clone(), cert1.clone(), cert2.clone()]).is_ok()
         );
 
+        // The CA is only valid from 2024 to 2034.
+        let ca_der = pem_rfc7468::decode_vec(&fs::read(ca_pem).unwrap())
+            .unwrap()
+            .1;
+        let at = |date: &str| chrono::NaiveDateTime::parse_from_str(date, "%Y%m%d%H%M%SZ").unwrap();
+        assert!(matches!(
+            chain::validate_chain_at(&ca_der, &[cert1.as_slice()], at("20400101000000Z")),
+            Err(ChainError::Expired {
+                cert: CertRef::Ca,
+                ..
+            })
+        ));
+        assert!(matches!(
+            chain::validate_chain_at(&ca_der, &[cert1.as_slice()], at("20231231000000Z")),
+            Err(ChainError::NotYetValid {
+                cert: CertRef::Ca,
+                ..
+            })
+        ));
+
         // Corrupt the fist certificate in the chain and verify that the
         // certificate validation fails.
         let bad_value = cert0.pop().unwrap() + 1;
         cert0.push(bad_value);
-        assert!(
-            validate_certs_chain(ca_pem, &[cert0.clone(), cert1.clone(), cert2.clone()]).is_err()
-        );
+        let err = validate_certs_chain(ca_pem, &[cert0.clone(), cert1.clone(), cert2.clone()])
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ChainError>(),
+            Some(ChainError::BadSignature {
+                cert: CertRef::Index(0)
+            })
+        ));
     }
 }
@@ -7,12 +7,15 @@ use std::time::Duration;
 use anyhow::Result;
 use clap::Parser;
 
+use cert_lib::Pkcs11Input;
 use cp_lib::{reset_and_lock, run_sram_cp_provision, ManufCpProvisioningDataInput};
+use opentitanlib::app::TransportWrapper;
 use opentitanlib::console::spi::SpiConsoleDevice;
 use opentitanlib::dif::lc_ctrl::DifLcCtrlState;
 use opentitanlib::test_utils::init::InitializeTest;
 use opentitanlib::test_utils::lc::read_lc_state;
 use opentitanlib::test_utils::load_sram_program::SramProgramParams;
+use record_lib::{ProvisioningRecord, RecordInput, Stage};
 use ujson_lib::provisioning_data::ManufCpProvisioningData;
 use util_lib::{hash_lc_token, hex_string_to_u32_arrayvec};
 
@@ -27,6 +30,12 @@ struct Opts {
     #[command(flatten)]
     provisioning_data: ManufCpProvisioningDataInput,
 
+    #[command(flatten)]
+    pkcs11: Pkcs11Input,
+
+    #[command(flatten)]
+    record: RecordInput,
+
     /// Console receive timeout.
     #[arg(long, value_parser = humantime::parse_duration, default_value = "600s")]
     timeout: Duration,
@@ -42,7 +51,23 @@ fn main() -> Result<()> {
     let transport = opts.init.init_target()?;
     let spi = transport.spi(&opts.console_spi)?;
     let spi_console_device = SpiConsoleDevice::new(&*spi)?;
+    let station_key = opts.record.station_key(&opts.pkcs11)?;
+
+    let mut record = opts
+        .record
+        .start(Stage::Cp, &opts.provisioning_data.device_id);
+    let result = provision(&opts, &transport, &spi_console_device, &mut record);
+    record.finish(&result);
+    opts.record.write(&record, station_key.as_ref())?;
+    result
+}
 
+fn provision(
+    opts: &Opts,
+    transport: &TransportWrapper,
+    spi_console_device: &SpiConsoleDevice,
+    record: &mut ProvisioningRecord,
+) -> Result<()> {
     let provisioning_data = ManufCpProvisioningData {
         device_id: hex_string_to_u32_arrayvec::<8>(opts.provisioning_data.device_id.as_str())?,
         manuf_state: hex_string_to_u32_arrayvec::<8>(opts.provisioning_data.manuf_state.as_str())?,
@@ -57,7 +82,7 @@ fn main() -> Result<()> {
     // state (TestUnlocked7), as this state requires special handling of the wafer authentication
     // secret, which is not yet implemented.
     let lc_state = read_lc_state(
-        &transport,
+        transport,
         &opts.init.jtag_params,
         opts.init.bootstrap.options.reset_delay,
     )?;
@@ -70,22 +95,28 @@ fn main() -> Result<()> {
         | DifLcCtrlState::TestUnlocked5
         | DifLcCtrlState::TestUnlocked6 => {
             run_sram_cp_provision(
-                &transport,
+                transport,
                 &opts.init.jtag_params,
                 opts.init.bootstrap.options.reset_delay,
                 &opts.sram_program,
                 &provisioning_data,
-                &spi_console_device,
+                spi_console_device,
                 opts.timeout,
             )?;
+            record.add_token_hash("test_unlock", &provisioning_data.test_unlock_token_hash);
+            record.add_token_hash("test_exit", &provisioning_data.test_exit_token_hash);
             // Only perform lock if we are in TEST_UNLOCKED0, otherwise we are running from a later
             // stage and want to run FT stage directly after.
             if lc_state == DifLcCtrlState::TestUnlocked0 {
                 reset_and_lock(
-                    &transport,
+                    transport,
                     &opts.init.jtag_params,
                     opts.init.bootstrap.options.reset_delay,
                 )?;
+                record.add_lc_transition(
+                    &lc_state.to_string(),
+                    &DifLcCtrlState::TestLocked0.to_string(),
+                );
             } else {
                 log::info!("Skipping resetting and locking the device.");
             }
@@ -7,16 +7,24 @@ use std::time::Duration;
 
 use anyhow::{bail, Result};
 use clap::{Args, Parser};
+use zerocopy::AsBytes;
 
-use ft_lib::{run_ft_personalize, run_sram_ft_individualize, test_exit, test_unlock, KeyWrapper};
+use cert_lib::Pkcs11Input;
+use ft_ext_api::{FtExtContext, FtExtRegistry};
+use ft_lib::{
+    default_extensions, run_ft_personalize, run_sram_ft_individualize, test_exit, test_unlock,
+    KeyWrapper,
+};
+use opentitanlib::app::TransportWrapper;
 use opentitanlib::backend;
 use opentitanlib::console::spi::SpiConsoleDevice;
 use opentitanlib::dif::lc_ctrl::DifLcCtrlState;
 use opentitanlib::test_utils::init::InitializeTest;
 use opentitanlib::test_utils::lc::read_lc_state;
 use opentitanlib::test_utils::load_sram_program::SramProgramParams;
+use record_lib::{FirmwareMeasurements, ProvisioningRecord, RecordInput, Stage};
 use ujson_lib::provisioning_data::{ManufCertgenInputs, ManufFtIndividualizeData};
-use util_lib::{hex_string_to_u32_arrayvec, hex_string_to_u8_arrayvec};
+use util_lib::{hash_lc_token, hex_string_to_u32_arrayvec, hex_string_to_u8_arrayvec};
 
 /// Provisioning data command-line parameters.
 #[derive(Debug, Args, Clone)]
@@ -60,12 +68,24 @@ pub struct ManufFtProvisioningDataInput {
     pub owner_security_version: u32,
 
     /// CA (ECC P256) endorsement key as a DER file path.
-    #[arg(long, default_value = None,  required = true, conflicts_with = "ca_key_ckms_id")]
+    #[arg(
+        long,
+        default_value = None,
+        required = true,
+        conflicts_with = "ca_key_pkcs11_label",
+    )]
     ca_key_der_file: Option<PathBuf>,
 
-    /// CA endorsement key as a Google Cloud KMS key ID string.
-    #[arg(long, default_value = None, required = true, conflicts_with = "ca_key_der_file")]
-    pub ca_key_ckms_id: Option<String>,
+    /// CA endorsement key as the label of a private key in a PKCS#11 token.
+    ///
+    /// Cloud KMS keys are reached through the Cloud KMS PKCS#11 library.
+    #[arg(
+        long,
+        default_value = None,
+        required = true,
+        conflicts_with = "ca_key_der_file",
+    )]
+    pub ca_key_pkcs11_label: Option<String>,
 
     /// CA key ID hexstring.
     #[arg(long)]
@@ -91,6 +111,12 @@ struct Opts {
     #[command(flatten)]
     provisioning_data: ManufFtProvisioningDataInput,
 
+    #[command(flatten)]
+    pkcs11: Pkcs11Input,
+
+    #[command(flatten)]
+    record: RecordInput,
+
     /// Console receive timeout.
     #[arg(long, value_parser = humantime::parse_duration, default_value = "600s")]
     timeout: Duration,
@@ -111,7 +137,35 @@ fn main() -> Result<()> {
     let spi = transport.spi(&opts.console_spi)?;
     let spi_console_device = SpiConsoleDevice::new(&*spi)?;
     InitializeTest::print_result("load_bitstream", opts.init.load_bitstream.init(&transport))?;
+    let extensions = default_extensions();
+    let station_key = opts.record.station_key(&opts.pkcs11)?;
+
+    let mut record = opts
+        .record
+        .start(Stage::Ft, &opts.provisioning_data.device_id);
+    let result = provision(
+        &opts,
+        &transport,
+        &spi_console_device,
+        &extensions,
+        &mut record,
+    );
+    record.finish(&result);
+    opts.record.write(&record, station_key.as_ref())?;
+    result?;
+
+    log::info!("Provisioning Done");
 
+    Ok(())
+}
+
+fn provision(
+    opts: &Opts,
+    transport: &TransportWrapper,
+    spi_console_device: &SpiConsoleDevice,
+    extensions: &FtExtRegistry,
+    record: &mut ProvisioningRecord,
+) -> Result<()> {
     // Format test tokens.
     let _test_unlock_token =
         hex_string_to_u32_arrayvec::<4>(opts.provisioning_data.test_unlock_token.as_str())?;
@@ -143,13 +197,21 @@ fn main() -> Result<()> {
         owner_security_version,
         auth_key_key_id: ca_key_id.clone(),
     };
+    record.measurements = Some(FirmwareMeasurements {
+        rom_ext_measurement: opts.provisioning_data.rom_ext_measurement.clone(),
+        rom_ext_security_version,
+        owner_manifest_measurement: opts.provisioning_data.owner_manifest_measurement.clone(),
+        owner_measurement: opts.provisioning_data.owner_measurement.clone(),
+        owner_security_version,
+    });
 
     // Only run test unlock operation if we are in a locked LC state.
-    match read_lc_state(
-        &transport,
+    let initial_lc_state = read_lc_state(
+        transport,
         &opts.init.jtag_params,
         opts.init.bootstrap.options.reset_delay,
-    )? {
+    )?;
+    match initial_lc_state {
         DifLcCtrlState::TestLocked0
         | DifLcCtrlState::TestLocked1
         | DifLcCtrlState::TestLocked2
@@ -158,11 +220,15 @@ fn main() -> Result<()> {
         | DifLcCtrlState::TestLocked5
         | DifLcCtrlState::TestLocked6 => {
             test_unlock(
-                &transport,
+                transport,
                 &opts.init.jtag_params,
                 opts.init.bootstrap.options.reset_delay,
                 &_test_unlock_token,
             )?;
+            record.add_token_hash(
+                "test_unlock",
+                &hash_lc_token(_test_unlock_token.as_bytes())?,
+            );
         }
         _ => {
             log::info!("Skipping test unlock operation. Device is already unlocked.");
@@ -171,11 +237,15 @@ fn main() -> Result<()> {
 
     // Only run the SRAM individualize program in a test unlocked state. If we have transitioned to
     // a mission state already, then we can skip this step.
-    match read_lc_state(
-        &transport,
+    let lc_state = read_lc_state(
+        transport,
         &opts.init.jtag_params,
         opts.init.bootstrap.options.reset_delay,
-    )? {
+    )?;
+    if lc_state != initial_lc_state {
+        record.add_lc_transition(&initial_lc_state.to_string(), &lc_state.to_string());
+    }
+    match lc_state {
         DifLcCtrlState::TestUnlocked0 => {
             bail!("FT stage cannot be run from test unlocked 0. Run CP stage first.");
         }
@@ -187,21 +257,29 @@ fn main() -> Result<()> {
         | DifLcCtrlState::TestUnlocked6
         | DifLcCtrlState::TestUnlocked7 => {
             run_sram_ft_individualize(
-                &transport,
+                transport,
                 &opts.init.jtag_params,
                 opts.init.bootstrap.options.reset_delay,
                 &opts.sram_program,
                 &_ft_individualize_data_in,
                 opts.timeout,
-                &spi_console_device,
+                spi_console_device,
             )?;
+            extensions.pre_lc_transition(&FtExtContext {
+                transport: Some(transport),
+                console: spi_console_device,
+                timeout: opts.timeout,
+            })?;
+            let target_lc_state = opts.provisioning_data.target_mission_mode_lc_state;
             test_exit(
-                &transport,
+                transport,
                 &opts.init.jtag_params,
                 opts.init.bootstrap.options.reset_delay,
                 &_test_exit_token,
-                opts.provisioning_data.target_mission_mode_lc_state,
+                target_lc_state,
             )?;
+            record.add_token_hash("test_exit", &hash_lc_token(_test_exit_token.as_bytes())?);
+            record.add_lc_transition(&lc_state.to_string(), &target_lc_state.to_string());
         }
         _ => {
             log::info!("Skipping individualize operation. Device is already in a mission mode.");
@@ -213,28 +291,34 @@ fn main() -> Result<()> {
     transport.ignore_dft_straps_on_reset()?;
 
     let cert_endorsement_key_wrapper = match (
-        opts.provisioning_data.ca_key_ckms_id,
-        opts.provisioning_data.ca_key_der_file,
+        opts.provisioning_data.ca_key_der_file.clone(),
+        opts.provisioning_data.ca_key_pkcs11_label.clone(),
     ) {
-        (Some(ckms), None) => KeyWrapper::CkmsKey(ckms),
-        (None, Some(local)) => KeyWrapper::LocalKey(local),
+        (Some(local), None) => KeyWrapper::LocalKey(local),
+        (None, Some(label)) => KeyWrapper::Pkcs11Key(label, opts.pkcs11.config()?),
         (_, _) => {
             log::error!("One and only one endorsement key parameter must be included");
             bail!("Incorrect command line endorsement key settings");
         }
     };
-    run_ft_personalize(
-        &transport,
+    let certificates = run_ft_personalize(
+        transport,
         &opts.init,
+        extensions,
         cert_endorsement_key_wrapper,
         &_perso_certgen_inputs,
         opts.timeout,
-        opts.provisioning_data.ca_certificate,
+        opts.provisioning_data.ca_certificate.clone(),
         &rma_unlock_token_hash,
-        &spi_console_device,
+        spi_console_device,
     )?;
-
-    log::info!("Provisioning Done");
+    record.add_token_hash(
+        "rma_unlock",
+        &hash_lc_token(rma_unlock_token_hash.as_bytes())?,
+    );
+    for (name, cert) in &certificates {
+        record.add_certificate(name, cert);
+    }
 
     Ok(())
 }
@@ -0,0 +1,212 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Extension points of the FT provisioning flow.
+//!
+//! SKU-specific code hooks into the reference FT flow by implementing
+//! [`FtExtension`].  Extensions are collected in a [`FtExtRegistry`], and each
+//! hook runs the registered extensions in registration order.  The extension
+//! library selected at build time (`ft_ext_lib`) registers its extensions with
+//! `register_extensions(&mut FtExtRegistry)`.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use opentitanlib::app::TransportWrapper;
+use opentitanlib::io::console::ConsoleDevice;
+use perso_tlv_lib::PersoObject;
+
+/// State of the FT flow available to extension hooks.
+pub struct FtExtContext<'a> {
+    /// The transport connected to the device, or `None` when the device is
+    /// simulated.
+    pub transport: Option<&'a TransportWrapper>,
+    /// The console of the firmware running on the device.
+    pub console: &'a dyn ConsoleDevice,
+    /// Console receive timeout.
+    pub timeout: Duration,
+}
+
+/// An extension of the FT flow.  All hooks default to doing nothing.
+pub trait FtExtension {
+    /// The name of the extension, used in logs and errors.
+    fn name(&self) -> &str;
+
+    /// Called before the device transitions from TEST_UNLOCKED to a mission
+    /// mode LC state.
+    fn pre_lc_transition(&self, _ctx: &FtExtContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once the host has endorsed the TBS certificates exported by the
+    /// personalization firmware.  `endorsed_certs` holds the objects that will
+    /// be sent back to the device; extensions may modify or add to them.
+    fn post_cert_endorse(
+        &self,
+        _ctx: &FtExtContext,
+        _endorsed_certs: &mut Vec<PersoObject>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after the personalization firmware reports completion.
+    fn post_personalize(&self, _ctx: &FtExtContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An ordered collection of FT extensions.
+#[derive(Default)]
+pub struct FtExtRegistry {
+    extensions: Vec<Box<dyn FtExtension>>,
+}
+
+impl FtExtRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an extension, to be run after the ones already registered.
+    pub fn register(&mut self, extension: impl FtExtension + 'static) {
+        log::info!("Registering FT extension {}", extension.name());
+        self.extensions.push(Box::new(extension));
+    }
+
+    /// Returns the names of the registered extensions, in order.
+    pub fn names(&self) -> Vec<&str> {
+        self.extensions.iter().map(|ext| ext.name()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    /// Runs `hook` on every extension, stopping at the first error.
+    fn run(&self, hook: &str, mut f: impl FnMut(&dyn FtExtension) -> Result<()>) -> Result<()> {
+        for ext in &self.extensions {
+            log::info!("Running FT extension {} {hook} hook", ext.name());
+            f(ext.as_ref())
+                .with_context(|| format!("FT extension {} failed in {hook}", ext.name()))?;
+        }
+        Ok(())
+    }
+
+    pub fn pre_lc_transition(&self, ctx: &FtExtContext) -> Result<()> {
+        self.run("pre_lc_transition", |ext| ext.pre_lc_transition(ctx))
+    }
+
+    pub fn post_cert_endorse(
+        &self,
+        ctx: &FtExtContext,
+        endorsed_certs: &mut Vec<PersoObject>,
+    ) -> Result<()> {
+        self.run("post_cert_endorse", |ext| {
+            ext.post_cert_endorse(ctx, endorsed_certs)
+        })
+    }
+
+    pub fn post_personalize(&self, ctx: &FtExtContext) -> Result<()> {
+        self.run("post_personalize", |ext| ext.post_personalize(ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct NoConsole;
+
+    impl ConsoleDevice for NoConsole {}
+
+    struct Recorder {
+        name: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+        fail: bool,
+    }
+
+    impl FtExtension for Recorder {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn pre_lc_transition(&self, _ctx: &FtExtContext) -> Result<()> {
+            self.log.borrow_mut().push(format!("{}:pre_lc", self.name));
+            if self.fail {
+                bail!("injected failure");
+            }
+            Ok(())
+        }
+
+        fn post_cert_endorse(
+            &self,
+            _ctx: &FtExtContext,
+            endorsed_certs: &mut Vec<PersoObject>,
+        ) -> Result<()> {
+            endorsed_certs.push(PersoObject::EndorsedX509Cert {
+                name: self.name.into(),
+                cert: vec![0x30, 0x00],
+            });
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn runs_in_order() -> Result<()> {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut registry = FtExtRegistry::new();
+        for name in ["first", "second"] {
+            registry.register(Recorder {
+                name,
+                log: log.clone(),
+                fail: false,
+            });
+        }
+        assert_eq!(registry.names(), ["first", "second"]);
+
+        let ctx = FtExtContext {
+            transport: None,
+            console: &NoConsole,
+            timeout: Duration::from_secs(1),
+        };
+        registry.pre_lc_transition(&ctx)?;
+        assert_eq!(*log.borrow(), ["first:pre_lc", "second:pre_lc"]);
+
+        let mut certs = Vec::new();
+        registry.post_cert_endorse(&ctx, &mut certs)?;
+        let names = certs.iter().filter_map(|c| c.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["first", "second"]);
+
+        // Hooks that an extension does not implement do nothing.
+        registry.post_personalize(&ctx)?;
+        Ok(())
+    }
+
+    #[test]
+    fn stops_at_first_error() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut registry = FtExtRegistry::new();
+        for (name, fail) in [("first", true), ("second", false)] {
+            registry.register(Recorder {
+                name,
+                log: log.clone(),
+                fail,
+            });
+        }
+        let ctx = FtExtContext {
+            transport: None,
+            console: &NoConsole,
+            timeout: Duration::from_secs(1),
+        };
+        let err = registry.pre_lc_transition(&ctx).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "FT extension first failed in pre_lc_transition"
+        );
+        assert_eq!(*log.borrow(), ["first:pre_lc"]);
+    }
+}
@@ -6,7 +6,7 @@ use sha2::{Digest, Sha256};
 use std::path::PathBuf;
 use std::time::Duration;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Context, Result};
 use arrayvec::ArrayVec;
 use elliptic_curve::pkcs8::DecodePrivateKey;
 use elliptic_curve::SecretKey;
@@ -15,11 +15,13 @@ use zerocopy::AsBytes;
 
 use cert_lib::{
     get_cert_size, parse_and_endorse_x509_cert, validate_certs_chain, CertEndorsementKey,
+    Pkcs11Config, Pkcs11Key,
 };
-use ft_ext_lib::ft_ext;
+use ft_ext_api::{FtExtContext, FtExtRegistry};
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::console::spi::SpiConsoleDevice;
 use opentitanlib::dif::lc_ctrl::{DifLcCtrlState, LcCtrlReg};
+use opentitanlib::io::console::ConsoleDevice;
 use opentitanlib::io::jtag::{JtagParams, JtagTap};
 use opentitanlib::test_utils::init::InitializeTest;
 use opentitanlib::test_utils::lc_transition::trigger_lc_transition;
@@ -29,13 +31,17 @@ use opentitanlib::test_utils::load_sram_program::{
 use opentitanlib::test_utils::rpc::{ConsoleRecv, ConsoleSend};
 use opentitanlib::uart::console::UartConsole;
 use ot_certs::x509::parse_certificate;
-use perso_tlv_lib::perso_tlv_get_field;
-use perso_tlv_lib::{CertHeader, CertHeaderType, ObjHeader, ObjHeaderType, ObjType};
+use perso_tlv_lib::{encode_objects, PersoObject};
 use ujson_lib::provisioning_data::{
     LcTokenHash, ManufCertgenInputs, ManufFtIndividualizeData, PersoBlob, SerdesSha256Hash,
 };
 use util_lib::hash_lc_token;
 
+pub mod sim;
+
+/// The name and contents of a certificate.
+pub type NamedCert = (String, Vec<u8>);
+
 pub fn test_unlock(
     transport: &TransportWrapper,
     jtag_params: &JtagParams,
@@ -162,17 +168,19 @@ pub fn test_exit(
 
 // This enum provides two different certificate signing key representations. In
 // case the local fake certificate is used for certificate chain validation, the
-// key is a path to the file containing the private key. In case a Cloud KMS
-// certificate is used, the key is a string, the ID of the key in cloud storage.
+// key is a path to the file containing the private key. In case a PKCS#11 token
+// is used (an on-prem HSM, or Cloud KMS through its PKCS#11 library), the key is
+// the label of the private key in the token described by the accompanying
+// configuration.
 pub enum KeyWrapper {
     LocalKey(PathBuf),
-    CkmsKey(String),
+    Pkcs11Key(String, Pkcs11Config),
 }
 
 fn send_rma_unlock_token_hash(
     rma_unlock_token_hash: &ArrayVec<u32, 4>,
     timeout: Duration,
-    spi_console: &SpiConsoleDevice,
+    spi_console: &dyn ConsoleDevice,
 ) -> Result<()> {
     let rma_token_hash = LcTokenHash {
         hash: hash_lc_token(rma_unlock_token_hash.as_bytes())?,
@@ -188,94 +196,17 @@ fn send_rma_unlock_token_hash(
     Ok(())
 }
 
-// Extract LTV object header from the input buffer.
-fn get_obj_header(data: &[u8]) -> Result<ObjHeader> {
-    let header_len = std::mem::size_of::<ObjHeaderType>();
-    // The header is 2 bytes in size.
-    if data.len() < header_len {
-        bail!(
-            "Insufficient amount of data ({} bytes) for object header",
-            data.len()
-        );
-    }
-
-    let typesize = u16::from_be_bytes([data[0], data[1]]);
-    let obj_size = perso_tlv_get_field!("obj", "size", typesize);
-    let obj_type = ObjType::from_usize(perso_tlv_get_field!("obj", "type", typesize))?;
-
-    if obj_size > data.len() {
-        bail!(
-            "Object {} length {} exceeds buffer size {}",
-            obj_type as u8,
-            obj_size,
-            data.len()
-        );
-    }
-    Ok(ObjHeader { obj_type, obj_size })
-}
-
-// Extract certificate payload header from the input buffer.
-fn get_cert(data: &[u8]) -> Result<CertHeader> {
-    let header_len = std::mem::size_of::<CertHeaderType>();
-
-    if data.len() < header_len {
-        bail!(
-            "Insufficient amount of data ({} bytes) for cert header",
-            data.len()
-        );
-    }
-
-    let header = u16::from_be_bytes([data[0], data[1]]);
-    let wrapped_size = perso_tlv_get_field!("crth", "size", header);
-    if wrapped_size > data.len() {
+// Check that a certificate body holds exactly one certificate.
+fn check_cert_size(name: &str, cert: &[u8]) -> Result<()> {
+    let cert_size = get_cert_size(cert)?;
+    if cert_size != cert.len() {
         bail!(
-            "Cert object size {} exceeds buffer size {}",
-            wrapped_size,
-            data.len()
-        );
-    }
-
-    let name_len = perso_tlv_get_field!("crth", "name", header);
-    let cert_name = std::str::from_utf8(&data[header_len..header_len + name_len])?;
-    log::info!("processing cert {cert_name}");
-    let header_size = header_len + name_len;
-    let cert_body: Vec<u8> = data[header_size..wrapped_size].to_vec();
-
-    // Sanity check, total size and cert size must  match
-    let cert_size = get_cert_size(&cert_body)?;
-    if cert_size != cert_body.len() {
-        bail!(
-            "cert size {} does not match length {}",
+            "{} cert size {} does not match length {}",
+            name,
             cert_size,
-            cert_body.len()
+            cert.len()
         );
     }
-    Ok(CertHeader {
-        wrapped_size,
-        cert_name,
-        cert_body,
-    })
-}
-
-fn push_endorsed_cert(
-    cert: &Vec<u8>,
-    ref_cert: &CertHeader,
-    output: &mut ArrayVec<u8, 4096>,
-) -> Result<()> {
-    // Need to wrap the new cert in CertHeader
-    let total_size = std::mem::size_of::<ObjHeaderType>()
-        + std::mem::size_of::<CertHeaderType>()
-        + ref_cert.cert_name.len()
-        + cert.len();
-
-    let obj_header = perso_tlv_lib::make_obj_header(total_size, ObjType::EndorsedX509Cert)?;
-    let cert_wrapper_header =
-        perso_tlv_lib::make_cert_wrapper_header(cert.len(), ref_cert.cert_name)?;
-    output.try_extend_from_slice(&obj_header.to_be_bytes())?;
-    output.try_extend_from_slice(&cert_wrapper_header.to_be_bytes())?;
-    output.try_extend_from_slice(ref_cert.cert_name.as_bytes())?;
-    output.try_extend_from_slice(cert.as_slice())?;
-
     Ok(())
 }
 
@@ -295,13 +226,24 @@ fn process_dev_seeds(seeds: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// Data exported by the device that goes into the hash of what is written to flash.
+enum HashInput {
+    /// Device seeds.
+    DevSeeds(Vec<u8>),
+    /// A certificate, given by its index in the list of provisioned certificates.
+    Cert(usize),
+}
+
 fn provision_certificates(
+    ctx: &FtExtContext,
+    extensions: &FtExtRegistry,
     cert_endorsement_key_wrapper: KeyWrapper,
     perso_certgen_inputs: &ManufCertgenInputs,
-    timeout: Duration,
     ca_certificate: PathBuf,
-    spi_console: &SpiConsoleDevice,
-) -> Result<()> {
+) -> Result<Vec<NamedCert>> {
+    let spi_console = ctx.console;
+    let timeout = ctx.timeout;
+
     // Send attestation TCB measurements for generating DICE certificates.
     let _ = UartConsole::wait_for(spi_console, r"Waiting for certificate inputs ...", timeout)?;
     perso_certgen_inputs.send(spi_console)?;
@@ -316,86 +258,101 @@ fn provision_certificates(
             log::info!("Using local key for cert endorsement");
             CertEndorsementKey::LocalKey(SecretKey::<NistP256>::read_pkcs8_der_file(path)?)
         }
-        KeyWrapper::CkmsKey(key_id) => {
-            log::info!("Using Cloud KMS key for cert endorsement");
-            CertEndorsementKey::CkmsKey(key_id)
+        KeyWrapper::Pkcs11Key(label, config) => {
+            log::info!("Using PKCS#11 key {label:?} for cert endorsement");
+            CertEndorsementKey::Pkcs11Key(Pkcs11Key::open(&config, &label)?)
         }
     };
 
-    // Extract certificate byte vectors, endorse TBS certs, and ensure they parse with OpenSSL.
+    // Extract certificate byte vectors, endorse TBS certs, and ensure they parse.
     // During the process, both:
     //   1. prepare a UJSON payload of endorsed certs to send back to the device,
-    //   2. collect the certs that were endorsed to verify their endorsement signatures with OpenSSL, and
-    //   3. hash all certs to check the integrity of what gets written back to the device.
-    let mut cert_hasher = Sha256::new();
-    let mut start: usize = 0;
+    //   2. collect the certs that were endorsed to verify their endorsement signatures, and
+    //   3. record what to hash to check the integrity of what gets written back to the device.
+    let mut hash_inputs: Vec<HashInput> = Vec::new();
     let mut host_endorsed_certs: Vec<Vec<u8>> = Vec::new();
-    let mut num_host_endorsed_certs = 0;
-    let mut endorsed_cert_concat = ArrayVec::<u8, 4096>::new();
+    let mut endorsed_certs: Vec<PersoObject> = Vec::new();
+    let mut certificates: Vec<NamedCert> = Vec::new();
 
-    for _ in 0..perso_blob.num_objs {
+    let mut num_objs = 0;
+    for object in perso_tlv_lib::objects(&perso_blob.body).take(perso_blob.num_objs) {
         log::info!("Processing next object");
-        let header = get_obj_header(&perso_blob.body[start..])?;
-        let obj_header_size = std::mem::size_of::<ObjHeaderType>();
-
-        if header.obj_size > (perso_blob.body.len() - start) {
-            bail!("Perso blob overflow!");
-        }
-        start += obj_header_size;
-        match header.obj_type {
-            ObjType::EndorsedX509Cert | ObjType::UnendorsedX509Cert => (),
-            ObjType::DevSeed => {
-                let dev_seed_size = header.obj_size - obj_header_size;
-                let seeds = &perso_blob.body[start..start + dev_seed_size];
-                cert_hasher.update(seeds);
-                process_dev_seeds(seeds)?;
-                start += dev_seed_size;
+        num_objs += 1;
+        let (cert_name, cert_bytes) = match object? {
+            PersoObject::DevSeed(seeds) => {
+                process_dev_seeds(&seeds)?;
+                hash_inputs.push(HashInput::DevSeeds(seeds));
                 continue;
             }
-        }
-
-        // The next object is a cert, let's retrieve its properties (name, needs
-        // endorsement, etc.)
-        let cert = get_cert(&perso_blob.body[start..])?;
-        start += cert.wrapped_size;
-
-        let cert_bytes = if header.obj_type == ObjType::UnendorsedX509Cert {
-            // Endorse the cert and updates its size.
-            let cert_bytes = parse_and_endorse_x509_cert(cert.cert_body.clone(), &key)?;
+            PersoObject::UnendorsedX509Cert { name, tbs } => {
+                log::info!("processing cert {name}");
+                check_cert_size(&name, &tbs)?;
+                // Endorse the cert and updates its size.
+                let cert_bytes = parse_and_endorse_x509_cert(tbs, &key)?;
 
-            // Prepare a collection of certs whose endorsements should be checked with OpenSSL.
-            // TODO: verify the endorsement of the UDS certificate with OpenSSL. It is currently
-            // failing signature verification due to the custom DiceTcbInfo extension being a
-            // non-recognizable extension that is marked "critical".
-            if cert.cert_name != "UDS" {
+                // Prepare a collection of certs whose endorsements should be checked.
                 host_endorsed_certs.push(cert_bytes.clone());
-            }
 
-            // Prepare the UJSON data payloads that will be sent back to the device.
-            push_endorsed_cert(&cert_bytes, &cert, &mut endorsed_cert_concat)?;
-            num_host_endorsed_certs += 1;
-            cert_bytes
-        } else {
-            cert.cert_body
+                // Prepare the objects that will be sent back to the device.
+                endorsed_certs.push(PersoObject::EndorsedX509Cert {
+                    name: name.clone(),
+                    cert: cert_bytes.clone(),
+                });
+                (name, cert_bytes)
+            }
+            PersoObject::EndorsedX509Cert { name, cert } => {
+                log::info!("processing cert {name}");
+                check_cert_size(&name, &cert)?;
+                (name, cert)
+            }
         };
-        // Ensure all certs parse with OpenSSL (even those that where endorsed on device).
-        log::info!("{} Cert: {}", cert.cert_name, hex::encode(&cert_bytes));
+        // Ensure all certs parse (even those that where endorsed on device).
+        log::info!("{} Cert: {}", cert_name, hex::encode(&cert_bytes));
         let _ = parse_certificate(&cert_bytes)?;
-        // Push the cert into the hasher so we can ensure the certs written to the device's flash
-        // info pages match those verified on the host.
-        cert_hasher.update(cert_bytes);
+        // Hash the cert once extensions are done with it, so we can ensure the certs written to
+        // the device's flash info pages match those verified on the host.
+        hash_inputs.push(HashInput::Cert(certificates.len()));
+        certificates.push((cert_name, cert_bytes));
     }
 
-    // Execute extension hook.
-    endorsed_cert_concat = ft_ext(endorsed_cert_concat)?;
+    ensure!(
+        num_objs == perso_blob.num_objs,
+        "the device sent {} objects instead of {}",
+        num_objs,
+        perso_blob.num_objs
+    );
+
+    // Execute extension hooks.
+    extensions.post_cert_endorse(ctx, &mut endorsed_certs)?;
+
+    // Account for certificates that extensions replaced or added.
+    for object in &endorsed_certs {
+        if let PersoObject::EndorsedX509Cert { name, cert } = object {
+            match certificates.iter_mut().find(|(n, _)| n == name) {
+                Some(entry) => entry.1.clone_from(cert),
+                None => certificates.push((name.clone(), cert.clone())),
+            }
+        }
+    }
 
-    // Complete hash of all certs that will be sent back to the device and written to flash. This
-    // is used as integrity check on what will be written to flash.
+    // Complete hash of all certs that will be sent back to the device and written to flash, as
+    // modified by the extensions. This is used as integrity check on what will be written to
+    // flash.
+    let mut cert_hasher = Sha256::new();
+    for input in &hash_inputs {
+        match input {
+            HashInput::DevSeeds(seeds) => cert_hasher.update(seeds),
+            HashInput::Cert(index) => cert_hasher.update(&certificates[*index].1),
+        }
+    }
     let host_computed_certs_hash = cert_hasher.finalize();
 
     // Send endorsed certificates back to the device.
+    let endorsed_cert_concat =
+        ArrayVec::<u8, 4096>::try_from(encode_objects(&endorsed_certs)?.as_slice())
+            .context("endorsed certificates do not fit in a perso blob")?;
     let manuf_perso_data_back = PersoBlob {
-        num_objs: num_host_endorsed_certs,
+        num_objs: endorsed_certs.len(),
         next_free: endorsed_cert_concat.len(),
         body: endorsed_cert_concat,
     };
@@ -421,40 +378,78 @@ fn provision_certificates(
         )
     }
 
-    // Validate the certificate endorsements with OpenSSL.
+    // Validate the certificate endorsements against the CA.
     if !host_endorsed_certs.is_empty() {
         validate_certs_chain(ca_certificate.to_str().unwrap(), &host_endorsed_certs)?;
     }
 
-    Ok(())
+    Ok(certificates)
+}
+
+/// Runs the personalization protocol with a device that is already executing
+/// the personalization firmware and waiting for the RMA unlock token hash.
+///
+/// The device is reached through the console of `ctx`, which allows running
+/// the protocol against the [`sim::FtSimulator`] instead of a chip.  Returns
+/// the certificates provisioned in the device.
+pub fn run_ft_personalize_protocol(
+    ctx: &FtExtContext,
+    extensions: &FtExtRegistry,
+    cert_endorsement_key_wrapper: KeyWrapper,
+    perso_certgen_inputs: &ManufCertgenInputs,
+    ca_certificate: PathBuf,
+    rma_unlock_token_hash: &ArrayVec<u32, 4>,
+) -> Result<Vec<NamedCert>> {
+    send_rma_unlock_token_hash(rma_unlock_token_hash, ctx.timeout, ctx.console)?;
+    let certificates = provision_certificates(
+        ctx,
+        extensions,
+        cert_endorsement_key_wrapper,
+        perso_certgen_inputs,
+        ca_certificate,
+    )?;
+
+    let _ = UartConsole::wait_for(ctx.console, r"Personalization done.", ctx.timeout)?;
+    extensions.post_personalize(ctx)?;
+
+    Ok(certificates)
+}
+
+/// Returns the extensions of the FT flow selected at build time.
+pub fn default_extensions() -> FtExtRegistry {
+    let mut extensions = FtExtRegistry::new();
+    ft_ext_lib::register_extensions(&mut extensions);
+    extensions
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn run_ft_personalize(
     transport: &TransportWrapper,
     init: &InitializeTest,
+    extensions: &FtExtRegistry,
     cert_endorsement_key_wrapper: KeyWrapper,
     perso_certgen_inputs: &ManufCertgenInputs,
     timeout: Duration,
     ca_certificate: PathBuf,
     rma_unlock_token_hash: &ArrayVec<u32, 4>,
     spi_console: &SpiConsoleDevice,
-) -> Result<()> {
+) -> Result<Vec<NamedCert>> {
     // Bootstrap personalization binary into flash.
     init.bootstrap.init(transport)?;
     // Bootstrap again since the flash scrambling seeds were provisioned in the previous step.
     let _ = UartConsole::wait_for(spi_console, r"Bootstrap requested.", timeout)?;
     init.bootstrap.init(transport)?;
-    send_rma_unlock_token_hash(rma_unlock_token_hash, timeout, spi_console)?;
-    provision_certificates(
+    let ctx = FtExtContext {
+        transport: Some(transport),
+        console: spi_console,
+        timeout,
+    };
+    run_ft_personalize_protocol(
+        &ctx,
+        extensions,
         cert_endorsement_key_wrapper,
         perso_certgen_inputs,
-        timeout,
         ca_certificate,
-        spi_console,
-    )?;
-
-    let _ = UartConsole::wait_for(spi_console, r"Personalization done.", timeout)?;
-
-    Ok(())
+        rma_unlock_token_hash,
+    )
 }
@@ -0,0 +1,610 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A simulated device for the FT personalization protocol.
+//!
+//! [`FtSimulator`] stands in for the personalization firmware running on a
+//! chip: it implements `ConsoleDevice`, speaks the same ujson protocol as
+//! `ft_personalize.c`, generates its DICE keys and certificates with the
+//! `ot_certs` templates and checks the certificates endorsed by the host.
+//! This allows running [`run_ft_personalize_protocol`](crate::run_ft_personalize_protocol)
+//! without hardware.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use arrayvec::ArrayVec;
+use crc::{Crc, CRC_32_ISO_HDLC};
+use elliptic_curve::sec1::ToEncodedPoint;
+use elliptic_curve::SecretKey;
+use num_bigint_dig::BigUint;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature as P256Signature, SigningKey};
+use p256::NistP256;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use zerocopy::AsBytes;
+
+use cert_lib::chain::{tbs_certificate, validate_chain};
+use opentitanlib::io::console::ConsoleDevice;
+use opentitanlib::test_utils::rpc::OttfCrc;
+use ot_certs::template::subst::{Subst, SubstValue};
+use ot_certs::template::{EcdsaSignature, Signature, Template, Value};
+use ot_certs::x509;
+use perso_tlv_lib::PersoObject;
+use ujson_lib::provisioning_data::{LcTokenHash, ManufCertgenInputs, PersoBlob, SerdesSha256Hash};
+
+use crate::NamedCert;
+
+const UDS_TEMPLATE: &str = include_str!("../../../../device/silicon_creator/lib/cert/uds.hjson");
+const CDI_0_TEMPLATE: &str =
+    include_str!("../../../../device/silicon_creator/lib/cert/cdi_0.hjson");
+
+/// Size of the device seeds exported to the host.
+const DEV_SEEDS_SIZE: usize = 128;
+
+/// Faults that the simulator injects to exercise the error handling of the
+/// host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FtSimFault {
+    /// Send the perso blob with a wrong CRC.
+    BadBlobCrc,
+    /// Truncate the UDS TBS certificate in the perso blob.
+    TruncatedTbs,
+    /// Announce one more object in the perso blob than it contains.
+    MissingObject,
+    /// Report a wrong hash of the certificates written to flash.
+    BadCertsHash,
+}
+
+/// Configuration of a [`FtSimulator`].
+#[derive(Clone, Debug, Default)]
+pub struct FtSimConfig {
+    /// Seed from which the device keys and seeds are derived.  Simulated
+    /// devices with different seeds get different keys.
+    pub device_seed: u64,
+    /// DER encoded CA certificate against which the certificates endorsed by
+    /// the host are validated.  If not set, they are only parsed.
+    pub ca_certificate: Option<Vec<u8>>,
+    /// Fault to inject, if any.
+    pub fault: Option<FtSimFault>,
+}
+
+/// The step of the protocol the simulated device is at.
+enum Stage {
+    /// Waiting for the RMA unlock token hash.
+    RmaUnlockTokenHash,
+    /// Waiting for the CRC of the RMA unlock token hash `json`.
+    RmaUnlockTokenCrc { json: String },
+    /// Waiting for the certificate generation inputs.
+    CertgenInputs,
+    /// Waiting for the endorsed certificates, after exporting `objects`.
+    EndorsedCerts { objects: Vec<PersoObject> },
+    /// Personalization completed.
+    Done,
+    /// Personalization failed; all further input is ignored.
+    Failed,
+}
+
+struct SimState {
+    stage: Stage,
+    input: Vec<u8>,
+    output: VecDeque<u8>,
+    rma_unlock_token_hash: Option<LcTokenHash>,
+    certificates: Vec<NamedCert>,
+    error: Option<String>,
+}
+
+impl SimState {
+    fn print(&mut self, line: &str) {
+        self.output.extend(line.as_bytes());
+        self.output.push_back(b'\n');
+    }
+
+    fn respond_ok<T: Serialize>(&mut self, value: &T, corrupt_crc: bool) -> Result<()> {
+        let json = serde_json::to_string(value)?;
+        let mut crc = Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(json.as_bytes());
+        if corrupt_crc {
+            crc ^= 1;
+        }
+        self.print(&format!("RESP_OK:{json} CRC:{crc}"));
+        Ok(())
+    }
+}
+
+/// A simulated device running the FT personalization firmware.
+///
+/// The simulator starts out waiting for the RMA unlock token hash, i.e. after
+/// the personalization firmware has been bootstrapped.  Host output is parsed
+/// as it is written and the device responses are queued for `console_read`.
+/// Errors detected by the simulator stop the protocol and are reported by
+/// [`FtSimulator::error`]; the host then times out waiting for the device.
+pub struct FtSimulator {
+    config: FtSimConfig,
+    state: RefCell<SimState>,
+}
+
+impl FtSimulator {
+    pub fn new(config: FtSimConfig) -> Self {
+        let mut state = SimState {
+            stage: Stage::RmaUnlockTokenHash,
+            input: Vec::new(),
+            output: VecDeque::new(),
+            rma_unlock_token_hash: None,
+            certificates: Vec::new(),
+            error: None,
+        };
+        state.print("Waiting For RMA Unlock Token Hash ...");
+        FtSimulator {
+            config,
+            state: RefCell::new(state),
+        }
+    }
+
+    /// Returns the error which stopped the protocol, if any.
+    pub fn error(&self) -> Option<String> {
+        self.state.borrow().error.clone()
+    }
+
+    /// Returns true once personalization has completed.
+    pub fn is_done(&self) -> bool {
+        matches!(self.state.borrow().stage, Stage::Done)
+    }
+
+    /// Returns the RMA unlock token hash received from the host.
+    pub fn rma_unlock_token_hash(&self) -> Option<LcTokenHash> {
+        self.state.borrow().rma_unlock_token_hash.clone()
+    }
+
+    /// Returns the names and contents of the certificates written to flash,
+    /// in the order in which they are written.
+    pub fn certificates(&self) -> Vec<NamedCert> {
+        self.state.borrow().certificates.clone()
+    }
+
+    fn has_fault(&self, fault: FtSimFault) -> bool {
+        self.config.fault == Some(fault)
+    }
+
+    /// Derives a P256 key from the device seed.
+    fn derive_key(&self, name: &str) -> Result<SecretKey<NistP256>> {
+        let digest = Sha256::new()
+            .chain_update(name.as_bytes())
+            .chain_update(self.config.device_seed.to_le_bytes())
+            .finalize();
+        Ok(SecretKey::from_slice(&digest)?)
+    }
+
+    fn dev_seeds(&self) -> Vec<u8> {
+        (0u32..)
+            .flat_map(|i| {
+                Sha256::new()
+                    .chain_update(b"DEV_SEED")
+                    .chain_update(self.config.device_seed.to_le_bytes())
+                    .chain_update(i.to_le_bytes())
+                    .finalize()
+            })
+            .take(DEV_SEEDS_SIZE)
+            .collect()
+    }
+
+    /// Generates the objects exported to the host: the device seeds, the UDS
+    /// TBS certificate and the CDI_0 certificate endorsed by the UDS key.
+    fn export_objects(&self, inputs: &ManufCertgenInputs) -> Result<Vec<PersoObject>> {
+        let uds_key = self.derive_key("UDS")?;
+        let cdi_0_key = self.derive_key("CDI_0")?;
+        let (uds_x, uds_y, uds_id) = public_key_values(&uds_key);
+        let (cdi_0_x, cdi_0_y, cdi_0_id) = public_key_values(&cdi_0_key);
+
+        let uds_tbs = tbs_from_template(
+            UDS_TEMPLATE,
+            vec![
+                ("creator_pub_key_ec_x", SubstValue::ByteArray(uds_x)),
+                ("creator_pub_key_ec_y", SubstValue::ByteArray(uds_y)),
+                ("creator_pub_key_id", SubstValue::ByteArray(uds_id.clone())),
+                (
+                    "auth_key_key_id",
+                    SubstValue::ByteArray(inputs.auth_key_key_id.to_vec()),
+                ),
+            ],
+        )
+        .context("cannot generate the UDS TBS certificate")?;
+        let cdi_0_tbs = tbs_from_template(
+            CDI_0_TEMPLATE,
+            vec![
+                (
+                    "owner_intermediate_pub_key_ec_x",
+                    SubstValue::ByteArray(cdi_0_x),
+                ),
+                (
+                    "owner_intermediate_pub_key_ec_y",
+                    SubstValue::ByteArray(cdi_0_y),
+                ),
+                (
+                    "owner_intermediate_pub_key_id",
+                    SubstValue::ByteArray(cdi_0_id),
+                ),
+                ("creator_pub_key_id", SubstValue::ByteArray(uds_id)),
+                (
+                    "rom_ext_hash",
+                    SubstValue::ByteArray(inputs.rom_ext_measurement.as_bytes().to_vec()),
+                ),
+                (
+                    "rom_ext_security_version",
+                    SubstValue::Int32(inputs.rom_ext_security_version as i32),
+                ),
+            ],
+        )
+        .context("cannot generate the CDI_0 TBS certificate")?;
+        let cdi_0_cert = sign_tbs(&uds_key, cdi_0_tbs)?;
+
+        let mut uds_tbs = uds_tbs;
+        if self.has_fault(FtSimFault::TruncatedTbs) {
+            uds_tbs.truncate(uds_tbs.len() / 2);
+        }
+        Ok(vec![
+            PersoObject::DevSeed(self.dev_seeds()),
+            PersoObject::UnendorsedX509Cert {
+                name: "UDS".into(),
+                tbs: uds_tbs,
+            },
+            PersoObject::EndorsedX509Cert {
+                name: "CDI_0".into(),
+                cert: cdi_0_cert,
+            },
+        ])
+    }
+
+    /// Checks that `cert`, received from the host, endorses `tbs`.
+    fn check_endorsed_cert(&self, name: &str, tbs: &[u8], cert: &[u8]) -> Result<()> {
+        let cert_tbs =
+            tbs_certificate(cert).map_err(|e| anyhow!("malformed {name} certificate: {e}"))?;
+        ensure!(
+            cert_tbs == tbs,
+            "the endorsed {name} certificate does not match its TBS"
+        );
+        x509::parse_certificate(cert)
+            .with_context(|| format!("cannot parse {name} certificate"))?;
+        if let Some(ca) = &self.config.ca_certificate {
+            validate_chain(ca, &[cert])
+                .with_context(|| format!("the endorsed {name} certificate is not valid"))?;
+        }
+        Ok(())
+    }
+
+    /// Matches the certificates endorsed by the host with the exported TBS
+    /// certificates.  Returns the certificates to write to flash and the
+    /// SHA256 digest of the perso objects, computed in the same order as the
+    /// host does.
+    fn import_certs(
+        &self,
+        exported: &[PersoObject],
+        blob: &PersoBlob,
+    ) -> Result<(Vec<NamedCert>, Vec<u8>)> {
+        let mut endorsed = Vec::new();
+        for object in perso_tlv_lib::objects(&blob.body).take(blob.num_objs) {
+            match object? {
+                PersoObject::EndorsedX509Cert { name, cert } => endorsed.push((name, cert)),
+                object => bail!("unexpected {:?} object from the host", object.obj_type()),
+            }
+        }
+        ensure!(
+            endorsed.len() == blob.num_objs,
+            "the host sent {} objects instead of {}",
+            endorsed.len(),
+            blob.num_objs
+        );
+
+        let mut hasher = Sha256::new();
+        let mut certs = Vec::new();
+        for object in exported {
+            match object {
+                PersoObject::DevSeed(seeds) => hasher.update(seeds),
+                PersoObject::EndorsedX509Cert { name, cert } => {
+                    hasher.update(cert);
+                    certs.push((name.clone(), cert.clone()));
+                }
+                PersoObject::UnendorsedX509Cert { name, tbs } => {
+                    let index = endorsed
+                        .iter()
+                        .position(|(endorsed_name, _)| endorsed_name == name)
+                        .with_context(|| {
+                            format!("the host did not endorse the {name} certificate")
+                        })?;
+                    let (name, cert) = endorsed.remove(index);
+                    self.check_endorsed_cert(&name, tbs, &cert)?;
+                    hasher.update(&cert);
+                    certs.push((name, cert));
+                }
+            }
+        }
+        // Certificates added by host extensions are written after the DICE
+        // certificates.
+        certs.extend(endorsed);
+        Ok((certs, hasher.finalize().to_vec()))
+    }
+
+    /// Handles a ujson value received from the host.
+    fn receive(&self, state: &mut SimState, value: serde_json::Value, json: &str) -> Result<()> {
+        match std::mem::replace(&mut state.stage, Stage::Failed) {
+            Stage::RmaUnlockTokenHash => {
+                let hash: LcTokenHash = serde_json::from_value(value)?;
+                state.rma_unlock_token_hash = Some(hash);
+                state.stage = Stage::RmaUnlockTokenCrc { json: json.into() };
+            }
+            Stage::RmaUnlockTokenCrc { json } => {
+                let crc: OttfCrc = serde_json::from_value(value)?;
+                let expected = Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(json.as_bytes());
+                ensure!(
+                    crc.crc == expected,
+                    "RMA unlock token hash CRC {} does not match {}",
+                    crc.crc,
+                    expected
+                );
+                state.print("Waiting for certificate inputs ...");
+                state.stage = Stage::CertgenInputs;
+            }
+            Stage::CertgenInputs => {
+                let inputs: ManufCertgenInputs = serde_json::from_value(value)?;
+                let objects = self.export_objects(&inputs)?;
+                let body = perso_tlv_lib::encode_objects(&objects)?;
+                let blob = PersoBlob {
+                    num_objs: objects.len() + self.has_fault(FtSimFault::MissingObject) as usize,
+                    next_free: body.len(),
+                    body: ArrayVec::try_from(body.as_slice())?,
+                };
+                state.print("Exporting TBS certificates ...");
+                state.respond_ok(&blob, self.has_fault(FtSimFault::BadBlobCrc))?;
+                state.print("Importing endorsed certificates ...");
+                state.stage = Stage::EndorsedCerts { objects };
+            }
+            Stage::EndorsedCerts { objects } => {
+                let blob: PersoBlob = serde_json::from_value(value)?;
+                let (certs, mut digest) = self.import_certs(&objects, &blob)?;
+                for (name, _) in &certs {
+                    state.print(&format!("Imported {name} certificate."));
+                }
+                state.print("Finished importing certificates.");
+
+                if self.has_fault(FtSimFault::BadCertsHash) {
+                    digest[0] ^= 1;
+                }
+                // The device sends the digest as little-endian words, least
+                // significant word first.
+                digest.reverse();
+                let hash = SerdesSha256Hash {
+                    data: digest
+                        .chunks(4)
+                        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+                        .collect(),
+                };
+                state.respond_ok(&hash, false)?;
+                state.print("Personalization done.");
+                state.certificates = certs;
+                state.stage = Stage::Done;
+            }
+            Stage::Done | Stage::Failed => bail!("unexpected input {json}"),
+        }
+        Ok(())
+    }
+}
+
+impl ConsoleDevice for FtSimulator {
+    fn console_read(&self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        let mut state = self.state.borrow_mut();
+        if state.output.is_empty() {
+            drop(state);
+            std::thread::sleep(timeout);
+            return Ok(0);
+        }
+        let len = buf.len().min(state.output.len());
+        for (dst, src) in buf.iter_mut().zip(state.output.drain(..len)) {
+            *dst = src;
+        }
+        Ok(len)
+    }
+
+    fn console_write(&self, buf: &[u8]) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.input.extend_from_slice(buf);
+        while !matches!(state.stage, Stage::Done | Stage::Failed) {
+            let (value, end) = {
+                let mut values = serde_json::Deserializer::from_slice(&state.input)
+                    .into_iter::<serde_json::Value>();
+                match values.next() {
+                    Some(Ok(value)) => (Ok(value), values.byte_offset()),
+                    Some(Err(e)) if e.is_eof() => break,
+                    Some(Err(e)) => (Err(e.into()), state.input.len()),
+                    None => break,
+                }
+            };
+            let json = String::from_utf8_lossy(&state.input[..end])
+                .trim()
+                .to_string();
+            state.input.drain(..end);
+            if let Err(e) = value.and_then(|value| self.receive(&mut state, value, &json)) {
+                log::error!("FT simulator: {e:#}");
+                state.print(&format!("ERROR: {e:#}"));
+                state.error = Some(format!("{e:#}"));
+                state.stage = Stage::Failed;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the affine coordinates and the key identifier of the public key
+/// of `key`.
+fn public_key_values(key: &SecretKey<NistP256>) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let point = key.public_key().to_encoded_point(false);
+    let x = point.x().expect("uncompressed point").to_vec();
+    let y = point.y().expect("uncompressed point").to_vec();
+    let mut id = Sha256::new()
+        .chain_update(&x)
+        .chain_update(&y)
+        .finalize()
+        .to_vec();
+    id.truncate(20);
+    (x, y, id)
+}
+
+/// Generates a TBS certificate from a template, using `values` for the given
+/// variables and random values for the others.
+fn tbs_from_template(template: &str, values: Vec<(&str, SubstValue)>) -> Result<Vec<u8>> {
+    let template = Template::from_hjson_str(template)?;
+    let mut data = template.random_test()?;
+    for (name, value) in values {
+        data.values.insert(name.to_string(), value);
+    }
+    x509::generate_tbs_certificate(&template.subst(&data)?)
+}
+
+/// Signs `tbs` with `key` and returns the certificate.
+fn sign_tbs(key: &SecretKey<NistP256>, tbs: Vec<u8>) -> Result<Vec<u8>> {
+    let signature: P256Signature = SigningKey::from(key).sign(&tbs);
+    let (r, s) = signature.split_bytes();
+    let signature = Signature::EcdsaWithSha256 {
+        value: Some(EcdsaSignature {
+            r: Value::Literal(BigUint::from_bytes_be(&r)),
+            s: Value::Literal(BigUint::from_bytes_be(&s)),
+        }),
+    };
+    x509::generate_certificate_from_tbs(tbs, &signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use opentitanlib::test_utils::rpc::{ConsoleRecv, ConsoleSend};
+    use util_lib::hash_lc_token;
+
+    use crate::{run_ft_personalize_protocol, KeyWrapper};
+    use ft_ext_api::{FtExtContext, FtExtRegistry};
+
+    const CA_KEY: &str = "./sw/device/silicon_creator/manuf/keys/fake/cert_endorsement_key.sk.der";
+    const CA_PEM: &str = "./sw/device/silicon_creator/manuf/keys/fake/fake_ca.pem";
+    // Subject key identifier of the fake CA.
+    const CA_KEY_ID: [u8; 20] = [
+        0xfe, 0x58, 0x4a, 0xe7, 0x53, 0x79, 0x0c, 0xfd, 0x86, 0x01, 0xa3, 0x12, 0xfb, 0x32, 0xd3,
+        0xc1, 0xb8, 0x22, 0xd1, 0x12,
+    ];
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn ca_der() -> Vec<u8> {
+        let pem = fs::read(CA_PEM).unwrap();
+        pem_rfc7468::decode_vec(&pem).unwrap().1
+    }
+
+    fn certgen_inputs() -> ManufCertgenInputs {
+        ManufCertgenInputs {
+            rom_ext_measurement: ArrayVec::from([0x11; 8]),
+            rom_ext_security_version: 1,
+            owner_manifest_measurement: ArrayVec::from([0x22; 8]),
+            owner_measurement: ArrayVec::from([0x33; 8]),
+            owner_security_version: 2,
+            auth_key_key_id: ArrayVec::from(CA_KEY_ID),
+        }
+    }
+
+    fn simulator(fault: Option<FtSimFault>) -> FtSimulator {
+        FtSimulator::new(FtSimConfig {
+            device_seed: 1,
+            ca_certificate: Some(ca_der()),
+            fault,
+        })
+    }
+
+    fn personalize(sim: &FtSimulator) -> Result<Vec<NamedCert>> {
+        let ctx = FtExtContext {
+            transport: None,
+            console: sim,
+            timeout: TIMEOUT,
+        };
+        run_ft_personalize_protocol(
+            &ctx,
+            &FtExtRegistry::new(),
+            KeyWrapper::LocalKey(CA_KEY.into()),
+            &certgen_inputs(),
+            PathBuf::from(CA_PEM),
+            &ArrayVec::from([1, 2, 3, 4]),
+        )
+    }
+
+    #[test]
+    fn personalize_ok() -> Result<()> {
+        let sim = simulator(None);
+        let certs = personalize(&sim)?;
+        assert_eq!(sim.error(), None);
+        assert!(sim.is_done());
+        let token: ArrayVec<u32, 4> = ArrayVec::from([1, 2, 3, 4]);
+        assert_eq!(
+            sim.rma_unlock_token_hash().unwrap().hash,
+            hash_lc_token(token.as_bytes())?
+        );
+        let names = sim
+            .certificates()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["UDS", "CDI_0"]);
+        assert_eq!(certs, sim.certificates());
+        Ok(())
+    }
+
+    #[test]
+    fn host_errors() {
+        for fault in [
+            FtSimFault::BadBlobCrc,
+            FtSimFault::TruncatedTbs,
+            FtSimFault::MissingObject,
+            FtSimFault::BadCertsHash,
+        ] {
+            let sim = simulator(Some(fault));
+            assert!(personalize(&sim).is_err(), "{fault:?} not detected");
+        }
+    }
+
+    #[test]
+    fn device_rejects_bad_endorsement() -> Result<()> {
+        let sim = simulator(None);
+        let token = LcTokenHash {
+            hash: ArrayVec::from([1, 2]),
+        };
+        token.send_with_crc(&sim)?;
+        certgen_inputs().send(&sim)?;
+        let blob = PersoBlob::recv(&sim, TIMEOUT, true)?;
+
+        // Return the UDS TBS with a bogus signature.
+        let mut body = Vec::new();
+        for object in perso_tlv_lib::objects(&blob.body).take(blob.num_objs) {
+            if let PersoObject::UnendorsedX509Cert { name, tbs } = object? {
+                let signature = Signature::EcdsaWithSha256 {
+                    value: Some(EcdsaSignature {
+                        r: Value::Literal(BigUint::from(1u32)),
+                        s: Value::Literal(BigUint::from(1u32)),
+                    }),
+                };
+                let cert = x509::generate_certificate_from_tbs(tbs, &signature)?;
+                body.extend(PersoObject::EndorsedX509Cert { name, cert }.encode()?);
+            }
+        }
+        PersoBlob {
+            num_objs: 1,
+            next_free: body.len(),
+            body: ArrayVec::try_from(body.as_slice())?,
+        }
+        .send(&sim)?;
+        assert!(!sim.is_done());
+        assert!(sim
+            .error()
+            .unwrap()
+            .contains("UDS certificate is not valid"));
+        Ok(())
+    }
+}
@@ -4,6 +4,11 @@
 
 use anyhow::{bail, Result};
 
+mod object;
+pub use object::{
+    decode_objects, encode_objects, get_cert, get_obj_header, objects, PersoObject, PersoObjects,
+};
+
 // Types of objects which can come from the device in the perso blob.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -0,0 +1,293 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    make_cert_wrapper_header, make_obj_header, perso_tlv_get_field, CertHeader, CertHeaderType,
+    ObjHeader, ObjHeaderType, ObjType,
+};
+
+// Extract LTV object header from the input buffer.
+pub fn get_obj_header(data: &[u8]) -> Result<ObjHeader> {
+    let header_len = std::mem::size_of::<ObjHeaderType>();
+    // The header is 2 bytes in size.
+    if data.len() < header_len {
+        bail!(
+            "Insufficient amount of data ({} bytes) for object header",
+            data.len()
+        );
+    }
+
+    let typesize = u16::from_be_bytes([data[0], data[1]]);
+    let obj_size = perso_tlv_get_field!("obj", "size", typesize);
+    let obj_type = ObjType::from_usize(perso_tlv_get_field!("obj", "type", typesize))?;
+
+    if obj_size > data.len() {
+        bail!(
+            "Object {} length {} exceeds buffer size {}",
+            obj_type as u8,
+            obj_size,
+            data.len()
+        );
+    }
+    Ok(ObjHeader { obj_type, obj_size })
+}
+
+// Extract certificate payload header from the input buffer.
+pub fn get_cert(data: &[u8]) -> Result<CertHeader<'_>> {
+    let header_len = std::mem::size_of::<CertHeaderType>();
+
+    if data.len() < header_len {
+        bail!(
+            "Insufficient amount of data ({} bytes) for cert header",
+            data.len()
+        );
+    }
+
+    let header = u16::from_be_bytes([data[0], data[1]]);
+    let wrapped_size = perso_tlv_get_field!("crth", "size", header);
+    if wrapped_size > data.len() {
+        bail!(
+            "Cert object size {} exceeds buffer size {}",
+            wrapped_size,
+            data.len()
+        );
+    }
+
+    let name_len = perso_tlv_get_field!("crth", "name", header);
+    let header_size = header_len + name_len;
+    if header_size > wrapped_size {
+        bail!(
+            "Cert name length {} exceeds cert object size {}",
+            name_len,
+            wrapped_size
+        );
+    }
+    let cert_name = std::str::from_utf8(&data[header_len..header_size])?;
+    let cert_body: Vec<u8> = data[header_size..wrapped_size].to_vec();
+    Ok(CertHeader {
+        wrapped_size,
+        cert_name,
+        cert_body,
+    })
+}
+
+/// A decoded object of a perso blob.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PersoObject {
+    /// A TBS certificate to be endorsed by the host.
+    UnendorsedX509Cert { name: String, tbs: Vec<u8> },
+    /// A certificate endorsed either by the device or by the host.
+    EndorsedX509Cert { name: String, cert: Vec<u8> },
+    /// The device seeds.
+    DevSeed(Vec<u8>),
+}
+
+impl PersoObject {
+    pub fn obj_type(&self) -> ObjType {
+        match self {
+            PersoObject::UnendorsedX509Cert { .. } => ObjType::UnendorsedX509Cert,
+            PersoObject::EndorsedX509Cert { .. } => ObjType::EndorsedX509Cert,
+            PersoObject::DevSeed(_) => ObjType::DevSeed,
+        }
+    }
+
+    /// Returns the name of a certificate object.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            PersoObject::UnendorsedX509Cert { name, .. }
+            | PersoObject::EndorsedX509Cert { name, .. } => Some(name),
+            PersoObject::DevSeed(_) => None,
+        }
+    }
+
+    /// Returns the payload of the object: the TBS, the certificate or the
+    /// seeds.
+    pub fn payload(&self) -> &[u8] {
+        match self {
+            PersoObject::UnendorsedX509Cert { tbs, .. } => tbs,
+            PersoObject::EndorsedX509Cert { cert, .. } => cert,
+            PersoObject::DevSeed(seeds) => seeds,
+        }
+    }
+
+    /// Decodes the object at the start of `data`, returning it along with the
+    /// number of bytes it occupies.
+    pub fn decode(data: &[u8]) -> Result<(PersoObject, usize)> {
+        let header = get_obj_header(data)?;
+        let obj_header_size = std::mem::size_of::<ObjHeaderType>();
+        if header.obj_size < obj_header_size {
+            bail!("Object size {} is smaller than its header", header.obj_size);
+        }
+        let body = &data[obj_header_size..header.obj_size];
+        let object = match header.obj_type {
+            ObjType::DevSeed => PersoObject::DevSeed(body.to_vec()),
+            ObjType::UnendorsedX509Cert | ObjType::EndorsedX509Cert => {
+                let cert = get_cert(body)?;
+                if cert.wrapped_size != body.len() {
+                    bail!(
+                        "Cert {} size {} does not match object size {}",
+                        cert.cert_name,
+                        cert.wrapped_size,
+                        header.obj_size
+                    );
+                }
+                let name = cert.cert_name.to_string();
+                if header.obj_type == ObjType::UnendorsedX509Cert {
+                    PersoObject::UnendorsedX509Cert {
+                        name,
+                        tbs: cert.cert_body,
+                    }
+                } else {
+                    PersoObject::EndorsedX509Cert {
+                        name,
+                        cert: cert.cert_body,
+                    }
+                }
+            }
+        };
+        Ok((object, header.obj_size))
+    }
+
+    /// Encodes the object, headers included.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let obj_header_size = std::mem::size_of::<ObjHeaderType>();
+        let mut body = Vec::new();
+        if let Some(name) = self.name() {
+            let cert = self.payload();
+            let cert_header = make_cert_wrapper_header(cert.len(), name)?;
+            body.extend_from_slice(&cert_header.to_be_bytes());
+            body.extend_from_slice(name.as_bytes());
+            body.extend_from_slice(cert);
+        } else {
+            body.extend_from_slice(self.payload());
+        }
+        let obj_header = make_obj_header(obj_header_size + body.len(), self.obj_type())?;
+        let mut res = obj_header.to_be_bytes().to_vec();
+        res.extend(body);
+        Ok(res)
+    }
+}
+
+/// An iterator over the objects of a perso blob body.
+///
+/// Iteration stops at the end of the data or after the first error.
+pub struct PersoObjects<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> PersoObjects<'a> {
+    /// Returns the offset of the next object in the data.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the data which has not been decoded yet.
+    pub fn remainder(&self) -> &'a [u8] {
+        &self.data[self.offset..]
+    }
+}
+
+impl<'a> Iterator for PersoObjects<'a> {
+    type Item = Result<PersoObject>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset == self.data.len() {
+            return None;
+        }
+        match PersoObject::decode(self.remainder()) {
+            Ok((object, size)) => {
+                self.offset += size;
+                Some(Ok(object))
+            }
+            Err(e) => {
+                let offset = self.offset;
+                self.offset = self.data.len();
+                Some(Err(
+                    e.context(format!("bad perso object at offset {offset}"))
+                ))
+            }
+        }
+    }
+}
+
+/// Returns an iterator over the objects in `data`, the body of a perso blob.
+pub fn objects(data: &[u8]) -> PersoObjects<'_> {
+    PersoObjects { data, offset: 0 }
+}
+
+/// Decodes all objects in `data`, the body of a perso blob.
+pub fn decode_objects(data: &[u8]) -> Result<Vec<PersoObject>> {
+    objects(data).collect()
+}
+
+/// Encodes `objects` as the body of a perso blob.
+pub fn encode_objects(objects: &[PersoObject]) -> Result<Vec<u8>> {
+    let mut res = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        res.extend(
+            object
+                .encode()
+                .with_context(|| format!("cannot encode perso object {i}"))?,
+        );
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Vec<PersoObject> {
+        vec![
+            PersoObject::DevSeed(vec![0x5a; 128]),
+            PersoObject::UnendorsedX509Cert {
+                name: "UDS".into(),
+                tbs: vec![0x30, 0x03, 0x02, 0x01, 0x00],
+            },
+            PersoObject::EndorsedX509Cert {
+                name: "CDI_0".into(),
+                cert: vec![0x30, 0x00],
+            },
+        ]
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let objects = example();
+        let data = encode_objects(&objects)?;
+        assert_eq!(decode_objects(&data)?, objects);
+        Ok(())
+    }
+
+    #[test]
+    fn encoding() -> Result<()> {
+        let object = PersoObject::EndorsedX509Cert {
+            name: "UDS".into(),
+            cert: vec![0xaa, 0xbb],
+        };
+        // Object header: type 1, size 9; cert header: name length 3, size 7.
+        assert_eq!(
+            object.encode()?,
+            [0x10, 0x09, 0x30, 0x07, b'U', b'D', b'S', 0xaa, 0xbb]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn truncated() -> Result<()> {
+        let data = encode_objects(&example())?;
+        let mut iter = objects(&data[..data.len() - 1]);
+        assert!(iter.next().unwrap().is_ok());
+        assert!(iter.next().unwrap().is_ok());
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+
+        // A zero header (as found in the free space of a blob) is an error.
+        assert!(PersoObject::decode(&[0, 0]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! A minimal CBOR (RFC 8949) encoder and decoder.
+//!
+//! Only the subset needed for provisioning records is supported: integers,
+//! byte and text strings, arrays, maps, tags, booleans and null.  Encoding is
+//! deterministic (RFC 8949 section 4.2.1): arguments use their shortest form
+//! and map keys are sorted by their encoding.
+
+use anyhow::{bail, ensure, Context, Result};
+use serde_json::{Map, Number, Value};
+
+const MAJOR_UINT: u8 = 0;
+const MAJOR_NINT: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+const MAJOR_SIMPLE: u8 = 7;
+
+const SIMPLE_FALSE: u64 = 20;
+const SIMPLE_TRUE: u64 = 21;
+const SIMPLE_NULL: u64 = 22;
+
+// Nesting limit when decoding, records are much shallower than this.
+const MAX_DEPTH: usize = 16;
+
+/// A CBOR data item.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cbor {
+    /// An unsigned integer.
+    Uint(u64),
+    /// A negative integer with the value `-1 - n`.
+    Nint(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Cbor>),
+    Map(Vec<(Cbor, Cbor)>),
+    Tag(u64, Box<Cbor>),
+    Bool(bool),
+    Null,
+}
+
+impl Cbor {
+    /// Returns the integer `value` as a CBOR item.
+    pub fn int(value: i64) -> Self {
+        if value < 0 {
+            Cbor::Nint(!value as u64)
+        } else {
+            Cbor::Uint(value as u64)
+        }
+    }
+
+    /// Returns the deterministic encoding of the item.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Cbor::Uint(n) => encode_head(out, MAJOR_UINT, *n),
+            Cbor::Nint(n) => encode_head(out, MAJOR_NINT, *n),
+            Cbor::Bytes(b) => {
+                encode_head(out, MAJOR_BYTES, b.len() as u64);
+                out.extend_from_slice(b);
+            }
+            Cbor::Text(s) => {
+                encode_head(out, MAJOR_TEXT, s.len() as u64);
+                out.extend_from_slice(s.as_bytes());
+            }
+            Cbor::Array(items) => {
+                encode_head(out, MAJOR_ARRAY, items.len() as u64);
+                for item in items {
+                    item.encode_into(out);
+                }
+            }
+            Cbor::Map(entries) => {
+                let mut encoded = entries
+                    .iter()
+                    .map(|(k, v)| (k.encode(), v.encode()))
+                    .collect::<Vec<_>>();
+                encoded.sort();
+                encode_head(out, MAJOR_MAP, encoded.len() as u64);
+                for (k, v) in encoded {
+                    out.extend(k);
+                    out.extend(v);
+                }
+            }
+            Cbor::Tag(tag, item) => {
+                encode_head(out, MAJOR_TAG, *tag);
+                item.encode_into(out);
+            }
+            Cbor::Bool(false) => encode_head(out, MAJOR_SIMPLE, SIMPLE_FALSE),
+            Cbor::Bool(true) => encode_head(out, MAJOR_SIMPLE, SIMPLE_TRUE),
+            Cbor::Null => encode_head(out, MAJOR_SIMPLE, SIMPLE_NULL),
+        }
+    }
+
+    /// Decodes a single item that spans all of `data`.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut input = data;
+        let item = decode_item(&mut input, 0)?;
+        ensure!(
+            input.is_empty(),
+            "{} trailing bytes after CBOR item",
+            input.len()
+        );
+        Ok(item)
+    }
+
+    /// Converts a JSON value to CBOR.  Floating point numbers are not
+    /// supported.
+    pub fn from_json(value: &Value) -> Result<Self> {
+        Ok(match value {
+            Value::Null => Cbor::Null,
+            Value::Bool(b) => Cbor::Bool(*b),
+            Value::Number(n) => {
+                if let Some(n) = n.as_u64() {
+                    Cbor::Uint(n)
+                } else if let Some(n) = n.as_i64() {
+                    Cbor::int(n)
+                } else {
+                    bail!("unsupported non-integer number {n}");
+                }
+            }
+            Value::String(s) => Cbor::Text(s.clone()),
+            Value::Array(items) => {
+                Cbor::Array(items.iter().map(Cbor::from_json).collect::<Result<_>>()?)
+            }
+            Value::Object(map) => Cbor::Map(
+                map.iter()
+                    .map(|(k, v)| Ok((Cbor::Text(k.clone()), Cbor::from_json(v)?)))
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+
+    /// Converts the item to JSON.  Map keys must be text strings, and byte
+    /// strings and tags have no JSON equivalent.
+    pub fn to_json(&self) -> Result<Value> {
+        Ok(match self {
+            Cbor::Uint(n) => Value::Number(Number::from(*n)),
+            Cbor::Nint(n) => {
+                let n = i64::try_from(*n).context("negative integer out of range")?;
+                Value::Number(Number::from(!n))
+            }
+            Cbor::Text(s) => Value::String(s.clone()),
+            Cbor::Array(items) => {
+                Value::Array(items.iter().map(Cbor::to_json).collect::<Result<_>>()?)
+            }
+            Cbor::Map(entries) => {
+                let mut map = Map::new();
+                for (k, v) in entries {
+                    let Cbor::Text(k) = k else {
+                        bail!("unsupported non-text map key {k:?}");
+                    };
+                    map.insert(k.clone(), v.to_json()?);
+                }
+                Value::Object(map)
+            }
+            Cbor::Bool(b) => Value::Bool(*b),
+            Cbor::Null => Value::Null,
+            Cbor::Bytes(_) | Cbor::Tag(..) => bail!("CBOR item has no JSON equivalent"),
+        })
+    }
+}
+
+fn encode_head(out: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    if arg < 24 {
+        out.push(major | arg as u8);
+    } else if arg <= u8::MAX as u64 {
+        out.push(major | 24);
+        out.push(arg as u8);
+    } else if arg <= u16::MAX as u64 {
+        out.push(major | 25);
+        out.extend((arg as u16).to_be_bytes());
+    } else if arg <= u32::MAX as u64 {
+        out.push(major | 26);
+        out.extend((arg as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend(arg.to_be_bytes());
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    ensure!(input.len() >= len, "truncated CBOR item");
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Ok(head)
+}
+
+fn decode_head(input: &mut &[u8]) -> Result<(u8, u64)> {
+    let initial = take(input, 1)?[0];
+    let major = initial >> 5;
+    let arg = match initial & 0x1f {
+        n @ 0..=23 => n as u64,
+        24 => take(input, 1)?[0] as u64,
+        25 => u16::from_be_bytes(take(input, 2)?.try_into().unwrap()) as u64,
+        26 => u32::from_be_bytes(take(input, 4)?.try_into().unwrap()) as u64,
+        27 => u64::from_be_bytes(take(input, 8)?.try_into().unwrap()),
+        n => bail!("unsupported CBOR additional information {n}"),
+    };
+    Ok((major, arg))
+}
+
+fn decode_len(input: &[u8], arg: u64) -> Result<usize> {
+    // Every item takes at least one byte, which bounds lengths and counts.
+    match usize::try_from(arg) {
+        Ok(len) if len <= input.len() => Ok(len),
+        _ => bail!("truncated CBOR item"),
+    }
+}
+
+fn decode_item(input: &mut &[u8], depth: usize) -> Result<Cbor> {
+    ensure!(depth < MAX_DEPTH, "CBOR item nested too deeply");
+    let (major, arg) = decode_head(input)?;
+    Ok(match major {
+        MAJOR_UINT => Cbor::Uint(arg),
+        MAJOR_NINT => Cbor::Nint(arg),
+        MAJOR_BYTES => {
+            let len = decode_len(input, arg)?;
+            Cbor::Bytes(take(input, len)?.to_vec())
+        }
+        MAJOR_TEXT => {
+            let len = decode_len(input, arg)?;
+            Cbor::Text(String::from_utf8(take(input, len)?.to_vec()).context("invalid UTF-8")?)
+        }
+        MAJOR_ARRAY => {
+            let len = decode_len(input, arg)?;
+            let mut items = Vec::with_capacity(len);
+            for _ in 0..len {
+                items.push(decode_item(input, depth + 1)?);
+            }
+            Cbor::Array(items)
+        }
+        MAJOR_MAP => {
+            let len = decode_len(input, arg)?;
+            let mut entries = Vec::with_capacity(len);
+            for _ in 0..len {
+                let key = decode_item(input, depth + 1)?;
+                let value = decode_item(input, depth + 1)?;
+                entries.push((key, value));
+            }
+            Cbor::Map(entries)
+        }
+        MAJOR_TAG => Cbor::Tag(arg, Box::new(decode_item(input, depth + 1)?)),
+        _ => match arg {
+            SIMPLE_FALSE => Cbor::Bool(false),
+            SIMPLE_TRUE => Cbor::Bool(true),
+            SIMPLE_NULL => Cbor::Null,
+            _ => bail!("unsupported CBOR simple value {arg}"),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn encode_examples() {
+        // Examples from RFC 8949 appendix A.
+        assert_eq!(Cbor::Uint(10).encode(), [0x0a]);
+        assert_eq!(Cbor::Uint(500).encode(), [0x19, 0x01, 0xf4]);
+        assert_eq!(Cbor::int(-1000).encode(), [0x39, 0x03, 0xe7]);
+        assert_eq!(Cbor::Text("IETF".into()).encode(), b"\x64IETF");
+        assert_eq!(Cbor::Bytes(vec![1, 2, 3, 4]).encode(), [0x44, 1, 2, 3, 4]);
+        assert_eq!(Cbor::Null.encode(), [0xf6]);
+        assert_eq!(
+            Cbor::Tag(1, Box::new(Cbor::Uint(1363896240))).encode(),
+            [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]
+        );
+    }
+
+    #[test]
+    fn map_keys_are_sorted() {
+        let map = Cbor::Map(vec![
+            (Cbor::Text("aa".into()), Cbor::Uint(1)),
+            (Cbor::Text("b".into()), Cbor::Uint(2)),
+        ]);
+        // The shorter key sorts first.
+        assert_eq!(
+            map.encode(),
+            [0xa2, 0x61, b'b', 0x02, 0x62, b'a', b'a', 0x01]
+        );
+    }
+
+    #[test]
+    fn json_round_trip() -> Result<()> {
+        let value = json!({
+            "name": "UDS",
+            "version": 1,
+            "offset": -3,
+            "list": [true, false, null, "x"],
+            "nested": {"a": {"b": 18446744073709551615u64}},
+        });
+        let cbor = Cbor::from_json(&value)?;
+        let decoded = Cbor::decode(&cbor.encode())?;
+        assert_eq!(decoded.to_json()?, value);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!(Cbor::decode(&[]).is_err());
+        assert!(Cbor::decode(&[0x44, 1, 2]).is_err());
+        assert!(Cbor::decode(&[0x01, 0x02]).is_err());
+        assert!(Cbor::decode(&[0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(Cbor::decode(&[0x81; 64]).is_err());
+        assert!(Cbor::from_json(&json!(1.5)).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Signed, machine-readable records of the provisioning of a device.
+//!
+//! The CP and FT binaries fill a [`ProvisioningRecord`] as they provision a
+//! device and write it, signed with the key of the provisioning station, so
+//! that the manufacturing database can ingest it and audit which station
+//! provisioned which device.  Records never hold secrets: tokens are recorded
+//! by their LC hash and certificates by their SHA256 fingerprint.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use chrono::{SecondsFormat, Utc};
+use clap::Args;
+use elliptic_curve::pkcs8::DecodePrivateKey;
+use elliptic_curve::SecretKey;
+use p256::NistP256;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use cert_lib::{Pkcs11Input, Pkcs11Key};
+
+pub mod cbor;
+mod signed;
+
+pub use signed::{RecordFormat, StationKey};
+
+/// Version of the record format.
+pub const RECORD_VERSION: u32 = 1;
+
+/// The provisioning stage that produced a record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Cp,
+    Ft,
+}
+
+/// A life cycle state transition of the device.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LcTransition {
+    pub from: String,
+    pub to: String,
+    /// Time of the transition (RFC 3339).
+    pub time: String,
+}
+
+/// The LC hash of a token used to provision the device.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenHash {
+    pub name: String,
+    /// Hex encoding of the hash, as stored in OTP.
+    pub hash: String,
+}
+
+/// The fingerprint of a certificate provisioned in the device.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertFingerprint {
+    pub name: String,
+    /// Hex encoding of the SHA256 digest of the DER certificate.
+    pub sha256: String,
+}
+
+/// Measurements of the firmware that the device certificates attest to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareMeasurements {
+    pub rom_ext_measurement: String,
+    pub rom_ext_security_version: u32,
+    pub owner_manifest_measurement: String,
+    pub owner_measurement: String,
+    pub owner_security_version: u32,
+}
+
+/// The record of a provisioning run of a single device.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvisioningRecord {
+    pub version: u32,
+    pub stage: Stage,
+    /// Identifier of the provisioning station.
+    pub station: String,
+    /// Device ID, as given to the provisioning binary.
+    pub device_id: String,
+    /// Start time of the run (RFC 3339).
+    pub start_time: String,
+    /// End time of the run (RFC 3339), set by [`ProvisioningRecord::finish`].
+    pub end_time: Option<String>,
+    pub lc_transitions: Vec<LcTransition>,
+    pub tokens: Vec<TokenHash>,
+    pub certificates: Vec<CertFingerprint>,
+    pub measurements: Option<FirmwareMeasurements>,
+    /// Whether the run succeeded.
+    pub success: bool,
+    /// The error that ended a failed run.
+    pub error: Option<String>,
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+impl ProvisioningRecord {
+    /// Starts the record of a run.
+    pub fn new(stage: Stage, station: &str, device_id: &str) -> Self {
+        ProvisioningRecord {
+            version: RECORD_VERSION,
+            stage,
+            station: station.into(),
+            device_id: device_id.into(),
+            start_time: now(),
+            end_time: None,
+            lc_transitions: Vec::new(),
+            tokens: Vec::new(),
+            certificates: Vec::new(),
+            measurements: None,
+            success: false,
+            error: None,
+        }
+    }
+
+    /// Records a life cycle state transition happening now.
+    pub fn add_lc_transition(&mut self, from: &str, to: &str) {
+        self.lc_transitions.push(LcTransition {
+            from: from.into(),
+            to: to.into(),
+            time: now(),
+        });
+    }
+
+    /// Records the LC hash of a token, as returned by
+    /// `util_lib::hash_lc_token`.
+    pub fn add_token_hash(&mut self, name: &str, hash: &[u64]) {
+        let bytes = hash
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect::<Vec<_>>();
+        self.tokens.push(TokenHash {
+            name: name.into(),
+            hash: hex::encode(bytes),
+        });
+    }
+
+    /// Records the fingerprint of a DER certificate.
+    pub fn add_certificate(&mut self, name: &str, der: &[u8]) {
+        self.certificates.push(CertFingerprint {
+            name: name.into(),
+            sha256: hex::encode(Sha256::digest(der)),
+        });
+    }
+
+    /// Completes the record with the `result` of the run.
+    pub fn finish<T>(&mut self, result: &Result<T>) {
+        self.end_time = Some(now());
+        self.success = result.is_ok();
+        self.error = result.as_ref().err().map(|e| format!("{e:#}"));
+    }
+}
+
+/// Provisioning record command-line parameters.
+#[derive(Debug, Args, Clone)]
+pub struct RecordInput {
+    /// File to write the signed provisioning record of the device to.
+    #[arg(long, requires = "station_id")]
+    pub record: Option<PathBuf>,
+
+    /// Encoding of the provisioning record.
+    #[arg(long, value_enum, default_value_t = RecordFormat::Json)]
+    pub record_format: RecordFormat,
+
+    /// Identifier of the provisioning station, included in the record.
+    #[arg(long)]
+    pub station_id: Option<String>,
+
+    /// Station (ECC P256) signing key as a DER file path.
+    #[arg(long, conflicts_with = "station_key_pkcs11_label")]
+    pub station_key_der_file: Option<PathBuf>,
+
+    /// Station signing key as the label of a private key in a PKCS#11 token.
+    #[arg(long)]
+    pub station_key_pkcs11_label: Option<String>,
+}
+
+impl RecordInput {
+    /// Loads the station key if a record was requested.  Call this before
+    /// provisioning the device so that key errors are reported early.
+    pub fn station_key(&self, pkcs11: &Pkcs11Input) -> Result<Option<StationKey>> {
+        if self.record.is_none() {
+            return Ok(None);
+        }
+        let key = match (&self.station_key_der_file, &self.station_key_pkcs11_label) {
+            (Some(path), None) => StationKey::LocalKey(
+                SecretKey::<NistP256>::read_pkcs8_der_file(path)
+                    .with_context(|| format!("failed to read station key {}", path.display()))?,
+            ),
+            (None, Some(label)) => {
+                StationKey::Pkcs11Key(Pkcs11Key::open(&pkcs11.config()?, label)?)
+            }
+            _ => bail!("--record requires one station key parameter"),
+        };
+        Ok(Some(key))
+    }
+
+    /// Starts the record of a run on the device `device_id`.
+    pub fn start(&self, stage: Stage, device_id: &str) -> ProvisioningRecord {
+        let station = self.station_id.as_deref().unwrap_or_default();
+        ProvisioningRecord::new(stage, station, device_id)
+    }
+
+    /// Signs `record` with the station `key` and writes it to the record file,
+    /// if a record was requested.
+    pub fn write(&self, record: &ProvisioningRecord, key: Option<&StationKey>) -> Result<()> {
+        let (Some(path), Some(key)) = (&self.record, key) else {
+            return Ok(());
+        };
+        let data = record.sign(key, self.record_format)?;
+        fs::write(path, data).with_context(|| format!("failed to write {}", path.display()))?;
+        log::info!("Provisioning record written to {}", path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{SigningKey, VerifyingKey};
+
+    fn station_key() -> (StationKey, VerifyingKey) {
+        let sk = SecretKey::<NistP256>::from_slice(&[0x11; 32]).unwrap();
+        let vk = *SigningKey::from(&sk).verifying_key();
+        (StationKey::LocalKey(sk), vk)
+    }
+
+    fn record() -> ProvisioningRecord {
+        let mut record = ProvisioningRecord::new(Stage::Ft, "station-1", "0x1234");
+        record.add_lc_transition("TestUnlocked1", "Prod");
+        record.add_token_hash("test_exit", &[0x0123456789abcdef, 1]);
+        record.add_certificate("UDS", &[0x30, 0x00]);
+        record.measurements = Some(FirmwareMeasurements {
+            rom_ext_measurement: "11".repeat(32),
+            rom_ext_security_version: 1,
+            owner_manifest_measurement: "22".repeat(32),
+            owner_measurement: "33".repeat(32),
+            owner_security_version: 2,
+        });
+        record.finish(&anyhow::Ok(()));
+        record
+    }
+
+    #[test]
+    fn token_and_cert_encoding() {
+        let record = record();
+        assert_eq!(record.tokens[0].hash, "efcdab89674523010100000000000000");
+        assert_eq!(
+            record.certificates[0].sha256,
+            "e4f60d0aa6d7f3d3b6a6494b1c861b99f649c6f9ec51abaf201b20f297327c95"
+        );
+        assert!(record.success);
+        assert_eq!(record.error, None);
+    }
+
+    #[test]
+    fn sign_and_verify() -> Result<()> {
+        let (key, vk) = station_key();
+        let record = record();
+        for format in [RecordFormat::Json, RecordFormat::Cbor] {
+            let data = record.sign(&key, format)?;
+            assert_eq!(ProvisioningRecord::verify(&data, format, &vk)?, record);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tampered_record_is_rejected() -> Result<()> {
+        let (key, vk) = station_key();
+        let record = record();
+
+        let data = record.sign(&key, RecordFormat::Json)?;
+        let tampered = String::from_utf8(data)?.replace("station-1", "station-2");
+        assert!(ProvisioningRecord::verify(tampered.as_bytes(), RecordFormat::Json, &vk).is_err());
+
+        let mut data = record.sign(&key, RecordFormat::Cbor)?;
+        let pos = data.windows(9).position(|w| w == b"station-1").unwrap();
+        data[pos + 8] = b'2';
+        assert!(ProvisioningRecord::verify(&data, RecordFormat::Cbor, &vk).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn failed_run() {
+        let mut record = ProvisioningRecord::new(Stage::Cp, "station-1", "0x1234");
+        record.finish::<()>(&Err(anyhow::anyhow!("inner").context("outer")));
+        assert!(!record.success);
+        assert_eq!(record.error.as_deref(), Some("outer: inner"));
+        assert!(record.end_time.is_some());
+    }
+}
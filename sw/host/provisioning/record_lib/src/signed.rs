@@ -0,0 +1,206 @@
+// Copyright lowRISC contributors (OpenTitan project).
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::ValueEnum;
+use elliptic_curve::SecretKey;
+use p256::ecdsa::signature::{Signer, Verifier};
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::NistP256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use cert_lib::Pkcs11Key;
+
+use crate::cbor::Cbor;
+use crate::ProvisioningRecord;
+
+/// Name of the signature algorithm, ECDSA P256 with SHA256.
+const ES256: &str = "ES256";
+/// COSE algorithm identifier of ES256.
+const COSE_ALG_ES256: i64 = -7;
+/// COSE header label of the algorithm.
+const COSE_HEADER_ALG: u64 = 1;
+/// CBOR tag of a COSE_Sign1 structure.
+const COSE_SIGN1_TAG: u64 = 18;
+
+/// Encodings of a signed provisioning record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RecordFormat {
+    /// A JSON object holding the record and a signature over its canonical
+    /// JSON encoding (sorted keys, no whitespace).
+    Json,
+    /// A COSE_Sign1 structure (RFC 9052) whose payload is the deterministic
+    /// CBOR encoding of the record.
+    Cbor,
+}
+
+/// The key of a provisioning station, used to sign provisioning records.
+pub enum StationKey {
+    LocalKey(SecretKey<NistP256>),
+    Pkcs11Key(Pkcs11Key),
+}
+
+impl StationKey {
+    /// Signs the SHA256 digest of `data`, returning the ECDSA signature as the
+    /// concatenation of the 32-byte big-endian `r` and `s` values.
+    pub fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            StationKey::LocalKey(sk) => {
+                let signature: Signature = SigningKey::from(sk).sign(data);
+                Ok(signature.to_bytes().to_vec())
+            }
+            StationKey::Pkcs11Key(key) => {
+                let (r, s) = key.sign(data)?;
+                let mut signature = Vec::with_capacity(64);
+                for half in [r, s] {
+                    ensure!(half.len() <= 32, "Unexpected ECDSA signature length");
+                    signature.resize(signature.len() + 32 - half.len(), 0);
+                    signature.extend(half);
+                }
+                Ok(signature)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonSignature {
+    algorithm: String,
+    value: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonSignedRecord {
+    record: Value,
+    signature: JsonSignature,
+}
+
+/// Writes `value` as JSON with sorted object keys and no whitespace.
+fn canonical_json(value: &Value, out: &mut Vec<u8>) -> Result<()> {
+    match value {
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                canonical_json(item, out)?;
+            }
+            out.push(b']');
+        }
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by_key(|(k, _)| k.as_str());
+            out.push(b'{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, k)?;
+                out.push(b':');
+                canonical_json(v, out)?;
+            }
+            out.push(b'}');
+        }
+        _ => serde_json::to_writer(&mut *out, value)?,
+    }
+    Ok(())
+}
+
+fn cose_protected_header() -> Vec<u8> {
+    Cbor::Map(vec![(
+        Cbor::Uint(COSE_HEADER_ALG),
+        Cbor::int(COSE_ALG_ES256),
+    )])
+    .encode()
+}
+
+/// Returns the COSE Sig_structure that the signature of a COSE_Sign1 covers.
+fn cose_sig_structure(protected: &[u8], payload: &[u8]) -> Vec<u8> {
+    Cbor::Array(vec![
+        Cbor::Text("Signature1".into()),
+        Cbor::Bytes(protected.to_vec()),
+        Cbor::Bytes(Vec::new()),
+        Cbor::Bytes(payload.to_vec()),
+    ])
+    .encode()
+}
+
+impl ProvisioningRecord {
+    /// Encodes the record in `format` and signs it with the station `key`.
+    pub fn sign(&self, key: &StationKey, format: RecordFormat) -> Result<Vec<u8>> {
+        let record = serde_json::to_value(self)?;
+        match format {
+            RecordFormat::Json => {
+                let mut payload = Vec::new();
+                canonical_json(&record, &mut payload)?;
+                let signed = JsonSignedRecord {
+                    record,
+                    signature: JsonSignature {
+                        algorithm: ES256.into(),
+                        value: hex::encode(key.sign(&payload)?),
+                    },
+                };
+                Ok(serde_json::to_vec_pretty(&signed)?)
+            }
+            RecordFormat::Cbor => {
+                let payload = Cbor::from_json(&record)?.encode();
+                let protected = cose_protected_header();
+                let signature = key.sign(&cose_sig_structure(&protected, &payload))?;
+                let sign1 = Cbor::Array(vec![
+                    Cbor::Bytes(protected),
+                    Cbor::Map(Vec::new()),
+                    Cbor::Bytes(payload),
+                    Cbor::Bytes(signature),
+                ]);
+                Ok(Cbor::Tag(COSE_SIGN1_TAG, Box::new(sign1)).encode())
+            }
+        }
+    }
+
+    /// Checks the signature of a record produced by [`ProvisioningRecord::sign`]
+    /// and returns the record.
+    pub fn verify(data: &[u8], format: RecordFormat, key: &VerifyingKey) -> Result<Self> {
+        let (record, signed_data, signature) = match format {
+            RecordFormat::Json => {
+                let signed: JsonSignedRecord =
+                    serde_json::from_slice(data).context("malformed JSON record")?;
+                if signed.signature.algorithm != ES256 {
+                    bail!(
+                        "unsupported signature algorithm {:?}",
+                        signed.signature.algorithm
+                    );
+                }
+                let mut payload = Vec::new();
+                canonical_json(&signed.record, &mut payload)?;
+                let signature = hex::decode(&signed.signature.value)?;
+                (signed.record, payload, signature)
+            }
+            RecordFormat::Cbor => {
+                let Cbor::Tag(COSE_SIGN1_TAG, sign1) = Cbor::decode(data)? else {
+                    bail!("record is not a COSE_Sign1 structure");
+                };
+                let Cbor::Array(items) = *sign1 else {
+                    bail!("malformed COSE_Sign1 structure");
+                };
+                let Ok(
+                    [Cbor::Bytes(protected), Cbor::Map(_), Cbor::Bytes(payload), Cbor::Bytes(signature)],
+                ) = <[Cbor; 4]>::try_from(items)
+                else {
+                    bail!("malformed COSE_Sign1 structure");
+                };
+                if protected != cose_protected_header() {
+                    bail!("unsupported COSE protected header");
+                }
+                let record = Cbor::decode(&payload)?.to_json()?;
+                (record, cose_sig_structure(&protected, &payload), signature)
+            }
+        };
+        let signature = Signature::from_slice(&signature).context("malformed signature")?;
+        key.verify(&signed_data, &signature)
+            .context("record signature is not valid")?;
+        Ok(serde_json::from_value(record)?)
+    }
+}
@@ -3,6 +3,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 mod error;
+#[cfg(test)]
+mod kat;
 mod key;
 mod variants;
 
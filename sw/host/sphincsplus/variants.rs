@@ -31,28 +31,28 @@ macro_rules! algorithms {
         paste! {
             #[allow(dead_code)]
             impl $Enum {
-                pub(crate) fn public_key_len(&self) -> usize {
+                pub fn public_key_len(&self) -> usize {
                     match *self {
                     $(
                         $Enum::$enumerator => [<bindgen_ $stem>]::CRYPTO_PUBLICKEYBYTES as usize
                     ),*
                     }
                 }
-                pub(crate) fn secret_key_len(&self) -> usize {
+                pub fn secret_key_len(&self) -> usize {
                     match *self {
                     $(
                         $Enum::$enumerator => [<bindgen_ $stem>]::CRYPTO_SECRETKEYBYTES as usize
                     ),*
                     }
                 }
-                pub(crate) fn seed_len(&self) -> usize {
+                pub fn seed_len(&self) -> usize {
                     match *self {
                     $(
                         $Enum::$enumerator => [<bindgen_ $stem>]::CRYPTO_SEEDBYTES as usize
                     ),*
                     }
                 }
-                pub(crate) fn signature_len(&self) -> usize {
+                pub fn signature_len(&self) -> usize {
                     match *self {
                     $(
                         $Enum::$enumerator => [<bindgen_ $stem>]::CRYPTO_BYTES as usize
@@ -220,6 +220,24 @@ macro_rules! algorithms {
                             SpxError::BadSignature.to_string()
                         );
                     }
+
+                    #[test]
+                    fn key_layout_test() {
+                        // Check the key layout defined by the reference implementation:
+                        // sk = SK_SEED || SK_PRF || PUB_SEED || root and pk = PUB_SEED || root.
+                        let seed = (0..ALGO.seed_len()).map(|i| i as u8).collect::<Vec<_>>();
+                        let (pk, sk) = ALGO.keypair_from_seed(&seed).unwrap();
+                        let n = ALGO.seed_len() / 3;
+                        assert_eq!(ALGO.public_key_len(), 2 * n);
+                        assert_eq!(ALGO.secret_key_len(), 4 * n);
+                        assert_eq!(&sk[..3 * n], seed.as_slice());
+                        assert_eq!(&sk[2 * n..], pk.as_slice());
+                    }
+
+                    #[test]
+                    fn kat_test() {
+                        crate::kat::check(ALGO, stringify!($stem)).unwrap();
+                    }
                 }
             )*
         } // end paste
@@ -236,8 +254,86 @@ algorithms! {
         #[serde(rename="SPHINCS+-SHAKE-128s-simple", alias="SHAKE-128s-simple")]
         #[strum(serialize="SPHINCS+-SHAKE-128s-simple", serialize="SHAKE-128s-simple")]
         Shake128sSimple => shake_128s_simple,
+        #[serde(rename="SPHINCS+-SHAKE-128s-robust", alias="SHAKE-128s-robust")]
+        #[strum(serialize="SPHINCS+-SHAKE-128s-robust", serialize="SHAKE-128s-robust")]
+        Shake128sRobust => shake_128s_robust,
+        #[serde(rename="SPHINCS+-SHAKE-128f-simple", alias="SHAKE-128f-simple")]
+        #[strum(serialize="SPHINCS+-SHAKE-128f-simple", serialize="SHAKE-128f-simple")]
+        Shake128fSimple => shake_128f_simple,
+        #[serde(rename="SPHINCS+-SHAKE-128f-robust", alias="SHAKE-128f-robust")]
+        #[strum(serialize="SPHINCS+-SHAKE-128f-robust", serialize="SHAKE-128f-robust")]
+        Shake128fRobust => shake_128f_robust,
+        #[serde(rename="SPHINCS+-SHAKE-192s-simple", alias="SHAKE-192s-simple")]
+        #[strum(serialize="SPHINCS+-SHAKE-192s-simple", serialize="SHAKE-192s-simple")]
+        Shake192sSimple => shake_192s_simple,
+        #[serde(rename="SPHINCS+-SHAKE-192s-robust", alias="SHAKE-192s-robust")]
+        #[strum(serialize="SPHINCS+-SHAKE-192s-robust", serialize="SHAKE-192s-robust")]
+        Shake192sRobust => shake_192s_robust,
+        #[serde(rename="SPHINCS+-SHAKE-192f-simple", alias="SHAKE-192f-simple")]
+        #[strum(serialize="SPHINCS+-SHAKE-192f-simple", serialize="SHAKE-192f-simple")]
+        Shake192fSimple => shake_192f_simple,
+        #[serde(rename="SPHINCS+-SHAKE-192f-robust", alias="SHAKE-192f-robust")]
+        #[strum(serialize="SPHINCS+-SHAKE-192f-robust", serialize="SHAKE-192f-robust")]
+        Shake192fRobust => shake_192f_robust,
+        #[serde(rename="SPHINCS+-SHAKE-256s-simple", alias="SHAKE-256s-simple")]
+        #[strum(serialize="SPHINCS+-SHAKE-256s-simple", serialize="SHAKE-256s-simple")]
+        Shake256sSimple => shake_256s_simple,
+        #[serde(rename="SPHINCS+-SHAKE-256s-robust", alias="SHAKE-256s-robust")]
+        #[strum(serialize="SPHINCS+-SHAKE-256s-robust", serialize="SHAKE-256s-robust")]
+        Shake256sRobust => shake_256s_robust,
+        #[serde(rename="SPHINCS+-SHAKE-256f-simple", alias="SHAKE-256f-simple")]
+        #[strum(serialize="SPHINCS+-SHAKE-256f-simple", serialize="SHAKE-256f-simple")]
+        Shake256fSimple => shake_256f_simple,
+        #[serde(rename="SPHINCS+-SHAKE-256f-robust", alias="SHAKE-256f-robust")]
+        #[strum(serialize="SPHINCS+-SHAKE-256f-robust", serialize="SHAKE-256f-robust")]
+        Shake256fRobust => shake_256f_robust,
+        #[serde(rename="SPHINCS+-SHAKE-128s-q20-simple", alias="SHAKE-128s-q20-simple")]
+        #[strum(serialize="SPHINCS+-SHAKE-128s-q20-simple", serialize="SHAKE-128s-q20-simple")]
+        Shake128sQ20Simple => shake_128s_q20_simple,
+        #[serde(rename="SPHINCS+-SHAKE-128s-q20-robust", alias="SHAKE-128s-q20-robust")]
+        #[strum(serialize="SPHINCS+-SHAKE-128s-q20-robust", serialize="SHAKE-128s-q20-robust")]
+        Shake128sQ20Robust => shake_128s_q20_robust,
         #[serde(rename="SPHINCS+-SHA2-128s-simple", alias="SHA2-128s-simple")]
         #[strum(serialize="SPHINCS+-SHA2-128s-simple", serialize="SHA2-128s-simple")]
         Sha2128sSimple => sha2_128s_simple,
+        #[serde(rename="SPHINCS+-SHA2-128s-robust", alias="SHA2-128s-robust")]
+        #[strum(serialize="SPHINCS+-SHA2-128s-robust", serialize="SHA2-128s-robust")]
+        Sha2128sRobust => sha2_128s_robust,
+        #[serde(rename="SPHINCS+-SHA2-128f-simple", alias="SHA2-128f-simple")]
+        #[strum(serialize="SPHINCS+-SHA2-128f-simple", serialize="SHA2-128f-simple")]
+        Sha2128fSimple => sha2_128f_simple,
+        #[serde(rename="SPHINCS+-SHA2-128f-robust", alias="SHA2-128f-robust")]
+        #[strum(serialize="SPHINCS+-SHA2-128f-robust", serialize="SHA2-128f-robust")]
+        Sha2128fRobust => sha2_128f_robust,
+        #[serde(rename="SPHINCS+-SHA2-192s-simple", alias="SHA2-192s-simple")]
+        #[strum(serialize="SPHINCS+-SHA2-192s-simple", serialize="SHA2-192s-simple")]
+        Sha2192sSimple => sha2_192s_simple,
+        #[serde(rename="SPHINCS+-SHA2-192s-robust", alias="SHA2-192s-robust")]
+        #[strum(serialize="SPHINCS+-SHA2-192s-robust", serialize="SHA2-192s-robust")]
+        Sha2192sRobust => sha2_192s_robust,
+        #[serde(rename="SPHINCS+-SHA2-192f-simple", alias="SHA2-192f-simple")]
+        #[strum(serialize="SPHINCS+-SHA2-192f-simple", serialize="SHA2-192f-simple")]
+        Sha2192fSimple => sha2_192f_simple,
+        #[serde(rename="SPHINCS+-SHA2-192f-robust", alias="SHA2-192f-robust")]
+        #[strum(serialize="SPHINCS+-SHA2-192f-robust", serialize="SHA2-192f-robust")]
+        Sha2192fRobust => sha2_192f_robust,
+        #[serde(rename="SPHINCS+-SHA2-256s-simple", alias="SHA2-256s-simple")]
+        #[strum(serialize="SPHINCS+-SHA2-256s-simple", serialize="SHA2-256s-simple")]
+        Sha2256sSimple => sha2_256s_simple,
+        #[serde(rename="SPHINCS+-SHA2-256s-robust", alias="SHA2-256s-robust")]
+        #[strum(serialize="SPHINCS+-SHA2-256s-robust", serialize="SHA2-256s-robust")]
+        Sha2256sRobust => sha2_256s_robust,
+        #[serde(rename="SPHINCS+-SHA2-256f-simple", alias="SHA2-256f-simple")]
+        #[strum(serialize="SPHINCS+-SHA2-256f-simple", serialize="SHA2-256f-simple")]
+        Sha2256fSimple => sha2_256f_simple,
+        #[serde(rename="SPHINCS+-SHA2-256f-robust", alias="SHA2-256f-robust")]
+        #[strum(serialize="SPHINCS+-SHA2-256f-robust", serialize="SHA2-256f-robust")]
+        Sha2256fRobust => sha2_256f_robust,
+        #[serde(rename="SPHINCS+-SHA2-128s-q20-simple", alias="SHA2-128s-q20-simple")]
+        #[strum(serialize="SPHINCS+-SHA2-128s-q20-simple", serialize="SHA2-128s-q20-simple")]
+        Sha2128sQ20Simple => sha2_128s_q20_simple,
+        #[serde(rename="SPHINCS+-SHA2-128s-q20-robust", alias="SHA2-128s-q20-robust")]
+        #[strum(serialize="SPHINCS+-SHA2-128s-q20-robust", serialize="SHA2-128s-q20-robust")]
+        Sha2128sQ20Robust => sha2_128s_q20_robust,
     }
 }
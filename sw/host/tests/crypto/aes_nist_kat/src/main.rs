@@ -8,13 +8,13 @@ use clap::Parser;
 use std::fs;
 use std::time::Duration;
 
-use serde::Deserialize;
-
 use cryptotest_commands::aes_commands::{
     AesSubcommand, CryptotestAesData, CryptotestAesMode, CryptotestAesOperation,
     CryptotestAesOutput, CryptotestAesPadding,
 };
 use cryptotest_commands::commands::CryptotestCommand;
+use cryptotest_vectors::aes::{self as aes_vectors, AesTestCase};
+use cryptotest_vectors::{Report, Selection};
 
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::execute_test;
@@ -31,21 +31,16 @@ struct Opts {
     #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
     timeout: Duration,
 
+    /// Test vectors in the cryptotest JSON format.
     #[arg(long, num_args = 1..)]
     aes_json: Vec<String>,
-}
 
-#[derive(Debug, Deserialize)]
-struct AesTestCase {
-    algorithm: String,
-    operation: String,
-    key_len: usize,
-    mode: String,
-    padding: String,
-    key: Vec<u8>,
-    iv: Option<Vec<u8>>,
-    ciphertext: Vec<u8>,
-    plaintext: Vec<u8>,
+    /// NIST CAVP AESAVS known-answer `.rsp` files (e.g. `CBCGFSbox128.rsp`).
+    #[arg(long, num_args = 1..)]
+    nist_rsp: Vec<String>,
+
+    #[command(flatten)]
+    selection: Selection,
 }
 
 const AES_CMD_MAX_MSG_BYTES: usize = 64;
@@ -56,7 +51,13 @@ fn run_aes_testcase(
     test_case: &AesTestCase,
     opts: &Opts,
     transport: &TransportWrapper,
+    report: &mut Report,
 ) -> Result<()> {
+    log::info!(
+        "vendor: {}, test case: {}",
+        test_case.vendor,
+        test_case.test_case_id
+    );
     let uart = transport.uart("console")?;
 
     assert_eq!(test_case.algorithm.as_str(), "aes");
@@ -122,9 +123,11 @@ fn run_aes_testcase(
     .send(&*uart)?;
 
     let aes_output = CryptotestAesOutput::recv(&*uart, opts.timeout, false)?;
-    assert_eq!(
-        aes_output.output[0..input_len],
-        expected_output[0..input_len]
+    report.record(
+        &test_case.vendor,
+        &format!("{}/{}", test_case.group(), test_case.operation),
+        test_case.test_case_id,
+        aes_output.output[0..input_len] == expected_output[0..input_len],
     );
     Ok(())
 }
@@ -134,19 +137,25 @@ fn test_aes(opts: &Opts, transport: &TransportWrapper) -> Result<()> {
     uart.set_flow_control(true)?;
     let _ = UartConsole::wait_for(&*uart, r"Running [^\r\n]*", opts.timeout)?;
 
+    let mut aes_tests = Vec::new();
+    for file in &opts.aes_json {
+        aes_tests.extend(aes_vectors::load_json(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.nist_rsp {
+        aes_tests.extend(aes_vectors::load_nist(&fs::read_to_string(file)?)?);
+    }
+
     let mut test_counter = 0u32;
-    let test_vector_files = &opts.aes_json;
-    for file in test_vector_files {
-        let raw_json = fs::read_to_string(file)?;
-        let aes_tests: Vec<AesTestCase> = serde_json::from_str(&raw_json)?;
-
-        for aes_test in &aes_tests {
-            test_counter += 1;
-            log::info!("Test counter: {}", test_counter);
-            run_aes_testcase(aes_test, opts, transport)?;
-        }
+    let mut report = Report::default();
+    for aes_test in aes_tests
+        .iter()
+        .filter(|t| opts.selection.matches(&t.group(), t.test_case_id))
+    {
+        test_counter += 1;
+        log::info!("Test counter: {}", test_counter);
+        run_aes_testcase(aes_test, opts, transport, &mut report)?;
     }
-    Ok(())
+    report.check()
 }
 
 fn main() -> Result<()> {
@@ -8,10 +8,10 @@ use clap::Parser;
 use std::fs;
 use std::time::Duration;
 
-use serde::Deserialize;
-
 use cryptotest_commands::commands::CryptotestCommand;
 use cryptotest_commands::drbg_commands::{CryptotestDrbgInput, CryptotestDrbgOutput};
+use cryptotest_vectors::drbg::{self as drbg_vectors, DrbgTestCase};
+use cryptotest_vectors::{Report, Selection};
 
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::execute_test;
@@ -28,32 +28,23 @@ struct Opts {
     #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
     timeout: Duration,
 
+    /// Test vectors in the cryptotest JSON format.
     #[arg(long, num_args = 1..)]
     drbg_json: Vec<String>,
-}
 
-#[derive(Debug, Deserialize)]
-struct DrbgTestCase {
-    vendor: String,
-    test_case_id: usize,
-    entropy: Vec<u8>,
-    personalization_string: Vec<u8>,
-    reseed: bool,
-    #[serde(default)]
-    reseed_entropy: Vec<u8>,
-    #[serde(default)]
-    reseed_additional_input: Vec<u8>,
-    additional_input_1: Vec<u8>,
-    additional_input_2: Vec<u8>,
-    output: Vec<u8>,
-    result: bool,
+    /// NIST CAVP SP 800-90A `CTR_DRBG.rsp` files.
+    #[arg(long, num_args = 1..)]
+    nist_rsp: Vec<String>,
+
+    #[command(flatten)]
+    selection: Selection,
 }
 
 fn run_drbg_testcase(
     test_case: &DrbgTestCase,
     opts: &Opts,
     transport: &TransportWrapper,
-    fail_counter: &mut u32,
+    report: &mut Report,
 ) -> Result<()> {
     log::info!(
         "vendor: {}, test case: {}",
@@ -121,8 +112,13 @@ fn run_drbg_testcase(
             test_case.result,
             success
         );
-        *fail_counter += 1;
     }
+    report.record(
+        &test_case.vendor,
+        &test_case.group(),
+        test_case.test_case_id,
+        test_case.result == success,
+    );
     Ok(())
 }
 
@@ -131,25 +127,25 @@ fn test_drbg(opts: &Opts, transport: &TransportWrapper) -> Result<()> {
     uart.set_flow_control(true)?;
     let _ = UartConsole::wait_for(&*uart, r"Running [^\r\n]*", opts.timeout)?;
 
+    let mut drbg_tests = Vec::new();
+    for file in &opts.drbg_json {
+        drbg_tests.extend(drbg_vectors::load_json(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.nist_rsp {
+        drbg_tests.extend(drbg_vectors::load_nist(&fs::read_to_string(file)?)?);
+    }
+
     let mut test_counter = 0u32;
-    let mut fail_counter = 0u32;
-    let test_vector_files = &opts.drbg_json;
-    for file in test_vector_files {
-        let raw_json = fs::read_to_string(file)?;
-        let drbg_tests: Vec<DrbgTestCase> = serde_json::from_str(&raw_json)?;
-
-        for drbg_test in &drbg_tests {
-            test_counter += 1;
-            log::info!("Test counter: {}", test_counter);
-            run_drbg_testcase(drbg_test, opts, transport, &mut fail_counter)?;
-        }
+    let mut report = Report::default();
+    for drbg_test in drbg_tests
+        .iter()
+        .filter(|t| opts.selection.matches(&t.group(), t.test_case_id))
+    {
+        test_counter += 1;
+        log::info!("Test counter: {}", test_counter);
+        run_drbg_testcase(drbg_test, opts, transport, &mut report)?;
     }
-    assert_eq!(
-        0, fail_counter,
-        "Failed {} out of {} tests.",
-        fail_counter, test_counter
-    );
-    Ok(())
+    report.check()
 }
 
 fn main() -> Result<()> {
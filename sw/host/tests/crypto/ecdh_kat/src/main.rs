@@ -9,13 +9,13 @@ use num_bigint_dig::BigUint;
 use std::fs;
 use std::time::Duration;
 
-use serde::Deserialize;
-
 use cryptotest_commands::commands::CryptotestCommand;
 use cryptotest_commands::ecdh_commands::{
     CryptotestEcdhCoordinate, CryptotestEcdhCurve, CryptotestEcdhDeriveOutput,
     CryptotestEcdhPrivateKey,
 };
+use cryptotest_vectors::ecdh::{self as ecdh_vectors, EcdhTestCase};
+use cryptotest_vectors::{Report, Selection};
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::execute_test;
 use opentitanlib::test_utils::init::InitializeTest;
@@ -45,29 +45,27 @@ struct Opts {
     #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
     timeout: Duration,
 
+    /// Test vectors in the cryptotest JSON format.
     #[arg(long, num_args = 1..)]
     ecdh_json: Vec<String>,
-}
 
-#[derive(Debug, Deserialize)]
-struct EcdhTestCase {
-    vendor: String,
-    test_case_id: usize,
-    algorithm: String,
-    curve: String,
-    d: Vec<u8>,
-    qx: Vec<u8>,
-    qy: Vec<u8>,
-    z: Vec<u8>,
-    result: bool,
+    /// NIST CAVP SP 800-56A `KAS_ECC_CDH_PrimitiveTest.txt` files.
+    #[arg(long, num_args = 1..)]
+    nist_rsp: Vec<String>,
+
+    /// Wycheproof `EcdhTest` files.
+    #[arg(long, num_args = 1..)]
+    wycheproof: Vec<String>,
+
+    #[command(flatten)]
+    selection: Selection,
 }
 
 fn run_ecdh_testcase(
     test_case: &EcdhTestCase,
     opts: &Opts,
     transport: &TransportWrapper,
-    fail_counter: &mut usize,
-    failures: &mut Vec<usize>,
+    report: &mut Report,
 ) -> Result<()> {
     log::info!(
         "vendor: {}, test case: {}",
@@ -206,15 +204,19 @@ fn run_ecdh_testcase(
     z.reverse();
     let success = ecdh_output.ok != 0 && z == ecdh_output.shared_secret[..out_len];
     if success != test_case.result {
-        *fail_counter += 1;
         log::info!(
             "FAILED test #{}: expected = {}, actual = {}",
             test_case.test_case_id,
             test_case.result,
             success
         );
-        failures.push(test_case.test_case_id);
     }
+    report.record(
+        &test_case.vendor,
+        &test_case.group(),
+        test_case.test_case_id,
+        test_case.result == success,
+    );
     Ok(())
 }
 
@@ -223,26 +225,28 @@ fn test_ecdh(opts: &Opts, transport: &TransportWrapper) -> Result<()> {
     uart.set_flow_control(true)?;
     let _ = UartConsole::wait_for(&*uart, r"Running [^\r\n]*", opts.timeout)?;
 
+    let mut ecdh_tests = Vec::new();
+    for file in &opts.ecdh_json {
+        ecdh_tests.extend(ecdh_vectors::load_json(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.nist_rsp {
+        ecdh_tests.extend(ecdh_vectors::load_nist(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.wycheproof {
+        ecdh_tests.extend(ecdh_vectors::load_wycheproof(&fs::read_to_string(file)?)?);
+    }
+
     let mut test_counter = 0u32;
-    let mut fail_counter = 0usize;
-    let mut failures = vec![];
-    let test_vector_files = &opts.ecdh_json;
-    for file in test_vector_files {
-        let raw_json = fs::read_to_string(file)?;
-        let ecdh_tests: Vec<EcdhTestCase> = serde_json::from_str(&raw_json)?;
-
-        for ecdh_test in &ecdh_tests {
-            test_counter += 1;
-            log::info!("Test counter: {}", test_counter);
-            run_ecdh_testcase(ecdh_test, opts, transport, &mut fail_counter, &mut failures)?;
-        }
+    let mut report = Report::default();
+    for ecdh_test in ecdh_tests
+        .iter()
+        .filter(|t| opts.selection.matches(&t.group(), t.test_case_id))
+    {
+        test_counter += 1;
+        log::info!("Test counter: {}", test_counter);
+        run_ecdh_testcase(ecdh_test, opts, transport, &mut report)?;
     }
-    assert_eq!(
-        0, fail_counter,
-        "Failed {} out of {} tests. Failed tests: {:?}",
-        fail_counter, test_counter, failures,
-    );
-    Ok(())
+    report.check()
 }
 
 fn main() -> Result<()> {
@@ -15,7 +15,6 @@ use p256::pkcs8::ObjectIdentifier;
 use p256::U256;
 use p384::elliptic_curve::scalar::ScalarPrimitive as ScalarPrimitiveP384;
 use p384::U384;
-use serde::Deserialize;
 use sha2::digest::generic_array::GenericArray;
 use sha2::{Digest, Sha256, Sha384, Sha512};
 use std::fs;
@@ -27,6 +26,8 @@ use cryptotest_commands::ecdsa_commands::{
     CryptotestEcdsaMessage, CryptotestEcdsaOperation, CryptotestEcdsaPrivateKey,
     CryptotestEcdsaSignature, CryptotestEcdsaVerifyOutput,
 };
+use cryptotest_vectors::ecdsa::{self as ecdsa_vectors, EcdsaTestCase};
+use cryptotest_vectors::{Report, Selection};
 
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::execute_test;
@@ -43,32 +44,24 @@ struct Opts {
     #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
     timeout: Duration,
 
+    /// Test vectors in the cryptotest JSON format.
     #[arg(long, num_args = 1..)]
     ecdsa_json: Vec<String>,
-}
 
-fn scalar_zero() -> String {
-    String::from("00")
-}
+    /// NIST CAVP FIPS 186-4 `SigVer.rsp` files.
+    #[arg(long, num_args = 1..)]
+    nist_sig_ver: Vec<String>,
+
+    /// NIST CAVP FIPS 186-4 `SigGen.txt` files.
+    #[arg(long, num_args = 1..)]
+    nist_sig_gen: Vec<String>,
 
-#[derive(Debug, Deserialize)]
-struct EcdsaTestCase {
-    vendor: String,
-    test_case_id: usize,
-    algorithm: String,
-    operation: String,
-    curve: String,
-    hash_alg: String,
-    message: Vec<u8>,
-    qx: String,
-    qy: String,
-    #[serde(default = "scalar_zero")]
-    r: String,
-    #[serde(default = "scalar_zero")]
-    s: String,
-    #[serde(default)]
-    d: String,
-    result: bool,
+    /// Wycheproof `EcdsaVerify` files.
+    #[arg(long, num_args = 1..)]
+    wycheproof: Vec<String>,
+
+    #[command(flatten)]
+    selection: Selection,
 }
 
 const ECDSA_CMD_MAX_SIGNATURE_SCALAR_BYTES_P256: usize = 32;
@@ -181,7 +174,7 @@ fn run_ecdsa_testcase(
     test_case: &EcdsaTestCase,
     opts: &Opts,
     transport: &TransportWrapper,
-    failures: &mut Vec<String>,
+    report: &mut Report,
 ) -> Result<()> {
     log::info!(
         "vendor: {}, test case: {}",
@@ -496,15 +489,13 @@ fn run_ecdsa_testcase(
             test_case.result,
             success
         );
-        failures.push(format!(
-            "{} {} {} {} #{}",
-            test_case.vendor,
-            test_case.curve,
-            test_case.operation,
-            test_case.hash_alg,
-            test_case.test_case_id
-        ));
     }
+    report.record(
+        &test_case.vendor,
+        &format!("{}/{}", test_case.group(), test_case.operation),
+        test_case.test_case_id,
+        test_case.result == success,
+    );
     Ok(())
 }
 
@@ -513,28 +504,37 @@ fn test_ecdsa(opts: &Opts, transport: &TransportWrapper) -> Result<()> {
     uart.set_flow_control(true)?;
     let _ = UartConsole::wait_for(&*uart, r"Running [^\r\n]*", opts.timeout)?;
 
-    let mut test_counter = 0u32;
-    let mut failures = vec![];
-    let test_vector_files = &opts.ecdsa_json;
-    for file in test_vector_files {
-        let raw_json = fs::read_to_string(file)?;
-        let ecdsa_tests: Vec<EcdsaTestCase> = serde_json::from_str(&raw_json)?;
+    let mut ecdsa_tests = Vec::new();
+    for file in &opts.ecdsa_json {
+        ecdsa_tests.extend(ecdsa_vectors::load_json(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.nist_sig_ver {
+        ecdsa_tests.extend(ecdsa_vectors::load_nist(
+            &fs::read_to_string(file)?,
+            "verify",
+        )?);
+    }
+    for file in &opts.nist_sig_gen {
+        ecdsa_tests.extend(ecdsa_vectors::load_nist(
+            &fs::read_to_string(file)?,
+            "sign",
+        )?);
+    }
+    for file in &opts.wycheproof {
+        ecdsa_tests.extend(ecdsa_vectors::load_wycheproof(&fs::read_to_string(file)?)?);
+    }
 
-        for ecdsa_test in &ecdsa_tests {
-            test_counter += 1;
-            log::info!("Test counter: {}", test_counter);
-            run_ecdsa_testcase(ecdsa_test, opts, transport, &mut failures)?;
-        }
+    let mut test_counter = 0u32;
+    let mut report = Report::default();
+    for ecdsa_test in ecdsa_tests
+        .iter()
+        .filter(|t| opts.selection.matches(&t.group(), t.test_case_id))
+    {
+        test_counter += 1;
+        log::info!("Test counter: {}", test_counter);
+        run_ecdsa_testcase(ecdsa_test, opts, transport, &mut report)?;
     }
-    assert_eq!(
-        0,
-        failures.len(),
-        "Failed {} out of {} tests. Failures: {:?}",
-        failures.len(),
-        test_counter,
-        failures
-    );
-    Ok(())
+    report.check()
 }
 
 fn main() -> Result<()> {
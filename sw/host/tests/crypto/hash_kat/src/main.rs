@@ -6,15 +6,16 @@ use anyhow::Result;
 use arrayvec::ArrayVec;
 use clap::Parser;
 use std::fs;
+use std::path::Path;
 use std::time::Duration;
 
-use serde::Deserialize;
-
 use cryptotest_commands::commands::CryptotestCommand;
 use cryptotest_commands::hash_commands::{
     CryptotestHashAlgorithm, CryptotestHashMessage, CryptotestHashOutput,
     CryptotestHashShakeDigestLength,
 };
+use cryptotest_vectors::hash::{self as hash_vectors, HashTestCase};
+use cryptotest_vectors::{Report, Selection};
 
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::execute_test;
@@ -31,21 +32,21 @@ struct Opts {
     #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
     timeout: Duration,
 
+    /// Test vectors in the cryptotest JSON format.
     #[arg(long, num_args = 1..)]
     hash_json: Vec<String>,
-}
 
-#[derive(Debug, Deserialize)]
-struct HashTestCase {
-    vendor: String,
-    test_case_id: usize,
-    algorithm: String,
-    message: Vec<u8>,
-    // Customization string used for cSHAKE only
-    #[serde(default)]
-    customization_string: Vec<u8>,
-    digest: Vec<u8>,
-    result: bool,
+    /// NIST CAVP FIPS 180-4 and FIPS 202 `.rsp` files.  The hash algorithm is
+    /// determined by the file name (e.g. `SHA3_256ShortMsg.rsp`).
+    #[arg(long, num_args = 1..)]
+    nist_rsp: Vec<String>,
+
+    /// Hand-written `*_hardcoded.hjson` test vector files.
+    #[arg(long, num_args = 1..)]
+    hjson: Vec<String>,
+
+    #[command(flatten)]
+    selection: Selection,
 }
 
 const HASH_CMD_MAX_MESSAGE_BYTES: usize = 17068;
@@ -55,7 +56,7 @@ fn run_hash_testcase(
     test_case: &HashTestCase,
     opts: &Opts,
     transport: &TransportWrapper,
-    fail_counter: &mut u32,
+    report: &mut Report,
 ) -> Result<()> {
     log::info!(
         "vendor: {}, algorithm: {}, test case: {}",
@@ -117,7 +118,6 @@ fn run_hash_testcase(
     // Get hash output
     let hash_output = CryptotestHashOutput::recv(&*uart, opts.timeout, false)?;
     // Stepwise hashing is currently supported by SHA2 only.
-    let mut failed = false;
     match test_case.algorithm.as_str() {
         "sha-256" | "sha-384" | "sha-512" => {
             vec![
@@ -148,12 +148,14 @@ fn run_hash_testcase(
                 test_case.result,
                 success
             );
-            failed = true;
         }
+        report.record(
+            &test_case.vendor,
+            &format!("{}/{}", test_case.group(), mode),
+            test_case.test_case_id,
+            test_case.result == success,
+        );
     });
-    if failed {
-        *fail_counter += 1;
-    }
     Ok(())
 }
 
@@ -162,25 +164,36 @@ fn test_hash(opts: &Opts, transport: &TransportWrapper) -> Result<()> {
     uart.set_flow_control(true)?;
     let _ = UartConsole::wait_for(&*uart, r"Running [^\r\n]*", opts.timeout)?;
 
+    let mut hash_tests = Vec::new();
+    for file in &opts.hash_json {
+        hash_tests.extend(hash_vectors::load_json(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.nist_rsp {
+        let name = Path::new(file)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let algorithm = hash_vectors::nist_algorithm(&name)?;
+        hash_tests.extend(hash_vectors::load_nist(
+            &fs::read_to_string(file)?,
+            algorithm,
+        )?);
+    }
+    for file in &opts.hjson {
+        hash_tests.extend(hash_vectors::load_hjson(&fs::read_to_string(file)?)?);
+    }
+
     let mut test_counter = 0u32;
-    let mut fail_counter = 0u32;
-    let test_vector_files = &opts.hash_json;
-    for file in test_vector_files {
-        let raw_json = fs::read_to_string(file)?;
-        let hash_tests: Vec<HashTestCase> = serde_json::from_str(&raw_json)?;
-
-        for hash_test in &hash_tests {
-            test_counter += 1;
-            log::info!("Test counter: {}", test_counter);
-            run_hash_testcase(hash_test, opts, transport, &mut fail_counter)?;
-        }
+    let mut report = Report::default();
+    for hash_test in hash_tests
+        .iter()
+        .filter(|t| opts.selection.matches(&t.group(), t.test_case_id))
+    {
+        test_counter += 1;
+        log::info!("Test counter: {}", test_counter);
+        run_hash_testcase(hash_test, opts, transport, &mut report)?;
     }
-    assert_eq!(
-        0, fail_counter,
-        "Failed {} out of {} tests.",
-        fail_counter, test_counter
-    );
-    Ok(())
+    report.check()
 }
 
 fn main() -> Result<()> {
@@ -8,12 +8,12 @@ use clap::Parser;
 use std::fs;
 use std::time::Duration;
 
-use serde::Deserialize;
-
 use cryptotest_commands::commands::CryptotestCommand;
 use cryptotest_commands::hmac_commands::{
     CryptotestHmacHashAlg, CryptotestHmacKey, CryptotestHmacMessage, CryptotestHmacTag,
 };
+use cryptotest_vectors::hmac::{self as hmac_vectors, HmacTestCase};
+use cryptotest_vectors::{Report, Selection};
 
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::execute_test;
@@ -30,20 +30,20 @@ struct Opts {
     #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
     timeout: Duration,
 
+    /// Test vectors in the cryptotest JSON format.
     #[arg(long, num_args = 1..)]
     hmac_json: Vec<String>,
-}
 
-#[derive(Debug, Deserialize)]
-struct HmacTestCase {
-    vendor: String,
-    test_case_id: usize,
-    algorithm: String,
-    hash_alg: String,
-    key: Vec<u8>,
-    message: Vec<u8>,
-    tag: Vec<u8>,
-    result: bool,
+    /// NIST CAVP FIPS 198-1 `HMAC.rsp` files.
+    #[arg(long, num_args = 1..)]
+    nist_rsp: Vec<String>,
+
+    /// Wycheproof HMAC `MacTest` files.
+    #[arg(long, num_args = 1..)]
+    wycheproof: Vec<String>,
+
+    #[command(flatten)]
+    selection: Selection,
 }
 
 const HMAC_CMD_MAX_MESSAGE_BYTES: usize = 256;
@@ -53,7 +53,7 @@ fn run_hmac_testcase(
     test_case: &HmacTestCase,
     opts: &Opts,
     transport: &TransportWrapper,
-    fail_counter: &mut u32,
+    report: &mut Report,
 ) -> Result<()> {
     log::info!(
         "vendor: {}, test case: {}",
@@ -119,9 +119,12 @@ fn run_hmac_testcase(
             success
         );
     }
-    if success != test_case.result {
-        *fail_counter += 1;
-    }
+    report.record(
+        &test_case.vendor,
+        &test_case.group(),
+        test_case.test_case_id,
+        test_case.result == success,
+    );
     Ok(())
 }
 
@@ -130,25 +133,28 @@ fn test_hmac(opts: &Opts, transport: &TransportWrapper) -> Result<()> {
     uart.set_flow_control(true)?;
     let _ = UartConsole::wait_for(&*uart, r"Running [^\r\n]*", opts.timeout)?;
 
+    let mut hmac_tests = Vec::new();
+    for file in &opts.hmac_json {
+        hmac_tests.extend(hmac_vectors::load_json(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.nist_rsp {
+        hmac_tests.extend(hmac_vectors::load_nist(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.wycheproof {
+        hmac_tests.extend(hmac_vectors::load_wycheproof(&fs::read_to_string(file)?)?);
+    }
+
     let mut test_counter = 0u32;
-    let mut fail_counter = 0u32;
-    let test_vector_files = &opts.hmac_json;
-    for file in test_vector_files {
-        let raw_json = fs::read_to_string(file)?;
-        let hmac_tests: Vec<HmacTestCase> = serde_json::from_str(&raw_json)?;
-
-        for hmac_test in &hmac_tests {
-            test_counter += 1;
-            log::info!("Test counter: {}", test_counter);
-            run_hmac_testcase(hmac_test, opts, transport, &mut fail_counter)?;
-        }
+    let mut report = Report::default();
+    for hmac_test in hmac_tests
+        .iter()
+        .filter(|t| opts.selection.matches(&t.group(), t.test_case_id))
+    {
+        test_counter += 1;
+        log::info!("Test counter: {}", test_counter);
+        run_hmac_testcase(hmac_test, opts, transport, &mut report)?;
     }
-    assert_eq!(
-        0, fail_counter,
-        "Failed {} out of {} tests.",
-        fail_counter, test_counter
-    );
-    Ok(())
+    report.check()
 }
 
 fn main() -> Result<()> {
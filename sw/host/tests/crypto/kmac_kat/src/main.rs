@@ -8,13 +8,13 @@ use clap::Parser;
 use std::fs;
 use std::time::Duration;
 
-use serde::Deserialize;
-
 use cryptotest_commands::commands::CryptotestCommand;
 use cryptotest_commands::kmac_commands::{
     CryptotestKmacCustomizationString, CryptotestKmacKey, CryptotestKmacMessage,
     CryptotestKmacMode, CryptotestKmacRequiredTagLength, CryptotestKmacTag,
 };
+use cryptotest_vectors::kmac::{self as kmac_vectors, KmacTestCase};
+use cryptotest_vectors::{Report, Selection};
 
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::execute_test;
@@ -31,21 +31,16 @@ struct Opts {
     #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
     timeout: Duration,
 
+    /// Test vectors in the cryptotest JSON format.
     #[arg(long, num_args = 1..)]
     kmac_json: Vec<String>,
-}
 
-#[derive(Debug, Deserialize)]
-struct KmacTestCase {
-    vendor: String,
-    test_case_id: usize,
-    algorithm: String,
-    mode: usize,
-    key: Vec<u8>,
-    message: Vec<u8>,
-    customization_string: Vec<u8>,
-    tag: Vec<u8>,
-    result: bool,
+    /// Wycheproof KMAC `MacTest` files without customization strings.
+    #[arg(long, num_args = 1..)]
+    wycheproof: Vec<String>,
+
+    #[command(flatten)]
+    selection: Selection,
 }
 
 const KMAC_CMD_MAX_MESSAGE_BYTES: usize = 256;
@@ -58,7 +53,7 @@ fn run_kmac_testcase(
     test_case: &KmacTestCase,
     opts: &Opts,
     transport: &TransportWrapper,
-    fail_counter: &mut u32,
+    report: &mut Report,
 ) -> Result<()> {
     log::info!(
         "vendor: {}, test case: {}",
@@ -134,9 +129,12 @@ fn run_kmac_testcase(
             success
         );
     }
-    if success != test_case.result {
-        *fail_counter += 1;
-    }
+    report.record(
+        &test_case.vendor,
+        &test_case.group(),
+        test_case.test_case_id,
+        test_case.result == success,
+    );
     Ok(())
 }
 
@@ -145,25 +143,25 @@ fn test_kmac(opts: &Opts, transport: &TransportWrapper) -> Result<()> {
     uart.set_flow_control(true)?;
     let _ = UartConsole::wait_for(&*uart, r"Running [^\r\n]*", opts.timeout)?;
 
+    let mut kmac_tests = Vec::new();
+    for file in &opts.kmac_json {
+        kmac_tests.extend(kmac_vectors::load_json(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.wycheproof {
+        kmac_tests.extend(kmac_vectors::load_wycheproof(&fs::read_to_string(file)?)?);
+    }
+
     let mut test_counter = 0u32;
-    let mut fail_counter = 0u32;
-    let test_vector_files = &opts.kmac_json;
-    for file in test_vector_files {
-        let raw_json = fs::read_to_string(file)?;
-        let kmac_tests: Vec<KmacTestCase> = serde_json::from_str(&raw_json)?;
-
-        for kmac_test in &kmac_tests {
-            test_counter += 1;
-            log::info!("Test counter: {}", test_counter);
-            run_kmac_testcase(kmac_test, opts, transport, &mut fail_counter)?;
-        }
+    let mut report = Report::default();
+    for kmac_test in kmac_tests
+        .iter()
+        .filter(|t| opts.selection.matches(&t.group(), t.test_case_id))
+    {
+        test_counter += 1;
+        log::info!("Test counter: {}", test_counter);
+        run_kmac_testcase(kmac_test, opts, transport, &mut report)?;
     }
-    assert_eq!(
-        0, fail_counter,
-        "Failed {} out of {} tests.",
-        fail_counter, test_counter
-    );
-    Ok(())
+    report.check()
 }
 
 fn main() -> Result<()> {
@@ -8,14 +8,14 @@ use clap::Parser;
 use std::fs;
 use std::time::Duration;
 
-use serde::Deserialize;
-
 use cryptotest_commands::commands::CryptotestCommand;
 use cryptotest_commands::sphincsplus_commands::{
     CryptotestSphincsPlusHashAlg, CryptotestSphincsPlusMessage, CryptotestSphincsPlusOperation,
     CryptotestSphincsPlusPublicKey, CryptotestSphincsPlusSignature,
     CryptotestSphincsPlusVerifyOutput,
 };
+use cryptotest_vectors::sphincsplus::{self as sphincsplus_vectors, SphincsPlusTestCase};
+use cryptotest_vectors::{Report, Selection};
 
 use opentitanlib::app::TransportWrapper;
 use opentitanlib::execute_test;
@@ -32,28 +32,24 @@ struct Opts {
     #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
     timeout: Duration,
 
+    /// Test vectors in the cryptotest JSON format.
     #[arg(long, num_args = 1..)]
     sphincsplus_json: Vec<String>,
-}
 
-#[derive(Debug, Deserialize)]
-struct SphincsPlusTestCase {
-    vendor: String,
-    test_case_id: usize,
-    algorithm: String,
-    operation: String,
-    hash_alg: String,
-    public: Vec<u8>,
-    message: Vec<u8>,
-    signature: Vec<u8>,
-    result: bool,
+    /// NIST PQC `PQCsignKAT_*.rsp` files.  The hash algorithm is determined by
+    /// the parameter set's directory (e.g. `sphincs-sha256-128s-simple`).
+    #[arg(long, num_args = 1..)]
+    nist_rsp: Vec<String>,
+
+    #[command(flatten)]
+    selection: Selection,
 }
 
 fn run_sphincsplus_testcase(
     test_case: &SphincsPlusTestCase,
     opts: &Opts,
     transport: &TransportWrapper,
-    fail_counter: &mut u32,
+    report: &mut Report,
 ) -> Result<()> {
     log::info!(
         "vendor: {}, algorithm: {}, test case: {}",
@@ -120,8 +116,13 @@ fn run_sphincsplus_testcase(
             test_case.result,
             success
         );
-        *fail_counter += 1;
     }
+    report.record(
+        &test_case.vendor,
+        &format!("{}/{}", test_case.group(), test_case.operation),
+        test_case.test_case_id,
+        test_case.result == success,
+    );
     Ok(())
 }
 
@@ -130,25 +131,29 @@ fn test_sphincsplus(opts: &Opts, transport: &TransportWrapper) -> Result<()> {
     uart.set_flow_control(true)?;
     let _ = UartConsole::wait_for(&*uart, r"Running [^\r\n]*", opts.timeout)?;
 
+    let mut sphincsplus_tests = Vec::new();
+    for file in &opts.sphincsplus_json {
+        sphincsplus_tests.extend(sphincsplus_vectors::load_json(&fs::read_to_string(file)?)?);
+    }
+    for file in &opts.nist_rsp {
+        let hash_alg = sphincsplus_vectors::nist_hash_alg(file)?;
+        sphincsplus_tests.extend(sphincsplus_vectors::load_nist(
+            &fs::read_to_string(file)?,
+            hash_alg,
+        )?);
+    }
+
     let mut test_counter = 0u32;
-    let mut fail_counter = 0u32;
-    let test_vector_files = &opts.sphincsplus_json;
-    for file in test_vector_files {
-        let raw_json = fs::read_to_string(file)?;
-        let sphincsplus_tests: Vec<SphincsPlusTestCase> = serde_json::from_str(&raw_json)?;
-
-        for sphincsplus_test in &sphincsplus_tests {
-            test_counter += 1;
-            log::info!("Test counter: {}", test_counter);
-            run_sphincsplus_testcase(sphincsplus_test, opts, transport, &mut fail_counter)?;
-        }
+    let mut report = Report::default();
+    for sphincsplus_test in sphincsplus_tests
+        .iter()
+        .filter(|t| opts.selection.matches(&t.group(), t.test_case_id))
+    {
+        test_counter += 1;
+        log::info!("Test counter: {}", test_counter);
+        run_sphincsplus_testcase(sphincsplus_test, opts, transport, &mut report)?;
     }
-    assert_eq!(
-        0, fail_counter,
-        "Failed {} out of {} tests.",
-        fail_counter, test_counter
-    );
-    Ok(())
+    report.check()
 }
 
 fn main() -> Result<()> {
@@ -37,6 +37,7 @@ def sphincsplus_repos(local = None):
         sha256 = "b301faa7a42ef538323a732929d49341b1cbd8375f643f7d98ca32cd6efacc32",
         patches = [
             Label("//third_party/sphincsplus:sphincsplus-namespace.patch"),
+            Label("//third_party/sphincsplus:sphincsplus-q20.patch"),
         ],
         patch_args = ["-p2"],
     )